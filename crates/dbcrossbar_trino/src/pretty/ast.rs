@@ -91,6 +91,24 @@ pub enum Expr {
         /// The time zone to use.
         time_zone: Box<Expr>,
     },
+    /// A `NOT` expression.
+    Not(Box<Expr>),
+    /// An `IS NULL` or `IS NOT NULL` test.
+    IsNull {
+        /// The expression to test.
+        expr: Box<Expr>,
+        /// Is this `IS NOT NULL`?
+        negated: bool,
+    },
+    /// An `IN (..)` or `NOT IN (..)` test.
+    InList {
+        /// The expression to test.
+        expr: Box<Expr>,
+        /// The values to compare against.
+        list: Vec<Expr>,
+        /// Is this `NOT IN`?
+        negated: bool,
+    },
     /// Raw SQL.
     RawSql(String),
 }
@@ -125,6 +143,28 @@ impl Expr {
         }
     }
 
+    /// A `NOT` expression.
+    pub fn negate(expr: Expr) -> Expr {
+        Expr::Not(Box::new(expr))
+    }
+
+    /// An `IS NULL` (or `IS NOT NULL`, if `negated`) test.
+    pub fn is_null(expr: Expr, negated: bool) -> Expr {
+        Expr::IsNull {
+            expr: Box::new(expr),
+            negated,
+        }
+    }
+
+    /// An `IN (..)` (or `NOT IN (..)`, if `negated`) test.
+    pub fn in_list(expr: Expr, list: Vec<Expr>, negated: bool) -> Expr {
+        Expr::InList {
+            expr: Box::new(expr),
+            list,
+            negated,
+        }
+    }
+
     /// A function call.
     pub fn func(name: &'static str, args: Vec<Expr>) -> Expr {
         Expr::Func { name, args }
@@ -256,6 +296,29 @@ impl Expr {
 }
 
 impl Expr {
+    /// Is this an operator expression, which might need to be wrapped in
+    /// parentheses when used as an operand?
+    fn is_operator(&self) -> bool {
+        matches!(
+            self,
+            Expr::BinOp { .. }
+                | Expr::Not(_)
+                | Expr::IsNull { .. }
+                | Expr::InList { .. }
+        )
+    }
+
+    /// Return a pretty-printed version of `self`, for use as an operand of
+    /// another operator. We don't try to be clever about precedence; we just
+    /// wrap all nested operators in parentheses.
+    fn to_operand_doc(&self) -> RcDoc<'static, ()> {
+        if self.is_operator() {
+            parens(self.to_doc())
+        } else {
+            self.to_doc()
+        }
+    }
+
     /// Return a pretty-printed version of `self``.
     pub fn to_doc(&self) -> RcDoc<'static, ()> {
         match self {
@@ -263,11 +326,11 @@ impl Expr {
             Expr::Var(ident) => RcDoc::as_string(ident),
             // Canonical multi-line format for a binop is "LHS\nOP RHS".
             Expr::BinOp { lhs, op, rhs } => RcDoc::concat(vec![
-                lhs.to_doc(),
+                lhs.to_operand_doc(),
                 RcDoc::line(),
                 op.to_doc(),
                 RcDoc::space(),
-                rhs.to_doc(),
+                rhs.to_operand_doc(),
             ])
             .group(),
             Expr::Func { name, args } => {
@@ -364,6 +427,30 @@ impl Expr {
                 RcDoc::space(),
                 time_zone.to_doc(),
             ])),
+            Expr::Not(expr) => RcDoc::concat(vec![
+                RcDoc::as_string("NOT"),
+                RcDoc::space(),
+                expr.to_operand_doc(),
+            ])
+            .group(),
+            Expr::IsNull { expr, negated } => RcDoc::concat(vec![
+                expr.to_operand_doc(),
+                RcDoc::space(),
+                RcDoc::as_string(if *negated { "IS NOT NULL" } else { "IS NULL" }),
+            ])
+            .group(),
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => RcDoc::concat(vec![
+                expr.to_operand_doc(),
+                RcDoc::space(),
+                RcDoc::as_string(if *negated { "NOT IN" } else { "IN" }),
+                RcDoc::space(),
+                parens(comma_sep_list(list.iter().map(|e| e.to_doc().group()))),
+            ])
+            .group(),
             Expr::RawSql(s) => RcDoc::as_string(s),
         }
     }
@@ -418,11 +505,26 @@ impl fmt::Display for SimpleValue {
 /// A binary operator in Trino SQL. We only include operators that we actually
 /// use.
 ///
-/// TODO: Handle precedence when quoting, once it matters.
+/// We don't track precedence. Instead, [`Expr::to_doc`] wraps any operator
+/// expression used as an operand in parentheses.
 #[derive(Clone, Debug, PartialEq)]
 pub enum BinOp {
     /// The `=` operator.
     Eq,
+    /// The `<>` operator.
+    NotEq,
+    /// The `<` operator.
+    Lt,
+    /// The `<=` operator.
+    LtEq,
+    /// The `>` operator.
+    Gt,
+    /// The `>=` operator.
+    GtEq,
+    /// The `AND` operator.
+    And,
+    /// The `OR` operator.
+    Or,
 }
 
 impl BinOp {
@@ -436,6 +538,39 @@ impl fmt::Display for BinOp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             BinOp::Eq => write!(f, "="),
+            BinOp::NotEq => write!(f, "<>"),
+            BinOp::Lt => write!(f, "<"),
+            BinOp::LtEq => write!(f, "<="),
+            BinOp::Gt => write!(f, ">"),
+            BinOp::GtEq => write!(f, ">="),
+            BinOp::And => write!(f, "AND"),
+            BinOp::Or => write!(f, "OR"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_operators_are_parenthesized() {
+        let expr = Expr::binop(
+            Expr::binop(Expr::Var(ident("a")), BinOp::Gt, Expr::int(1)),
+            BinOp::Or,
+            Expr::negate(Expr::in_list(
+                Expr::Var(ident("b")),
+                vec![Expr::str("x"), Expr::str("y")],
+                false,
+            )),
+        );
+        assert_eq!(
+            expr.to_string(),
+            r#"("a" > 1) OR (NOT ("b" IN ('x', 'y')))"#
+        );
+        assert_eq!(
+            Expr::is_null(Expr::Var(ident("c")), true).to_string(),
+            r#""c" IS NOT NULL"#
+        );
+    }
+}
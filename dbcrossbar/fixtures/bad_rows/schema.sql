@@ -0,0 +1,4 @@
+CREATE TABLE people (
+    id int NOT NULL,
+    name text NOT NULL
+);
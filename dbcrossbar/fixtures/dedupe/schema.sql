@@ -0,0 +1,5 @@
+CREATE TABLE vendor_rows (
+    id int NOT NULL,
+    name text,
+    updated_at timestamp with time zone
+);
@@ -0,0 +1,7 @@
+CREATE TABLE customers (
+    id int NOT NULL,
+    email text NOT NULL,
+    phone text,
+    birthday date,
+    notes text
+);
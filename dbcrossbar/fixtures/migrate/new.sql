@@ -0,0 +1,5 @@
+CREATE TABLE example (
+    id bigint NOT NULL,
+    name text,
+    created_at timestamp with time zone
+);
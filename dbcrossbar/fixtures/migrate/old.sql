@@ -0,0 +1,5 @@
+CREATE TABLE example (
+    id int NOT NULL,
+    name text NOT NULL,
+    legacy text
+);
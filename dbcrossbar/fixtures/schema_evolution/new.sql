@@ -0,0 +1,5 @@
+CREATE TABLE example (
+    id bigint NOT NULL,
+    score double precision,
+    name text
+);
@@ -0,0 +1,4 @@
+CREATE TABLE example (
+    id int NOT NULL,
+    name text
+);
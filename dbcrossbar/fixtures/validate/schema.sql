@@ -0,0 +1,6 @@
+CREATE TABLE people (
+    id int NOT NULL,
+    name text NOT NULL,
+    born date,
+    score numeric
+);
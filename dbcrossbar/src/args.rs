@@ -2,8 +2,14 @@
 
 use std::{fmt, marker::PhantomData};
 
+use crate::bad_rows::BadRows;
 use crate::common::*;
 use crate::data_streams::DataFormat;
+use crate::dedupe::Dedupe;
+use crate::filter::Filter;
+use crate::order_by::OrderBy;
+use crate::partition::PartitionBy;
+use crate::sampling::Sample;
 use crate::separator::Separator;
 
 /// Trait used to add new methods to `EnumSet`.
@@ -64,6 +70,12 @@ pub struct SharedArguments<S: ArgumentState> {
     /// How many streams should we process at once?
     max_streams: usize,
 
+    /// How should we handle rows that can't be converted?
+    bad_rows: BadRows,
+
+    /// Split source files into byte ranges of at least this many bytes.
+    split_files: Option<u64>,
+
     /// We need to include a reference to `ArgumentState` somewhere, so use a
     /// 0-byte phantom value.
     _phantom: PhantomData<S>,
@@ -89,6 +101,8 @@ impl<S: ArgumentState> SharedArguments<S> {
             schema: f(self.schema.clone()),
             temporary_storage: self.temporary_storage.clone(),
             max_streams: self.max_streams,
+            bad_rows: self.bad_rows.clone(),
+            split_files: self.split_files,
             _phantom: PhantomData,
         }
     }
@@ -106,10 +120,26 @@ impl SharedArguments<Unverified> {
             schema,
             temporary_storage,
             max_streams,
+            bad_rows: BadRows::default(),
+            split_files: None,
             _phantom: PhantomData,
         }
     }
 
+    /// Skip rows which can't be converted, as described by `bad_rows`, instead
+    /// of failing.
+    pub(crate) fn with_bad_rows(mut self, bad_rows: BadRows) -> Self {
+        self.bad_rows = bad_rows;
+        self
+    }
+
+    /// Split large source files into byte ranges of at least `split_files`
+    /// bytes, so that we can read them in parallel.
+    pub(crate) fn with_split_files(mut self, split_files: Option<u64>) -> Self {
+        self.split_files = split_files;
+        self
+    }
+
     /// Verify that this structure only contains supported arguments. This uses
     /// the [type state][] pattern to keep track of whether our arguments have
     /// been verified to be supported.
@@ -121,6 +151,8 @@ impl SharedArguments<Unverified> {
             schema: self.schema,
             temporary_storage: self.temporary_storage,
             max_streams: self.max_streams,
+            bad_rows: self.bad_rows,
+            split_files: self.split_files,
             _phantom: PhantomData,
         })
     }
@@ -137,6 +169,16 @@ impl SharedArguments<Verified> {
     pub fn temporary_storage(&self) -> &TemporaryStorage {
         &self.temporary_storage
     }
+
+    /// Get our policy for handling rows which can't be converted.
+    pub(crate) fn bad_rows(&self) -> &BadRows {
+        &self.bad_rows
+    }
+
+    /// The minimum size of byte ranges to split source files into, if any.
+    pub(crate) fn split_files(&self) -> Option<u64> {
+        self.split_files
+    }
 }
 
 /// What `SourceArguments` features are supported by a given driver?
@@ -148,6 +190,18 @@ pub enum SourceArgumentsFeatures {
     Format,
     /// Accepts `--where` to specify a database-specific `WHERE` clause.
     WhereClause,
+    /// Accepts `--filter` to specify a portable filter expression.
+    Filter,
+    /// Accepts `--dedupe-on` and `--keep` to remove rows with duplicate keys.
+    Dedupe,
+    /// Accepts `--order-by` to sort rows.
+    OrderBy,
+    /// Accepts `--limit` to specify a maximum number of rows.
+    Limit,
+    /// Accepts `--sample` to specify a fraction of rows to copy.
+    Sample,
+    /// Accepts `--sample-seed` to make `--sample` repeatable.
+    SampleSeed,
 }
 
 impl fmt::Display for DisplayEnumSet<SourceArgumentsFeatures> {
@@ -162,6 +216,24 @@ impl fmt::Display for DisplayEnumSet<SourceArgumentsFeatures> {
         if self.0.contains(SourceArgumentsFeatures::WhereClause) {
             write!(f, "{}--where=$SQL_EXPR", sep.display())?;
         }
+        if self.0.contains(SourceArgumentsFeatures::Filter) {
+            write!(f, "{}--filter=$EXPR", sep.display())?;
+        }
+        if self.0.contains(SourceArgumentsFeatures::Dedupe) {
+            write!(f, "{}--dedupe-on=$COLS --keep=$KEEP", sep.display())?;
+        }
+        if self.0.contains(SourceArgumentsFeatures::OrderBy) {
+            write!(f, "{}--order-by=$COLS", sep.display())?;
+        }
+        if self.0.contains(SourceArgumentsFeatures::Limit) {
+            write!(f, "{}--limit=$N", sep.display())?;
+        }
+        if self.0.contains(SourceArgumentsFeatures::Sample) {
+            write!(f, "{}--sample=$FRACTION", sep.display())?;
+        }
+        if self.0.contains(SourceArgumentsFeatures::SampleSeed) {
+            write!(f, "{}--sample-seed=$SEED", sep.display())?;
+        }
         Ok(())
     }
 }
@@ -178,6 +250,25 @@ pub struct SourceArguments<ArgumentState> {
     /// A `WHERE` clause for this query.
     where_clause: Option<String>,
 
+    /// A portable filter expression for this query.
+    filter: Option<Filter>,
+
+    /// Only return one row for each key.
+    dedupe: Option<Dedupe>,
+
+    /// The order in which to return rows.
+    order_by: Option<OrderBy>,
+
+    /// The maximum number of rows to return.
+    limit: Option<u64>,
+
+    /// A random sample of rows to return.
+    sample: Option<Sample>,
+
+    /// A `--filter` which will be applied after reading, but which drivers
+    /// may use to skip Hive-style partition directories.
+    partition_filter: Option<Filter>,
+
     /// We need to include a reference to `ArgumentState` somewhere, so use a
     /// 0-byte phantom value.
     _phantom: PhantomData<ArgumentState>,
@@ -195,10 +286,57 @@ impl SourceArguments<Unverified> {
             driver_args,
             format,
             where_clause,
+            filter: None,
+            dedupe: None,
+            order_by: None,
+            limit: None,
+            sample: None,
+            partition_filter: None,
             _phantom: PhantomData,
         }
     }
 
+    /// Only return rows matching `filter`.
+    pub(crate) fn with_filter(mut self, filter: Option<Filter>) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Only return one row for each key in `dedupe`.
+    pub(crate) fn with_dedupe(mut self, dedupe: Option<Dedupe>) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Return rows in the order specified by `order_by`.
+    pub(crate) fn with_order_by(mut self, order_by: Option<OrderBy>) -> Self {
+        self.order_by = order_by;
+        self
+    }
+
+    /// Only return up to `limit` rows.
+    pub(crate) fn with_limit(mut self, limit: Option<u64>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Only return a random sample of rows.
+    pub(crate) fn with_sample(mut self, sample: Option<Sample>) -> Self {
+        self.sample = sample;
+        self
+    }
+
+    /// Allow drivers to skip partition directories which can't match
+    /// `partition_filter`. Drivers which don't understand partitions may
+    /// ignore this, because the caller will apply the filter to every row.
+    pub(crate) fn with_partition_filter(
+        mut self,
+        partition_filter: Option<Filter>,
+    ) -> Self {
+        self.partition_filter = partition_filter;
+        self
+    }
+
     /// Construct a new `SourceArguments` with typical values for a temporary
     /// storage location.
     pub fn for_temporary() -> Self {
@@ -246,10 +384,60 @@ impl SourceArguments<Unverified> {
         {
             return Err(format_err!("this data source does not support --where"));
         }
+        if !features
+            .source_args
+            .contains(SourceArgumentsFeatures::Filter)
+            && self.filter.is_some()
+        {
+            return Err(format_err!("this data source does not support --filter"));
+        }
+        if !features
+            .source_args
+            .contains(SourceArgumentsFeatures::Dedupe)
+            && self.dedupe.is_some()
+        {
+            return Err(format_err!("this data source does not support --dedupe-on"));
+        }
+        if !features
+            .source_args
+            .contains(SourceArgumentsFeatures::OrderBy)
+            && self.order_by.is_some()
+        {
+            return Err(format_err!("this data source does not support --order-by"));
+        }
+        if !features
+            .source_args
+            .contains(SourceArgumentsFeatures::Limit)
+            && self.limit.is_some()
+        {
+            return Err(format_err!("this data source does not support --limit"));
+        }
+        if !features
+            .source_args
+            .contains(SourceArgumentsFeatures::Sample)
+            && self.sample.is_some()
+        {
+            return Err(format_err!("this data source does not support --sample"));
+        }
+        if !features
+            .source_args
+            .contains(SourceArgumentsFeatures::SampleSeed)
+            && self.sample.is_some_and(|s| s.seed().is_some())
+        {
+            return Err(format_err!(
+                "this data source does not support --sample-seed"
+            ));
+        }
         Ok(SourceArguments {
             driver_args: self.driver_args,
             format: self.format,
             where_clause: self.where_clause,
+            filter: self.filter,
+            dedupe: self.dedupe,
+            order_by: self.order_by,
+            limit: self.limit,
+            sample: self.sample,
+            partition_filter: self.partition_filter,
             _phantom: PhantomData,
         })
     }
@@ -271,6 +459,36 @@ impl SourceArguments<Verified> {
     pub fn where_clause(&self) -> Option<&str> {
         self.where_clause.as_ref().map(|s| &s[..])
     }
+
+    /// A portable filter expression for this query.
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+
+    /// How to remove rows with duplicate keys.
+    pub fn dedupe(&self) -> Option<&Dedupe> {
+        self.dedupe.as_ref()
+    }
+
+    /// The order in which to return rows.
+    pub fn order_by(&self) -> Option<&OrderBy> {
+        self.order_by.as_ref()
+    }
+
+    /// The maximum number of rows to return.
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    /// A random sample of rows to return.
+    pub fn sample(&self) -> Option<&Sample> {
+        self.sample.as_ref()
+    }
+
+    /// A filter which may be used to skip partition directories.
+    pub(crate) fn partition_filter(&self) -> Option<&Filter> {
+        self.partition_filter.as_ref()
+    }
 }
 
 /// What `DestinationArguments` features are supported by a given driver?
@@ -280,6 +498,10 @@ pub enum DestinationArgumentsFeatures {
     DriverArgs,
     /// Accepts `--format` to specify a file format.
     Format,
+    /// Accepts `--allow-schema-evolution` when appending.
+    SchemaEvolution,
+    /// Accepts `--partition-by` to write Hive-style partition directories.
+    PartitionBy,
 }
 
 impl fmt::Display for DisplayEnumSet<DestinationArgumentsFeatures> {
@@ -291,6 +513,15 @@ impl fmt::Display for DisplayEnumSet<DestinationArgumentsFeatures> {
         if self.0.contains(DestinationArgumentsFeatures::Format) {
             write!(f, "{}--format=$FORMAT", sep.display())?;
         }
+        if self
+            .0
+            .contains(DestinationArgumentsFeatures::SchemaEvolution)
+        {
+            write!(f, "{}--allow-schema-evolution", sep.display())?;
+        }
+        if self.0.contains(DestinationArgumentsFeatures::PartitionBy) {
+            write!(f, "{}--partition-by=$COLS", sep.display())?;
+        }
         Ok(())
     }
 }
@@ -306,6 +537,12 @@ pub struct DestinationArguments<ArgumentState> {
     /// The format we're converting to, if applicable.
     format: Option<DataFormat>,
 
+    /// Should we add new columns and widen column types when appending?
+    allow_schema_evolution: bool,
+
+    /// Columns to use for Hive-style partition directories.
+    partition_by: Option<PartitionBy>,
+
     /// We need to include a reference to `ArgumentState` somewhere, so use a
     /// 0-byte phantom value.
     _phantom: PhantomData<ArgumentState>,
@@ -323,10 +560,28 @@ impl DestinationArguments<Unverified> {
             driver_args,
             format,
             if_exists,
+            allow_schema_evolution: false,
+            partition_by: None,
             _phantom: PhantomData,
         }
     }
 
+    /// Allow the destination to add columns and widen column types when
+    /// appending.
+    pub fn with_schema_evolution(mut self, allow_schema_evolution: bool) -> Self {
+        self.allow_schema_evolution = allow_schema_evolution;
+        self
+    }
+
+    /// Write rows into Hive-style partition directories using `partition_by`.
+    pub(crate) fn with_partition_by(
+        mut self,
+        partition_by: Option<PartitionBy>,
+    ) -> Self {
+        self.partition_by = partition_by;
+        self
+    }
+
     /// Construct a new `DestinationArguments` with typical values for a
     /// temporary storage location.
     pub fn for_temporary() -> Self {
@@ -370,10 +625,36 @@ impl DestinationArguments<Unverified> {
             ));
         }
         self.if_exists.verify(features.dest_if_exists)?;
+        if self.allow_schema_evolution {
+            if !features
+                .dest_args
+                .contains(DestinationArgumentsFeatures::SchemaEvolution)
+            {
+                return Err(format_err!(
+                    "this data destination does not support --allow-schema-evolution"
+                ));
+            }
+            if self.if_exists != IfExists::Append {
+                return Err(format_err!(
+                    "--allow-schema-evolution requires --if-exists=append"
+                ));
+            }
+        }
+        if !features
+            .dest_args
+            .contains(DestinationArgumentsFeatures::PartitionBy)
+            && self.partition_by.is_some()
+        {
+            return Err(format_err!(
+                "this data destination does not support --partition-by"
+            ));
+        }
         Ok(DestinationArguments {
             driver_args: self.driver_args,
             if_exists: self.if_exists,
             format: self.format,
+            allow_schema_evolution: self.allow_schema_evolution,
+            partition_by: self.partition_by,
             _phantom: PhantomData,
         })
     }
@@ -395,4 +676,14 @@ impl DestinationArguments<Verified> {
     pub fn if_exists(&self) -> &IfExists {
         &self.if_exists
     }
+
+    /// Should we add new columns and widen column types when appending?
+    pub fn allow_schema_evolution(&self) -> bool {
+        self.allow_schema_evolution
+    }
+
+    /// Columns to use for Hive-style partition directories.
+    pub fn partition_by(&self) -> Option<&PartitionBy> {
+        self.partition_by.as_ref()
+    }
 }
@@ -0,0 +1,301 @@
+//! Support for `--max-bad-rows` and `--rejects`.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use csv::StringRecord;
+use futures::future;
+
+use crate::common::*;
+use crate::schema::{Column, DataType};
+use crate::tokio_glue::bytes_channel;
+use crate::transform::spawn_sync_transform;
+use crate::validate::validate_cell;
+
+/// Keeps track of rows which could not be converted, and optionally sends them
+/// to a "rejects" output.
+///
+/// This is cheap to clone, and all clones share the same state. The default
+/// value does not tolerate any bad rows.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BadRows {
+    inner: Arc<BadRowsInner>,
+}
+
+/// Shared state for `BadRows`.
+#[derive(Debug, Default)]
+struct BadRowsInner {
+    /// The maximum number of bad rows we allow before failing.
+    max_bad_rows: u64,
+    /// The number of bad rows we've seen so far.
+    count: AtomicU64,
+    /// Where to send rejected rows, as CSV data. This will be set to `None`
+    /// by [`BadRows::close`].
+    rejects: Mutex<Option<mpsc::Sender<Result<BytesMut>>>>,
+    /// Are we masking any columns? If so, rows rejected before masking must
+    /// not be sent to `rejects`.
+    masking: bool,
+}
+
+impl BadRows {
+    /// Allow up to `max_bad_rows` bad rows, and discard them.
+    pub(crate) fn new(max_bad_rows: u64) -> Self {
+        Self {
+            inner: Arc::new(BadRowsInner {
+                max_bad_rows,
+                ..BadRowsInner::default()
+            }),
+        }
+    }
+
+    /// Allow up to `max_bad_rows` bad rows, and return a CSV stream containing
+    /// the rejected rows. This stream matches [`BadRows::rejects_schema`], and
+    /// it will end once [`BadRows::close`] is called.
+    ///
+    /// If `masking` is true, rejected rows must be masked before they are
+    /// diverted. See [`BadRows::check_can_divert_unmasked_rows`].
+    pub(crate) fn with_rejects(
+        max_bad_rows: u64,
+        masking: bool,
+    ) -> Result<(Self, CsvStream)> {
+        let (sender, receiver) = bytes_channel(BUFFER_SIZE);
+        let bad_rows = Self {
+            inner: Arc::new(BadRowsInner {
+                max_bad_rows,
+                count: AtomicU64::new(0),
+                rejects: Mutex::new(Some(sender)),
+                masking,
+            }),
+        };
+        let header = csv_line(REJECTS_COLUMNS.iter().copied())?;
+        let data = stream::once(async { Ok(header) }).chain(receiver).boxed();
+        let stream = CsvStream {
+            name: "rejects".to_owned(),
+            data,
+        };
+        Ok((bad_rows, stream))
+    }
+
+    /// The schema of the rejected rows written by [`BadRows::with_rejects`].
+    pub(crate) fn rejects_schema(table_name: &str) -> Result<Schema> {
+        let column = |name: &str, data_type: DataType, is_nullable: bool| Column {
+            name: name.to_owned(),
+            is_nullable,
+            data_type,
+            comment: None,
+        };
+        Schema::from_table(Table {
+            name: table_name.to_owned(),
+            columns: vec![
+                column(REJECTS_COLUMNS[0], DataType::Text, false),
+                column(REJECTS_COLUMNS[1], DataType::Int64, true),
+                column(REJECTS_COLUMNS[2], DataType::Text, false),
+                column(REJECTS_COLUMNS[3], DataType::Text, false),
+            ],
+        })
+    }
+
+    /// Should we try to recover from bad rows at all? Converters may use this
+    /// to skip extra work when we can't tolerate any bad rows.
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.inner.max_bad_rows > 0
+    }
+
+    /// Fail if rows which haven't been masked yet can't be diverted without
+    /// leaking masked columns to our rejects output. Converters which divert
+    /// rows before masking should call this before reading any data.
+    pub(crate) fn check_can_divert_unmasked_rows(&self) -> Result<()> {
+        let has_rejects = self.inner.rejects.lock().expect("lock poisoned").is_some();
+        if self.inner.masking && has_rejects {
+            return Err(format_err!(
+                "cannot use --rejects with --mask or --mask-config when reading \
+                 JSON Lines, because rejected rows would not be masked"
+            ));
+        }
+        Ok(())
+    }
+
+    /// How many bad rows have we seen?
+    pub(crate) fn count(&self) -> u64 {
+        self.inner.count.load(Ordering::SeqCst)
+    }
+
+    /// Record a bad row. `data` should contain the original row as text, and
+    /// `line` should be the line number in the stream, if known.
+    ///
+    /// If we're allowed to skip this row, return `Ok(())`. Otherwise, return
+    /// `err` so that the caller can fail as usual.
+    ///
+    /// This may block while writing to our rejects output, so it should only
+    /// be called from synchronous code running in a background thread.
+    pub(crate) fn divert(
+        &self,
+        stream_name: &str,
+        line: Option<u64>,
+        data: &str,
+        err: Error,
+    ) -> Result<()> {
+        let max_bad_rows = self.inner.max_bad_rows;
+        if max_bad_rows == 0 {
+            return Err(err);
+        }
+        let count = self.inner.count.fetch_add(1, Ordering::SeqCst) + 1;
+        if count > max_bad_rows {
+            return Err(err.context(format!(
+                "found more than {} bad rows (see --max-bad-rows)",
+                max_bad_rows,
+            )));
+        }
+        match line {
+            Some(line) => warn!(
+                "skipping bad row in {} at line {}: {:#}",
+                stream_name, line, err
+            ),
+            None => warn!("skipping bad row in {}: {:#}", stream_name, err),
+        }
+
+        let sender = self
+            .inner
+            .rejects
+            .lock()
+            .expect("lock poisoned")
+            .as_ref()
+            .cloned();
+        if let Some(sender) = sender {
+            let line = line.map(|l| l.to_string()).unwrap_or_default();
+            let error = format!("{:#}", err);
+            let record = csv_line([stream_name, &line, data, &error])?;
+            sender
+                .blocking_send(Ok(record))
+                .map_err(|_| format_err!("could not write to rejects output"))?;
+        }
+        Ok(())
+    }
+
+    /// Close our rejects output, if any. Call this once all data has been
+    /// copied.
+    pub(crate) fn close(&self) {
+        self.inner.rejects.lock().expect("lock poisoned").take();
+    }
+}
+
+/// Check each row of `data` against `schema`, and pass any invalid rows to
+/// `bad_rows`.
+///
+/// This allows us to catch most bad rows before they reach a destination
+/// driver, even if that driver has no special support for bad rows.
+pub(crate) fn divert_invalid_rows(
+    ctx: Context,
+    schema: Schema,
+    bad_rows: BadRows,
+    data: BoxStream<CsvStream>,
+) -> BoxStream<CsvStream> {
+    data.and_then(move |stream| {
+        let schema = schema.clone();
+        let bad_rows = bad_rows.clone();
+        let name = stream.name.clone();
+        let result = spawn_sync_transform(
+            ctx.clone(),
+            "divert_invalid_rows".to_owned(),
+            stream.data,
+            move |_ctx, rdr, wtr| copy_valid_rows(&name, &schema, &bad_rows, rdr, wtr),
+        )
+        .map(|data| CsvStream {
+            name: stream.name,
+            data,
+        });
+        future::ready(result)
+    })
+    .boxed()
+}
+
+/// Synchronously copy valid CSV rows from `rdr` to `wtr`, passing invalid
+/// rows to `bad_rows`.
+fn copy_valid_rows(
+    name: &str,
+    schema: &Schema,
+    bad_rows: &BadRows,
+    rdr: Box<dyn Read>,
+    wtr: Box<dyn Write>,
+) -> Result<()> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+    wtr.write_record(rdr.headers()?)?;
+    for row in rdr.records() {
+        let row = row?;
+        match check_row(schema, &row) {
+            Ok(()) => wtr.write_record(&row)?,
+            Err(err) => {
+                let line = row.position().map(|p| p.line());
+                bad_rows.divert(name, line, &record_to_string(&row)?, err)?;
+            }
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Check whether `row` is valid for `schema`.
+fn check_row(schema: &Schema, row: &StringRecord) -> Result<()> {
+    let columns = &schema.table.columns;
+    if row.len() != columns.len() {
+        return Err(format_err!(
+            "expected {} columns, found {}",
+            columns.len(),
+            row.len(),
+        ));
+    }
+    for (cell, col) in row.iter().zip(columns) {
+        validate_cell(schema, col, cell)
+            .with_context(|| format!("invalid column {:?}", col.name))?;
+    }
+    Ok(())
+}
+
+/// The columns of our rejects output.
+const REJECTS_COLUMNS: &[&str] = &["stream", "line", "data", "error"];
+
+/// Convert a CSV record back into a line of text, without a trailing newline.
+pub(crate) fn record_to_string(record: &StringRecord) -> Result<String> {
+    let line = csv_line(record.iter())?;
+    Ok(String::from_utf8_lossy(&line)
+        .trim_end_matches('\n')
+        .to_owned())
+}
+
+/// Write `fields` as a CSV record, with a trailing newline.
+fn csv_line<'a>(fields: impl IntoIterator<Item = &'a str>) -> Result<BytesMut> {
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    wtr.write_record(fields)?;
+    let bytes = wtr
+        .into_inner()
+        .map_err(|_| format_err!("could not serialize CSV record"))?;
+    Ok(BytesMut::from(&bytes[..]))
+}
+
+#[test]
+fn divert_until_limit() {
+    let bad_rows = BadRows::new(2);
+    assert!(bad_rows.is_enabled());
+    for _ in 0..2 {
+        bad_rows
+            .divert("s", Some(2), "a,b", format_err!("bad"))
+            .unwrap();
+    }
+    assert!(bad_rows
+        .divert("s", Some(3), "a,b", format_err!("bad"))
+        .is_err());
+    assert_eq!(bad_rows.count(), 3);
+
+    let default = BadRows::default();
+    assert!(!default.is_enabled());
+    assert!(default.divert("s", None, "", format_err!("bad")).is_err());
+}
+
+#[test]
+fn record_to_string_quotes_fields() {
+    let record = StringRecord::from(vec!["a", "b,c", "d\"e"]);
+    assert_eq!(record_to_string(&record).unwrap(), r#"a,"b,c","d""e""#);
+}
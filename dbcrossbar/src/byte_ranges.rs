@@ -0,0 +1,432 @@
+//! Splitting large files into byte ranges which can be read in parallel.
+//!
+//! JSON Lines files can be split at any newline. CSV files are trickier,
+//! because quoted fields may contain newlines. Near each split point, we try
+//! both possible quote states, and we keep the one which produces records
+//! with the same number of fields as the header. If we can't tell, we don't
+//! split there.
+//!
+//! As we read each CSV range, we also check that it ends outside of any
+//! quoted field. The first range starts at the beginning of the file, so if
+//! every range passes this check, every range started on a record boundary.
+
+use async_trait::async_trait;
+use std::{ops::Range, sync::Arc};
+
+use crate::common::*;
+use crate::data_streams::DataStream;
+use crate::DataFormat;
+
+/// How much data should we read when looking for a record boundary?
+const RESYNC_WINDOW_SIZE: u64 = 1024 * 1024;
+
+/// A file or object which supports reading byte ranges.
+#[async_trait]
+pub(crate) trait ReadRange: Send + Sync + 'static {
+    /// Read the bytes in `range`.
+    async fn read_range(&self, range: Range<u64>) -> Result<BoxStream<BytesMut>>;
+}
+
+/// Split `file`, which is `len` bytes long, into at most `max_streams`
+/// streams of at least `min_range_size` bytes, each starting on a record
+/// boundary. Each CSV stream has its own copy of the header.
+///
+/// If `min_range_size` is `None`, or the file is too small to split, or it
+/// uses another format, we return a single stream named `name`.
+pub(crate) async fn split_into_ranges(
+    name: &str,
+    format: &DataFormat,
+    len: u64,
+    max_streams: usize,
+    min_range_size: Option<u64>,
+    file: Arc<dyn ReadRange>,
+) -> Result<Vec<DataStream>> {
+    let range_count = match min_range_size {
+        Some(min_range_size) => usize::try_from(len / min_range_size.max(1))
+            .unwrap_or(usize::MAX)
+            .clamp(1, max_streams.max(1)),
+        None => 1,
+    };
+    split_into_n_ranges(name, format, len, range_count, RESYNC_WINDOW_SIZE, file).await
+}
+
+/// Split `file` into at most `range_count` streams, reading `window_size`
+/// bytes at each split point to find a record boundary.
+async fn split_into_n_ranges(
+    name: &str,
+    format: &DataFormat,
+    len: u64,
+    range_count: usize,
+    window_size: u64,
+    file: Arc<dyn ReadRange>,
+) -> Result<Vec<DataStream>> {
+    let (header, starts) = match format {
+        DataFormat::Csv | DataFormat::JsonLines if range_count > 1 => {
+            find_range_starts(name, format, len, range_count, window_size, &*file)
+                .await?
+        }
+        _ => (None, vec![]),
+    };
+    if starts.is_empty() {
+        let data = file.read_range(0..len).await?;
+        return Ok(vec![DataStream {
+            name: name.to_owned(),
+            format: format.to_owned(),
+            data,
+        }]);
+    }
+    debug!("splitting {} into {} ranges", name, starts.len() + 1);
+
+    let mut bounds = vec![0];
+    bounds.extend(starts);
+    bounds.push(len);
+    let mut data_streams = vec![];
+    for (i, range) in bounds.windows(2).enumerate() {
+        let range = range[0]..range[1];
+        let is_last = range.end == len;
+        let range_name = format!("{}_{:04}", name, i + 1);
+        trace!("reading {} from bytes {:?}", range_name, range);
+        let mut data = file.read_range(range.clone()).await?;
+        if let DataFormat::Csv = format {
+            if !is_last {
+                data = check_csv_range_ends_at_record(range_name.clone(), range, data);
+            }
+            if let Some(header) = header.as_ref().filter(|_| i > 0) {
+                data = box_stream_once(Ok(header.clone())).chain(data).boxed();
+            }
+        }
+        data_streams.push(DataStream {
+            name: range_name,
+            format: format.to_owned(),
+            data,
+        });
+    }
+    Ok(data_streams)
+}
+
+/// Find where each range after the first should start. For CSV files, also
+/// return the header, which we'll need to add to each range.
+async fn find_range_starts(
+    name: &str,
+    format: &DataFormat,
+    len: u64,
+    range_count: usize,
+    window_size: u64,
+    file: &dyn ReadRange,
+) -> Result<(Option<BytesMut>, Vec<u64>)> {
+    // Find our CSV header and count its fields.
+    let mut header = None;
+    let mut header_fields = 0;
+    if let DataFormat::Csv = format {
+        let window = read_bytes(file, 0..len.min(window_size)).await?;
+        match first_csv_record(&window) {
+            Some((end, fields)) => {
+                header = Some(BytesMut::from(&window[..end]));
+                header_fields = fields;
+            }
+            None => {
+                debug!("could not find header in {}, so not splitting it", name);
+                return Ok((None, vec![]));
+            }
+        }
+    }
+    let header_len = header.as_ref().map_or(0, |header| header.len() as u64);
+
+    let mut starts = vec![];
+    for i in 1..range_count {
+        // Read a window beginning just before our split point, so that we
+        // can tell if the split point is already at the start of a line.
+        let split_point =
+            u64::try_from(u128::from(len) * i as u128 / range_count as u128)
+                .expect("split point should be less than len");
+        let window_start = split_point.saturating_sub(1);
+        let window =
+            read_bytes(file, window_start..len.min(split_point + window_size)).await?;
+        let offset = match format {
+            DataFormat::Csv => find_csv_record_start(&window, header_fields),
+            _ => window.iter().position(|&b| b == b'\n').map(|pos| pos + 1),
+        };
+        match offset.map(|offset| window_start + offset as u64) {
+            Some(start)
+                if start > header_len
+                    && start > starts.last().copied().unwrap_or(0)
+                    && start < len =>
+            {
+                starts.push(start);
+            }
+            Some(_) => {}
+            None => debug!(
+                "could not find a record boundary near byte {} of {}",
+                split_point, name,
+            ),
+        }
+    }
+    Ok((header, starts))
+}
+
+/// Read `range` from `file` into memory.
+async fn read_bytes(file: &dyn ReadRange, range: Range<u64>) -> Result<BytesMut> {
+    file.read_range(range).await?.try_concat().await
+}
+
+/// Wrap `data`, the CSV data in `range`, and return an error if it doesn't
+/// end on a record boundary.
+fn check_csv_range_ends_at_record(
+    name: String,
+    range: Range<u64>,
+    data: BoxStream<BytesMut>,
+) -> BoxStream<BytesMut> {
+    stream::try_unfold(
+        (data, CsvScanner::new(false)),
+        move |(mut data, mut scanner)| {
+            let name = name.clone();
+            let range = range.clone();
+            async move {
+                match data.try_next().await? {
+                    Some(bytes) => {
+                        for &b in bytes.iter() {
+                            scanner.scan(b);
+                        }
+                        Ok(Some((bytes, (data, scanner))))
+                    }
+                    None if scanner.at_record_start() => Ok(None),
+                    None => Err(format_err!(
+                        "split {} at byte {}, but this was inside a CSV record (try a larger --split-files, or leave it out)",
+                        name,
+                        range.end,
+                    )),
+                }
+            }
+        },
+    )
+    .boxed()
+}
+
+/// Find the end of the first CSV record in `bytes`, and count its fields.
+fn first_csv_record(bytes: &[u8]) -> Option<(usize, usize)> {
+    let mut scanner = CsvScanner::new(false);
+    bytes
+        .iter()
+        .enumerate()
+        .find_map(|(i, &b)| match scanner.scan(b) {
+            Scan::EndOfRecord(fields) => Some((i + 1, fields)),
+            _ => None,
+        })
+}
+
+/// Given a `window` of CSV data, where `window[0]` is the byte just before
+/// our split point, find the offset of the first record starting at or after
+/// our split point. Return `None` if we're not sure.
+fn find_csv_record_start(window: &[u8], expected_fields: usize) -> Option<usize> {
+    let mut found = None;
+    for starts_in_quotes in [false, true] {
+        if let Some(start) = first_line_start(window, starts_in_quotes) {
+            if records_look_valid(&window[start..], expected_fields) {
+                if found.is_some() {
+                    // Both quote states look plausible.
+                    return None;
+                }
+                found = Some(start);
+            }
+        }
+    }
+    found
+}
+
+/// Find the start of the first line in `window` which doesn't begin inside
+/// quotes, assuming that `window` begins inside quotes if `in_quotes` is true.
+fn first_line_start(window: &[u8], mut in_quotes: bool) -> Option<usize> {
+    for (i, &b) in window.iter().enumerate() {
+        match b {
+            b'"' => in_quotes = !in_quotes,
+            b'\n' if !in_quotes => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Do the CSV records at the start of `bytes` look valid? We require at
+/// least one complete record, and we ignore any incomplete record at the end.
+fn records_look_valid(bytes: &[u8], expected_fields: usize) -> bool {
+    let mut scanner = CsvScanner::new(true);
+    let mut records = 0;
+    for &b in bytes {
+        match scanner.scan(b) {
+            Scan::Continue => {}
+            Scan::EndOfRecord(fields) if fields == expected_fields => records += 1,
+            Scan::EndOfRecord(_) | Scan::Invalid => return false,
+        }
+    }
+    records > 0
+}
+
+/// The state of a `CsvScanner`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CsvState {
+    /// At the start of a field.
+    FieldStart,
+    /// Inside an unquoted field.
+    Unquoted,
+    /// Inside a quoted field.
+    Quoted,
+    /// After a `"` in a quoted field, which either ends the field or begins
+    /// an escaped `""`.
+    QuoteInQuoted,
+}
+
+/// The result of scanning a byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scan {
+    /// Keep going.
+    Continue,
+    /// We reached the end of a record with this many fields.
+    EndOfRecord(usize),
+    /// We found a quote in the wrong place.
+    Invalid,
+}
+
+/// A minimal CSV parser which tracks where fields and records end, without
+/// keeping any data.
+#[derive(Clone, Debug)]
+struct CsvScanner {
+    state: CsvState,
+    /// How many fields have we finished in the current record?
+    fields: usize,
+    /// Should we report misplaced quotes? If not, we treat them as data, the
+    /// same way the `csv` crate does.
+    strict: bool,
+}
+
+impl CsvScanner {
+    /// Create a new scanner at the start of a record.
+    fn new(strict: bool) -> Self {
+        Self {
+            state: CsvState::FieldStart,
+            fields: 0,
+            strict,
+        }
+    }
+
+    /// Are we at the start of a record?
+    fn at_record_start(&self) -> bool {
+        self.state == CsvState::FieldStart && self.fields == 0
+    }
+
+    /// Scan the next byte.
+    fn scan(&mut self, b: u8) -> Scan {
+        use CsvState::*;
+        match (self.state, b) {
+            (Quoted, b'"') => self.state = QuoteInQuoted,
+            (Quoted, _) => {}
+            (QuoteInQuoted, b'"') | (FieldStart, b'"') => self.state = Quoted,
+            (_, b',') => {
+                self.fields += 1;
+                self.state = FieldStart;
+            }
+            (_, b'\n') => {
+                let fields = self.fields + 1;
+                self.fields = 0;
+                self.state = FieldStart;
+                return Scan::EndOfRecord(fields);
+            }
+            (QuoteInQuoted, b'\r') => self.state = Unquoted,
+            (QuoteInQuoted, _) | (Unquoted, b'"') if self.strict => {
+                return Scan::Invalid
+            }
+            _ => self.state = Unquoted,
+        }
+        Scan::Continue
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl ReadRange for Vec<u8> {
+    async fn read_range(&self, range: Range<u64>) -> Result<BoxStream<BytesMut>> {
+        let start = usize::try_from(range.start)?;
+        let end = usize::try_from(range.end)?;
+        let bytes = BytesMut::from(&self[start..end]);
+        Ok(box_stream_once(Ok(bytes)))
+    }
+}
+
+#[test]
+fn find_csv_record_starts() {
+    // Outside quotes.
+    assert_eq!(find_csv_record_start(b"1,x\n2,y\n3,z\n", 2), Some(4));
+    assert_eq!(find_csv_record_start(b"\n2,y\n3,z\n", 2), Some(1));
+    // Inside a quoted field containing a newline.
+    assert_eq!(
+        find_csv_record_start(b"b\nc\",1\n\"d\ne\",2\n\"f\",3\n", 2),
+        Some(7)
+    );
+    // Only a partial record.
+    assert_eq!(find_csv_record_start(b"1,x\n2,", 2), None);
+    // Both quote states look plausible.
+    assert_eq!(find_csv_record_start(b"\n\"\n\"\n\"\n\"\n", 1), None);
+}
+
+#[tokio::test]
+async fn split_into_ranges_preserves_records() {
+    let mut csv = b"id,text\n".to_vec();
+    for i in 0..200 {
+        if i % 3 == 0 {
+            csv.extend_from_slice(
+                format!("{},\"multi\nline, \"\"{}\"\"\"\n", i, i).as_bytes(),
+            );
+        } else {
+            csv.extend_from_slice(format!("{},plain {}\n", i, i).as_bytes());
+        }
+    }
+    let len = csv.len() as u64;
+    let file: Arc<dyn ReadRange> = Arc::new(csv.clone());
+
+    let data_streams = split_into_n_ranges("data", &DataFormat::Csv, len, 8, 64, file)
+        .await
+        .unwrap();
+    assert_eq!(data_streams.len(), 8);
+    let mut records = vec![];
+    for data_stream in data_streams {
+        let bytes = read_bytes_from_stream(data_stream.data).await;
+        let mut rdr = csv::Reader::from_reader(&bytes[..]);
+        assert_eq!(rdr.headers().unwrap(), vec!["id", "text"]);
+        for record in rdr.records() {
+            records.push(record.unwrap());
+        }
+    }
+    let expected = csv::Reader::from_reader(&csv[..])
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(records, expected);
+}
+
+#[tokio::test]
+async fn split_into_ranges_keeps_name_of_unsplit_files() {
+    let csv = b"id\n1\n2\n3\n4\n".to_vec();
+    let len = csv.len() as u64;
+    for min_range_size in [None, Some(len)] {
+        let file: Arc<dyn ReadRange> = Arc::new(csv.clone());
+        let data_streams =
+            split_into_ranges("data", &DataFormat::Csv, len, 4, min_range_size, file)
+                .await
+                .unwrap();
+        assert_eq!(data_streams.len(), 1);
+        assert_eq!(data_streams[0].name, "data");
+    }
+}
+
+#[tokio::test]
+async fn check_csv_range_ends_at_record_rejects_bad_splits() {
+    let data = box_stream_once(Ok(BytesMut::from(&b"1,\"a\n"[..])));
+    let result = check_csv_range_ends_at_record("data_0001".to_owned(), 0..5, data)
+        .try_collect::<Vec<_>>()
+        .await;
+    assert!(result.is_err());
+}
+
+#[cfg(test)]
+async fn read_bytes_from_stream(data: BoxStream<BytesMut>) -> Vec<u8> {
+    data.try_concat().await.unwrap().to_vec()
+}
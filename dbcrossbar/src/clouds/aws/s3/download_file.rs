@@ -1,6 +1,14 @@
 //! Download files from S3.
 
-use std::process::Stdio;
+use std::{
+    pin::Pin,
+    process::Stdio,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{self, Poll},
+};
 use tokio::io::BufReader;
 
 use super::aws_s3_command;
@@ -22,7 +30,66 @@ pub(crate) async fn download_file(
         .context("error running `aws s3 cp`")?;
     let child_stdout = child.stdout.take().expect("child should have stdout");
     let child_stdout = BufReader::with_capacity(BUFFER_SIZE, child_stdout);
-    let data = copy_reader_to_stream(child_stdout)?;
-    ctx.spawn_process(format!("aws s3 cp {} -", file_url), child);
+    let data = AbandonableStream::new(copy_reader_to_stream(child_stdout)?.boxed());
+
+    // If our caller stops reading early (because of `--limit`, for example),
+    // `aws s3 cp` will fail with a broken pipe. That's fine.
+    let abandoned = data.abandoned.clone();
+    let name = format!("aws s3 cp {} -", file_url);
+    ctx.spawn_worker(
+        debug_span!("spawn_process", name = ?name),
+        async move {
+            match child.wait().await {
+                Ok(ref status) if status.success() => Ok(()),
+                Ok(status) if abandoned.load(Ordering::SeqCst) => {
+                    debug!("{} stopped early with {}", name, status);
+                    Ok(())
+                }
+                Ok(status) => Err(format_err!("{} failed with {}", name, status)),
+                Err(err) => Err(format_err!("{} failed with error: {}", name, err)),
+            }
+        }
+        .boxed(),
+    );
     Ok(data.boxed())
 }
+
+/// A stream which records whether it was dropped before it finished.
+struct AbandonableStream {
+    inner: BoxStream<BytesMut>,
+    finished: bool,
+    abandoned: Arc<AtomicBool>,
+}
+
+impl AbandonableStream {
+    fn new(inner: BoxStream<BytesMut>) -> Self {
+        Self {
+            inner,
+            finished: false,
+            abandoned: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl Stream for AbandonableStream {
+    type Item = Result<BytesMut>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let result = self.inner.as_mut().poll_next(cx);
+        if let Poll::Ready(None) = result {
+            self.finished = true;
+        }
+        result
+    }
+}
+
+impl Drop for AbandonableStream {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.abandoned.store(true, Ordering::SeqCst);
+        }
+    }
+}
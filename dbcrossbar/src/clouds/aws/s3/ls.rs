@@ -15,6 +15,18 @@ pub(crate) async fn ls(
     ctx: &Context,
     url: &Url,
 ) -> Result<impl Stream<Item = Result<Url>> + Send + Unpin + 'static> {
+    Ok(ls_with_sizes(ctx, url)
+        .await?
+        .map_ok(|(file_url, _size)| file_url))
+}
+
+/// List all the files at the specified `s3://` URL, recursively, including the
+/// size of each file in bytes.
+#[instrument(level = "trace", skip(ctx))]
+pub(crate) async fn ls_with_sizes(
+    ctx: &Context,
+    url: &Url,
+) -> Result<impl Stream<Item = Result<(Url, u64)>> + Send + Unpin + 'static> {
     // Start a child process to list files at that URL.
     debug!("listing {}", url);
     let mut child = aws_s3_command()
@@ -40,8 +52,8 @@ pub(crate) async fn ls(
                 async move {
                     trace!("`aws s3 ls` line: {}", line);
                     let bucket_url = bucket_url(&url)?;
-                    let path = path_from_line(&line)?;
-                    Ok(bucket_url.join(&path)?)
+                    let (path, size) = path_and_size_from_line(&line)?;
+                    Ok((bucket_url.join(&path)?, size))
                 }
             });
 
@@ -76,15 +88,24 @@ fn bucket_url_extracts_bucket() {
 }
 
 /// Given a line of `aws s3 ls` output, extract the path.
+#[cfg(test)]
 fn path_from_line(line: &str) -> Result<String> {
+    Ok(path_and_size_from_line(line)?.0)
+}
+
+/// Given a line of `aws s3 ls` output, extract the path and the size in bytes.
+fn path_and_size_from_line(line: &str) -> Result<(String, u64)> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"^[-0-9]+ [:0-9]+ +[0-9]+ ([^\r\n]+)")
+        static ref RE: Regex = Regex::new(r"^[-0-9]+ [:0-9]+ +([0-9]+) ([^\r\n]+)")
             .expect("invalid regex in source");
     }
     let cap = RE
         .captures(line)
         .ok_or_else(|| format_err!("cannot parse S3 ls output: {:?}", line))?;
-    Ok(cap[1].to_owned())
+    let size = cap[1]
+        .parse::<u64>()
+        .with_context(|| format!("cannot parse S3 ls output: {:?}", line))?;
+    Ok((cap[2].to_owned(), size))
 }
 
 #[test]
@@ -101,3 +122,11 @@ fn path_from_line_returns_entire_path() {
         assert_eq!(path_from_line(line).unwrap(), rel_path);
     }
 }
+
+#[test]
+fn path_and_size_from_line_returns_size() {
+    assert_eq!(
+        path_and_size_from_line("2013-09-02 21:37:53    2863288 foo.zip").unwrap(),
+        ("foo.zip".to_owned(), 2863288),
+    );
+}
@@ -13,7 +13,7 @@ mod rmdir;
 mod upload_file;
 
 pub(crate) use download_file::download_file;
-pub(crate) use ls::ls;
+pub(crate) use ls::{ls, ls_with_sizes};
 pub(crate) use rmdir::rmdir;
 pub(crate) use upload_file::upload_file;
 
@@ -6,6 +6,7 @@ use super::{
     super::{percent_encode, Client, NoQuery},
     TableSchema,
 };
+use crate::clouds::gcloud::ClientError;
 use crate::common::*;
 use crate::drivers::bigquery_shared::{BqTable, TableName};
 
@@ -19,6 +20,29 @@ struct Table {
 /// Look up the schema of the specified table.
 #[instrument(level = "trace", skip(client))]
 pub(crate) async fn schema(client: &Client, name: &TableName) -> Result<BqTable> {
+    Ok(schema_helper(client, name).await?)
+}
+
+/// Look up the schema of the specified table, returning `None` if the table
+/// does not exist.
+#[instrument(level = "trace", skip(client))]
+pub(crate) async fn schema_if_exists(
+    client: &Client,
+    name: &TableName,
+) -> Result<Option<BqTable>> {
+    match schema_helper(client, name).await {
+        Ok(table) => Ok(Some(table)),
+        Err(ClientError::NotFound { .. }) => Ok(None),
+        Err(ClientError::Other(err)) => Err(err),
+    }
+}
+
+/// Look up the schema of the specified table, returning a [`ClientError`] so
+/// that our callers can check for missing tables.
+async fn schema_helper(
+    client: &Client,
+    name: &TableName,
+) -> Result<BqTable, ClientError> {
     trace!("fetching schema for {:?}", name);
 
     // Build our URL.
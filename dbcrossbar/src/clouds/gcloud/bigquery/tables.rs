@@ -94,3 +94,66 @@ pub(crate) async fn create_view(
         .await?;
     Ok(())
 }
+
+/// URL query parameters for listing tables.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ListTablesQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_token: Option<String>,
+}
+
+/// One page of tables.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TableList {
+    next_page_token: Option<String>,
+
+    #[serde(default)]
+    tables: Vec<TableListItem>,
+}
+
+/// A table returned by [`list_tables`].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TableListItem {
+    /// The name of this table.
+    pub(crate) table_reference: TableReference,
+    /// The type of this table, such as `"TABLE"` or `"VIEW"`.
+    #[serde(rename = "type")]
+    pub(crate) table_type: String,
+}
+
+/// List all the tables in the specified dataset.
+///
+/// See the [documentation][list].
+///
+/// [list]: https://cloud.google.com/bigquery/docs/reference/rest/v2/tables/list
+#[instrument(level = "trace", skip(client))]
+pub(crate) async fn list_tables(
+    client: &Client,
+    project: &str,
+    dataset: &str,
+) -> Result<Vec<TableListItem>> {
+    let url = format!(
+        "https://bigquery.googleapis.com/bigquery/v2/projects/{}/datasets/{}/tables",
+        percent_encode(project),
+        percent_encode(dataset),
+    );
+
+    // Keep asking for results until there are no more.
+    let mut tables = vec![];
+    let mut page_token = None;
+    loop {
+        let query = ListTablesQuery {
+            page_token: page_token.clone(),
+        };
+        let mut page = client.get::<TableList, _, _>(&url, query).await?;
+        tables.append(&mut page.tables);
+        if page.next_page_token.is_none() || page.next_page_token == page_token {
+            break;
+        }
+        page_token = page.next_page_token;
+    }
+    Ok(tables)
+}
@@ -27,11 +27,13 @@ struct DownloadQuery {
     if_generation_match: i64,
 }
 
-/// Download the file at the specified URL as a stream.
+/// Download the bytes in `range` of the file at the specified URL as a
+/// stream.
 #[instrument(level = "trace", skip(client, item), fields(item = %item.to_url_string()))]
-pub(crate) async fn download_file(
+pub(crate) async fn download_file_range(
     client: &Client,
     item: &StorageObject,
+    range: ops::Range<u64>,
 ) -> Result<BoxStream<BytesMut>> {
     let file_url = item.to_url_string().parse::<Url>()?;
     debug!("streaming from {}", file_url);
@@ -49,7 +51,7 @@ pub(crate) async fn download_file(
     // Build a stream of download tasks.
     let generation = item.generation;
     let client = client.to_owned();
-    let stream = stream::iter(chunk_ranges(CHUNK_SIZE, item.size))
+    let stream = stream::iter(chunk_ranges(CHUNK_SIZE, range))
         .map(move |range| {
             download_range(
                 client.clone(),
@@ -155,8 +157,8 @@ where
 struct ChunkRanges {
     /// The size of chunk we want to return.
     chunk_size: u64,
-    /// The total length of our file.
-    len: u64,
+    /// The end of the last range.
+    end: u64,
     /// The place to start our next range.
     next_start: u64,
 }
@@ -165,8 +167,8 @@ impl Iterator for ChunkRanges {
     type Item = ops::Range<u64>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.next_start < self.len {
-            let end = min(self.next_start + self.chunk_size, self.len);
+        if self.next_start < self.end {
+            let end = min(self.next_start + self.chunk_size, self.end);
             let range = self.next_start..end;
             self.next_start = end;
             Some(range)
@@ -176,19 +178,21 @@ impl Iterator for ChunkRanges {
     }
 }
 
-/// Return an iterator over successive subranges of a file, each containing
+/// Return an iterator over successive subranges of `range`, each containing
 /// `chunk_size` bytes except the last.
-fn chunk_ranges(chunk_size: u64, len: u64) -> ChunkRanges {
+fn chunk_ranges(chunk_size: u64, range: ops::Range<u64>) -> ChunkRanges {
     assert!(chunk_size > 0);
     ChunkRanges {
         chunk_size,
-        len,
-        next_start: 0,
+        end: range.end,
+        next_start: range.start,
     }
 }
 
 #[test]
 fn chunk_ranges_returns_sequential_ranges() {
-    let ranges = chunk_ranges(10, 25).collect::<Vec<_>>();
+    let ranges = chunk_ranges(10, 0..25).collect::<Vec<_>>();
     assert_eq!(ranges, &[0..10, 10..20, 20..25]);
+    let ranges = chunk_ranges(10, 5..25).collect::<Vec<_>>();
+    assert_eq!(ranges, &[5..15, 15..25]);
 }
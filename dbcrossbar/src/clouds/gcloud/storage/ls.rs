@@ -117,11 +117,6 @@ pub(crate) async fn ls(
                     continue;
                 }
 
-                // Filter out non-CSV files.
-                if !item.name.to_ascii_lowercase().ends_with(".csv") {
-                    continue;
-                }
-
                 // Make sure that we either return the file that we were asked
                 // for, or something in a subdirectory. We don't want to accidentally
                 // return `object + "_trailing"`, but since cloud bucket stores don't
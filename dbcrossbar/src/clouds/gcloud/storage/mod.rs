@@ -14,7 +14,7 @@ mod ls;
 mod rm_r;
 mod upload_file;
 
-pub(crate) use download_file::download_file;
+pub(crate) use download_file::download_file_range;
 pub(crate) use ls::ls;
 pub(crate) use rm_r::rm_r;
 pub(crate) use upload_file::upload_file;
@@ -83,6 +83,11 @@ impl StorageObject {
     pub(crate) fn to_url_string(&self) -> String {
         format!("gs://{}/{}", self.bucket, self.name)
     }
+
+    /// Is this a CSV file?
+    pub(crate) fn is_csv(&self) -> bool {
+        self.name.to_ascii_lowercase().ends_with(".csv")
+    }
 }
 
 /// A helper function which can deserialize integers represented as either
@@ -1,15 +1,40 @@
 //! The `cp` subcommand.
 
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use anyhow::{format_err, Context as _, Result};
 use clap::Parser;
-use futures::{pin_mut, stream, FutureExt, StreamExt, TryStreamExt};
+use futures::{pin_mut, stream, try_join, FutureExt, StreamExt, TryStreamExt};
 use humanize_rs::bytes::Bytes as HumanizedBytes;
 use opinionated_telemetry::tracing::{field, Span};
-use tokio::io;
+use tokio::{io, time::sleep};
 use tokio_util::codec::{FramedWrite, LinesCodec};
 
+use super::head::render_text_table;
 use crate::{
-    common::*, config::Configuration, rechunk::rechunk_csvs, tokio_glue::try_forward,
+    bad_rows::{divert_invalid_rows, BadRows},
+    common::*,
+    config::Configuration,
+    dedupe::{dedupe_csv_streams, Dedupe},
+    drivers::find_driver,
+    file_name_template::FileNameTemplate,
+    filter::{filter_csv_streams, Filter},
+    fingerprint::{Fingerprint, VerifyMode},
+    flatten::Flatten,
+    masking::Masks,
+    order_by::{sort_csv_streams, OrderBy},
+    partition::PartitionBy,
+    projection::Projection,
+    rechunk::rechunk_csvs,
+    sampling::{limit_csv_streams, sample_csv_streams, Sample},
+    time_zones::TimeZones,
+    tokio_glue::try_forward,
+    union::{union_csv_streams, SourceColumn, SourceColumnValue},
+    wildcard::{glob_matches, WildcardLocator, WildcardPattern},
     Context, DataFormat, DestinationArguments, DisplayOutputLocators, DriverArguments,
     IfExists, SharedArguments, SourceArguments, TemporaryStorage, UnparsedLocator,
 };
@@ -21,6 +46,11 @@ pub(crate) struct Opt {
     #[clap(long = "if-exists", default_value = "error")]
     if_exists: IfExists,
 
+    /// When appending, add new nullable columns and apply safe type widenings
+    /// (like `int32` to `int64`) to the destination table before loading.
+    #[clap(long = "allow-schema-evolution")]
+    allow_schema_evolution: bool,
+
     /// The schema to use (defaults to input table schema).
     #[clap(long = "schema")]
     schema: Option<UnparsedLocator>,
@@ -37,6 +67,18 @@ pub(crate) struct Opt {
     #[clap(long = "stream-size")]
     stream_size: Option<HumanizedBytes>, // usize
 
+    /// Split the output into streams of at most this many rows. For
+    /// directory-like destinations, each stream is written as a separate
+    /// file.
+    #[clap(long = "rows-per-file")]
+    rows_per_file: Option<u64>,
+
+    /// For directory-like destinations, how to name output files. May contain
+    /// `{table}`, `{stream}` and `{n}`, which is the 1-based file number.
+    /// Example: "{table}-{n:05}.csv".
+    #[clap(long = "file-name-template")]
+    file_name_template: Option<String>,
+
     /// Pass an extra argument of the form `key=value` to the source driver.
     #[clap(long = "from-arg")]
     from_args: Vec<String>,
@@ -57,36 +99,730 @@ pub(crate) struct Opt {
     #[clap(long = "to-format", short = 'F')]
     to_format: Option<DataFormat>,
 
+    /// For directory-like `file:`, `csv:`, `s3:` and `gs:` destinations, write
+    /// rows into Hive-style `COL=VALUE/part-NNNN` directories. Example:
+    /// "country" or "year,month".
+    #[clap(long = "partition-by")]
+    partition_by: Option<String>,
+
     /// SQL where clause specifying rows to use.
     #[clap(long = "where")]
     where_clause: Option<String>,
 
+    /// A portable filter specifying rows to use, which works with any source.
+    /// Example: "status IN ('a', 'b') AND created >= DATE '2024-01-01'".
+    #[clap(long = "filter")]
+    filter: Option<String>,
+
+    /// Only copy one row for each distinct value of these columns. Example:
+    /// "id" or "tenant_id,id".
+    #[clap(long = "dedupe-on")]
+    dedupe_on: Option<String>,
+
+    /// With `--dedupe-on`, which row to keep for each key: `any` (the
+    /// default) or `latest-by=COL`, which keeps the row with the largest
+    /// value of `COL`.
+    #[clap(long = "keep")]
+    keep: Option<String>,
+
+    /// Sort the output by these columns. Example: "created_at DESC,id".
+    /// Sorted data is always copied as a single stream, or as sequentially
+    /// numbered chunks with `--stream-size`.
+    #[clap(long = "order-by")]
+    order_by: Option<String>,
+
+    /// Copy at most this many rows.
+    #[clap(long = "limit")]
+    limit: Option<u64>,
+
+    /// Copy a random sample of rows. Example: "0.01" copies about 1% of rows.
+    #[clap(long = "sample")]
+    sample: Option<f64>,
+
+    /// A seed for `--sample`, to copy the same rows each time.
+    #[clap(long = "sample-seed")]
+    sample_seed: Option<u64>,
+
+    /// Only copy these columns, in this order. Example: "id,name".
+    #[clap(long = "select")]
+    select: Option<String>,
+
+    /// Rename a column in the destination. Example: "old_name=new_name". May
+    /// be repeated.
+    #[clap(long = "rename")]
+    renames: Vec<String>,
+
+    /// Mask a column in the destination. Example: "email=hmac". Policies are
+    /// `hmac`, `null`, `truncate-date:UNIT`, `round-geo:DIGITS` and
+    /// `tokenize`. May be repeated.
+    #[clap(long = "mask")]
+    masks: Vec<String>,
+
+    /// A TOML file with a `[mask]` table mapping column names to masking
+    /// policies.
+    #[clap(long = "mask-config")]
+    mask_config: Option<PathBuf>,
+
+    /// Replace each struct column with one column per field, named like
+    /// `address_city`. Useful for destinations like CSV and RedShift.
+    #[clap(long = "flatten-structs")]
+    flatten_structs: bool,
+
+    /// Replace an array column with its elements, copying the rest of the row
+    /// once per element. Rows with empty or `NULL` arrays are skipped.
+    #[clap(long = "explode")]
+    explode: Option<String>,
+
+    /// The time zone of timestamps without time zones in the source, such as
+    /// "America/New_York". These are converted to UTC, or to
+    /// `--to-time-zone`.
+    #[clap(long = "from-time-zone")]
+    from_time_zone: Option<String>,
+
+    /// Write all timestamps as local times in this time zone, without time
+    /// zones. Example: "Europe/Paris".
+    #[clap(long = "to-time-zone")]
+    to_time_zone: Option<String>,
+
+    /// Skip up to this many rows which can't be converted, instead of failing.
+    /// This forces the data to be copied via the local machine.
+    #[clap(long = "max-bad-rows", default_value = "0")]
+    max_bad_rows: u64,
+
+    /// Write rows skipped by `--max-bad-rows` to this location, replacing
+    /// any existing data. With `--watch`, rows are appended instead.
+    #[clap(long = "rejects")]
+    rejects: Option<UnparsedLocator>,
+
     /// How many data streams should we attempt to copy in parallel?
     #[clap(long = "max-streams", short = 'J', default_value = "4")]
     max_streams: usize,
 
+    /// Split large CSV and JSON Lines source files into up to `--max-streams`
+    /// byte ranges of at least this size, so that they can be read in
+    /// parallel. Supported by `file:`, `csv:` and `gs:`. Example: "64MB".
+    #[clap(long = "split-files")]
+    split_files: Option<HumanizedBytes>,
+
+    /// When copying from a wildcard locator, only copy tables matching this
+    /// pattern. Example: "user_*". May be repeated.
+    #[clap(long = "include")]
+    includes: Vec<String>,
+
+    /// When copying from a wildcard locator, skip tables matching this
+    /// pattern. Example: "*_tmp". May be repeated.
+    #[clap(long = "exclude")]
+    excludes: Vec<String>,
+
+    /// When copying from a wildcard locator, how many tables should we copy
+    /// in parallel?
+    #[clap(long = "parallel-tables", default_value = "2")]
+    parallel_tables: usize,
+
+    /// After copying, compare the source and the destination, and fail if
+    /// they differ. `--verify` or `--verify=count` compares row counts, and
+    /// `--verify=fingerprint` also compares `NULL` counts and sums of
+    /// integer and decimal columns.
+    #[clap(
+        long = "verify",
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "count"
+    )]
+    verify: Option<VerifyMode>,
+
+    /// Keep polling a directory-like source for new files, and copy each one
+    /// as it arrives. Requires `--watch-ledger` and `--if-exists=append` or
+    /// `--if-exists=upsert-on:...`.
+    #[clap(long = "watch")]
+    watch: bool,
+
+    /// A local file listing the source files which `--watch` has already
+    /// copied, one locator per line.
+    #[clap(long = "watch-ledger")]
+    watch_ledger: Option<PathBuf>,
+
+    /// How many seconds should `--watch` wait between polls?
+    #[clap(long = "watch-interval", default_value = "60")]
+    watch_interval: u64,
+
+    /// Poll for new files only once, copy them, and exit. Useful when running
+    /// `--watch` from `cron`.
+    #[clap(long = "watch-once")]
+    watch_once: bool,
+
+    /// How many seconds should `--watch` wait before listing new files a
+    /// second time? Files whose size or modification time changed in the
+    /// meantime are still being written, and are left for the next poll.
+    #[clap(long = "watch-settle", default_value = "5")]
+    watch_settle: u64,
+
+    /// A local file listing more input tables, one locator per line. These
+    /// are copied after any input tables on the command line.
+    #[clap(long = "from-list")]
+    from_list: Option<PathBuf>,
+
+    /// Add a column with this name recording where each row came from.
+    /// Useful when copying several input tables into one output table.
+    #[clap(long = "add-source-column")]
+    add_source_column: Option<String>,
+
+    /// With `--add-source-column`, what to record: `locator` (the default),
+    /// or `stream`, the name of the data stream within each input.
+    #[clap(long = "source-column-value", default_value = "locator")]
+    source_column_value: SourceColumnValue,
+
     /// Display where we wrote our output data.
     #[clap(long = "display-output-locators")]
     display_output_locators: bool,
 
-    /// The input table.
-    from_locator: UnparsedLocator,
+    /// The input tables, followed by the output table. If there is more than
+    /// one input table, they are all copied into the output table. An input
+    /// may contain a `*` to copy many tables, as in
+    /// `postgres://host/db#public.*`, in which case the output must, too.
+    #[clap(required = true, value_name = "LOCATOR")]
+    locators: Vec<UnparsedLocator>,
+}
+
+impl Opt {
+    /// The input tables passed on the command line.
+    fn input_locators(&self) -> &[UnparsedLocator] {
+        &self.locators[..self.locators.len() - 1]
+    }
 
     /// The output table.
-    to_locator: UnparsedLocator,
+    fn to_locator(&self) -> &UnparsedLocator {
+        self.locators.last().expect("clap should require a locator")
+    }
 }
 
 /// Perform our schema conversion.
-#[instrument(level = "debug", name = "cp", skip_all, fields(from, to))]
 pub(crate) async fn run(
     ctx: Context,
     config: Configuration,
     enable_unstable: bool,
     opt: Opt,
 ) -> Result<()> {
-    let schema_opt = opt.schema.map(|s| s.parse(enable_unstable)).transpose()?;
-    let from_locator = opt.from_locator.parse(enable_unstable)?;
-    let to_locator = opt.to_locator.parse(enable_unstable)?;
+    let mut from_locators = opt.input_locators().to_vec();
+    if let Some(path) = &opt.from_list {
+        from_locators.extend(read_from_list(path).await?);
+    }
+    let from_locator = match &from_locators[..] {
+        [] => return Err(format_err!("must specify at least one input locator")),
+        [from_locator] => from_locator.clone(),
+        _ => return run_union(ctx, config, enable_unstable, opt, from_locators).await,
+    };
+
+    if opt.watch {
+        return run_watch(ctx, config, enable_unstable, opt, from_locator).await;
+    } else if opt.watch_ledger.is_some() || opt.watch_once {
+        return Err(format_err!(
+            "--watch-ledger and --watch-once can only be used with --watch"
+        ));
+    }
+    if let Some(wildcard) = WildcardLocator::parse(from_locator.as_str())? {
+        return run_wildcard(ctx, config, enable_unstable, opt, wildcard).await;
+    }
+    if !opt.includes.is_empty() || !opt.excludes.is_empty() {
+        return Err(format_err!(
+            "--include and --exclude can only be used with wildcard locators"
+        ));
+    }
+    let from_locator = from_locator.parse(enable_unstable)?;
+    let to_locator = opt.to_locator().parse(enable_unstable)?;
+    copy_table(
+        ctx,
+        &config,
+        enable_unstable,
+        &opt,
+        vec![from_locator],
+        to_locator,
+        false,
+    )
+    .await
+}
+
+/// Read a `--from-list` file, skipping blank lines and lines starting with
+/// `#`.
+async fn read_from_list(path: &Path) -> Result<Vec<UnparsedLocator>> {
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("cannot read {}", path.display()))?;
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.parse::<UnparsedLocator>())
+        .collect()
+}
+
+/// Copy several input tables into a single output table.
+#[instrument(level = "debug", name = "cp_union", skip_all)]
+async fn run_union(
+    ctx: Context,
+    config: Configuration,
+    enable_unstable: bool,
+    opt: Opt,
+    from_locators: Vec<UnparsedLocator>,
+) -> Result<()> {
+    if opt.watch {
+        return Err(format_err!(
+            "--watch can only be used with a single input locator"
+        ));
+    }
+    if !opt.includes.is_empty() || !opt.excludes.is_empty() {
+        return Err(format_err!(
+            "--include and --exclude can only be used with wildcard locators"
+        ));
+    }
+
+    // Parse all our locators before we start, so that typos fail quickly.
+    let from_locators = from_locators
+        .iter()
+        .map(|from_locator| {
+            if WildcardLocator::parse(from_locator.as_str())?.is_some() {
+                return Err(format_err!(
+                    "cannot copy wildcard locator {} along with other input locators",
+                    from_locator.as_str(),
+                ));
+            }
+            from_locator.parse(enable_unstable)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    debug!("copying {} input tables", from_locators.len());
+    let to_locator = opt.to_locator().parse(enable_unstable)?;
+    copy_table(
+        ctx,
+        &config,
+        enable_unstable,
+        &opt,
+        from_locators,
+        to_locator,
+        false,
+    )
+    .await
+}
+
+/// The result of copying one table matched by a wildcard locator.
+struct TableResult {
+    /// The part of the table name matched by the `*`.
+    name: String,
+    /// Where we copied the table. This may hide passwords.
+    dest: String,
+    /// Did the copy succeed?
+    result: Result<()>,
+}
+
+/// Copy every table matching `wildcard`, a few at a time, and print a summary.
+#[instrument(level = "debug", name = "cp_wildcard", skip_all)]
+async fn run_wildcard(
+    ctx: Context,
+    config: Configuration,
+    enable_unstable: bool,
+    opt: Opt,
+    wildcard: WildcardLocator,
+) -> Result<()> {
+    if opt.schema.is_some() {
+        return Err(format_err!(
+            "--schema cannot be used with wildcard locators"
+        ));
+    }
+    if opt.rejects.is_some() {
+        return Err(format_err!(
+            "--rejects cannot be used with wildcard locators"
+        ));
+    }
+    if opt.display_output_locators {
+        return Err(format_err!(
+            "--display-output-locators cannot be used with wildcard locators"
+        ));
+    }
+    let to_pattern =
+        WildcardPattern::parse(opt.to_locator().as_str())?.ok_or_else(|| {
+            format_err!("the destination must contain a `*` if the source does")
+        })?;
+
+    // List the tables in our container, and decide which ones to copy.
+    let from_args = DriverArguments::from_cli_args(&opt.from_args)?;
+    let entries = wildcard
+        .container()
+        .parse::<UnparsedLocator>()?
+        .list(
+            ctx,
+            enable_unstable,
+            SourceArguments::new(from_args, None, None),
+        )
+        .await?;
+    let names = entries
+        .iter()
+        .filter_map(|entry| wildcard.capture(&entry.name))
+        .filter(|&name| {
+            (opt.includes.is_empty()
+                || opt.includes.iter().any(|glob| glob_matches(glob, name)))
+                && !opt.excludes.iter().any(|glob| glob_matches(glob, name))
+        })
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        return Err(format_err!("no tables matched the source locator"));
+    }
+
+    // Parse all our locators before we start, so that typos fail quickly.
+    let copies = names
+        .into_iter()
+        .map(|name| {
+            let from_locator = wildcard
+                .replace(name)
+                .parse::<UnparsedLocator>()?
+                .parse(enable_unstable)?;
+            let to_locator = to_pattern
+                .replace(name)
+                .parse::<UnparsedLocator>()?
+                .parse(enable_unstable)?;
+            Ok((name.to_owned(), from_locator, to_locator))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let table_count = copies.len();
+    debug!("copying {} tables", table_count);
+
+    // Copy our tables, a few at a time.
+    let copy_futs = copies
+        .into_iter()
+        .map(|(name, from_locator, to_locator)| {
+            copy_wildcard_table(
+                &config,
+                enable_unstable,
+                &opt,
+                name,
+                from_locator,
+                to_locator,
+            )
+        })
+        .collect::<Vec<_>>();
+    let results = stream::iter(copy_futs)
+        .buffered(opt.parallel_tables.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    // Print our summary.
+    let mut out = std::io::stdout().lock();
+    write!(out, "{}", render_summary(&results))?;
+    out.flush()?;
+    let failed = results.iter().filter(|r| r.result.is_err()).count();
+    if failed > 0 {
+        return Err(format_err!(
+            "{} of {} tables failed to copy",
+            failed,
+            table_count
+        ));
+    }
+    Ok(())
+}
+
+/// Copy one of the tables matched by a wildcard locator. This gets its own
+/// `Context`, so that one failed table doesn't stop the others.
+async fn copy_wildcard_table(
+    config: &Configuration,
+    enable_unstable: bool,
+    opt: &Opt,
+    name: String,
+    from_locator: BoxLocator,
+    to_locator: BoxLocator,
+) -> TableResult {
+    let dest = to_locator.to_string();
+    let (ctx, worker_fut) = Context::create();
+    let copy_fut = copy_table(
+        ctx,
+        config,
+        enable_unstable,
+        opt,
+        vec![from_locator],
+        to_locator,
+        true,
+    );
+    let result = try_join!(copy_fut, worker_fut).map(|_| ());
+    if let Err(err) = &result {
+        error!("error copying {}: {:?}", name, err);
+    }
+    TableResult { name, dest, result }
+}
+
+/// Render a summary of our table copies.
+fn render_summary(results: &[TableResult]) -> String {
+    let headers = ["table", "destination", "status", "error"]
+        .iter()
+        .map(|&h| h.to_owned())
+        .collect::<Vec<_>>();
+    let cells = results
+        .iter()
+        .map(|r| {
+            let (status, error) = match &r.result {
+                Ok(()) => ("ok", String::new()),
+                Err(err) => ("failed", format!("{:#}", err).replace('\n', " ")),
+            };
+            vec![r.name.clone(), r.dest.clone(), status.to_owned(), error]
+        })
+        .collect::<Vec<_>>();
+    render_text_table(&headers, &cells, &[false; 4])
+}
+
+/// The source files which `cp --watch` has already copied. We store these in
+/// a local file, so that we can pick up where we left off after a restart.
+struct WatchLedger {
+    /// The file containing our ledger, with one locator per line.
+    path: PathBuf,
+    /// The locators we've already copied.
+    copied: HashSet<String>,
+}
+
+impl WatchLedger {
+    /// Load the ledger at `path`, or create an empty ledger if it doesn't
+    /// exist yet.
+    async fn load(path: PathBuf) -> Result<Self> {
+        let copied = match tokio::fs::read_to_string(&path).await {
+            Ok(text) => text
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_owned())
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("cannot read {}", path.display()))
+            }
+        };
+        Ok(Self { path, copied })
+    }
+
+    /// Have we already copied `locator`?
+    fn contains(&self, locator: &str) -> bool {
+        self.copied.contains(locator)
+    }
+
+    /// Record that we've copied `locator`, and make sure it's on disk before
+    /// returning.
+    async fn record(&mut self, locator: String) -> Result<()> {
+        let mut wtr = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("cannot open {}", self.path.display()))?;
+        wtr.write_all(format!("{}\n", locator).as_bytes())
+            .await
+            .with_context(|| format!("error writing {}", self.path.display()))?;
+        wtr.sync_data()
+            .await
+            .with_context(|| format!("error writing {}", self.path.display()))?;
+        self.copied.insert(locator);
+        Ok(())
+    }
+}
+
+/// Poll a directory-like source for new files, and copy each one to our
+/// destination. Files are recorded in `--watch-ledger` once they've been
+/// copied, and files which fail to copy are retried on the next poll.
+#[instrument(level = "debug", name = "cp_watch", skip_all)]
+async fn run_watch(
+    ctx: Context,
+    config: Configuration,
+    enable_unstable: bool,
+    opt: Opt,
+    from_locator: UnparsedLocator,
+) -> Result<()> {
+    let ledger_path = opt
+        .watch_ledger
+        .clone()
+        .ok_or_else(|| format_err!("--watch requires --watch-ledger"))?;
+    if !matches!(opt.if_exists, IfExists::Append | IfExists::Upsert(_)) {
+        return Err(format_err!(
+            "--watch requires --if-exists=append or --if-exists=upsert-on:..."
+        ));
+    }
+    if opt.display_output_locators {
+        return Err(format_err!(
+            "--display-output-locators cannot be used with --watch"
+        ));
+    }
+    // Check our destination before we start, so that typos fail quickly.
+    opt.to_locator().parse(enable_unstable)?;
+
+    // Each watched file is copied separately, so we append their rejected
+    // rows to `--rejects` instead of replacing them each time.
+    if let Some(rejects) = &opt.rejects {
+        let rejects = rejects.parse(enable_unstable)?;
+        let features = find_driver(rejects.dyn_scheme(), enable_unstable)?.features();
+        if !features.dest_if_exists.contains(IfExistsFeatures::Append) {
+            return Err(format_err!(
+                "--rejects with --watch requires a location which supports --if-exists=append"
+            ));
+        }
+    }
+
+    let mut ledger = WatchLedger::load(ledger_path).await?;
+    let from_args = DriverArguments::from_cli_args(&opt.from_args)?;
+    loop {
+        // List our new files twice, and only copy the ones which didn't change
+        // in between, so that we don't load files which are still being
+        // written.
+        let list_new_files = || {
+            list_new_watched_files(
+                ctx.clone(),
+                enable_unstable,
+                &from_locator,
+                &from_args,
+                &ledger,
+            )
+        };
+        let settled = async {
+            let before = list_new_files().await?;
+            if before.is_empty() {
+                return Ok(vec![]);
+            }
+            sleep(Duration::from_secs(opt.watch_settle)).await;
+            let after = list_new_files().await?;
+            Ok::<_, anyhow::Error>(settled_files(&before, after))
+        }
+        .await;
+        let mut new_locators = match settled {
+            Ok(new_locators) => new_locators,
+            Err(err) if !opt.watch_once => {
+                error!("error listing {}: {:?}", from_locator.as_str(), err);
+                Vec::new()
+            }
+            Err(err) => return Err(err),
+        };
+        new_locators.sort();
+        debug!("found {} new files", new_locators.len());
+
+        // Copy our new files in order, because the destination may care.
+        let new_count = new_locators.len();
+        let mut failed = 0;
+        for locator in new_locators {
+            match copy_watched_file(&config, enable_unstable, &opt, &locator).await {
+                Ok(()) => {
+                    info!("copied {}", locator);
+                    ledger.record(locator).await?;
+                }
+                Err(err) => {
+                    error!("error copying {}: {:?}", locator, err);
+                    failed += 1;
+                }
+            }
+        }
+
+        if opt.watch_once {
+            if failed > 0 {
+                return Err(format_err!(
+                    "{} of {} new files failed to copy",
+                    failed,
+                    new_count
+                ));
+            }
+            return Ok(());
+        }
+        sleep(Duration::from_secs(opt.watch_interval)).await;
+    }
+}
+
+/// List the files at `from_locator` which aren't in `ledger` yet.
+async fn list_new_watched_files(
+    ctx: Context,
+    enable_unstable: bool,
+    from_locator: &UnparsedLocator,
+    from_args: &DriverArguments,
+    ledger: &WatchLedger,
+) -> Result<Vec<ListEntry>> {
+    let entries = from_locator
+        .clone()
+        .list(
+            ctx,
+            enable_unstable,
+            SourceArguments::new(from_args.clone(), None, None),
+        )
+        .await?;
+    Ok(entries
+        .into_iter()
+        .filter(|entry| entry.kind == ListEntryKind::File)
+        .filter(|entry| !ledger.contains(&entry.locator))
+        // Skip files we can't read, like `_SUCCESS` markers in buckets.
+        .filter(|entry| {
+            let parsed = entry
+                .locator
+                .parse::<UnparsedLocator>()
+                .and_then(|l| l.parse(enable_unstable));
+            if parsed.is_err() {
+                trace!("skipping {}, which isn't a valid source", entry.locator);
+            }
+            parsed.is_ok()
+        })
+        .collect())
+}
+
+/// Return the locators of the files in `after` which have the same size and
+/// modification time in `before`.
+fn settled_files(before: &[ListEntry], after: Vec<ListEntry>) -> Vec<String> {
+    after
+        .into_iter()
+        .filter(|entry| {
+            let settled = before.iter().any(|b| {
+                b.locator == entry.locator
+                    && b.bytes == entry.bytes
+                    && b.modified == entry.modified
+            });
+            if !settled {
+                debug!("waiting for {} to finish being written", entry.locator);
+            }
+            settled
+        })
+        .map(|entry| entry.locator)
+        .collect()
+}
+
+/// Copy a single file found by `--watch`. This gets its own `Context`, so
+/// that one failed file doesn't stop the watcher.
+async fn copy_watched_file(
+    config: &Configuration,
+    enable_unstable: bool,
+    opt: &Opt,
+    from: &str,
+) -> Result<()> {
+    let from_locator = from.parse::<UnparsedLocator>()?.parse(enable_unstable)?;
+    let to_locator = opt.to_locator().parse(enable_unstable)?;
+    let (ctx, worker_fut) = Context::create();
+    let copy_fut = copy_table(
+        ctx,
+        config,
+        enable_unstable,
+        opt,
+        vec![from_locator],
+        to_locator,
+        true,
+    );
+    try_join!(copy_fut, worker_fut).map(|_| ())
+}
+
+/// Copy one or more tables into a single table. If `quiet` is true, never
+/// display our output locators.
+#[instrument(level = "debug", name = "cp", skip_all, fields(from, to))]
+async fn copy_table(
+    ctx: Context,
+    config: &Configuration,
+    enable_unstable: bool,
+    opt: &Opt,
+    from_locators: Vec<BoxLocator>,
+    to_locator: BoxLocator,
+    quiet: bool,
+) -> Result<()> {
+    let schema_opt = opt
+        .schema
+        .as_ref()
+        .map(|s| s.parse(enable_unstable))
+        .transpose()?;
+    let rejects_opt = opt
+        .rejects
+        .as_ref()
+        .map(|s| s.parse(enable_unstable))
+        .transpose()?;
 
     describe_counter!(
         "dbcrossbar.cp.count",
@@ -106,32 +842,99 @@ pub(crate) async fn run(
     // potentially increases our label cardinality to `num_schemes^2`. This is
     // because knowing the source and dest for a copy will allow us to consider
     // focused optimizations.
-    let from_scheme: &'static str = from_locator.dyn_scheme().trim_end_matches(':');
+    let from_scheme: &'static str =
+        from_locators[0].dyn_scheme().trim_end_matches(':');
     let to_scheme: &'static str = to_locator.dyn_scheme().trim_end_matches(':');
     metrics::counter!("dbcrossbar.cp.count", "from" => from_scheme, "to" => to_scheme)
         .increment(1);
 
     // Fill in our span fields.
     let span = Span::current();
-    span.record("from", field::display(&from_locator));
+    let from_display = from_locators
+        .iter()
+        .map(|l| l.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    span.record("from", field::display(&from_display));
     span.record("to", field::display(&to_locator));
 
+    // Decide whether our source can handle --filter, --dedupe-on,
+    // --order-by, --sample and --limit itself. If not, we'll apply them
+    // locally. Each of these can only be pushed down if the ones before it
+    // were, because they need to happen in order.
+    let sample = match (opt.sample, opt.sample_seed) {
+        (Some(fraction), seed) => Some(Sample::new(fraction, seed)?),
+        (None, Some(_)) => return Err(format_err!("--sample-seed requires --sample")),
+        (None, None) => None,
+    };
+    let mut source_features = EnumSet::all();
+    for from_locator in &from_locators {
+        source_features &= find_driver(from_locator.dyn_scheme(), enable_unstable)?
+            .features()
+            .source_args;
+    }
+    // When we copy several tables, each one can apply --filter, but
+    // everything else needs to be done after we combine them. And
+    // --add-source-column adds a column our sources know nothing about.
+    if from_locators.len() > 1 {
+        source_features &= SourceArgumentsFeatures::Filter;
+    }
+    if opt.add_source_column.is_some() {
+        source_features = EnumSet::empty();
+    }
+    let push_down_filter = opt.filter.is_none()
+        || source_features.contains(SourceArgumentsFeatures::Filter);
+    let push_down_dedupe = push_down_filter
+        && (opt.dedupe_on.is_none()
+            || source_features.contains(SourceArgumentsFeatures::Dedupe));
+    let push_down_order_by = push_down_dedupe
+        && (opt.order_by.is_none()
+            || source_features.contains(SourceArgumentsFeatures::OrderBy));
+    // Database samples happen before deduplication, so we always sample
+    // locally when deduplicating.
+    let push_down_sample = push_down_order_by
+        && sample.is_none_or(|sample| {
+            opt.dedupe_on.is_none()
+                && source_features.contains(SourceArgumentsFeatures::Sample)
+                && (sample.seed().is_none()
+                    || source_features.contains(SourceArgumentsFeatures::SampleSeed))
+        });
+    let push_down_limit = opt.limit.is_none()
+        || (push_down_sample
+            && source_features.contains(SourceArgumentsFeatures::Limit));
+
+    if let Some(verify) = opt.verify {
+        check_verify_args(verify, opt, enable_unstable, &from_locators, &to_locator)?;
+        if !push_down_filter {
+            return Err(format_err!(
+                "--verify cannot be used with --filter unless {} supports --filter",
+                from_locators[0],
+            ));
+        }
+    }
+
     // Build our source arguments.
     let from_args = DriverArguments::from_cli_args(&opt.from_args)?;
     let source_args = SourceArguments::new(
         from_args,
         opt.from_format.clone(),
         opt.where_clause.clone(),
-    );
+    )
+    .with_limit(opt.limit.filter(|_| push_down_limit))
+    .with_sample(sample.filter(|_| push_down_sample));
 
     // Build our destination arguments.
     let to_args = DriverArguments::from_cli_args(&opt.to_args)?;
-    let dest_args =
-        DestinationArguments::new(to_args, opt.to_format.clone(), opt.if_exists);
+    let dest_args = DestinationArguments::new(
+        to_args,
+        opt.to_format.clone(),
+        opt.if_exists.clone(),
+    )
+    .with_schema_evolution(opt.allow_schema_evolution);
 
     // Figure out what table schema to use.
     let schema = {
-        let schema_locator = schema_opt.as_ref().unwrap_or(&from_locator);
+        let schema_locator = schema_opt.as_ref().unwrap_or(&from_locators[0]);
         schema_locator
             .schema(ctx.clone(), source_args.clone())
             .await
@@ -141,21 +944,281 @@ pub(crate) async fn run(
             })
     }?;
 
+    // Add our --add-source-column to the schema, so that the flags below can
+    // use it like any other column.
+    let source_column = opt
+        .add_source_column
+        .as_deref()
+        .map(|name| SourceColumn::new(name, opt.source_column_value, &schema))
+        .transpose()?;
+    let schema = match &source_column {
+        Some(source_column) => source_column.add_to_schema(schema),
+        None => schema,
+    };
+
+    // Check our --filter against the schema, and pass it to our source if it
+    // can handle it.
+    let filter = opt
+        .filter
+        .as_deref()
+        .map(|text| Filter::new(text, &schema))
+        .transpose()?;
+    let source_args = source_args
+        .with_filter(filter.clone().filter(|_| push_down_filter))
+        .with_partition_filter(filter.clone().filter(|_| !push_down_filter));
+
+    // Check --dedupe-on and --keep in the same way.
+    let dedupe = match (&opt.dedupe_on, &opt.keep) {
+        (Some(keys), keep) => Some(Dedupe::new(keys, keep.as_deref(), &schema)?),
+        (None, Some(_)) => return Err(format_err!("--keep requires --dedupe-on")),
+        (None, None) => None,
+    };
+    let source_args =
+        source_args.with_dedupe(dedupe.clone().filter(|_| push_down_dedupe));
+
+    // Check --order-by in the same way.
+    let order_by = opt
+        .order_by
+        .as_deref()
+        .map(|text| OrderBy::new(text, &schema))
+        .transpose()?;
+    let source_args =
+        source_args.with_order_by(order_by.clone().filter(|_| push_down_order_by));
+
     // Build our shared arguments.
     let temporaries = opt.temporaries.clone();
-    let temporary_storage = TemporaryStorage::with_config(temporaries, &config)?;
-    let shared_args = SharedArguments::new(schema, temporary_storage, opt.max_streams);
+    let temporary_storage = TemporaryStorage::with_config(temporaries, config)?;
+
+    // Decide how to handle bad rows, and start writing rejected rows if
+    // requested.
+    let bad_rows = match rejects_opt {
+        Some(_) if opt.max_bad_rows == 0 => {
+            return Err(format_err!("--rejects requires --max-bad-rows"));
+        }
+        Some(rejects_locator) => {
+            let masking = !opt.masks.is_empty() || opt.mask_config.is_some();
+            let (bad_rows, rejects) =
+                BadRows::with_rejects(opt.max_bad_rows, masking)?;
+            let rejects_shared_args = SharedArguments::new(
+                BadRows::rejects_schema("rejects")?,
+                temporary_storage.clone(),
+                1,
+            );
+            let rejects_dest_args = DestinationArguments::new(
+                DriverArguments::default(),
+                None,
+                if opt.watch {
+                    IfExists::Append
+                } else {
+                    IfExists::Overwrite
+                },
+            );
+            let worker_ctx = ctx.clone();
+            ctx.spawn_worker(
+                debug_span!("write_rejects", rejects = %rejects_locator),
+                async move {
+                    rejects_locator
+                        .write_local_data(
+                            worker_ctx,
+                            box_stream_once(Ok(rejects)),
+                            rejects_shared_args,
+                            rejects_dest_args,
+                        )
+                        .await?
+                        .try_buffer_unordered(1)
+                        .try_collect::<Vec<_>>()
+                        .await
+                        .context("error writing rejected rows")?;
+                    Ok(())
+                },
+            );
+            bad_rows
+        }
+        None => BadRows::new(opt.max_bad_rows),
+    };
+
+    // Figure out which columns to copy, and what to call them. Our source
+    // only needs to export the selected columns, using their original names.
+    let projection = Projection::new(opt.select.as_deref(), &opt.renames, &schema)?;
+    let dest_schema = projection.dest_schema(projection.source_schema(schema.clone()));
+
+    // If we need to apply --filter, --dedupe-on or --order-by locally, our
+    // source must export every column they use.
+    let local_columns =
+        filter
+            .iter()
+            .filter(|_| !push_down_filter)
+            .flat_map(|filter| filter.column_names().map(|name| ("--filter", name)))
+            .chain(
+                dedupe
+                    .iter()
+                    .filter(|_| !push_down_dedupe)
+                    .flat_map(|dedupe| {
+                        dedupe.column_names().map(|name| ("--dedupe-on", name))
+                    }),
+            )
+            .chain(order_by.iter().filter(|_| !push_down_order_by).flat_map(
+                |order_by| order_by.column_names().map(|name| ("--order-by", name)),
+            ));
+    let source_schema = projection.source_schema(schema.clone());
+    for (flag, name) in local_columns {
+        if !source_schema.table.columns.iter().any(|c| c.name == name) {
+            return Err(format_err!(
+                "{} uses column {:?}, so it must also be passed to --select",
+                flag,
+                name,
+            ));
+        }
+    }
+
+    // These are the columns we expect to read from each source.
+    let source_columns = source_schema
+        .table
+        .columns
+        .iter()
+        .map(|c| c.name.clone())
+        .filter(|name| source_column.as_ref().is_none_or(|sc| sc.name() != name))
+        .collect::<Vec<_>>();
+
+    // Figure out how to flatten nested columns. These use destination column
+    // names.
+    let flatten =
+        Flatten::new(opt.flatten_structs, opt.explode.as_deref(), &dest_schema)?;
+    let dest_schema = flatten.flattened_schema(dest_schema);
+
+    // Figure out which timestamps to convert to other time zones.
+    let time_zones = TimeZones::new(
+        opt.from_time_zone.as_deref(),
+        opt.to_time_zone.as_deref(),
+        &dest_schema,
+    )?;
+    let dest_schema = time_zones.converted_schema(dest_schema);
+
+    // Figure out which columns to mask. These use flattened destination
+    // column names.
+    let masks = Masks::new(&opt.masks, opt.mask_config.as_deref(), &dest_schema)?;
+    let dest_schema = masks.masked_schema(dest_schema);
+
+    // Check --partition-by against the destination schema.
+    let partition_by = opt
+        .partition_by
+        .as_deref()
+        .map(|text| PartitionBy::new(text, &dest_schema))
+        .transpose()?;
+    let dest_args = dest_args.with_partition_by(partition_by);
+
+    // Parse --file-name-template. Partitioned output is always named
+    // `part-NNNN`, so the two can't be combined.
+    let file_name_template = opt
+        .file_name_template
+        .as_deref()
+        .map(|template| {
+            if opt.partition_by.is_some() {
+                return Err(format_err!(
+                    "cannot use --file-name-template with --partition-by"
+                ));
+            }
+            FileNameTemplate::new(template, &opt.to_format.clone().unwrap_or_default())
+        })
+        .transpose()?;
+    let table_name = schema.table.name.clone();
+
+    let shared_args = SharedArguments::new(schema, temporary_storage, opt.max_streams)
+        .with_bad_rows(bad_rows.clone())
+        .with_split_files(opt.split_files.map(|size| size.size() as u64));
+    let source_shared_args =
+        shared_args.with_modified_schema(|schema| projection.source_schema(schema));
+    let shared_args = source_shared_args.with_modified_schema(|schema| {
+        masks.masked_schema(time_zones.converted_schema(
+            flatten.flattened_schema(projection.dest_schema(schema)),
+        ))
+    });
+    // Our sources don't know about --add-source-column, so we add it later.
+    let source_shared_args = match &source_column {
+        Some(source_column) => source_shared_args
+            .with_modified_schema(|schema| source_column.remove_from_schema(schema)),
+        None => source_shared_args,
+    };
+
+    // If we're verifying our copy, check our source before we start, because
+    // we may need to give `from_locators` away.
+    let verify_source = match opt.verify {
+        None => None,
+        Some(VerifyMode::Count) => {
+            let mut count = 0;
+            for from_locator in &from_locators {
+                count += from_locator
+                    .count(
+                        ctx.clone(),
+                        source_shared_args.clone(),
+                        source_args.clone(),
+                    )
+                    .await
+                    .with_context(|| {
+                        format!("error counting rows in {}", from_locator)
+                    })?;
+            }
+            Some(Fingerprint::from_count(count)?)
+        }
+        Some(VerifyMode::Fingerprint) => Some(
+            from_locators[0]
+                .fingerprint(
+                    ctx.clone(),
+                    source_shared_args.clone(),
+                    source_args.clone(),
+                )
+                .await
+                .with_context(|| {
+                    format!("error fingerprinting {}", from_locators[0])
+                })?,
+        ),
+    };
+    if let Some(verify_source) = &verify_source {
+        debug!("source has {} rows", verify_source.count());
+    }
+    // Our destination has the same columns as our source, possibly renamed,
+    // plus any --add-source-column.
+    let verify_shared_args = match &source_column {
+        Some(source_column) => shared_args
+            .with_modified_schema(|schema| source_column.remove_from_schema(schema)),
+        None => shared_args.clone(),
+    };
 
     // Can we short-circuit this particular copy using special features of the
     // the source and destination, or do we need to pull the data down to the
     // local machine?
     let should_use_remote = opt.stream_size.is_none()
-        && to_locator.supports_write_remote_data(from_locator.as_ref());
+        && opt.rows_per_file.is_none()
+        && file_name_template.is_none()
+        && !bad_rows.is_enabled()
+        && projection.is_empty()
+        && flatten.is_empty()
+        && time_zones.is_empty()
+        && masks.is_empty()
+        && push_down_filter
+        && push_down_dedupe
+        && opt.order_by.is_none()
+        && opt.partition_by.is_none()
+        && push_down_sample
+        && push_down_limit
+        && from_locators.len() == 1
+        && source_column.is_none()
+        && to_locator.supports_write_remote_data(from_locators[0].as_ref());
     let dests = if should_use_remote {
         // Perform a remote transfer.
         debug!("performing remote data transfer");
+        let from_locator = from_locators
+            .into_iter()
+            .next()
+            .expect("should have exactly one source");
         let dests = to_locator
-            .write_remote_data(ctx, from_locator, shared_args, source_args, dest_args)
+            .write_remote_data(
+                ctx.clone(),
+                from_locator,
+                shared_args,
+                source_args,
+                dest_args,
+            )
             .await?;
 
         // Convert our list of output locators into a stream.
@@ -165,20 +1228,87 @@ pub(crate) async fn run(
         // input.
         debug!("performing local data transfer");
 
-        let mut data = from_locator
-            .local_data(ctx.clone(), shared_args.clone(), source_args)
-            .await?
-            .ok_or_else(|| {
-                format_err!("don't know how to read data from {}", from_locator)
-            })?;
+        let mut sources = vec![];
+        for from_locator in &from_locators {
+            let data = from_locator
+                .local_data(
+                    ctx.clone(),
+                    source_shared_args.clone(),
+                    source_args.clone(),
+                )
+                .await?
+                .ok_or_else(|| {
+                    format_err!("don't know how to read data from {}", from_locator)
+                })?;
+            sources.push((from_locator.to_string(), data));
+        }
+
+        // If we have more than one source, or need to add --add-source-column,
+        // combine our sources into a single stream with matching columns.
+        let mut data = if sources.len() == 1 && source_column.is_none() {
+            sources.pop().expect("should have one source").1
+        } else {
+            union_csv_streams(ctx.clone(), sources, source_columns, source_column)
+        };
+
+        // Honor --filter, --dedupe-on, --order-by, --sample and --limit if
+        // our source couldn't. We always apply --limit locally, because it's
+        // cheap and it lets us stop reading early.
+        if let Some(filter) = filter.filter(|_| !push_down_filter) {
+            data = filter_csv_streams(ctx.clone(), filter, data);
+        }
+        if let Some(dedupe) = dedupe.filter(|_| !push_down_dedupe) {
+            data = dedupe_csv_streams(ctx.clone(), dedupe, data)?;
+        }
+        if let Some(order_by) = order_by.filter(|_| !push_down_order_by) {
+            data = sort_csv_streams(ctx.clone(), order_by, data)?;
+        }
+        if let Some(sample) = sample.filter(|_| !push_down_sample) {
+            data = sample_csv_streams(ctx.clone(), sample, data);
+        }
+        if let Some(limit) = opt.limit {
+            data = limit_csv_streams(ctx.clone(), limit, data);
+        }
+
+        // Honor --select and --rename if passed.
+        if !projection.is_empty() {
+            data = projection.project_csv_streams(ctx.clone(), data);
+        }
+
+        // Honor --flatten-structs and --explode if passed.
+        if !flatten.is_empty() {
+            data = flatten.flatten_csv_streams(ctx.clone(), data);
+        }
+
+        // Honor --from-time-zone and --to-time-zone if passed.
+        if !time_zones.is_empty() {
+            data = time_zones.convert_csv_streams(ctx.clone(), data);
+        }
+
+        // Honor --mask and --mask-config if passed. We do this before checking
+        // for bad rows, so that rejected rows don't contain unmasked data.
+        if !masks.is_empty() {
+            data = masks.mask_csv_streams(ctx.clone(), data);
+        }
 
         // Record the amount of data we're copying locally.
         data = report_cp_local_metrics(from_scheme, to_scheme, data);
 
-        // Honor --stream-size if passed.
-        if let Some(stream_size) = opt.stream_size {
-            let stream_size = stream_size.size();
-            data = rechunk_csvs(ctx.clone(), stream_size, data)?;
+        // Honor --max-bad-rows if passed.
+        if bad_rows.is_enabled() {
+            data =
+                divert_invalid_rows(ctx.clone(), dest_schema, bad_rows.clone(), data);
+        }
+
+        // Honor --stream-size and --rows-per-file if passed.
+        if opt.stream_size.is_some() || opt.rows_per_file.is_some() {
+            let stream_size = opt.stream_size.map(|size| size.size());
+            data = rechunk_csvs(ctx.clone(), stream_size, opt.rows_per_file, data)?;
+        }
+
+        // Honor --file-name-template if passed.
+        if let Some(file_name_template) = &file_name_template {
+            data = file_name_template.rename_csv_streams(&table_name, data);
         }
 
         // Write data to output.
@@ -218,7 +1348,7 @@ pub(crate) async fn run(
     };
 
     // Print our destination
-    if display_output_locators {
+    if display_output_locators && !quiet {
         // Display our output locators incrementally on standard output using
         // `LinesCodec` to insert newlines.
         let stdout_sink = FramedWrite::new(io::stdout(), LinesCodec::new());
@@ -244,6 +1374,95 @@ pub(crate) async fn run(
         let dests = dests.try_collect::<Vec<_>>().boxed().await?;
         debug!("destination locators: {:?}", dests);
     }
+
+    // Finish writing any rejected rows.
+    bad_rows.close();
+    if bad_rows.count() > 0 {
+        warn!("skipped {} bad rows", bad_rows.count());
+    }
+
+    // Honor --verify by checking our destination.
+    if let Some(verify_source) = verify_source {
+        let verify_dest = match opt.verify {
+            Some(VerifyMode::Fingerprint) => to_locator
+                .fingerprint(ctx, verify_shared_args, SourceArguments::for_temporary())
+                .await
+                .with_context(|| format!("error fingerprinting {}", to_locator))?,
+            _ => Fingerprint::from_count(
+                to_locator
+                    .count(ctx, verify_shared_args, SourceArguments::for_temporary())
+                    .await
+                    .with_context(|| {
+                        format!("error counting rows in {}", to_locator)
+                    })?,
+            )?,
+        };
+        verify_source.check_matches(&verify_dest)?;
+        debug!("verified {} rows", verify_dest.count());
+    }
+    Ok(())
+}
+
+/// Make sure that we can honor `--verify` for this copy. We check this before
+/// we copy anything, so that the user doesn't need to wait to find out.
+fn check_verify_args(
+    verify: VerifyMode,
+    opt: &Opt,
+    enable_unstable: bool,
+    from_locators: &[BoxLocator],
+    to_locator: &BoxLocator,
+) -> Result<()> {
+    if opt.limit.is_some() || opt.sample.is_some() {
+        return Err(format_err!(
+            "--verify cannot be used with --limit or --sample"
+        ));
+    }
+    if opt.max_bad_rows > 0 {
+        return Err(format_err!("--verify cannot be used with --max-bad-rows"));
+    }
+    if opt.dedupe_on.is_some() {
+        return Err(format_err!("--verify cannot be used with --dedupe-on"));
+    }
+    if opt.explode.is_some() {
+        return Err(format_err!("--verify cannot be used with --explode"));
+    }
+    if !matches!(opt.if_exists, IfExists::Error | IfExists::Overwrite) {
+        return Err(format_err!(
+            "--verify can only be used with --if-exists=error or --if-exists=overwrite"
+        ));
+    }
+    if verify == VerifyMode::Fingerprint {
+        // These change the values in our columns.
+        if !opt.masks.is_empty() || opt.mask_config.is_some() {
+            return Err(format_err!(
+                "--verify=fingerprint cannot be used with --mask or --mask-config"
+            ));
+        }
+        if opt.flatten_structs {
+            return Err(format_err!(
+                "--verify=fingerprint cannot be used with --flatten-structs"
+            ));
+        }
+        if from_locators.len() > 1 {
+            return Err(format_err!(
+                "--verify=fingerprint can only be used with a single source"
+            ));
+        }
+    }
+    let (feature, description) = match verify {
+        VerifyMode::Count => (LocatorFeatures::Count, "counting rows"),
+        VerifyMode::Fingerprint => (LocatorFeatures::Fingerprint, "fingerprints"),
+    };
+    for locator in from_locators.iter().chain([to_locator]) {
+        let features = find_driver(locator.dyn_scheme(), enable_unstable)?.features();
+        if !features.locator.contains(feature) {
+            return Err(format_err!(
+                "--verify cannot be used because {} does not support {}",
+                locator,
+                description,
+            ));
+        }
+    }
     Ok(())
 }
 
@@ -275,3 +1494,22 @@ fn report_cp_local_metrics(
     })
     .boxed()
 }
+
+#[test]
+fn settled_files_skips_files_which_changed() {
+    let entry = |locator: &str, bytes: u64| ListEntry {
+        name: locator.to_owned(),
+        locator: locator.to_owned(),
+        kind: ListEntryKind::File,
+        rows: None,
+        bytes: Some(bytes),
+        modified: None,
+    };
+    let before = vec![entry("csv:a.csv", 10), entry("csv:b.csv", 10)];
+    let after = vec![
+        entry("csv:a.csv", 10),
+        entry("csv:b.csv", 20),
+        entry("csv:c.csv", 10),
+    ];
+    assert_eq!(settled_files(&before, after), vec!["csv:a.csv".to_owned()]);
+}
@@ -0,0 +1,316 @@
+//! The `head` subcommand.
+
+use std::io::{self, Write};
+
+use anyhow::{format_err, Context as _, Result};
+use clap::Parser;
+use futures::{future, TryStreamExt};
+use serde_json::Value;
+use tracing::{field, instrument, Span};
+
+use crate::{
+    args::SourceArgumentsFeatures,
+    config::Configuration,
+    data_streams::jsonl_converter::convert_csv_field_to_json,
+    drivers::find_driver,
+    sampling::limit_csv_streams,
+    schema::{DataType, Schema},
+    Context, DataFormat, DriverArguments, SharedArguments, SourceArguments,
+    TemporaryStorage, UnparsedLocator,
+};
+
+/// Preview arguments.
+#[derive(Debug, Parser)]
+pub(crate) struct Opt {
+    /// The number of rows to display.
+    #[clap(long = "rows", short = 'n', default_value = "10")]
+    rows: u64,
+
+    /// Print one JSON object per line, instead of a table.
+    #[clap(long = "json")]
+    json: bool,
+
+    /// The schema to use (defaults to input table schema).
+    #[clap(long = "schema")]
+    schema: Option<UnparsedLocator>,
+
+    /// Temporary directories, cloud storage buckets, datasets to use during
+    /// transfer (can be repeated).
+    #[clap(long = "temporary")]
+    temporaries: Vec<String>,
+
+    /// Pass an extra argument of the form `key=value` to the source driver.
+    #[clap(long = "from-arg")]
+    from_args: Vec<String>,
+
+    /// For directory- and file-like data sources, the format to assume. If not
+    /// specified, `dbcrossbar` will use the file extension to guess the format.
+    #[clap(long = "from-format")]
+    from_format: Option<DataFormat>,
+
+    /// SQL where clause specifying rows to use.
+    #[clap(long = "where")]
+    where_clause: Option<String>,
+
+    /// The data to preview.
+    locator: UnparsedLocator,
+}
+
+/// Display the first few rows of a table.
+#[instrument(level = "debug", name = "head", skip_all, fields(locator))]
+pub(crate) async fn run(
+    ctx: Context,
+    config: Configuration,
+    enable_unstable: bool,
+    opt: Opt,
+) -> Result<()> {
+    let schema_opt = opt.schema.map(|s| s.parse(enable_unstable)).transpose()?;
+    let locator = opt.locator.parse(enable_unstable)?;
+
+    // Fill in our span fields.
+    let span = Span::current();
+    span.record("locator", field::display(&locator));
+
+    // Build our source arguments, asking the source to limit the rows it
+    // returns if it can.
+    let from_args = DriverArguments::from_cli_args(&opt.from_args)?;
+    let source_features = find_driver(locator.dyn_scheme(), enable_unstable)?
+        .features()
+        .source_args;
+    let limit = Some(opt.rows)
+        .filter(|_| source_features.contains(SourceArgumentsFeatures::Limit));
+    let source_args = SourceArguments::new(
+        from_args,
+        opt.from_format.clone(),
+        opt.where_clause.clone(),
+    )
+    .with_limit(limit);
+
+    // Figure out what table schema to use.
+    let schema = {
+        let schema_locator = schema_opt.as_ref().unwrap_or(&locator);
+        schema_locator
+            .schema(ctx.clone(), source_args.clone())
+            .await
+            .with_context(|| format!("error reading schema from {}", schema_locator))?
+            .ok_or_else(|| {
+                format_err!("don't know how to read schema from {}", schema_locator)
+            })
+    }?;
+
+    // Build our shared arguments. We only read one stream at a time.
+    let temporary_storage = TemporaryStorage::with_config(opt.temporaries, &config)?;
+    let shared_args = SharedArguments::new(schema.clone(), temporary_storage, 1);
+
+    // Read our data, stopping as soon as we have enough rows.
+    let data = locator
+        .local_data(ctx.clone(), shared_args, source_args)
+        .await?
+        .ok_or_else(|| format_err!("don't know how to read data from {}", locator))?;
+    let mut data = limit_csv_streams(ctx, opt.rows, data);
+    let mut rows = vec![];
+    while let Some(stream) = data.try_next().await? {
+        let bytes = stream
+            .data
+            .try_fold(vec![], |mut bytes, chunk| {
+                bytes.extend_from_slice(&chunk);
+                future::ready(Ok(bytes))
+            })
+            .await?;
+        let mut rdr = csv::Reader::from_reader(&bytes[..]);
+        for row in rdr.records() {
+            let row = row?;
+            rows.push(
+                convert_row(&schema, &row)
+                    .with_context(|| format!("error reading {}", stream.name))?,
+            );
+        }
+    }
+
+    // Print our rows.
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if opt.json {
+        for row in &rows {
+            write_json_row(&mut out, &schema, row)?;
+        }
+    } else {
+        write!(out, "{}", render_table(&schema, &rows))?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Convert a CSV row to typed JSON values, in column order.
+fn convert_row(schema: &Schema, row: &csv::StringRecord) -> Result<Vec<Value>> {
+    let columns = &schema.table.columns;
+    if row.len() != columns.len() {
+        return Err(format_err!(
+            "expected {} columns, found {}",
+            columns.len(),
+            row.len(),
+        ));
+    }
+    columns
+        .iter()
+        .zip(row.iter())
+        .map(|(col, cell)| {
+            convert_csv_field_to_json(schema, col, cell)
+                .with_context(|| format!("invalid column {:?}", col.name))
+        })
+        .collect()
+}
+
+/// Write a row as a JSON object, keeping our columns in order.
+fn write_json_row(out: &mut dyn Write, schema: &Schema, row: &[Value]) -> Result<()> {
+    write!(out, "{{")?;
+    for (idx, (col, value)) in schema.table.columns.iter().zip(row).enumerate() {
+        if idx > 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "{}:{}", serde_json::to_string(&col.name)?, value)?;
+    }
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+/// Render `rows` as an aligned text table, similar to `psql`. Numbers are
+/// aligned to the right, and `NULL` values are left blank.
+fn render_table(schema: &Schema, rows: &[Vec<Value>]) -> String {
+    let columns = &schema.table.columns;
+    let headers = columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+    let cells = rows
+        .iter()
+        .map(|row| row.iter().map(display_value).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let right_align = columns
+        .iter()
+        .map(|col| {
+            let data_type = match &col.data_type {
+                DataType::Named(name) => schema.data_type_for_name(name),
+                other => other,
+            };
+            matches!(
+                data_type,
+                DataType::Decimal
+                    | DataType::Float32
+                    | DataType::Float64
+                    | DataType::Int16
+                    | DataType::Int32
+                    | DataType::Int64
+            )
+        })
+        .collect::<Vec<_>>();
+    render_text_table(&headers, &cells, &right_align)
+}
+
+/// Render `cells` as an aligned text table with the specified `headers`,
+/// similar to `psql`.
+pub(crate) fn render_text_table(
+    headers: &[String],
+    cells: &[Vec<String>],
+    right_align: &[bool],
+) -> String {
+    let widths = (0..headers.len())
+        .map(|idx| {
+            cells
+                .iter()
+                .map(|row| row[idx].chars().count())
+                .chain([headers[idx].chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+    let mut push_line = |cells: Vec<String>, sep: &str| {
+        out.push_str(cells.join(sep).trim_end());
+        out.push('\n');
+    };
+    push_line(
+        headers
+            .iter()
+            .zip(&widths)
+            .map(|(h, &w)| format!("{:<w$}", h, w = w))
+            .collect(),
+        " | ",
+    );
+    push_line(widths.iter().map(|&w| "-".repeat(w)).collect(), "-+-");
+    for row in cells {
+        push_line(
+            row.iter()
+                .zip(&widths)
+                .zip(right_align)
+                .map(|((cell, &w), &right)| {
+                    if right {
+                        format!("{:>w$}", cell, w = w)
+                    } else {
+                        format!("{:<w$}", cell, w = w)
+                    }
+                })
+                .collect(),
+            " | ",
+        );
+    }
+    out
+}
+
+/// Format a value for display in a table cell, escaping anything which would
+/// break our layout.
+fn display_value(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.to_owned(),
+        other => other.to_string(),
+    };
+    text.chars()
+        .flat_map(|c| match c {
+            '\n' => vec!['\\', 'n'],
+            '\r' => vec!['\\', 'r'],
+            '\t' => vec!['\\', 't'],
+            c => vec![c],
+        })
+        .collect()
+}
+
+#[test]
+fn render_aligned_table() {
+    use crate::schema::{Column, Table};
+    use serde_json::json;
+
+    let column = |name: &str, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable: true,
+        data_type,
+        comment: None,
+    };
+    let schema = Schema::from_table(Table {
+        name: "example".to_owned(),
+        columns: vec![
+            column("id", DataType::Int64),
+            column("name", DataType::Text),
+            column("tags", DataType::Json),
+        ],
+    })
+    .unwrap();
+    let rows = vec![
+        vec![json!(1), json!("Alice"), json!(["a"])],
+        vec![json!(200), json!("line 1\nline 2"), Value::Null],
+    ];
+    assert_eq!(
+        render_table(&schema, &rows),
+        "\
+id  | name           | tags
+----+----------------+------
+  1 | Alice          | [\"a\"]
+200 | line 1\\nline 2 |
+",
+    );
+
+    let mut out = vec![];
+    write_json_row(&mut out, &schema, &rows[1]).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "{\"id\":200,\"name\":\"line 1\\nline 2\",\"tags\":null}\n",
+    );
+}
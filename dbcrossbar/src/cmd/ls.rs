@@ -0,0 +1,107 @@
+//! The `ls` subcommand.
+
+use std::io::{self, Write};
+
+use anyhow::Result;
+use clap::Parser;
+use tracing::instrument;
+
+use super::head::render_text_table;
+use crate::{
+    config::Configuration, locator::ListEntry, Context, DriverArguments,
+    SourceArguments, UnparsedLocator,
+};
+
+/// List arguments.
+#[derive(Debug, Parser)]
+pub(crate) struct Opt {
+    /// Print one JSON object per line, instead of a table.
+    #[clap(long = "json")]
+    json: bool,
+
+    /// Pass an extra argument of the form `key=value` to the source driver.
+    #[clap(long = "from-arg")]
+    from_args: Vec<String>,
+
+    /// The database, dataset, schema or directory to list.
+    locator: UnparsedLocator,
+}
+
+/// List the tables or files behind a locator.
+#[instrument(level = "debug", name = "ls", skip_all)]
+pub(crate) async fn run(
+    ctx: Context,
+    _config: Configuration,
+    enable_unstable: bool,
+    opt: Opt,
+) -> Result<()> {
+    let from_args = DriverArguments::from_cli_args(&opt.from_args)?;
+    let source_args = SourceArguments::new(from_args, None, None);
+    let entries = opt.locator.list(ctx, enable_unstable, source_args).await?;
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if opt.json {
+        for entry in &entries {
+            writeln!(out, "{}", serde_json::to_string(entry)?)?;
+        }
+    } else {
+        write!(out, "{}", render_entries(&entries))?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Render `entries` as an aligned text table.
+fn render_entries(entries: &[ListEntry]) -> String {
+    let headers = ["locator", "kind", "rows", "bytes"]
+        .iter()
+        .map(|&h| h.to_owned())
+        .collect::<Vec<_>>();
+    let display_count = |n: Option<u64>| n.map(|n| n.to_string()).unwrap_or_default();
+    let cells = entries
+        .iter()
+        .map(|entry| {
+            vec![
+                entry.locator.clone(),
+                entry.kind.to_string(),
+                display_count(entry.rows),
+                display_count(entry.bytes),
+            ]
+        })
+        .collect::<Vec<_>>();
+    render_text_table(&headers, &cells, &[false, false, true, true])
+}
+
+#[test]
+fn render_entries_as_table() {
+    use crate::locator::ListEntryKind;
+
+    let entries = vec![
+        ListEntry {
+            name: "public.users".to_owned(),
+            locator: "postgres://localhost/db#public.users".to_owned(),
+            kind: ListEntryKind::Table,
+            rows: Some(1200),
+            bytes: None,
+            modified: None,
+        },
+        ListEntry {
+            name: "public.active_users".to_owned(),
+            locator: "postgres://localhost/db#public.active_users".to_owned(),
+            kind: ListEntryKind::View,
+            rows: None,
+            bytes: None,
+            modified: None,
+        },
+    ];
+    assert_eq!(
+        render_entries(&entries),
+        "\
+locator                                     | kind  | rows | bytes
+--------------------------------------------+-------+------+------
+postgres://localhost/db#public.users        | table | 1200 |
+postgres://localhost/db#public.active_users | view  |      |
+",
+    );
+}
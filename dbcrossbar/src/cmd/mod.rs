@@ -9,8 +9,12 @@ pub(crate) mod config;
 pub(crate) mod count;
 pub(crate) mod cp;
 pub(crate) mod features;
+pub(crate) mod head;
 pub(crate) mod license;
+pub(crate) mod ls;
+pub(crate) mod run;
 pub(crate) mod schema;
+pub(crate) mod validate;
 
 /// Command-line options, parsed using `structopt`.
 #[derive(Debug, Parser)]
@@ -70,6 +74,18 @@ pub(crate) enum Command {
         command: features::Opt,
     },
 
+    /// Display the first few rows of a table.
+    #[clap(name = "head")]
+    #[clap(after_help = r#"EXAMPLE LOCATORS:
+    csv:data.csv
+    postgres://localhost:5432/db#table
+    bigquery:project:dataset.table
+"#)]
+    Head {
+        #[clap(flatten)]
+        command: head::Opt,
+    },
+
     /// Display license information.
     #[clap(name = "license")]
     License {
@@ -77,11 +93,42 @@ pub(crate) enum Command {
         command: license::Opt,
     },
 
+    /// List the tables or files behind a locator.
+    #[clap(name = "ls")]
+    #[clap(after_help = r#"EXAMPLE LOCATORS:
+    postgres://localhost:5432/db
+    bigquery:project:dataset
+    s3://bucket/dir/
+    gs://bucket/dir/
+"#)]
+    Ls {
+        #[clap(flatten)]
+        command: ls::Opt,
+    },
+
+    /// Run the copy jobs in a pipeline file.
+    #[clap(name = "run")]
+    Run {
+        #[clap(flatten)]
+        command: run::Opt,
+    },
+
     /// Schema-related commands.
     Schema {
         #[clap(flatten)]
         command: schema::Opt,
     },
+
+    /// Check that data matches a schema.
+    #[clap(name = "validate")]
+    #[clap(after_help = r#"EXAMPLE LOCATORS:
+    csv:data.csv
+    postgres://localhost:5432/db#table
+"#)]
+    Validate {
+        #[clap(flatten)]
+        command: validate::Opt,
+    },
 }
 
 pub(crate) fn run(ctx: Context, config: Configuration, opt: Opt) -> BoxFuture<()> {
@@ -97,11 +144,23 @@ pub(crate) fn run(ctx: Context, config: Configuration, opt: Opt) -> BoxFuture<()
         Command::Features { command } => {
             features::run(config, opt.enable_unstable, command).boxed()
         }
+        Command::Head { command } => {
+            head::run(ctx, config, opt.enable_unstable, command).boxed()
+        }
         Command::License { command } => {
             license::run(config, opt.enable_unstable, command).boxed()
         }
+        Command::Ls { command } => {
+            ls::run(ctx, config, opt.enable_unstable, command).boxed()
+        }
+        Command::Run { command } => {
+            run::run(config, opt.enable_unstable, command).boxed()
+        }
         Command::Schema { command } => {
             schema::run(ctx, config, opt.enable_unstable, command).boxed()
         }
+        Command::Validate { command } => {
+            validate::run(ctx, config, opt.enable_unstable, command).boxed()
+        }
     }
 }
@@ -0,0 +1,205 @@
+//! The `run` subcommand.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::PathBuf,
+    time::Instant,
+};
+
+use anyhow::{format_err, Result};
+use clap::Parser;
+use futures::{
+    future::BoxFuture, stream::FuturesUnordered, try_join, FutureExt, StreamExt,
+};
+use tracing::{error, info, instrument};
+
+use super::{cp, head::render_text_table};
+use crate::{
+    config::Configuration,
+    pipeline::{Job, Pipeline},
+    Context,
+};
+
+/// Pipeline arguments.
+#[derive(Debug, Parser)]
+pub(crate) struct Opt {
+    /// Only run this job, without running the jobs it depends on. May be
+    /// repeated.
+    #[clap(long = "only")]
+    only: Vec<String>,
+
+    /// Run this job and every job which depends on it.
+    #[clap(long = "from", conflicts_with = "only")]
+    from: Option<String>,
+
+    /// How many jobs should we run in parallel?
+    #[clap(long = "max-jobs", short = 'J', default_value = "1")]
+    max_jobs: usize,
+
+    /// The pipeline file to run.
+    pipeline: PathBuf,
+}
+
+/// What happened to a job?
+enum JobStatus {
+    /// The job succeeded.
+    Succeeded { seconds: f64 },
+    /// The job failed.
+    Failed { seconds: f64, error: String },
+    /// The job didn't run, because a job it depends on didn't succeed.
+    Skipped { dependency: String },
+}
+
+/// Run the copy jobs in a pipeline file.
+#[instrument(level = "debug", name = "run", skip_all)]
+pub(crate) async fn run(
+    config: Configuration,
+    enable_unstable: bool,
+    opt: Opt,
+) -> Result<()> {
+    let pipeline = Pipeline::from_path(&opt.pipeline)?;
+    let jobs = pipeline.select_jobs(&opt.only, opt.from.as_deref())?;
+
+    // Parse all our job arguments before we start, so that typos fail quickly.
+    let mut pending = jobs
+        .into_iter()
+        .map(|job| {
+            let cp_opt = cp::Opt::try_parse_from(job.cp_args())
+                .map_err(|err| format_err!("invalid job {:?}: {}", job.name, err))?;
+            Ok((job, cp_opt))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let job_names = pending
+        .iter()
+        .map(|(job, _)| job.name.clone())
+        .collect::<Vec<_>>();
+
+    // Start jobs as soon as their dependencies have succeeded, running at
+    // most `max_jobs` at a time. Dependencies which we weren't asked to run
+    // are assumed to have succeeded.
+    let mut statuses = HashMap::<String, JobStatus>::new();
+    let mut running =
+        FuturesUnordered::<BoxFuture<'static, (String, JobStatus)>>::new();
+    loop {
+        let mut idx = 0;
+        while idx < pending.len() {
+            let job = &pending[idx].0;
+            let mut ready = true;
+            let mut blocked_by = None;
+            for dep in job.depends_on.iter().filter(|d| job_names.contains(d)) {
+                match statuses.get(dep) {
+                    Some(JobStatus::Succeeded { .. }) => {}
+                    Some(_) => blocked_by = Some(dep.clone()),
+                    None => ready = false,
+                }
+            }
+            if let Some(dependency) = blocked_by {
+                let (job, _) = pending.remove(idx);
+                error!(
+                    "skipping {} because {} did not succeed",
+                    job.name, dependency
+                );
+                statuses.insert(job.name, JobStatus::Skipped { dependency });
+                // Start over, because this may cause other jobs to be skipped.
+                idx = 0;
+            } else if ready && running.len() < opt.max_jobs.max(1) {
+                let (job, cp_opt) = pending.remove(idx);
+                running.push(run_job(config.clone(), enable_unstable, job, cp_opt));
+            } else {
+                idx += 1;
+            }
+        }
+
+        match running.next().await {
+            Some((name, status)) => {
+                statuses.insert(name, status);
+            }
+            None => break,
+        }
+    }
+
+    // Print our summary.
+    let mut out = io::stdout().lock();
+    write!(out, "{}", render_summary(&job_names, &statuses))?;
+    out.flush()?;
+    let unsuccessful = statuses
+        .values()
+        .filter(|s| !matches!(s, JobStatus::Succeeded { .. }))
+        .count();
+    if unsuccessful > 0 {
+        return Err(format_err!(
+            "{} of {} jobs did not succeed",
+            unsuccessful,
+            job_names.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Run a single job. Each job gets its own `Context`, so that one failed job
+/// doesn't stop the others.
+fn run_job(
+    config: Configuration,
+    enable_unstable: bool,
+    job: Job,
+    cp_opt: cp::Opt,
+) -> BoxFuture<'static, (String, JobStatus)> {
+    async move {
+        info!("starting {}", job.name);
+        let start = Instant::now();
+        let (ctx, worker_fut) = Context::create();
+        let cp_fut = cp::run(ctx, config, enable_unstable, cp_opt);
+        let result = try_join!(cp_fut, worker_fut);
+        let seconds = start.elapsed().as_secs_f64();
+        let status = match result {
+            Ok(_) => {
+                info!("finished {} in {:.1}s", job.name, seconds);
+                JobStatus::Succeeded { seconds }
+            }
+            Err(err) => {
+                error!("error running {}: {:?}", job.name, err);
+                JobStatus::Failed {
+                    seconds,
+                    error: format!("{:#}", err).replace('\n', " "),
+                }
+            }
+        };
+        (job.name, status)
+    }
+    .boxed()
+}
+
+/// Render a summary of our jobs, in the order they appear in the pipeline.
+fn render_summary(
+    job_names: &[String],
+    statuses: &HashMap<String, JobStatus>,
+) -> String {
+    let headers = ["job", "status", "seconds", "error"]
+        .iter()
+        .map(|&h| h.to_owned())
+        .collect::<Vec<_>>();
+    let cells = job_names
+        .iter()
+        .map(|name| {
+            let (status, seconds, error) = match &statuses[name] {
+                JobStatus::Succeeded { seconds } => {
+                    ("ok", Some(*seconds), String::new())
+                }
+                JobStatus::Failed { seconds, error } => {
+                    ("failed", Some(*seconds), error.clone())
+                }
+                JobStatus::Skipped { dependency } => {
+                    ("skipped", None, format!("{} did not succeed", dependency))
+                }
+            };
+            vec![
+                name.clone(),
+                status.to_owned(),
+                seconds.map(|s| format!("{:.1}", s)).unwrap_or_default(),
+                error,
+            ]
+        })
+        .collect::<Vec<_>>();
+    render_text_table(&headers, &cells, &[false, false, true, false])
+}
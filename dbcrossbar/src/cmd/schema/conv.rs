@@ -5,8 +5,8 @@ use clap::Parser;
 use tracing::{field, instrument, Span};
 
 use crate::{
-    config::Configuration, Context, DestinationArguments, DriverArguments, IfExists,
-    SourceArguments, UnparsedLocator,
+    config::Configuration, flatten::Flatten, Context, DestinationArguments,
+    DriverArguments, IfExists, SourceArguments, UnparsedLocator,
 };
 
 /// Schema conversion arguments.
@@ -25,6 +25,16 @@ pub(crate) struct Opt {
     #[structopt(long = "to-arg")]
     to_args: Vec<String>,
 
+    /// Replace each struct column with one column per field, as `cp
+    /// --flatten-structs` would.
+    #[clap(long = "flatten-structs")]
+    flatten_structs: bool,
+
+    /// Replace an array column with its element type, as `cp --explode`
+    /// would.
+    #[clap(long = "explode")]
+    explode: Option<String>,
+
     /// The input schema.
     from_locator: UnparsedLocator,
 
@@ -60,6 +70,8 @@ pub(crate) async fn run(
         .ok_or_else(|| {
             format_err!("don't know how to read schema from {}", from_locator)
         })?;
+    let flatten = Flatten::new(opt.flatten_structs, opt.explode.as_deref(), &schema)?;
+    let schema = flatten.flattened_schema(schema);
     to_locator
         .write_schema(ctx, schema, opt.if_exists, dest_args)
         .await?;
@@ -0,0 +1,66 @@
+//! The `migrate` subcommand.
+
+use anyhow::{format_err, Result};
+use clap::Parser;
+use tracing::{field, instrument, Span};
+
+use crate::{
+    config::Configuration, Context, DriverArguments, SourceArguments, UnparsedLocator,
+};
+
+/// Schema migration arguments.
+#[derive(Debug, Parser)]
+pub(crate) struct Opt {
+    /// Pass an extra argument of the form `key=value` to the drivers which
+    /// read the old and new schemas.
+    #[structopt(long = "from-arg")]
+    from_args: Vec<String>,
+
+    /// The schema of the existing table.
+    old_locator: UnparsedLocator,
+
+    /// The schema we want the table to have.
+    new_locator: UnparsedLocator,
+
+    /// The table to migrate. This determines the SQL dialect and the table
+    /// name used in the output. We do not connect to this table.
+    table_locator: UnparsedLocator,
+}
+
+/// Print SQL which will migrate a table from one schema to another.
+#[instrument(level = "debug", name = "migrate", skip_all, fields(old, new, table))]
+pub(crate) async fn run(
+    ctx: Context,
+    _config: Configuration,
+    enable_unstable: bool,
+    opt: Opt,
+) -> Result<()> {
+    let old_locator = opt.old_locator.parse(enable_unstable)?;
+    let new_locator = opt.new_locator.parse(enable_unstable)?;
+    let table_locator = opt.table_locator.parse(enable_unstable)?;
+
+    // Fill in our span fields.
+    let span = Span::current();
+    span.record("old", field::display(&old_locator));
+    span.record("new", field::display(&new_locator));
+    span.record("table", field::display(&table_locator));
+
+    // Read both of our schemas.
+    let from_args = DriverArguments::from_cli_args(&opt.from_args)?;
+    let mut schemas = vec![];
+    for locator in [&old_locator, &new_locator] {
+        let source_args = SourceArguments::new(from_args.clone(), None, None);
+        let schema =
+            locator
+                .schema(ctx.clone(), source_args)
+                .await?
+                .ok_or_else(|| {
+                    format_err!("don't know how to read schema from {}", locator)
+                })?;
+        schemas.push(schema);
+    }
+
+    let sql = table_locator.migration_sql(&schemas[0], &schemas[1])?;
+    print!("{}", sql);
+    Ok(())
+}
@@ -4,6 +4,7 @@ use futures::FutureExt;
 use crate::{config::Configuration, tokio_glue::BoxFuture, Context};
 
 pub(crate) mod conv;
+pub(crate) mod migrate;
 
 /// Commands related to schemas.
 #[derive(Debug, Parser)]
@@ -28,6 +29,17 @@ pub(crate) enum Cmd {
         #[structopt(flatten)]
         command: conv::Opt,
     },
+
+    /// Print SQL which migrates a table from one schema to another.
+    #[clap(name = "migrate")]
+    #[clap(after_help = r#"EXAMPLE LOCATORS:
+    postgres-sql:old.sql postgres-sql:new.sql postgres://localhost:5432/db#table
+    bigquery-schema:old.json bigquery-schema:new.json bigquery:project:dataset.table
+"#)]
+    Migrate {
+        #[structopt(flatten)]
+        command: migrate::Opt,
+    },
 }
 
 pub(crate) fn run(
@@ -40,5 +52,8 @@ pub(crate) fn run(
         Opt {
             command: Cmd::Conv { command },
         } => conv::run(ctx, config, enable_unstable, command).boxed(),
+        Opt {
+            command: Cmd::Migrate { command },
+        } => migrate::run(ctx, config, enable_unstable, command).boxed(),
     }
 }
@@ -0,0 +1,218 @@
+//! The `validate` subcommand.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use anyhow::{format_err, Context as _, Result};
+use clap::Parser;
+use futures::{future, StreamExt, TryStreamExt};
+use tracing::{debug, field, instrument, Span};
+
+use crate::{
+    config::Configuration,
+    schema::{Column, DataType, Schema, Table},
+    tokio_glue::BoxStream,
+    transform::spawn_sync_transform,
+    validate::{validate_csv, ValidationError},
+    Context, CsvStream, DataFormat, DestinationArguments, DriverArguments, IfExists,
+    SharedArguments, SourceArguments, TemporaryStorage, UnparsedLocator,
+};
+
+/// Validation arguments.
+#[derive(Debug, Parser)]
+pub(crate) struct Opt {
+    /// The schema to validate against (defaults to input table schema).
+    #[clap(long = "schema")]
+    schema: Option<UnparsedLocator>,
+
+    /// Write invalid rows to this location, replacing any existing data. All
+    /// columns will be written as text.
+    #[clap(long = "rejects")]
+    rejects: Option<UnparsedLocator>,
+
+    /// Temporary directories, cloud storage buckets, datasets to use during
+    /// transfer (can be repeated).
+    #[clap(long = "temporary")]
+    temporaries: Vec<String>,
+
+    /// Pass an extra argument of the form `key=value` to the source driver.
+    #[clap(long = "from-arg")]
+    from_args: Vec<String>,
+
+    /// For directory- and file-like data sources, the format to assume. If not
+    /// specified, `dbcrossbar` will use the file extension to guess the format.
+    #[clap(long = "from-format")]
+    from_format: Option<DataFormat>,
+
+    /// SQL where clause specifying rows to use.
+    #[clap(long = "where")]
+    where_clause: Option<String>,
+
+    /// How many data streams should we attempt to validate in parallel?
+    #[clap(long = "max-streams", short = 'J', default_value = "4")]
+    max_streams: usize,
+
+    /// The data to validate.
+    locator: UnparsedLocator,
+}
+
+/// Validate data against a schema.
+#[instrument(level = "debug", name = "validate", skip_all, fields(locator))]
+pub(crate) async fn run(
+    ctx: Context,
+    config: Configuration,
+    enable_unstable: bool,
+    opt: Opt,
+) -> Result<()> {
+    let schema_opt = opt.schema.map(|s| s.parse(enable_unstable)).transpose()?;
+    let rejects_opt = opt.rejects.map(|s| s.parse(enable_unstable)).transpose()?;
+    let locator = opt.locator.parse(enable_unstable)?;
+
+    // Fill in our span fields.
+    let span = Span::current();
+    span.record("locator", field::display(&locator));
+
+    // Build our source arguments.
+    let from_args = DriverArguments::from_cli_args(&opt.from_args)?;
+    let source_args = SourceArguments::new(
+        from_args,
+        opt.from_format.clone(),
+        opt.where_clause.clone(),
+    );
+
+    // Figure out what table schema to use.
+    let schema = {
+        let schema_locator = schema_opt.as_ref().unwrap_or(&locator);
+        schema_locator
+            .schema(ctx.clone(), source_args.clone())
+            .await
+            .with_context(|| format!("error reading schema from {}", schema_locator))?
+            .ok_or_else(|| {
+                format_err!("don't know how to read schema from {}", schema_locator)
+            })
+    }?;
+
+    // Build our shared arguments.
+    let temporaries = opt.temporaries.clone();
+    let temporary_storage = TemporaryStorage::with_config(temporaries, &config)?;
+    let shared_args = SharedArguments::new(
+        schema.clone(),
+        temporary_storage.clone(),
+        opt.max_streams,
+    );
+
+    // Read our data.
+    let data = locator
+        .local_data(ctx.clone(), shared_args, source_args)
+        .await?
+        .ok_or_else(|| format_err!("don't know how to read data from {}", locator))?;
+
+    // Validate each stream, replacing its data with any rejected rows.
+    let invalid_rows = Arc::new(AtomicU64::new(0));
+    let rejects = {
+        let ctx = ctx.clone();
+        let schema = schema.clone();
+        let invalid_rows = invalid_rows.clone();
+        data.and_then(move |stream| {
+            let schema = schema.clone();
+            let invalid_rows = invalid_rows.clone();
+            let name = stream.name.clone();
+            let result = spawn_sync_transform(
+                ctx.clone(),
+                "validate_csv".to_owned(),
+                stream.data,
+                move |_ctx, rdr, mut wtr| {
+                    let mut report = |err: ValidationError| {
+                        print_validation_error(&name, err);
+                    };
+                    let count = validate_csv(&schema, rdr, &mut wtr, &mut report)
+                        .with_context(|| format!("error validating {}", name))?;
+                    // Record our count before we close `wtr`, so that it's
+                    // visible once the output stream ends.
+                    invalid_rows.fetch_add(count, Ordering::SeqCst);
+                    drop(wtr);
+                    Ok(())
+                },
+            )
+            .map(|data| CsvStream {
+                name: stream.name,
+                data,
+            });
+            future::ready(result)
+        })
+        .boxed()
+    };
+
+    if let Some(rejects_locator) = rejects_opt {
+        // Write rejected rows to `rejects_locator`, using all-text columns
+        // because the data didn't match our schema.
+        let rejects_shared_args = SharedArguments::new(
+            rejects_schema(&schema)?,
+            temporary_storage,
+            opt.max_streams,
+        );
+        let rejects_dest_args = DestinationArguments::new(
+            DriverArguments::default(),
+            None,
+            IfExists::Overwrite,
+        );
+        let dests = rejects_locator
+            .write_local_data(ctx, rejects, rejects_shared_args, rejects_dest_args)
+            .await?
+            .try_buffer_unordered(opt.max_streams)
+            .try_collect::<Vec<_>>()
+            .await?;
+        debug!("rejects locators: {:?}", dests);
+    } else {
+        consume_streams(rejects, opt.max_streams).await?;
+    }
+
+    match invalid_rows.load(Ordering::SeqCst) {
+        0 => Ok(()),
+        count => Err(format_err!("found {} invalid rows", count)),
+    }
+}
+
+/// Print a validation error to standard output.
+fn print_validation_error(stream_name: &str, err: ValidationError) {
+    match err.column {
+        Some(column) => {
+            println!("{}:{}:{}: {:#}", stream_name, err.line, column, err.error)
+        }
+        None => println!("{}:{}: {:#}", stream_name, err.line, err.error),
+    }
+}
+
+/// Build a schema for rejected rows, with the same column names as `schema`,
+/// but with every column nullable text.
+fn rejects_schema(schema: &Schema) -> Result<Schema> {
+    Schema::from_table(Table {
+        name: schema.table.name.clone(),
+        columns: schema
+            .table
+            .columns
+            .iter()
+            .map(|col| Column {
+                name: col.name.clone(),
+                is_nullable: true,
+                data_type: DataType::Text,
+                comment: None,
+            })
+            .collect(),
+    })
+}
+
+/// Read all our streams and throw away the data.
+async fn consume_streams(
+    streams: BoxStream<CsvStream>,
+    max_streams: usize,
+) -> Result<()> {
+    streams
+        .map_ok(|stream| stream.data.try_for_each(|_| future::ready(Ok(()))))
+        .try_buffer_unordered(max_streams)
+        .try_collect::<Vec<()>>()
+        .await?;
+    Ok(())
+}
@@ -101,9 +101,30 @@ async fn concatenate_csv_streams_strips_all_but_first_header() {
     try_join!(cmd_fut, worker_fut).unwrap();
 }
 
+#[tokio::test]
+async fn strip_csv_header_skips_empty_chunks() {
+    let (ctx, worker_fut) = Context::create();
+
+    let cmd_fut = async move {
+        let chunks = vec![
+            Ok(BytesMut::from(&b"a,b\n"[..])),
+            Ok(BytesMut::from(&b"3,4\n"[..])),
+        ];
+        let chunks = strip_csv_header(ctx.clone(), stream::iter(chunks).boxed())
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(chunks, vec![BytesMut::from(&b"3,4\n"[..])]);
+        Ok(())
+    };
+
+    try_join!(cmd_fut, worker_fut).unwrap();
+}
+
 /// Remove the CSV header from a CSV stream, passing everything else through
 /// untouched.
-fn strip_csv_header(
+pub(crate) fn strip_csv_header(
     ctx: Context,
     mut stream: BoxStream<BytesMut>,
 ) -> Result<BoxStream<BytesMut>> {
@@ -132,10 +153,13 @@ fn strip_csv_header(
                         Ok(Some(header_len)) => {
                             trace!("stripping {} bytes of headers", header_len);
                             let _headers = new_buffer.split_to(header_len);
-                            sender
-                                .send(Ok(new_buffer))
-                                .await
-                                .context("broken pipe prevented sending data")?;
+                            // Our readers don't expect empty chunks.
+                            if !new_buffer.is_empty() {
+                                sender
+                                    .send(Ok(new_buffer))
+                                    .await
+                                    .context("broken pipe prevented sending data")?;
+                            }
                             try_forward_to_sender(stream, &mut sender).await?;
                             return Ok(());
                         }
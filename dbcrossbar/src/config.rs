@@ -98,7 +98,7 @@ impl fmt::Display for Key<'_> {
 }
 
 /// Our `dbcrossbar.toml` configuration file.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Configuration {
     /// The path from which we read this file.
     path: PathBuf,
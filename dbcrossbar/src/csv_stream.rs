@@ -106,11 +106,14 @@ pub(crate) fn csv_stream_name<'a>(
         ));
     };
 
-    // Now strip any extension.
-    let name = basename_or_relative
-        .split('.')
-        .next()
-        .ok_or_else(|| format_err!("can't get basename of {}", file_path))?;
+    // Now strip any extension from the file name. We leave directory names
+    // alone, because Hive-style partition directories like `price=1.5` may
+    // contain a `.`.
+    let dir_len = basename_or_relative.rfind('/').map_or(0, |i| i + 1);
+    let name = match basename_or_relative[dir_len..].find('.') {
+        Some(i) => &basename_or_relative[..dir_len + i],
+        None => basename_or_relative,
+    };
     Ok(name)
 }
 
@@ -138,6 +141,7 @@ fn csv_stream_name_handles_directory_inputs() {
             "s3://bucket/dir/subdir/file3.csv",
             "subdir/file3",
         ),
+        ("dir/", "dir/price=1.5/file4.csv", "price=1.5/file4"),
     ];
     for &(base_path, file_path, stream_name) in expected {
         assert_eq!(csv_stream_name(base_path, file_path).unwrap(), stream_name);
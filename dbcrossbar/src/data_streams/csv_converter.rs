@@ -4,6 +4,7 @@ use async_trait::async_trait;
 
 use super::DataFormatConverter;
 use crate::{
+    bad_rows::BadRows,
     common::*,
     schema::{Column, DataType},
 };
@@ -60,7 +61,9 @@ impl DataFormatConverter for CsvConverter {
     async fn data_format_to_csv(
         &self,
         _ctx: &Context,
+        _name: &str,
         _schema: &Schema,
+        _bad_rows: &BadRows,
         data: BoxStream<BytesMut>,
     ) -> Result<BoxStream<BytesMut>> {
         Ok(data)
@@ -11,6 +11,7 @@ use uuid::Uuid;
 
 use super::DataFormatConverter;
 use crate::{
+    bad_rows::BadRows,
     common::*,
     from_csv_cell::FromCsvCell,
     schema::{Column, DataType},
@@ -33,16 +34,28 @@ impl DataFormatConverter for JsonLinesConverter {
     async fn data_format_to_csv(
         &self,
         ctx: &Context,
+        name: &str,
         schema: &Schema,
+        bad_rows: &BadRows,
         data: BoxStream<BytesMut>,
     ) -> Result<BoxStream<BytesMut>> {
+        // We divert bad rows here, before any columns are masked, so make sure
+        // that we won't leak them.
+        if bad_rows.is_enabled() {
+            bad_rows.check_can_divert_unmasked_rows()?;
+        }
+
         // Convert our JSON Lines stream to a CSV stream.
+        let name = name.to_owned();
         let transform_schema = schema.clone();
+        let bad_rows = bad_rows.clone();
         spawn_sync_transform(
             ctx.clone(),
             "copy_jsonl_to_csv".to_owned(),
             data,
-            move |_ctx, rdr, wtr| copy_jsonl_to_csv(&transform_schema, rdr, wtr),
+            move |_ctx, rdr, wtr| {
+                copy_jsonl_to_csv(&name, &transform_schema, &bad_rows, rdr, wtr)
+            },
         )
     }
 
@@ -66,7 +79,9 @@ impl DataFormatConverter for JsonLinesConverter {
 /// Synchronously copy a CSV file to a JSON Lines file. (This is a helper for
 /// `JsonLinesConverter::data_format_to_csv`.)
 fn copy_jsonl_to_csv(
+    name: &str,
     schema: &Schema,
+    bad_rows: &BadRows,
     rdr: Box<dyn Read>,
     mut wtr: Box<dyn Write>,
 ) -> Result<()> {
@@ -78,8 +93,15 @@ fn copy_jsonl_to_csv(
     write_header(&mut wtr, schema)?;
 
     let mut buffer = Vec::with_capacity(2 * 1024);
-    for line in rdr.lines() {
+    for (idx, line) in rdr.lines().enumerate() {
         let line = line?;
+        if bad_rows.is_enabled() {
+            // Make sure we can convert this row before we write any of it.
+            if let Err(err) = check_jsonl_row(schema, &line, &mut buffer) {
+                bad_rows.divert(name, Some(idx as u64 + 1), &line, err)?;
+                continue;
+            }
+        }
         let value: Value = serde_json::from_str(&line)?;
         write_row(&mut wtr, schema, value, &mut buffer)?;
     }
@@ -87,6 +109,18 @@ fn copy_jsonl_to_csv(
     Ok(())
 }
 
+/// Check whether `write_row` would succeed for `line`, without writing
+/// anything.
+fn check_jsonl_row(schema: &Schema, line: &str, buffer: &mut Vec<u8>) -> Result<()> {
+    let value: Value = serde_json::from_str(line)?;
+    let mut wtr = csv::WriterBuilder::new()
+        .buffer_capacity(256)
+        .from_writer(std::io::sink());
+    let result = write_row(&mut wtr, schema, value, buffer);
+    buffer.clear();
+    result
+}
+
 /// Write a series of JSON values as a CSV file.
 pub(crate) fn write_rows<W: Write>(
     wtr: &mut W,
@@ -151,7 +185,7 @@ fn write_row<W: Write>(
 }
 
 /// Write the specified value.
-fn write_json_value<W: Write>(
+pub(crate) fn write_json_value<W: Write>(
     wtr: &mut W,
     schema: &Schema,
     data_type: &DataType,
@@ -226,7 +260,7 @@ fn convert_csv_row_to_json(schema: &Schema, row: &StringRecord) -> Result<Value>
 }
 
 /// Convert a CSV field to a JSON value.
-fn convert_csv_field_to_json(
+pub(crate) fn convert_csv_field_to_json(
     schema: &Schema,
     column: &Column,
     value: &str,
@@ -2,6 +2,7 @@ use std::{borrow::Cow, ffi::OsStr, fmt, str::FromStr};
 
 use async_trait::async_trait;
 
+use crate::bad_rows::BadRows;
 use crate::common::*;
 
 mod csv_converter;
@@ -105,16 +106,18 @@ impl DataStream {
     }
 
     /// Convert this `DataStream` into a `CsvStream`. This is very cheap if
-    /// the data is already in CSV format.
+    /// the data is already in CSV format. Rows which can't be converted will
+    /// be passed to `bad_rows`.
     pub(crate) async fn into_csv_stream(
         self,
         ctx: &Context,
         schema: &Schema,
+        bad_rows: &BadRows,
     ) -> Result<CsvStream> {
         let data = self
             .format
             .converter()?
-            .data_format_to_csv(ctx, schema, self.data)
+            .data_format_to_csv(ctx, &self.name, schema, bad_rows, self.data)
             .await?;
         Ok(CsvStream {
             name: self.name,
@@ -155,11 +158,14 @@ trait DataFormatConverter: Send + Sync {
         Ok(None)
     }
 
-    /// Convert a stream to CSV format.
+    /// Convert a stream to CSV format, passing any rows which can't be
+    /// converted to `bad_rows`.
     async fn data_format_to_csv(
         &self,
         ctx: &Context,
+        name: &str,
         schema: &Schema,
+        bad_rows: &BadRows,
         data: BoxStream<BytesMut>,
     ) -> Result<BoxStream<BytesMut>>;
 
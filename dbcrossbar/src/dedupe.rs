@@ -0,0 +1,383 @@
+//! Support for `--dedupe-on` and `--keep`.
+
+use std::{cmp::Ordering, str::FromStr};
+
+use crate::common::*;
+use crate::concat::concatenate_csv_streams;
+use crate::external_sort::{external_sort, RowOrder, MAX_RUN_BYTES};
+use crate::filter::{cell_value, FilterType, Scalar};
+use crate::transform::spawn_sync_transform;
+
+/// Which row should we keep for each key?
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Keep {
+    /// Keep any one row. When deduplicating locally, this is the first row we
+    /// see.
+    Any,
+    /// Keep the row with the largest value in this column. `NULL` values are
+    /// smaller than everything else.
+    LatestBy(String),
+}
+
+impl FromStr for Keep {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "any" {
+            Ok(Keep::Any)
+        } else if let Some(column) = s.strip_prefix("latest-by=") {
+            if column.is_empty() {
+                return Err(format_err!("--keep=latest-by= needs a column name"));
+            }
+            Ok(Keep::LatestBy(column.to_owned()))
+        } else {
+            Err(format_err!(
+                "--keep must be `any` or `latest-by=COL`, found {:?}",
+                s
+            ))
+        }
+    }
+}
+
+/// Remove rows with duplicate keys.
+#[derive(Clone, Debug)]
+pub struct Dedupe {
+    /// The columns which make up our key.
+    keys: Vec<String>,
+    /// The type of each column in `keys`.
+    key_types: Vec<FilterType>,
+    /// Which row to keep for each key.
+    keep: Keep,
+    /// The type of our `latest-by` column, if we have one.
+    keep_type: Option<FilterType>,
+}
+
+impl Dedupe {
+    /// Parse `--dedupe-on` and `--keep`, and check them against `schema`.
+    pub(crate) fn new(
+        keys: &str,
+        keep: Option<&str>,
+        schema: &Schema,
+    ) -> Result<Self> {
+        let find_type = |name: &str| -> Result<FilterType> {
+            let column = schema
+                .table
+                .columns
+                .iter()
+                .find(|c| c.name == name)
+                .ok_or_else(|| format_err!("unknown column {:?}", name))?;
+            match FilterType::for_data_type(schema, &column.data_type) {
+                FilterType::Other => Err(format_err!(
+                    "cannot deduplicate using column {:?} of type {}",
+                    name,
+                    FilterType::Other,
+                )),
+                ty => Ok(ty),
+            }
+        };
+
+        let keys = keys
+            .split(',')
+            .map(|k| k.trim().to_owned())
+            .collect::<Vec<_>>();
+        let mut key_types = vec![];
+        for (i, key) in keys.iter().enumerate() {
+            if key.is_empty() {
+                return Err(format_err!("--dedupe-on contains an empty column name"));
+            }
+            if keys[..i].contains(key) {
+                return Err(format_err!("--dedupe-on lists {:?} twice", key));
+            }
+            key_types.push(find_type(key).context("invalid --dedupe-on")?);
+        }
+
+        let keep = keep.map(Keep::from_str).transpose()?.unwrap_or(Keep::Any);
+        let keep_type = match &keep {
+            Keep::Any => None,
+            Keep::LatestBy(column) => {
+                Some(find_type(column).context("invalid --keep")?)
+            }
+        };
+        Ok(Dedupe {
+            keys,
+            key_types,
+            keep,
+            keep_type,
+        })
+    }
+
+    /// The columns which make up our key.
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Which row to keep for each key.
+    pub(crate) fn keep(&self) -> &Keep {
+        &self.keep
+    }
+
+    /// All the columns we need to look at.
+    pub(crate) fn column_names(&self) -> impl Iterator<Item = &str> + '_ {
+        let latest_by = match &self.keep {
+            Keep::Any => None,
+            Keep::LatestBy(column) => Some(column.as_str()),
+        };
+        self.keys.iter().map(|k| k.as_str()).chain(latest_by)
+    }
+
+    /// Synchronously deduplicate a single CSV stream.
+    ///
+    /// We sort our rows by key, putting the row we want to keep first, and
+    /// then drop the other rows for each key. This uses an external sort, so
+    /// it can handle inputs which are much larger than RAM.
+    fn dedupe_csv(
+        &self,
+        rdr: impl Read,
+        wtr: impl Write,
+        max_run_bytes: usize,
+    ) -> Result<()> {
+        let mut rdr = csv::Reader::from_reader(rdr);
+        let mut wtr = csv::Writer::from_writer(wtr);
+
+        // Look up the columns we need.
+        let headers = rdr.headers()?.clone();
+        let find_index = |name: &str| {
+            headers.iter().position(|h| h == name).ok_or_else(|| {
+                format_err!("cannot find column {:?} in CSV data", name)
+            })
+        };
+        let order = DedupeOrder {
+            dedupe: self,
+            keys: self
+                .keys
+                .iter()
+                .map(|k| find_index(k))
+                .collect::<Result<Vec<_>>>()?,
+            keep: match &self.keep {
+                Keep::Any => None,
+                Keep::LatestBy(column) => Some(find_index(column)?),
+            },
+        };
+        wtr.write_record(&headers)?;
+
+        let rows = rdr.records().map(|row| {
+            let row = row?;
+            order.check_values(&row)?;
+            Ok(row)
+        });
+        external_sort(rows, &order, max_run_bytes, &mut wtr)?;
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Sorts rows by key, putting the row we want to keep first.
+struct DedupeOrder<'a> {
+    /// What we're doing.
+    dedupe: &'a Dedupe,
+    /// The CSV indices of our key columns.
+    keys: Vec<usize>,
+    /// The CSV index of our `latest-by` column, if any.
+    keep: Option<usize>,
+}
+
+impl DedupeOrder<'_> {
+    /// Make sure we can parse our key and `latest-by` values, so that
+    /// `compare` won't need to report errors.
+    fn check_values(&self, row: &csv::StringRecord) -> Result<()> {
+        for ((key, &ty), &i) in self
+            .dedupe
+            .keys
+            .iter()
+            .zip(&self.dedupe.key_types)
+            .zip(&self.keys)
+        {
+            if !row[i].is_empty() {
+                cell_value(&row[i], ty).with_context(|| {
+                    format!("cannot deduplicate using column {:?}", key)
+                })?;
+            }
+        }
+        if let (Some(i), Some(ty), Keep::LatestBy(column)) =
+            (self.keep, self.dedupe.keep_type, &self.dedupe.keep)
+        {
+            if !row[i].is_empty() {
+                cell_value(&row[i], ty).with_context(|| {
+                    format!("cannot deduplicate using column {:?}", column)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compare the values of the key column at index `i`, which has type `ty`.
+    /// We parse our values, so that `01` and `1` are the same key, just as
+    /// they would be in a database. `NULL` values are equal to each other.
+    fn compare_key(
+        ty: FilterType,
+        i: usize,
+        a: &csv::StringRecord,
+        b: &csv::StringRecord,
+    ) -> Ordering {
+        fn value(
+            ty: FilterType,
+            i: usize,
+            row: &csv::StringRecord,
+        ) -> Option<Scalar<'_>> {
+            if row[i].is_empty() {
+                None
+            } else {
+                cell_value(&row[i], ty).ok()
+            }
+        }
+        match (value(ty, i, a), value(ty, i, b)) {
+            // `NaN` can't be ordered, so fall back to comparing the text.
+            (Some(va), Some(vb)) => {
+                va.partial_cmp(&vb).unwrap_or_else(|| a[i].cmp(&b[i]))
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    /// Get our `latest-by` value, or `None` if it's `NULL` or we don't have one.
+    fn keep_value<'a>(&self, row: &'a csv::StringRecord) -> Option<Scalar<'a>> {
+        let (i, ty) = (self.keep?, self.dedupe.keep_type?);
+        if row[i].is_empty() {
+            None
+        } else {
+            cell_value(&row[i], ty).ok()
+        }
+    }
+}
+
+impl RowOrder for DedupeOrder<'_> {
+    fn compare(&self, a: &csv::StringRecord, b: &csv::StringRecord) -> Ordering {
+        self.keys
+            .iter()
+            .zip(&self.dedupe.key_types)
+            .map(|(&i, &ty)| Self::compare_key(ty, i, a, b))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| {
+                // Larger values come first, and `NULL` comes last.
+                match (self.keep_value(a), self.keep_value(b)) {
+                    (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            })
+    }
+
+    fn is_duplicate(&self, a: &csv::StringRecord, b: &csv::StringRecord) -> bool {
+        self.keys
+            .iter()
+            .zip(&self.dedupe.key_types)
+            .all(|(&i, &ty)| Self::compare_key(ty, i, a, b).is_eq())
+    }
+}
+
+/// Only keep one row for each key in `data`, counting across all streams. This
+/// combines all our streams into one.
+pub(crate) fn dedupe_csv_streams(
+    ctx: Context,
+    dedupe: Dedupe,
+    data: BoxStream<CsvStream>,
+) -> Result<BoxStream<CsvStream>> {
+    let input = concatenate_csv_streams(ctx.clone(), data)?;
+    let data = spawn_sync_transform(
+        ctx,
+        "dedupe_csv".to_owned(),
+        input.data,
+        move |_ctx, rdr, wtr| dedupe.dedupe_csv(rdr, wtr, MAX_RUN_BYTES),
+    )?;
+    Ok(box_stream_once(Ok(CsvStream {
+        name: "deduped".to_owned(),
+        data,
+    })))
+}
+
+#[test]
+fn dedupe_csv_with_and_without_runs() {
+    use std::io::Cursor;
+
+    let schema = crate::filter::test_schema();
+    let input = "\
+id,name,seen_at
+1,a,2024-01-01 00:00:00Z
+2,b,
+1,c,2024-03-01 00:00:00Z
+2,d,2024-02-01 00:00:00Z
+3,e,
+1,f,2024-02-01 00:00:00+02:00
+3,g,
+";
+    let examples = &[
+        (None, "1,a\n2,b\n3,e\n"),
+        (Some("latest-by=seen_at"), "1,c\n2,d\n3,e\n"),
+    ];
+    for &(keep, expected) in examples {
+        let dedupe = Dedupe::new("id", keep, &schema).unwrap();
+        // A tiny `max_run_bytes` forces us to write every row to a separate
+        // run.
+        for max_run_bytes in [MAX_RUN_BYTES, 1] {
+            let mut out = vec![];
+            dedupe
+                .dedupe_csv(Cursor::new(input.as_bytes()), &mut out, max_run_bytes)
+                .unwrap();
+            let mut rdr = csv::Reader::from_reader(&out[..]);
+            let rows = rdr
+                .records()
+                .map(|r| {
+                    let r = r.unwrap();
+                    format!("{},{}\n", &r[0], &r[1])
+                })
+                .collect::<String>();
+            assert_eq!(
+                rows, expected,
+                "keep: {:?}, max_run_bytes: {}",
+                keep, max_run_bytes
+            );
+        }
+    }
+}
+
+#[test]
+fn dedupe_compares_parsed_keys() {
+    use std::io::Cursor;
+
+    let schema = crate::filter::test_schema();
+    // These rows have the same `id` and `seen_at`, written differently.
+    let input = "\
+id,name,seen_at
+1,a,2024-01-01 00:00:00Z
+01,b,2024-01-01 00:00:00+00:00
+";
+    for keys in ["id", "seen_at", "id,seen_at"] {
+        let dedupe = Dedupe::new(keys, None, &schema).unwrap();
+        let mut out = vec![];
+        dedupe
+            .dedupe_csv(Cursor::new(input.as_bytes()), &mut out, MAX_RUN_BYTES)
+            .unwrap();
+        let mut rdr = csv::Reader::from_reader(&out[..]);
+        assert_eq!(rdr.records().count(), 1, "keys: {:?}", keys);
+    }
+
+    // Bad key values are reported.
+    let dedupe = Dedupe::new("id", None, &schema).unwrap();
+    let input = "id,name\nx,a\n";
+    assert!(dedupe
+        .dedupe_csv(Cursor::new(input.as_bytes()), vec![], MAX_RUN_BYTES)
+        .is_err());
+}
+
+#[test]
+fn dedupe_rejects_bad_columns() {
+    let schema = crate::filter::test_schema();
+    assert!(Dedupe::new("id,missing", None, &schema).is_err());
+    assert!(Dedupe::new("id,id", None, &schema).is_err());
+    assert!(Dedupe::new("tags", None, &schema).is_err());
+    assert!(Dedupe::new("id", Some("latest-by=tags"), &schema).is_err());
+    assert!(Dedupe::new("id", Some("newest"), &schema).is_err());
+}
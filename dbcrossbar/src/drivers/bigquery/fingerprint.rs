@@ -0,0 +1,72 @@
+//! Implementation of `fingerprint`, but as a real `async` function.
+
+use std::collections::HashMap;
+
+use crate::clouds::gcloud::bigquery;
+use crate::common::*;
+use crate::drivers::{
+    bigquery::BigQueryLocator,
+    bigquery_shared::{BqTable, GCloudDriverArguments, Usage},
+};
+use crate::fingerprint::Fingerprint;
+
+/// Implementation of `fingerprint`, but as a real `async` function.
+#[instrument(
+    level = "trace",
+    name = "bigquery::fingerprint",
+    skip(shared_args, source_args)
+)]
+pub(crate) async fn fingerprint_helper(
+    locator: BigQueryLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Fingerprint> {
+    let shared_args = shared_args.verify(BigQueryLocator::features())?;
+    let source_args = source_args.verify(BigQueryLocator::features())?;
+
+    // Get our billing labels.
+    let driver_args = GCloudDriverArguments::try_from(&source_args)?;
+    let job_labels = driver_args.job_labels.to_owned();
+
+    // In case the user wants to run the job in a different project for billing
+    // purposes.
+    let job_project_id = driver_args
+        .job_project_id
+        .clone()
+        .unwrap_or_else(|| locator.project().to_owned());
+
+    // Look up the arguments we need.
+    let schema = shared_args.schema();
+
+    // Construct a `BqTable` describing our source table.
+    let table_name = locator.as_table_name().to_owned();
+    let table = BqTable::for_table_name_and_columns(
+        schema,
+        table_name,
+        &schema.table.columns,
+        Usage::FinalTable,
+    )?;
+
+    // Generate our fingerprint SQL.
+    let mut sql_data = vec![];
+    table.write_fingerprint_sql(schema, &source_args, &mut sql_data)?;
+    let sql = String::from_utf8(sql_data).expect("should always be UTF-8");
+    debug!("fingerprint SQL: {}", sql);
+
+    // Run our query, and put our values back in order.
+    let client = driver_args.client().await?;
+    let mut row = bigquery::query_one::<HashMap<String, Option<String>>>(
+        &client,
+        &job_project_id,
+        &sql,
+        &job_labels,
+    )
+    .await?;
+    let values = (0..row.len())
+        .map(|i| {
+            row.remove(&format!("v{}", i))
+                .ok_or_else(|| format_err!("fingerprint query did not return v{}", i))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Fingerprint::from_values(schema, values)
+}
@@ -0,0 +1,69 @@
+//! Implementation of `list`, but as a real `async` function.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::BigQueryLocator;
+use crate::clouds::gcloud::bigquery;
+use crate::common::*;
+use crate::drivers::bigquery_shared::{GCloudDriverArguments, TableName};
+
+/// Implementation of `list`, but as a real `async` function.
+#[instrument(level = "trace", name = "bigquery::list", skip(source_args))]
+pub(crate) async fn list_helper(
+    container: String,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Vec<ListEntry>> {
+    let source_args = source_args.verify(BigQueryLocator::features())?;
+    let (project, dataset) = parse_dataset(&container)?;
+
+    let driver_args = GCloudDriverArguments::try_from(&source_args)?;
+    let client = driver_args.client().await?;
+
+    let tables = bigquery::list_tables(&client, &project, &dataset).await?;
+    tables
+        .into_iter()
+        .map(|table| {
+            let reference = table.table_reference;
+            let table_name = format!(
+                "{}:{}.{}",
+                reference.project_id, reference.dataset_id, reference.table_id,
+            )
+            .parse::<TableName>()?;
+            let kind = match table.table_type.as_str() {
+                "VIEW" | "MATERIALIZED_VIEW" => ListEntryKind::View,
+                _ => ListEntryKind::Table,
+            };
+            Ok(ListEntry {
+                name: reference.table_id,
+                locator: BigQueryLocator { table_name }.to_string(),
+                kind,
+                rows: None,
+                bytes: None,
+                modified: None,
+            })
+        })
+        .collect()
+}
+
+/// Parse a `bigquery:project:dataset` locator into a project and dataset.
+fn parse_dataset(container: &str) -> Result<(String, String)> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new("^bigquery:([^:.`]+):([^:.`]+)$")
+            .expect("invalid regex in source");
+    }
+    let cap = RE.captures(container).ok_or_else(|| {
+        format_err!("expected bigquery:project:dataset, found {:?}", container)
+    })?;
+    Ok((cap[1].to_owned(), cap[2].to_owned()))
+}
+
+#[test]
+fn parse_dataset_requires_project_and_dataset() {
+    assert_eq!(
+        parse_dataset("bigquery:project:dataset").unwrap(),
+        ("project".to_owned(), "dataset".to_owned()),
+    );
+    assert!(parse_dataset("bigquery:project:dataset.table").is_err());
+    assert!(parse_dataset("bigquery:dataset").is_err());
+}
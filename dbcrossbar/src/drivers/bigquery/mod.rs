@@ -3,15 +3,23 @@
 use std::{fmt, str::FromStr};
 
 use crate::common::*;
-use crate::drivers::{bigquery_shared::TableName, gs::GsLocator};
+use crate::drivers::{
+    bigquery_shared::{BqTable, TableName, Usage},
+    gs::GsLocator,
+};
+use crate::fingerprint::Fingerprint;
 
 mod count;
+mod fingerprint;
+mod list;
 mod local_data;
 mod schema;
 mod write_local_data;
 mod write_remote_data;
 
 use self::count::count_helper;
+use self::fingerprint::fingerprint_helper;
+use self::list::list_helper;
 use self::local_data::local_data_helper;
 use self::schema::schema_helper;
 use self::write_local_data::write_local_data_helper;
@@ -71,6 +79,26 @@ impl Locator for BigQueryLocator {
         schema_helper(self.to_owned(), source_args).boxed()
     }
 
+    fn migration_sql(
+        &self,
+        old_schema: &Schema,
+        new_schema: &Schema,
+    ) -> Result<String> {
+        let bq_table = |schema: &Schema| {
+            BqTable::for_table_name_and_columns(
+                schema,
+                self.table_name.clone(),
+                &schema.table.columns,
+                Usage::FinalTable,
+            )
+        };
+        let old_table = bq_table(old_schema)?;
+        let new_table = bq_table(new_schema)?;
+        let mut sql_bytes: Vec<u8> = vec![];
+        new_table.write_alter_table_sql(&old_table, &mut sql_bytes)?;
+        Ok(String::from_utf8(sql_bytes).expect("should always be UTF-8"))
+    }
+
     fn count(
         &self,
         _ctx: Context,
@@ -80,6 +108,15 @@ impl Locator for BigQueryLocator {
         count_helper(self.to_owned(), shared_args, source_args).boxed()
     }
 
+    fn fingerprint(
+        &self,
+        _ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Fingerprint> {
+        fingerprint_helper(self.to_owned(), shared_args, source_args).boxed()
+    }
+
     fn local_data(
         &self,
         ctx: Context,
@@ -101,9 +138,13 @@ impl Locator for BigQueryLocator {
     }
 
     fn supports_write_remote_data(&self, source: &dyn Locator) -> bool {
-        // We can only do `write_remote_data` if `source` is a `GsLocator`.
-        // Otherwise, we need to do `write_local_data` like normal.
-        source.as_any().is::<GsLocator>()
+        // We can only do `write_remote_data` if `source` is a `GsLocator`
+        // without a glob. Otherwise, we need to do `write_local_data` like
+        // normal.
+        source
+            .as_any()
+            .downcast_ref::<GsLocator>()
+            .is_some_and(|gs| !gs.is_glob())
     }
 
     fn write_remote_data(
@@ -135,11 +176,20 @@ impl LocatorStatic for BigQueryLocator {
             locator: LocatorFeatures::Schema
                 | LocatorFeatures::LocalData
                 | LocatorFeatures::WriteLocalData
-                | LocatorFeatures::Count,
+                | LocatorFeatures::Count
+                | LocatorFeatures::Fingerprint
+                | LocatorFeatures::MigrationSql
+                | LocatorFeatures::List,
             write_schema_if_exists: EnumSet::empty(),
             source_args: SourceArgumentsFeatures::DriverArgs
-                | SourceArgumentsFeatures::WhereClause,
-            dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
+                | SourceArgumentsFeatures::WhereClause
+                | SourceArgumentsFeatures::Filter
+                | SourceArgumentsFeatures::Dedupe
+                | SourceArgumentsFeatures::Limit
+                | SourceArgumentsFeatures::Sample
+                | SourceArgumentsFeatures::SampleSeed,
+            dest_args: DestinationArgumentsFeatures::DriverArgs
+                | DestinationArgumentsFeatures::SchemaEvolution,
             dest_if_exists: IfExistsFeatures::Error
                 | IfExistsFeatures::Overwrite
                 | IfExistsFeatures::Append
@@ -147,4 +197,12 @@ impl LocatorStatic for BigQueryLocator {
             _placeholder: (),
         }
     }
+
+    fn list(
+        _ctx: Context,
+        container: &str,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>> {
+        list_helper(container.to_owned(), source_args).boxed()
+    }
 }
@@ -1,12 +1,13 @@
 //! Implementation of `BigQueryLocator::write_remote_data`.
 
 use super::BigQueryLocator;
-use crate::clouds::gcloud::bigquery;
+use crate::clouds::gcloud::{bigquery, Client};
 use crate::common::*;
 use crate::drivers::{
     bigquery_shared::{BqTable, GCloudDriverArguments, SchemaBigQueryExt, Usage},
     gs::GsLocator,
 };
+use crate::schema_evolution::SchemaEvolution;
 
 /// Copy `source` to `dest` using `schema`.
 ///
@@ -68,6 +69,18 @@ pub(crate) async fn write_remote_data_helper(
         .job_project_id
         .unwrap_or_else(|| dest.project().to_owned());
 
+    // If we're allowed to, update the destination table to match our schema.
+    if dest_args.allow_schema_evolution() {
+        evolve_table_schema(
+            &dest_client,
+            &dest,
+            schema,
+            &final_job_project_id,
+            &job_labels,
+        )
+        .await?;
+    }
+
     // If our URL looks like a directory, add a glob.
     //
     // TODO: Is this the right way to default this? Or should we make users
@@ -153,3 +166,28 @@ pub(crate) async fn write_remote_data_helper(
 
     Ok(vec![dest.boxed()])
 }
+
+/// Add any missing columns to `dest`, and widen any column types that need it,
+/// so that it can hold data matching `schema`. This implements
+/// `--allow-schema-evolution`. If `dest` doesn't exist yet, we do nothing.
+#[instrument(level = "trace", skip(client, schema, job_labels))]
+async fn evolve_table_schema(
+    client: &Client,
+    dest: &BigQueryLocator,
+    schema: &Schema,
+    job_project_id: &str,
+    job_labels: &bigquery::Labels,
+) -> Result<()> {
+    let existing =
+        match BqTable::read_from_table_if_exists(client, &dest.table_name).await? {
+            Some(existing) => Schema::from_table(existing.to_table()?)?,
+            None => return Ok(()),
+        };
+    let evolution = SchemaEvolution::new(&existing, schema)?;
+    if evolution.is_empty() {
+        return Ok(());
+    }
+    let alter_sql = dest.migration_sql(&evolution.before, &evolution.after)?;
+    debug!("evolving schema of {}: {}", dest.table_name, alter_sql);
+    bigquery::execute_sql(client, job_project_id, &alter_sql, job_labels).await
+}
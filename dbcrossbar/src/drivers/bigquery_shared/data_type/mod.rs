@@ -96,6 +96,24 @@ impl BqDataType {
         matches!(self, BqDataType::Array(_))
     }
 
+    /// Can an existing column of this type be converted to `other` using
+    /// `ALTER COLUMN ... SET DATA TYPE`? BigQuery only supports a handful of
+    /// widening conversions.
+    pub(crate) fn can_be_altered_to(&self, other: &BqDataType) -> bool {
+        matches!(
+            (self, other),
+            (
+                BqDataType::NonArray(BqNonArrayDataType::Int64),
+                BqDataType::NonArray(
+                    BqNonArrayDataType::Numeric | BqNonArrayDataType::Float64
+                ),
+            ) | (
+                BqDataType::NonArray(BqNonArrayDataType::Numeric),
+                BqDataType::NonArray(BqNonArrayDataType::Float64),
+            )
+        )
+    }
+
     /// Can this type be safely represented as a JSON value?
     pub(crate) fn is_json_safe(&self) -> bool {
         match self {
@@ -7,11 +7,18 @@ use std::{
     fmt,
 };
 
-use super::{BqColumn, ColumnBigQueryExt, ColumnName, TableName, Usage};
+use super::{BqColumn, ColumnBigQueryExt, ColumnName, Ident, TableName, Usage};
 use crate::clouds::gcloud::{bigquery, Client};
 use crate::common::*;
+use crate::dedupe::{Dedupe, Keep};
+use crate::filter::SqlDialect;
+use crate::fingerprint::Aggregate;
 use crate::schema::{Column, Table};
 
+/// How many hash buckets to use for seeded samples. This determines how
+/// precisely we can represent `--sample` fractions.
+const SAMPLE_BUCKETS: u32 = 1_000_000;
+
 /// Which version of CREATE TABLE do we want to use?
 #[derive(Clone, Copy)]
 enum CreateTableType {
@@ -103,6 +110,14 @@ impl BqTable {
         bigquery::schema(client, name).await
     }
 
+    /// Read a table from BigQuery, returning `None` if it doesn't exist.
+    pub(crate) async fn read_from_table_if_exists(
+        client: &Client,
+        name: &TableName,
+    ) -> Result<Option<BqTable>> {
+        bigquery::schema_if_exists(client, name).await
+    }
+
     /// Create a new table based on this table, but with columns matching the
     /// the names and order of the columns in `other_table`. This is useful if
     /// we want to insert from `other_table` into `self`, or export `self` using
@@ -227,6 +242,100 @@ impl BqTable {
         Ok(())
     }
 
+    /// Write `ALTER TABLE` statements which will migrate `old_table` so that
+    /// it matches this table.
+    ///
+    /// BigQuery only allows a few kinds of schema changes: adding `NULLABLE`
+    /// columns, dropping columns, relaxing `REQUIRED` columns and widening a
+    /// few numeric types. We return an error for anything else.
+    pub(crate) fn write_alter_table_sql(
+        &self,
+        old_table: &BqTable,
+        f: &mut dyn Write,
+    ) -> Result<()> {
+        let name = self.name.dotted_and_quoted();
+        let new_columns = self
+            .columns
+            .iter()
+            .map(|c| (&c.name, c))
+            .collect::<HashMap<&ColumnName, &BqColumn>>();
+        let old_columns = old_table
+            .columns
+            .iter()
+            .map(|c| (&c.name, c))
+            .collect::<HashMap<&ColumnName, &BqColumn>>();
+
+        for old_col in &old_table.columns {
+            if !new_columns.contains_key(&old_col.name) {
+                writeln!(
+                    f,
+                    "ALTER TABLE {} DROP COLUMN {};",
+                    name,
+                    old_col.name.quoted(),
+                )?;
+            }
+        }
+        for new_col in &self.columns {
+            let old_col = match old_columns.get(&new_col.name) {
+                Some(old_col) => old_col,
+                None => {
+                    if new_col.is_not_null() {
+                        return Err(format_err!(
+                            "BigQuery cannot add REQUIRED column {} to an existing table",
+                            new_col.name.quoted(),
+                        ));
+                    }
+                    writeln!(
+                        f,
+                        "ALTER TABLE {} ADD COLUMN {} {};",
+                        name,
+                        new_col.name.quoted(),
+                        new_col.bq_data_type()?,
+                    )?;
+                    continue;
+                }
+            };
+
+            // Compare the SQL versions of our types, because several portable
+            // types may be stored as the same BigQuery type.
+            let old_ty = old_col.bq_data_type()?;
+            let new_ty = new_col.bq_data_type()?;
+            if old_ty.to_string() != new_ty.to_string() {
+                if !old_ty.can_be_altered_to(&new_ty) {
+                    return Err(format_err!(
+                        "BigQuery cannot change column {} from {} to {}",
+                        new_col.name.quoted(),
+                        old_ty,
+                        new_ty,
+                    ));
+                }
+                writeln!(
+                    f,
+                    "ALTER TABLE {} ALTER COLUMN {} SET DATA TYPE {};",
+                    name,
+                    new_col.name.quoted(),
+                    new_ty,
+                )?;
+            }
+            match (old_col.is_not_null(), new_col.is_not_null()) {
+                (true, false) => writeln!(
+                    f,
+                    "ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL;",
+                    name,
+                    new_col.name.quoted(),
+                )?,
+                (false, true) => {
+                    return Err(format_err!(
+                        "BigQuery cannot make existing column {} REQUIRED",
+                        new_col.name.quoted(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
     /// Generate SQL which `SELECT`s from a temp table, and fixes the types
     /// of columns that couldn't be imported from CSVs.
     ///
@@ -368,9 +477,44 @@ WHEN NOT MATCHED THEN INSERT (
             }
             col.write_export_select_expr(f, i)?;
         }
+
+        // If we're deduplicating, do it in a subquery, and then we only need
+        // to apply `--limit`.
+        if let Some(dedupe) = source_args.dedupe() {
+            if source_args.sample().is_some() {
+                return Err(format_err!("cannot use --sample with --dedupe-on"));
+            }
+            write!(f, " FROM ")?;
+            write_dedupe_subquery_sql(f, &self.name, dedupe, source_args)?;
+            if let Some(limit) = source_args.limit() {
+                write!(f, " LIMIT {}", limit)?;
+            }
+            return Ok(());
+        }
         write!(f, " FROM {}", self.name.dotted_and_quoted())?;
-        if let Some(where_clause) = source_args.where_clause() {
-            write!(f, " WHERE ({})", where_clause)?;
+
+        // Build our `WHERE` clause. Seeded samples hash each row, so that we
+        // get the same rows every time.
+        let mut conditions = filter_conditions(source_args)?;
+        if let Some(sample) = source_args.sample() {
+            match sample.seed() {
+                Some(seed) => {
+                    write!(f, " AS `__dbcrossbar_row`")?;
+                    conditions.push(format!(
+                        "(ABS(MOD(FARM_FINGERPRINT(CONCAT('{}:', TO_JSON_STRING(`__dbcrossbar_row`))), {})) < {:.0})",
+                        seed,
+                        SAMPLE_BUCKETS,
+                        sample.fraction() * f64::from(SAMPLE_BUCKETS),
+                    ));
+                }
+                None => conditions.push(format!("(RAND() < {})", sample.fraction())),
+            }
+        }
+        if !conditions.is_empty() {
+            write!(f, " WHERE {}", conditions.join(" AND "))?;
+        }
+        if let Some(limit) = source_args.limit() {
+            write!(f, " LIMIT {}", limit)?;
         }
         Ok(())
     }
@@ -382,10 +526,112 @@ WHEN NOT MATCHED THEN INSERT (
     ) -> Result<()> {
         write!(f, "SELECT COUNT(*) AS `count`")?;
         write!(f, " FROM {}", self.name.dotted_and_quoted())?;
-        if let Some(where_clause) = source_args.where_clause() {
-            write!(f, " WHERE ({})", where_clause)?;
+        let conditions = filter_conditions(source_args)?;
+        if !conditions.is_empty() {
+            write!(f, " WHERE {}", conditions.join(" AND "))?;
         }
 
         Ok(())
     }
+
+    /// Write a `SELECT` statement which computes each value of a
+    /// [`Fingerprint`](crate::fingerprint::Fingerprint) as a string. Our
+    /// columns must have been created from the columns of `schema`. The
+    /// values are named `v0`, `v1`, etc.
+    pub(crate) fn write_fingerprint_sql(
+        &self,
+        schema: &Schema,
+        source_args: &SourceArguments<Verified>,
+        f: &mut dyn Write,
+    ) -> Result<()> {
+        if self.columns.len() != schema.table.columns.len() {
+            return Err(format_err!(
+                "expected {} BigQuery columns, found {}",
+                schema.table.columns.len(),
+                self.columns.len(),
+            ));
+        }
+        write!(f, "SELECT CAST(COUNT(*) AS STRING) AS `v0`")?;
+        let mut idx = 1;
+        for (bq_col, col) in self.columns.iter().zip(&schema.table.columns) {
+            for aggregate in Aggregate::for_column(col) {
+                match aggregate {
+                    Aggregate::NullCount => write!(
+                        f,
+                        ", CAST(COUNTIF({} IS NULL) AS STRING)",
+                        bq_col.name.quoted(),
+                    )?,
+                    // Sum as `BIGNUMERIC`, so that large `INT64` sums don't
+                    // overflow.
+                    Aggregate::Sum => write!(
+                        f,
+                        ", CAST(SUM(CAST({} AS BIGNUMERIC)) AS STRING)",
+                        bq_col.name.quoted(),
+                    )?,
+                }
+                write!(f, " AS `v{}`", idx)?;
+                idx += 1;
+            }
+        }
+        write!(f, " FROM {}", self.name.dotted_and_quoted())?;
+        let conditions = filter_conditions(source_args)?;
+        if !conditions.is_empty() {
+            write!(f, " WHERE {}", conditions.join(" AND "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Build the conditions for `--where` and `--filter`, each wrapped in
+/// parentheses.
+fn filter_conditions(source_args: &SourceArguments<Verified>) -> Result<Vec<String>> {
+    let mut conditions = vec![];
+    if let Some(where_clause) = source_args.where_clause() {
+        conditions.push(format!("({})", where_clause));
+    }
+    if let Some(filter) = source_args.filter() {
+        conditions.push(format!("({})", filter.to_sql(SqlDialect::BigQuery)?));
+    }
+    Ok(conditions)
+}
+
+/// Write a subquery which applies `--where` and `--filter`, and then keeps one
+/// row for each key using `QUALIFY`.
+fn write_dedupe_subquery_sql(
+    f: &mut dyn Write,
+    name: &TableName,
+    dedupe: &Dedupe,
+    source_args: &SourceArguments<Verified>,
+) -> Result<()> {
+    let quote = |column: &str| -> Result<String> {
+        use std::fmt::Write as _;
+        let mut quoted = String::new();
+        write!(quoted, "{}", Ident(column)).map_err(|_| {
+            format_err!("cannot use column {:?} with --dedupe-on", column)
+        })?;
+        Ok(quoted)
+    };
+    let keys = dedupe
+        .keys()
+        .iter()
+        .map(|k| quote(k))
+        .collect::<Result<Vec<_>>>()?;
+    write!(f, "(SELECT * FROM {}", name.dotted_and_quoted())?;
+    // BigQuery requires a `WHERE` clause before `QUALIFY`.
+    let conditions = filter_conditions(source_args)?;
+    if conditions.is_empty() {
+        write!(f, " WHERE TRUE")?;
+    } else {
+        write!(f, " WHERE {}", conditions.join(" AND "))?;
+    }
+    write!(
+        f,
+        " QUALIFY ROW_NUMBER() OVER (PARTITION BY {}",
+        keys.join(", ")
+    )?;
+    if let Keep::LatestBy(column) = dedupe.keep() {
+        write!(f, " ORDER BY {} DESC NULLS LAST", quote(column)?)?;
+    }
+    write!(f, ") = 1) AS `__dbcrossbar_deduped`")?;
+    Ok(())
 }
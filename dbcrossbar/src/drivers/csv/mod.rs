@@ -2,7 +2,7 @@
 
 use std::{ffi::OsStr, fmt, str::FromStr};
 
-use super::file::FileLocator;
+use super::file::{list_helper, FileLocator};
 use crate::{common::*, locator::PathLikeLocator};
 
 /// (Incomplete.) A CSV file containing data, or a directory containing CSV
@@ -111,14 +111,23 @@ impl LocatorStatic for CsvLocator {
         Features {
             locator: LocatorFeatures::Schema
                 | LocatorFeatures::LocalData
-                | LocatorFeatures::WriteLocalData,
+                | LocatorFeatures::WriteLocalData
+                | LocatorFeatures::List,
             write_schema_if_exists: EnumSet::empty(),
             source_args: EnumSet::empty(),
-            dest_args: EnumSet::empty(),
-            dest_if_exists: IfExistsFeatures::no_append(),
+            dest_args: DestinationArgumentsFeatures::PartitionBy.into(),
+            dest_if_exists: IfExistsFeatures::no_append() | IfExistsFeatures::Append,
             _placeholder: (),
         }
     }
+
+    fn list(
+        _ctx: Context,
+        container: &str,
+        _source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>> {
+        list_helper(Self::scheme(), container.to_owned()).boxed()
+    }
 }
 
 impl PathLikeLocator for CsvLocator {
@@ -1,22 +1,32 @@
 //! Driver for working with CSV files.
 
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::{
     ffi::{OsStr, OsString},
     fmt,
-    path::PathBuf,
+    io::{Seek, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::Arc,
 };
 use tokio::{
     fs,
-    io::{self, BufReader},
+    io::{self, AsyncSeekExt, BufReader},
 };
 use tracing::{field, Span};
 use walkdir::WalkDir;
 
+use crate::byte_ranges::{split_into_ranges, ReadRange};
+use crate::concat::{concatenate_csv_streams, strip_csv_header};
+use crate::data_streams::DataStream;
+use crate::partition::{
+    add_partition_columns, partition_csv_streams, should_read_partition,
+};
 use crate::tokio_glue::{copy_reader_to_stream, copy_stream_to_writer};
 use crate::{common::*, locator::PathLikeLocator};
-use crate::{concat::concatenate_csv_streams, data_streams::DataStream};
-use crate::{csv_stream::csv_stream_name, DataFormat};
+use crate::{csv_stream::csv_stream_name, wildcard::PathGlob, DataFormat};
 
 /// (Incomplete.) A CSV file containing data, or a directory containing CSV
 /// files.
@@ -118,6 +128,11 @@ async fn schema_helper(ctx: Context, locator: FileLocator) -> Result<Option<Sche
         PathOrStdio::Path(_) if locator.is_directory_like() => {
             Err(format_err!("cannot read schema from directory {}", locator))
         }
+        PathOrStdio::Path(path)
+            if PathGlob::parse(&path.to_string_lossy()).is_some() =>
+        {
+            Err(format_err!("cannot read schema from glob {}", locator))
+        }
         PathOrStdio::Path(path) => {
             let data_stream = path_to_data_stream(
                 ctx.clone(),
@@ -145,9 +160,13 @@ async fn local_data_helper(
 ) -> Result<Option<BoxStream<CsvStream>>> {
     let shared_args = shared_args.verify(FileLocator::features())?;
     let schema = shared_args.schema().to_owned();
+    let bad_rows = shared_args.bad_rows().to_owned();
+    let max_streams = shared_args.max_streams();
+    let split_files = shared_args.split_files();
 
     let source_args = source_args.verify(FileLocator::features())?;
     let from_format = source_args.format().cloned();
+    let partition_filter = source_args.partition_filter().cloned();
 
     match path {
         PathOrStdio::Stdio => {
@@ -160,45 +179,40 @@ async fn local_data_helper(
                     .map_err(move |e| format_err!("cannot read stdin: {}", e))
                     .boxed(),
             };
-            let csv_stream = data_stream.into_csv_stream(&ctx, &schema).await?;
+            let csv_stream = data_stream
+                .into_csv_stream(&ctx, &schema, &bad_rows)
+                .await?;
             Ok(Some(box_stream_once(Ok(csv_stream))))
         }
-        PathOrStdio::Path(base_path) => {
-            // Recursively look at our paths, picking out the ones that look
-            // like CSVs. We do this synchronously because it's reasonably
-            // fast and we'd like to catch errors up front.
+        PathOrStdio::Path(path) => {
+            let (base_path, found_paths) = find_files(&path)?;
             let mut paths = vec![];
-            debug!("walking {}", base_path.display());
-            let walker = WalkDir::new(&base_path).follow_links(true);
             let mut common_ext: Option<Option<OsString>> = None;
-            for dirent in walker.into_iter() {
-                let dirent = dirent.with_context(|| {
-                    format!("error listing files in {}", base_path.display())
-                })?;
-                let p = dirent.path();
-                trace!("found dirent {}", p.display());
-                if dirent.file_type().is_dir() {
-                    continue;
-                } else if !dirent.file_type().is_file() {
-                    return Err(format_err!("not a file: {}", p.display()));
-                }
-
+            for p in found_paths {
                 let ext = p.extension().map(OsStr::to_ascii_lowercase);
                 if let Some(common_ext) = &common_ext {
                     if ext != *common_ext {
                         return Err(format_err!(
                             "all files in {} must have the same extension",
-                            base_path.display()
+                            path.display()
                         ));
                     }
                 } else {
                     common_ext = Some(ext);
                 }
-                paths.push(p.to_owned());
+
+                // Skip Hive-style partition directories which can't match
+                // our filter.
+                let file_path = p.to_string_lossy();
+                let name = csv_stream_name(&base_path.to_string_lossy(), &file_path)?;
+                if !should_read_partition(name, partition_filter.as_ref())? {
+                    trace!("skipping partition {}", p.display());
+                    continue;
+                }
+                paths.push(p);
             }
-            let common_ext = common_ext.ok_or_else(|| {
-                format_err!("no files found in {}", base_path.display())
-            })?;
+            let common_ext = common_ext
+                .ok_or_else(|| format_err!("no files found in {}", path.display()))?;
             let format = from_format
                 .or(common_ext.map(|ext| DataFormat::from_extension(&ext)))
                 .unwrap_or_default();
@@ -206,27 +220,113 @@ async fn local_data_helper(
             let csv_streams = stream::iter(paths).map(Ok).and_then(move |file_path| {
                 let ctx = ctx.clone();
                 let schema = schema.clone();
+                let bad_rows = bad_rows.clone();
                 let base_path = base_path.clone();
                 let file_path_copy = file_path.clone();
                 let format = format.clone();
                 async move {
-                    let data_stream = path_to_data_stream(
-                        ctx.clone(),
+                    let data_streams = path_to_data_streams(
                         base_path.clone(),
                         file_path,
                         format,
+                        max_streams,
+                        split_files,
                     ).await?;
-                    data_stream.into_csv_stream(&ctx, &schema).await
+                    let csv_streams = stream::iter(data_streams).map(Ok).and_then(move |data_stream| {
+                        let ctx = ctx.clone();
+                        let schema = schema.clone();
+                        let bad_rows = bad_rows.clone();
+                        async move {
+                            let csv_stream =
+                                data_stream.into_csv_stream(&ctx, &schema, &bad_rows).await?;
+                            add_partition_columns(ctx, &schema, csv_stream)
+                        }
+                    });
+                    Ok::<_, Error>(csv_streams)
                 }
                 .instrument(debug_span!("stream_from_file", file_path = %file_path_copy.display(), stream.name = field::Empty))
                 .boxed()
-            });
+            }).try_flatten();
 
             Ok(Some(csv_streams.boxed()))
         }
     }
 }
 
+/// Recursively find the files at `path`, which may be a file, a directory or
+/// a glob. Returns the path that we walked, and the files we found.
+fn find_files(path: &Path) -> Result<(PathBuf, Vec<PathBuf>)> {
+    // If we have a glob, we list its base directory and keep the files which
+    // match.
+    let glob = PathGlob::parse(&path.to_string_lossy());
+    let base_path = match &glob {
+        Some(glob) => PathBuf::from(glob.base()),
+        None => path.to_owned(),
+    };
+
+    // We do this synchronously because it's reasonably fast and we'd like to
+    // catch errors up front.
+    let mut paths = vec![];
+    debug!("walking {}", base_path.display());
+    let walker = WalkDir::new(&base_path)
+        .follow_links(true)
+        .sort_by_file_name();
+    for dirent in walker.into_iter() {
+        let dirent = dirent.with_context(|| {
+            format!("error listing files in {}", base_path.display())
+        })?;
+        let p = dirent.path();
+        trace!("found dirent {}", p.display());
+        if dirent.file_type().is_dir() {
+            continue;
+        } else if !dirent.file_type().is_file() {
+            return Err(format_err!("not a file: {}", p.display()));
+        } else if glob
+            .as_ref()
+            .is_some_and(|glob| !glob.matches(&p.to_string_lossy()))
+        {
+            continue;
+        }
+        paths.push(p.to_owned());
+    }
+    Ok((base_path, paths))
+}
+
+/// List the files in `container`, which is a `scheme` locator for a
+/// directory or a glob.
+#[instrument(level = "trace", name = "file::list")]
+pub(crate) async fn list_helper(
+    scheme: &'static str,
+    container: String,
+) -> Result<Vec<ListEntry>> {
+    let path = match PathOrStdio::from_str_locator_helper(scheme, &container)? {
+        PathOrStdio::Path(path) => path,
+        PathOrStdio::Stdio => return Err(format_err!("cannot list stdin")),
+    };
+    let (base_path, paths) = find_files(&path)?;
+    paths
+        .into_iter()
+        .map(|p| {
+            let metadata = p
+                .metadata()
+                .with_context(|| format!("cannot access {}", p.display()))?;
+            let name = p
+                .strip_prefix(&base_path)
+                .ok()
+                .filter(|name| !name.as_os_str().is_empty())
+                .unwrap_or(&p);
+            Ok(ListEntry {
+                name: name.to_string_lossy().into_owned(),
+                locator: format!("{}{}", scheme, p.display()),
+                kind: ListEntryKind::File,
+                rows: None,
+                bytes: Some(metadata.len()),
+                modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            })
+        })
+        .collect()
+}
+
 #[instrument(
     level = "debug",
     name = "file::path_to_data_stream",
@@ -258,6 +358,59 @@ async fn path_to_data_stream(
     Ok(data_stream)
 }
 
+/// Open a local file. If `split_files` is set, split large files into byte
+/// ranges which can be read in parallel.
+#[instrument(
+    level = "debug",
+    name = "file::path_to_data_streams",
+    skip_all,
+    fields(file_path = %file_path.display(), stream.name = field::Empty),
+)]
+async fn path_to_data_streams(
+    base_path: PathBuf,
+    file_path: PathBuf,
+    format: DataFormat,
+    max_streams: usize,
+    split_files: Option<u64>,
+) -> Result<Vec<DataStream>> {
+    // Get the name of our stream.
+    let name =
+        csv_stream_name(&base_path.to_string_lossy(), &file_path.to_string_lossy())?
+            .to_owned();
+    Span::current().record("stream.name", field::display(&name));
+
+    let len = fs::metadata(&file_path)
+        .await
+        .with_context(|| format!("cannot open {}", file_path.display()))?
+        .len();
+    let file = Arc::new(LocalFile { path: file_path });
+    split_into_ranges(&name, &format, len, max_streams, split_files, file).await
+}
+
+/// A local file, which we can read in byte ranges.
+struct LocalFile {
+    path: PathBuf,
+}
+
+#[async_trait]
+impl ReadRange for LocalFile {
+    async fn read_range(&self, range: Range<u64>) -> Result<BoxStream<BytesMut>> {
+        let mut f = fs::File::open(&self.path)
+            .await
+            .with_context(|| format!("cannot open {}", self.path.display()))?;
+        f.seek(SeekFrom::Start(range.start))
+            .await
+            .with_context(|| format!("cannot seek in {}", self.path.display()))?;
+        let rdr =
+            BufReader::with_capacity(BUFFER_SIZE, f.take(range.end - range.start));
+        let path = self.path.clone();
+        let data = copy_reader_to_stream(rdr)?
+            .map_err(move |e| format_err!("cannot read {}: {}", path.display(), e))
+            .boxed();
+        Ok(data)
+    }
+}
+
 #[instrument(
     level = "debug",
     name = "file::write_local_data",
@@ -275,6 +428,33 @@ async fn write_local_data_helper(
     let schema = shared_args.schema().to_owned();
     let dest_args = dest_args.verify(FileLocator::features())?;
     let if_exists = dest_args.if_exists().to_owned();
+    if let PathOrStdio::Path(p) = &path {
+        if PathGlob::parse(&p.to_string_lossy()).is_some() {
+            return Err(format_err!("cannot write to glob {}", p.display()));
+        }
+    }
+
+    // Honor --partition-by by splitting our data into one stream per
+    // partition directory.
+    let (schema, data) = match dest_args.partition_by() {
+        Some(partition_by) => {
+            if !matches!(&path, PathOrStdio::Path(p) if p.to_string_lossy().ends_with('/'))
+            {
+                return Err(format_err!(
+                    "--partition-by can only be used when writing to a directory"
+                ));
+            }
+            let data = partition_csv_streams(
+                ctx.clone(),
+                partition_by.to_owned(),
+                shared_args.max_streams(),
+                data,
+            )?;
+            (partition_by.data_schema(&schema), data)
+        }
+        None => (schema, data),
+    };
+
     match path {
         PathOrStdio::Stdio => {
             let format = dest_args.format().cloned().unwrap_or_default();
@@ -313,12 +493,17 @@ async fn write_local_data_helper(
                         Span::current().record("path", field::display(csv_path.display()));
                         let data_stream =
                             DataStream::from_csv_stream(&ctx, format, &schema, stream).await?;
-                        write_stream_to_file(
-                            data_stream.data,
-                            csv_path.clone(),
-                            if_exists,
-                        )
-                        .await?;
+                        let csv_path = if if_exists == IfExists::Append {
+                            write_stream_to_new_file(data_stream.data, csv_path).await?
+                        } else {
+                            write_stream_to_file(
+                                data_stream.data,
+                                csv_path.clone(),
+                                if_exists,
+                            )
+                            .await?;
+                            csv_path
+                        };
                         Ok(FileLocator::from_path(csv_path).boxed())
                     }.instrument(trace_span!("stream_to_file", stream.name = %stream_name, path = field::Empty))
                     .boxed()
@@ -339,8 +524,13 @@ async fn write_local_data_helper(
                 let data_stream =
                     DataStream::from_csv_stream(&ctx, format, &schema, stream).await?;
                 let fut = async move {
-                    write_stream_to_file(data_stream.data, path.clone(), if_exists)
-                        .await?;
+                    if if_exists == IfExists::Append {
+                        append_stream_to_file(&ctx, data_stream, &schema, path.clone())
+                            .await?;
+                    } else {
+                        write_stream_to_file(data_stream.data, path.clone(), if_exists)
+                            .await?;
+                    }
                     Ok(FileLocator::from_path(path).boxed())
                 }.instrument(trace_span!("stream_to_file", stream.name = %stream_name, path = %path_copy.display()));
                 Ok(box_stream_once(Ok(fut.boxed())))
@@ -349,19 +539,24 @@ async fn write_local_data_helper(
     }
 }
 
-/// Write `data` to `dest`, honoring `if_exists`.
-async fn write_stream_to_file(
-    data: BoxStream<BytesMut>,
-    dest: PathBuf,
-    if_exists: IfExists,
-) -> Result<()> {
-    // Make sure our destination directory exists.
+/// Make sure the directory containing `dest` exists.
+async fn create_parent_dir(dest: &Path) -> Result<()> {
     let dir = dest
         .parent()
         .ok_or_else(|| format_err!("cannot find parent dir for {}", dest.display()))?;
     fs::create_dir_all(dir)
         .await
         .with_context(|| format!("unable to create directory {}", dir.display()))?;
+    Ok(())
+}
+
+/// Write `data` to `dest`, honoring `if_exists`.
+async fn write_stream_to_file(
+    data: BoxStream<BytesMut>,
+    dest: PathBuf,
+    if_exists: IfExists,
+) -> Result<()> {
+    create_parent_dir(&dest).await?;
 
     // Write our our CSV stream.
     debug!("writing stream to file {}", dest.display());
@@ -376,6 +571,123 @@ async fn write_stream_to_file(
     Ok(())
 }
 
+/// Write `data` to a new file named `dest`, without touching any existing
+/// files. If `dest` is already taken, we try `name-2.ext`, `name-3.ext`, etc.,
+/// and return the path we actually wrote.
+async fn write_stream_to_new_file(
+    data: BoxStream<BytesMut>,
+    dest: PathBuf,
+) -> Result<PathBuf> {
+    create_parent_dir(&dest).await?;
+
+    let stem = dest.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = dest.extension().unwrap_or_default().to_string_lossy();
+    let mut n = 1;
+    let (path, wtr) = loop {
+        let path = if n == 1 {
+            dest.clone()
+        } else {
+            dest.with_file_name(format!("{}-{}.{}", stem, n, ext))
+        };
+        match IfExists::Error
+            .to_async_open_options_no_append()?
+            .open(&path)
+            .await
+        {
+            Ok(wtr) => break (path, wtr),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("cannot open {}", path.display()))
+            }
+        }
+    };
+    debug!("writing stream to new file {}", path.display());
+    copy_stream_to_writer(data, wtr)
+        .await
+        .with_context(|| format!("error writing {}", path.display()))?;
+    Ok(path)
+}
+
+/// Append `data_stream` to the file `dest`. If `dest` already contains CSV
+/// data, its header must match `schema`, and we leave out our own header.
+async fn append_stream_to_file(
+    ctx: &Context,
+    data_stream: DataStream,
+    schema: &Schema,
+    dest: PathBuf,
+) -> Result<()> {
+    create_parent_dir(&dest).await?;
+
+    // If we have nothing to append to, just write a normal file.
+    let existing_len = match fs::metadata(&dest).await {
+        Ok(metadata) => metadata.len(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("cannot access {}", dest.display()))
+        }
+    };
+    if existing_len == 0 {
+        return write_stream_to_file(data_stream.data, dest, IfExists::Overwrite)
+            .await;
+    }
+
+    // Check the existing header, and whether the file ends with a newline.
+    let is_csv = data_stream.format == DataFormat::Csv;
+    let columns = schema
+        .table
+        .columns
+        .iter()
+        .map(|c| c.name.clone())
+        .collect::<Vec<_>>();
+    let dest_copy = dest.clone();
+    let needs_newline = spawn_blocking(move || -> Result<bool> {
+        let mut file = std::fs::File::open(&dest_copy)
+            .with_context(|| format!("cannot open {}", dest_copy.display()))?;
+        if is_csv {
+            let mut rdr = csv::Reader::from_reader(&mut file);
+            let headers = rdr.headers().with_context(|| {
+                format!("cannot read header of {}", dest_copy.display())
+            })?;
+            if !headers.iter().eq(columns.iter().map(|c| c.as_str())) {
+                return Err(format_err!(
+                    "cannot append to {}: it has columns {:?}, but we're writing {:?}",
+                    dest_copy.display(),
+                    headers.iter().collect::<Vec<_>>(),
+                    columns,
+                ));
+            }
+        }
+        let mut last_byte = [0; 1];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last_byte)?;
+        Ok(last_byte[0] != b'\n')
+    })
+    .await?;
+
+    let mut data = data_stream.data;
+    if is_csv {
+        data = strip_csv_header(ctx.clone(), data)?;
+    }
+    if needs_newline {
+        data = box_stream_once(Ok(BytesMut::from(&b"\n"[..])))
+            .chain(data)
+            .boxed();
+    }
+
+    debug!("appending stream to file {}", dest.display());
+    let wtr = fs::OpenOptions::new()
+        .append(true)
+        .open(&dest)
+        .await
+        .with_context(|| format!("cannot open {}", dest.display()))?;
+    copy_stream_to_writer(data, wtr)
+        .await
+        .with_context(|| format!("error writing {}", dest.display()))?;
+    Ok(())
+}
+
 impl LocatorStatic for FileLocator {
     fn scheme() -> &'static str {
         "file:"
@@ -383,14 +695,25 @@ impl LocatorStatic for FileLocator {
 
     fn features() -> Features {
         Features {
-            locator: LocatorFeatures::LocalData | LocatorFeatures::WriteLocalData,
+            locator: LocatorFeatures::LocalData
+                | LocatorFeatures::WriteLocalData
+                | LocatorFeatures::List,
             write_schema_if_exists: EnumSet::empty(),
             source_args: SourceArgumentsFeatures::Format.into(),
-            dest_args: DestinationArgumentsFeatures::Format.into(),
-            dest_if_exists: IfExistsFeatures::no_append(),
+            dest_args: DestinationArgumentsFeatures::Format
+                | DestinationArgumentsFeatures::PartitionBy,
+            dest_if_exists: IfExistsFeatures::no_append() | IfExistsFeatures::Append,
             _placeholder: (),
         }
     }
+
+    fn list(
+        _ctx: Context,
+        container: &str,
+        _source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>> {
+        list_helper(Self::scheme(), container.to_owned()).boxed()
+    }
 }
 
 impl PathLikeLocator for FileLocator {
@@ -0,0 +1,58 @@
+//! Implementation of `list`, but as a real `async` function.
+
+use super::GsLocator;
+use crate::clouds::gcloud::storage;
+use crate::common::*;
+use crate::drivers::bigquery_shared::GCloudDriverArguments;
+use crate::wildcard::PathGlob;
+use futures::future;
+
+/// Implementation of `list`, but as a real `async` function.
+#[instrument(level = "trace", name = "gs::list", skip(ctx, source_args))]
+pub(crate) async fn list_helper(
+    ctx: Context,
+    container: String,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Vec<ListEntry>> {
+    let source_args = source_args.verify(GsLocator::features())?;
+    let locator = container.parse::<GsLocator>()?;
+    let glob = PathGlob::parse(locator.as_url().as_str());
+    let url = match &glob {
+        Some(glob) => glob.base().parse::<Url>()?,
+        None if locator.is_directory() => locator.as_url().to_owned(),
+        None => {
+            return Err(format_err!(
+                "{} must end with a '/' or contain a glob",
+                locator
+            ))
+        }
+    };
+
+    let driver_args = GCloudDriverArguments::try_from(&source_args)?;
+    let client = driver_args.client().await?;
+
+    storage::ls(&ctx, &client, &url)
+        .await?
+        .try_filter(|item| {
+            future::ready(
+                glob.as_ref()
+                    .is_none_or(|glob| glob.matches(&item.to_url_string())),
+            )
+        })
+        .map_ok(|item| {
+            let file_url = item.to_url_string();
+            ListEntry {
+                name: file_url
+                    .strip_prefix(url.as_str())
+                    .unwrap_or(&file_url)
+                    .to_owned(),
+                locator: file_url.clone(),
+                kind: ListEntryKind::File,
+                rows: None,
+                bytes: Some(item.size),
+                modified: None,
+            }
+        })
+        .try_collect()
+        .await
+}
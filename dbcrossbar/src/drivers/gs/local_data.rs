@@ -1,10 +1,21 @@
 //! Reading data from Google Cloud Storage.
 
+use async_trait::async_trait;
+use std::{ops::Range, sync::Arc};
+
 use super::GsLocator;
-use crate::clouds::gcloud::storage;
+use crate::byte_ranges::{split_into_ranges, ReadRange};
+use crate::clouds::gcloud::{
+    storage::{self, StorageObject},
+    Client,
+};
 use crate::common::*;
 use crate::csv_stream::csv_stream_name;
 use crate::drivers::bigquery_shared::GCloudDriverArguments;
+use crate::partition::{add_partition_columns, should_read_partition};
+use crate::wildcard::PathGlob;
+use crate::DataFormat;
+use futures::future;
 
 /// Implementation of `local_data`, but as a real `async` function.
 #[instrument(
@@ -19,34 +30,96 @@ pub(crate) async fn local_data_helper(
     shared_args: SharedArguments<Unverified>,
     source_args: SourceArguments<Unverified>,
 ) -> Result<Option<BoxStream<CsvStream>>> {
-    let _shared_args = shared_args.verify(GsLocator::features())?;
+    let shared_args = shared_args.verify(GsLocator::features())?;
+    let schema = shared_args.schema().to_owned();
+    let max_streams = shared_args.max_streams();
+    let split_files = shared_args.split_files();
     let source_args = source_args.verify(GsLocator::features())?;
+    let partition_filter = source_args.partition_filter().cloned();
     debug!("getting CSV files from {}", url);
 
     let driver_args = GCloudDriverArguments::try_from(&source_args)?;
     let client = driver_args.client().await?;
 
-    let file_urls = storage::ls(&ctx, &client, &url).await?;
+    // List the files at our URL. If we have a glob, we list its base
+    // directory and keep the files which match.
+    let glob = PathGlob::parse(url.as_str());
+    let url = match &glob {
+        Some(glob) => glob.base().parse::<Url>()?,
+        None => url,
+    };
+    let file_urls = storage::ls(&ctx, &client, &url)
+        .await?
+        .try_filter(move |item| {
+            // Skip non-CSV files, and files which don't match our glob.
+            future::ready(
+                item.is_csv()
+                    && glob
+                        .as_ref()
+                        .is_none_or(|glob| glob.matches(&item.to_url_string())),
+            )
+        });
 
-    let csv_streams = file_urls.and_then(move |item| {
+    let csv_streams = file_urls.try_filter_map(move |item| {
+        let ctx = ctx.clone();
         let url = url.clone();
         let client = client.clone();
+        let schema = schema.clone();
+        let partition_filter = partition_filter.clone();
         async move {
-            // Stream the file from the cloud.
+            // Skip Hive-style partition directories which can't match our
+            // filter.
             let file_url = item.to_url_string();
             let name = csv_stream_name(url.as_str(), &file_url)?;
-            let data = storage::download_file(&client, &item)
-                .instrument(trace_span!("stream_from_gs", stream = %name))
-                .await?;
-
-            // Assemble everything into a CSV stream.
-            Ok(CsvStream {
-                name: name.to_owned(),
-                data,
-            })
+            if !should_read_partition(name, partition_filter.as_ref())? {
+                trace!("skipping partition {}", file_url);
+                return Ok(None);
+            }
+
+            // Stream the file from the cloud, splitting large files into
+            // byte ranges which can be downloaded in parallel.
+            let len = item.size;
+            let file = Arc::new(GsObject { client, item });
+            let data_streams = split_into_ranges(
+                name,
+                &DataFormat::Csv,
+                len,
+                max_streams,
+                split_files,
+                file,
+            )
+            .instrument(trace_span!("stream_from_gs", stream = %name))
+            .await?;
+
+            // Assemble everything into CSV streams.
+            let csv_streams = data_streams
+                .into_iter()
+                .map(|data_stream| {
+                    let csv_stream = CsvStream {
+                        name: data_stream.name,
+                        data: data_stream.data,
+                    };
+                    add_partition_columns(ctx.clone(), &schema, csv_stream)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Some(stream::iter(csv_streams).map(Ok)))
         }
         .boxed()
     });
+    let csv_streams = csv_streams.try_flatten();
 
     Ok(Some(csv_streams.boxed()))
 }
+
+/// An object in Google Cloud Storage, which we can read in byte ranges.
+struct GsObject {
+    client: Client,
+    item: StorageObject,
+}
+
+#[async_trait]
+impl ReadRange for GsObject {
+    async fn read_range(&self, range: Range<u64>) -> Result<BoxStream<BytesMut>> {
+        storage::download_file_range(&self.client, &self.item, range).await
+    }
+}
@@ -6,12 +6,15 @@ use std::{fmt, str::FromStr};
 use crate::common::*;
 use crate::drivers::bigquery::BigQueryLocator;
 use crate::locator::PathLikeLocator;
+use crate::wildcard::PathGlob;
 
+mod list;
 mod local_data;
 mod prepare_as_destination;
 mod write_local_data;
 mod write_remote_data;
 
+use list::list_helper;
 use local_data::local_data_helper;
 pub(crate) use prepare_as_destination::prepare_as_destination_helper;
 use write_local_data::write_local_data_helper;
@@ -30,12 +33,17 @@ impl GsLocator {
 
     /// Does this locator point at a `gs://` directory?
     pub(crate) fn is_directory(&self) -> bool {
-        self.url.path().ends_with('/')
+        self.url.path().ends_with('/') && !self.is_glob()
     }
 
     /// Does this locator point at a `gs://` CSV file?
     pub(crate) fn is_csv_file(&self) -> bool {
-        self.url.path().to_ascii_lowercase().ends_with(".csv")
+        self.url.path().to_ascii_lowercase().ends_with(".csv") && !self.is_glob()
+    }
+
+    /// Does this locator contain a glob pattern like `*`?
+    pub(crate) fn is_glob(&self) -> bool {
+        PathGlob::parse(self.url.as_str()).is_some()
     }
 }
 
@@ -57,8 +65,14 @@ impl FromStr for GsLocator {
                 Err(format_err!("{} must start with gs://", url))
             } else {
                 let locator = GsLocator { url };
-                if !locator.is_directory() && !locator.is_csv_file() {
-                    Err(format_err!("{} must end with a '/' or '.csv'", locator))
+                if !locator.is_directory()
+                    && !locator.is_csv_file()
+                    && !locator.is_glob()
+                {
+                    Err(format_err!(
+                        "{} must end with a '/' or '.csv', or contain a glob",
+                        locator
+                    ))
                 } else {
                     Ok(locator)
                 }
@@ -135,14 +149,24 @@ impl LocatorStatic for GsLocator {
 
     fn features() -> Features {
         Features {
-            locator: LocatorFeatures::LocalData | LocatorFeatures::WriteLocalData,
+            locator: LocatorFeatures::LocalData
+                | LocatorFeatures::WriteLocalData
+                | LocatorFeatures::List,
             write_schema_if_exists: EnumSet::empty(),
             source_args: Default::default(),
-            dest_args: Default::default(),
+            dest_args: DestinationArgumentsFeatures::PartitionBy.into(),
             dest_if_exists: IfExistsFeatures::Overwrite.into(),
             _placeholder: (),
         }
     }
+
+    fn list(
+        ctx: Context,
+        container: &str,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>> {
+        list_helper(ctx, container.to_owned(), source_args).boxed()
+    }
 }
 
 impl PathLikeLocator for GsLocator {
@@ -5,6 +5,7 @@ use crate::clouds::gcloud::storage;
 use crate::common::*;
 use crate::concat::concatenate_csv_streams;
 use crate::drivers::bigquery_shared::GCloudDriverArguments;
+use crate::partition::partition_csv_streams;
 
 /// Implementation of `write_local_data`, but as a real `async` function.
 #[instrument(
@@ -20,8 +21,27 @@ pub(crate) async fn write_local_data_helper(
     shared_args: SharedArguments<Unverified>,
     dest_args: DestinationArguments<Unverified>,
 ) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
-    let _shared_args = shared_args.verify(GsLocator::features())?;
+    let shared_args = shared_args.verify(GsLocator::features())?;
     let dest_args = dest_args.verify(GsLocator::features())?;
+    if dest.is_glob() {
+        return Err(format_err!("cannot write to glob {}", dest));
+    }
+
+    // Split our data into partition directories if requested.
+    let data = match dest_args.partition_by() {
+        Some(_) if !dest.is_directory() => {
+            return Err(format_err!(
+                "--partition-by can only be used when writing to a directory"
+            ));
+        }
+        Some(partition_by) => partition_csv_streams(
+            ctx.clone(),
+            partition_by.to_owned(),
+            shared_args.max_streams(),
+            data,
+        )?,
+        None => data,
+    };
 
     let driver_args = GCloudDriverArguments::try_from(&dest_args)?;
     let client = driver_args.client().await?;
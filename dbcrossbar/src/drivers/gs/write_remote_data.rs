@@ -168,6 +168,9 @@ pub(crate) async fn write_remote_data_helper(
         let mut dest_urls = vec![];
         while let Some(storage_object) = storage_object_stream.next().await {
             let storage_object = storage_object?;
+            if !storage_object.is_csv() {
+                continue;
+            }
             let locator = storage_object.to_url_string().parse::<GsLocator>()?;
             dest_urls.push(locator.boxed());
         }
@@ -17,6 +17,7 @@ use std::{
 };
 use uuid::Uuid;
 
+use crate::bad_rows::{record_to_string, BadRows};
 use crate::common::*;
 use crate::drivers::postgres_shared::{
     PgColumn, PgDataType, PgScalarDataType, PgSchema,
@@ -43,9 +44,12 @@ pub(crate) type BufferedWriter = io::BufWriter<Box<dyn Write>>;
 /// run it in its own thread.
 ///
 /// This function will take care of reasonable buffering for `rdr` and `wtr`.
-#[instrument(level = "debug", skip(schema, rdr, wtr))]
+/// Rows which can't be converted will be passed to `bad_rows`.
+#[instrument(level = "debug", skip(schema, bad_rows, rdr, wtr))]
 pub(crate) fn copy_csv_to_pg_binary(
+    name: &str,
     schema: &PgSchema,
+    bad_rows: &BadRows,
     rdr: Box<dyn Read>,
     wtr: Box<dyn Write>,
 ) -> Result<()> {
@@ -53,7 +57,7 @@ pub(crate) fn copy_csv_to_pg_binary(
 
     // Set up wrappers for `rdr` and `wtr`, handling CSV parsing and buffering.
     let mut rdr = csv::Reader::from_reader(rdr);
-    let mut wtr = io::BufWriter::with_capacity(BUFFER_SIZE, wtr);
+    let mut wtr: BufferedWriter = io::BufWriter::with_capacity(BUFFER_SIZE, wtr);
 
     // Check to make sure our CSV headers and table column names match.
     let headers = rdr.headers()?;
@@ -82,33 +86,58 @@ pub(crate) fn copy_csv_to_pg_binary(
     wtr.write_u32::<NE>(0)?; // Flags.
     wtr.write_u32::<NE>(0)?; // Extension area length.
 
-    // Iterate over our CSV rows.
+    // Iterate over our CSV rows. If we're allowed to skip bad rows, we
+    // convert each row into `row_buffer` first, so that we never write part of
+    // a row.
+    let mut row_buffer = vec![];
     for (row_idx, row) in rdr.records().enumerate() {
         // Check for read errors.
         let row = row?;
 
-        // Write our tuple field count.
-        wtr.write_i16::<NE>(i16::try_from(row.len())?)?;
-
-        // Write each of our rows. Using `zip` allows Rust to omit bounds
-        // checks on the `row` and `columns` arrays.
-        for (cell, col) in row.iter().zip(table.columns.iter()) {
-            cell_to_binary(&mut wtr, col, cell).with_context(|| {
-                format!(
-                    "could not convert row {}, column {} ({:?})",
-                    row_idx + 1, // Add 1 for header row.
-                    col.name,
-                    cell,
-                )
-            })?;
+        if bad_rows.is_enabled() {
+            row_buffer.clear();
+            match row_to_binary(&mut row_buffer, &table.columns, &row, row_idx) {
+                Ok(()) => wtr.write_all(&row_buffer)?,
+                Err(err) => {
+                    let line = row.position().map(|p| p.line());
+                    bad_rows.divert(name, line, &record_to_string(&row)?, err)?;
+                }
+            }
+        } else {
+            row_to_binary(&mut wtr, &table.columns, &row, row_idx)?;
         }
     }
 
     Ok(())
 }
 
+/// Convert a CSV row to PostgreSQL `BINARY` format.
+fn row_to_binary<W: Write>(
+    wtr: &mut W,
+    columns: &[PgColumn],
+    row: &csv::StringRecord,
+    row_idx: usize,
+) -> Result<()> {
+    // Write our tuple field count.
+    wtr.write_i16::<NE>(i16::try_from(row.len())?)?;
+
+    // Write each of our rows. Using `zip` allows Rust to omit bounds
+    // checks on the `row` and `columns` arrays.
+    for (cell, col) in row.iter().zip(columns.iter()) {
+        cell_to_binary(wtr, col, cell).with_context(|| {
+            format!(
+                "could not convert row {}, column {} ({:?})",
+                row_idx + 1, // Add 1 for header row.
+                col.name,
+                cell,
+            )
+        })?;
+    }
+    Ok(())
+}
+
 /// Convert a cell to PostgreSQL `BINARY` format.
-fn cell_to_binary(wtr: &mut BufferedWriter, col: &PgColumn, cell: &str) -> Result<()> {
+fn cell_to_binary<W: Write>(wtr: &mut W, col: &PgColumn, cell: &str) -> Result<()> {
     if cell.is_empty() && col.is_nullable {
         // We found an empty string in the CSV and this column is
         // nullable, so represent it as an SQL `NULL`. If the column
@@ -132,8 +161,8 @@ fn cell_to_binary(wtr: &mut BufferedWriter, col: &PgColumn, cell: &str) -> Resul
 }
 
 /// Convert a JSON-syntax array (possibly nested) into a `BINARY` array.
-fn array_to_binary(
-    wtr: &mut BufferedWriter,
+fn array_to_binary<W: Write>(
+    wtr: &mut W,
     dimension_count: i32,
     data_type: &PgScalarDataType,
     cell: &str,
@@ -266,8 +295,8 @@ where
 }
 
 /// Convert a scalar value from a CSV file into a `BINARY` value.
-fn scalar_to_binary(
-    wtr: &mut BufferedWriter,
+fn scalar_to_binary<W: Write>(
+    wtr: &mut W,
     data_type: &PgScalarDataType,
     cell: &str,
 ) -> Result<()> {
@@ -345,11 +374,23 @@ fn parse_ewkb_fallback() {
         .unwrap();
 }
 
+#[test]
+fn skip_bad_rows() {
+    let schema =
+        PgSchema::parse("test.sql".to_owned(), "CREATE TABLE t (id int);".to_owned())
+            .unwrap();
+    let bad_rows = BadRows::new(1);
+    let rdr = Box::new(io::Cursor::new(b"id\n1\nx\n2\n".to_vec()));
+    let wtr = Box::new(io::sink());
+    copy_csv_to_pg_binary("t", &schema, &bad_rows, rdr, wtr).unwrap();
+    assert_eq!(bad_rows.count(), 1);
+}
+
 /// Parse a CSV cell and write it out as a PostgreSQL binary value. This works
 /// for any type implementing `FromCsvCell` and `WriteBinary`. More complicated
 /// cases will need to do this manually.
 fn write_cell_as_binary<T: FromCsvCell + WriteBinary>(
-    wtr: &mut BufferedWriter,
+    wtr: &mut impl Write,
     cell: &str,
 ) -> Result<()> {
     let value = T::from_csv_cell(cell)?;
@@ -0,0 +1,58 @@
+//! Implementation of `fingerprint`, but as a real `async` function.
+
+use super::PostgresLocator;
+use crate::common::*;
+use crate::drivers::postgres_shared::{connect, CheckCatalog, PgSchema};
+use crate::fingerprint::Fingerprint;
+
+/// Implementation of `fingerprint`, but as a real `async` function.
+#[instrument(
+    level = "trace",
+    name = "postgres::fingerprint",
+    skip(ctx, shared_args, source_args)
+)]
+pub(crate) async fn fingerprint_helper(
+    ctx: Context,
+    locator: PostgresLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Fingerprint> {
+    let shared_args = shared_args.verify(PostgresLocator::features())?;
+    let source_args = source_args.verify(PostgresLocator::features())?;
+
+    // Get the parts of our locator.
+    let url = locator.url.clone();
+    let table_name = locator.table_name.clone();
+
+    // Look up the arguments we'll need.
+    let schema = shared_args.schema();
+
+    // Convert our schema to a native PostgreSQL schema. We only need the table
+    // name, because we choose our aggregates using the portable schema.
+    let pg_schema = PgSchema::from_pg_catalog_or_default(
+        &ctx,
+        CheckCatalog::No,
+        &url,
+        &table_name,
+        schema,
+    )
+    .await?;
+
+    // Generate SQL for query.
+    let mut sql_bytes: Vec<u8> = vec![];
+    pg_schema.write_fingerprint_sql(&mut sql_bytes, schema, &source_args)?;
+    let sql = String::from_utf8(sql_bytes).expect("should always be UTF-8");
+    debug!("fingerprint SQL: {}", sql);
+
+    // Run our query.
+    let conn = connect(&ctx, &url).await?;
+    let stmt = conn.prepare(&sql).await?;
+    let row = conn
+        .query_one(&stmt, &[])
+        .await
+        .context("error running fingerprint query")?;
+    let values = (0..row.len())
+        .map(|i| row.try_get::<_, Option<String>>(i))
+        .collect::<Result<Vec<_>, _>>()?;
+    Fingerprint::from_values(schema, values)
+}
@@ -0,0 +1,76 @@
+//! Implementation of `list`, but as a real `async` function.
+
+use super::PostgresLocator;
+use crate::common::*;
+use crate::drivers::postgres_shared::{connect, PgName};
+
+/// SQL to list all the user tables and views in a database, with PostgreSQL's
+/// row estimates. `reltuples` is -1 for tables which have never been analyzed.
+const LIST_SQL: &str = r#"
+SELECT n.nspname AS "schema", c.relname AS "name", c.relkind::text AS "kind",
+       c.reltuples::bigint AS "rows"
+FROM pg_catalog.pg_class c
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f')
+  AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+  AND n.nspname NOT LIKE 'pg\_toast%'
+  AND n.nspname NOT LIKE 'pg\_temp\_%'
+ORDER BY 1, 2
+"#;
+
+/// Implementation of `list`, but as a real `async` function.
+#[instrument(level = "trace", name = "postgres::list", skip(ctx, source_args))]
+pub(crate) async fn list_helper(
+    ctx: Context,
+    container: String,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Vec<ListEntry>> {
+    let _source_args = source_args.verify(PostgresLocator::features())?;
+
+    let url = container
+        .parse::<Url>()
+        .context("cannot parse Postgres URL")?;
+    if url.scheme() != "postgres" {
+        return Err(format_err!("expected URL scheme postgres: {:?}", container));
+    }
+    if url.fragment().is_some() {
+        return Err(format_err!(
+            "expected a database URL without #table_name: {}",
+            UrlWithHiddenPassword::new(url),
+        ));
+    }
+    let url = UrlWithHiddenPassword::new(url);
+
+    let conn = connect(&ctx, &url).await?;
+    let rows = conn
+        .query(LIST_SQL, &[])
+        .await
+        .context("error listing tables")?;
+    rows.iter()
+        .map(|row| {
+            let table_name = PgName::new(
+                row.get::<_, String>("schema"),
+                row.get::<_, String>("name"),
+            );
+            let kind = match row.get::<_, String>("kind").as_str() {
+                "v" | "m" => ListEntryKind::View,
+                _ => ListEntryKind::Table,
+            };
+            let rows = row.get::<_, Option<i64>>("rows");
+            let locator = PostgresLocator {
+                url: url.clone(),
+                table_name: table_name.clone(),
+            };
+            Ok(ListEntry {
+                name: table_name.unquoted(),
+                locator: locator.to_string(),
+                kind,
+                rows: rows
+                    .filter(|_| kind == ListEntryKind::Table)
+                    .and_then(|r| u64::try_from(r).ok()),
+                bytes: None,
+                modified: None,
+            })
+        })
+        .collect()
+}
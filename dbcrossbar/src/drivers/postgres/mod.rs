@@ -10,18 +10,24 @@ use std::{
 
 use crate::common::*;
 use crate::drivers::postgres_shared::{Client, PgName, PgSchema};
+use crate::fingerprint::Fingerprint;
 
 mod count;
 mod csv_to_binary;
+mod fingerprint;
+mod list;
 mod local_data;
 mod write_local_data;
 
 use self::count::count_helper;
+use self::fingerprint::fingerprint_helper;
+use self::list::list_helper;
 use self::local_data::local_data_helper;
 use self::write_local_data::write_local_data_helper;
 
 pub(crate) use write_local_data::{
-    columns_to_update_for_upsert, create_temp_table_for, prepare_table,
+    columns_to_update_for_upsert, create_temp_table_for, evolve_table_schema,
+    prepare_table,
 };
 
 /// A Postgres database URL and a table name.
@@ -138,6 +144,29 @@ impl Locator for PostgresLocator {
         count_helper(ctx, self.to_owned(), shared_args, source_args).boxed()
     }
 
+    fn fingerprint(
+        &self,
+        ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Fingerprint> {
+        fingerprint_helper(ctx, self.to_owned(), shared_args, source_args).boxed()
+    }
+
+    fn migration_sql(
+        &self,
+        old_schema: &Schema,
+        new_schema: &Schema,
+    ) -> Result<String> {
+        let old_pg_schema =
+            PgSchema::from_schema_and_name(old_schema, &self.table_name)?;
+        let new_pg_schema =
+            PgSchema::from_schema_and_name(new_schema, &self.table_name)?;
+        let mut sql_bytes: Vec<u8> = vec![];
+        new_pg_schema.write_migration_sql(&old_pg_schema, &mut sql_bytes)?;
+        Ok(String::from_utf8(sql_bytes).expect("should always be UTF-8"))
+    }
+
     fn local_data(
         &self,
         ctx: Context,
@@ -176,10 +205,19 @@ impl LocatorStatic for PostgresLocator {
             locator: LocatorFeatures::Schema
                 | LocatorFeatures::LocalData
                 | LocatorFeatures::WriteLocalData
-                | LocatorFeatures::Count,
+                | LocatorFeatures::Count
+                | LocatorFeatures::Fingerprint
+                | LocatorFeatures::MigrationSql
+                | LocatorFeatures::List,
             write_schema_if_exists: EnumSet::empty(),
-            source_args: SourceArgumentsFeatures::WhereClause.into(),
-            dest_args: EnumSet::empty(),
+            source_args: SourceArgumentsFeatures::WhereClause
+                | SourceArgumentsFeatures::Filter
+                | SourceArgumentsFeatures::Dedupe
+                | SourceArgumentsFeatures::OrderBy
+                | SourceArgumentsFeatures::Limit
+                | SourceArgumentsFeatures::Sample
+                | SourceArgumentsFeatures::SampleSeed,
+            dest_args: DestinationArgumentsFeatures::SchemaEvolution.into(),
             dest_if_exists: IfExistsFeatures::Overwrite
                 | IfExistsFeatures::Append
                 | IfExistsFeatures::Error
@@ -187,4 +225,12 @@ impl LocatorStatic for PostgresLocator {
             _placeholder: (),
         }
     }
+
+    fn list(
+        ctx: Context,
+        container: &str,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>> {
+        list_helper(ctx, container.to_owned(), source_args).boxed()
+    }
 }
@@ -5,9 +5,11 @@ use itertools::Itertools;
 use std::{collections::HashSet, io::prelude::*, str};
 
 use super::{csv_to_binary::copy_csv_to_pg_binary, Client, PostgresLocator};
+use crate::bad_rows::BadRows;
 use crate::drivers::postgres_shared::{
-    connect, CheckCatalog, Ident, PgCreateTable, PgSchema,
+    connect, CheckCatalog, Ident, PgCreateTable, PgName, PgSchema,
 };
+use crate::schema_evolution::SchemaEvolution;
 use crate::tokio_glue::try_forward;
 use crate::transform::spawn_sync_transform;
 use crate::{common::*, drivers::postgres_shared::PgCreateType};
@@ -134,6 +136,38 @@ pub(crate) async fn prepare_table(
     create_table(client, &schema).await
 }
 
+/// Add any missing columns to an existing table, and widen any column types
+/// that need it, so that the table can hold data matching `schema`. This
+/// implements `--allow-schema-evolution`, and it's shared with RedShift.
+///
+/// `dest` is used to generate the appropriate `ALTER TABLE` SQL. If the table
+/// doesn't exist yet, we do nothing.
+#[instrument(level = "trace", skip(ctx, dest, schema))]
+pub(crate) async fn evolve_table_schema(
+    ctx: &Context,
+    dest: &dyn Locator,
+    url: &UrlWithHiddenPassword,
+    table_name: &PgName,
+    schema: &Schema,
+) -> Result<()> {
+    let existing = match PgSchema::from_pg_catalog(ctx, url, table_name).await? {
+        Some(existing) => existing.to_schema()?,
+        None => return Ok(()),
+    };
+    let evolution = SchemaEvolution::new(&existing, schema)?;
+    if evolution.is_empty() {
+        return Ok(());
+    }
+    let alter_sql = dest.migration_sql(&evolution.before, &evolution.after)?;
+    debug!("evolving schema of {}: {}", table_name.quoted(), alter_sql);
+    let client = connect(ctx, url).await?;
+    client
+        .batch_execute(&alter_sql)
+        .await
+        .with_context(|| format!("error altering {}", table_name.quoted()))?;
+    Ok(())
+}
+
 /// Generate the `COPY ... FROM ...` SQL we'll pass to `copy_in`. `data_format`
 /// should be something like `"CSV HRADER"` or `"BINARY"`.
 ///
@@ -293,6 +327,7 @@ pub(crate) async fn write_local_data_helper(
 
     // Look up our arguments.
     let schema = shared_args.schema();
+    let bad_rows = shared_args.bad_rows().to_owned();
     let if_exists = dest_args.if_exists().to_owned();
 
     let url = dest.url.clone();
@@ -303,6 +338,12 @@ pub(crate) async fn write_local_data_helper(
         table_name.quoted(),
     );
 
+    // If we're allowed to, update the destination table to match our schema.
+    if dest_args.allow_schema_evolution() {
+        evolve_table_schema(&ctx, &dest, dest.url(), dest.table_name(), schema)
+            .await?;
+    }
+
     // Try to look up our destination table schema in the database.
     let dest_schema = PgSchema::from_pg_catalog_or_default(
         &ctx,
@@ -332,6 +373,7 @@ pub(crate) async fn write_local_data_helper(
                         &mut client,
                         csv_stream,
                         &dest_schema,
+                        &bad_rows,
                         &if_exists,
                     )
                     .await?;
@@ -349,15 +391,20 @@ async fn load_stream(
     client: &mut Client,
     csv_stream: CsvStream,
     dest_schema: &PgSchema,
+    bad_rows: &BadRows,
     if_exists: &IfExists,
 ) -> Result<()> {
     // Convert our CSV stream into a PostgreSQL `BINARY` stream.
+    let name = csv_stream.name.clone();
     let transform_schema = dest_schema.clone();
+    let bad_rows = bad_rows.clone();
     let binary_stream = spawn_sync_transform(
         ctx.clone(),
         "copy_csv_to_pg_binary".to_owned(),
         csv_stream.data,
-        move |_ctx, rdr, wtr| copy_csv_to_pg_binary(&transform_schema, rdr, wtr),
+        move |_ctx, rdr, wtr| {
+            copy_csv_to_pg_binary(&name, &transform_schema, &bad_rows, rdr, wtr)
+        },
     )?;
 
     // Decide whether to do an upsert or regular insert.
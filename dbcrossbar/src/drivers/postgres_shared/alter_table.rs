@@ -0,0 +1,165 @@
+//! PostgreSQL `ALTER TABLE` statements used to migrate a table.
+
+use std::{collections::HashMap, fmt};
+
+use super::{Ident, PgColumn, PgCreateTable, PgDataType, PgName};
+
+/// A single change to make to a PostgreSQL table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum PgAlterTableAction {
+    /// Add a new column.
+    AddColumn(PgColumn),
+    /// Drop an existing column.
+    DropColumn(String),
+    /// Change the data type of an existing column.
+    AlterColumnType {
+        /// The column to change.
+        name: String,
+        /// The new data type.
+        data_type: PgDataType,
+    },
+    /// Allow `NULL` values in an existing column.
+    DropNotNull(String),
+    /// Forbid `NULL` values in an existing column.
+    SetNotNull(String),
+}
+
+impl fmt::Display for PgAlterTableAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgAlterTableAction::AddColumn(col) => write!(f, "ADD COLUMN {}", col),
+            PgAlterTableAction::DropColumn(name) => {
+                write!(f, "DROP COLUMN {}", Ident(name))
+            }
+            PgAlterTableAction::AlterColumnType { name, data_type } => write!(
+                f,
+                "ALTER COLUMN {name} TYPE {data_type} USING {name}::{data_type}",
+                name = Ident(name),
+                data_type = data_type,
+            ),
+            PgAlterTableAction::DropNotNull(name) => {
+                write!(f, "ALTER COLUMN {} DROP NOT NULL", Ident(name))
+            }
+            PgAlterTableAction::SetNotNull(name) => {
+                write!(f, "ALTER COLUMN {} SET NOT NULL", Ident(name))
+            }
+        }
+    }
+}
+
+/// A list of changes which will migrate a PostgreSQL table from one
+/// declaration to another.
+///
+/// We output each action as a separate `ALTER TABLE` statement, because
+/// RedShift can't handle more than one action per statement.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct PgAlterTable {
+    /// The name of the table to alter.
+    pub(crate) name: PgName,
+    /// The changes to make, in order.
+    pub(crate) actions: Vec<PgAlterTableAction>,
+}
+
+impl PgAlterTable {
+    /// Compute the actions needed to turn `old_table` into `new_table`. We
+    /// match columns by name, so renamed columns will be dropped and re-added.
+    /// New columns are added at the end of the table.
+    pub(crate) fn from_old_and_new(
+        old_table: &PgCreateTable,
+        new_table: &PgCreateTable,
+    ) -> PgAlterTable {
+        let new_columns = new_table
+            .columns
+            .iter()
+            .map(|c| (&c.name[..], c))
+            .collect::<HashMap<_, _>>();
+        let old_columns = old_table
+            .columns
+            .iter()
+            .map(|c| (&c.name[..], c))
+            .collect::<HashMap<_, _>>();
+
+        let mut actions = vec![];
+        for old_col in &old_table.columns {
+            if !new_columns.contains_key(&old_col.name[..]) {
+                actions.push(PgAlterTableAction::DropColumn(old_col.name.clone()));
+            }
+        }
+        for new_col in &new_table.columns {
+            match old_columns.get(&new_col.name[..]) {
+                None => actions.push(PgAlterTableAction::AddColumn(new_col.clone())),
+                Some(old_col) => {
+                    if old_col.data_type != new_col.data_type {
+                        actions.push(PgAlterTableAction::AlterColumnType {
+                            name: new_col.name.clone(),
+                            data_type: new_col.data_type.clone(),
+                        });
+                    }
+                    if old_col.is_nullable && !new_col.is_nullable {
+                        actions.push(PgAlterTableAction::SetNotNull(
+                            new_col.name.clone(),
+                        ));
+                    } else if !old_col.is_nullable && new_col.is_nullable {
+                        actions.push(PgAlterTableAction::DropNotNull(
+                            new_col.name.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+        PgAlterTable {
+            name: new_table.name.clone(),
+            actions,
+        }
+    }
+}
+
+impl fmt::Display for PgAlterTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for action in &self.actions {
+            writeln!(f, "ALTER TABLE {} {};", self.name.quoted(), action)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn alter_table_from_old_and_new() {
+    use super::PgScalarDataType;
+
+    let column = |name: &str, ty: PgScalarDataType, is_nullable: bool| PgColumn {
+        name: name.to_owned(),
+        data_type: PgDataType::Scalar(ty),
+        is_nullable,
+    };
+    let table = |columns: Vec<PgColumn>| PgCreateTable {
+        name: PgName::new(None, "example"),
+        columns,
+        if_not_exists: false,
+        temporary: false,
+    };
+    let old_table = table(vec![
+        column("id", PgScalarDataType::Int, false),
+        column("name", PgScalarDataType::Text, false),
+        column("legacy", PgScalarDataType::Text, true),
+    ]);
+    let new_table = table(vec![
+        column("id", PgScalarDataType::Bigint, false),
+        column("name", PgScalarDataType::Text, true),
+        column("created_at", PgScalarDataType::TimestampWithTimeZone, true),
+    ]);
+    let alter_table = PgAlterTable::from_old_and_new(&old_table, &new_table);
+    assert_eq!(
+        alter_table.to_string(),
+        r#"ALTER TABLE "example" DROP COLUMN "legacy";
+ALTER TABLE "example" ALTER COLUMN "id" TYPE bigint USING "id"::bigint;
+ALTER TABLE "example" ALTER COLUMN "name" DROP NOT NULL;
+ALTER TABLE "example" ADD COLUMN "created_at" timestamp with time zone;
+"#,
+    );
+
+    // Migrating a table to itself should be a no-op.
+    assert!(PgAlterTable::from_old_and_new(&new_table, &new_table)
+        .actions
+        .is_empty());
+}
@@ -7,6 +7,7 @@ use tokio_postgres::Config;
 use crate::common::*;
 use crate::tls::rustls_client_config;
 
+mod alter_table;
 mod catalog;
 mod column;
 mod create_type;
@@ -14,6 +15,7 @@ mod data_type;
 mod schema;
 mod table;
 
+pub(crate) use self::alter_table::{PgAlterTable, PgAlterTableAction};
 pub(crate) use self::column::PgColumn;
 pub(crate) use self::create_type::{PgCreateType, PgCreateTypeDefinition};
 pub(crate) use self::data_type::{PgDataType, PgScalarDataType};
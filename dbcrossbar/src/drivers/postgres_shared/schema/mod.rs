@@ -2,8 +2,12 @@
 
 use std::{fmt, sync::Arc};
 
-use super::{catalog, CheckCatalog, PgCreateTable, PgCreateType, PgName};
+use super::{
+    catalog, pg_quote, CheckCatalog, PgAlterTable, PgAlterTableAction, PgCreateTable,
+    PgCreateType, PgCreateTypeDefinition, PgName,
+};
 use crate::common::*;
+use crate::filter::SqlDialect;
 use crate::parse_error::{Annotation, FileInfo, ParseError};
 
 mod schema_sql;
@@ -159,6 +163,69 @@ impl PgSchema {
         })
     }
 
+    /// Write out SQL which will migrate a table declared by `old_schema` so
+    /// that it matches this schema.
+    ///
+    /// Any new types used by our table will be created, and new values will be
+    /// added to existing `ENUM` types. But we refuse to remove values from an
+    /// `ENUM`, because there's no safe way to do that, and we refuse to add
+    /// `NOT NULL` columns, because existing rows won't have values for them.
+    pub(crate) fn write_migration_sql(
+        &self,
+        old_schema: &PgSchema,
+        f: &mut dyn Write,
+    ) -> Result<()> {
+        let table = self.table()?;
+        let alter_table = PgAlterTable::from_old_and_new(old_schema.table()?, table);
+        for action in &alter_table.actions {
+            if let PgAlterTableAction::AddColumn(col) = action {
+                if !col.is_nullable {
+                    return Err(format_err!(
+                        "cannot add NOT NULL column {:?} to an existing PostgreSQL table",
+                        col.name,
+                    ));
+                }
+            }
+        }
+
+        let needed_types = table.named_type_names();
+        for ty in &self.types {
+            if !needed_types.contains(&ty.name) {
+                continue;
+            }
+            let old_ty = old_schema.types.iter().find(|t| t.name == ty.name);
+            match (old_ty.map(|t| &t.definition), &ty.definition) {
+                (None, _) => write!(f, "{}", ty)?,
+                (
+                    Some(PgCreateTypeDefinition::Enum(old_values)),
+                    PgCreateTypeDefinition::Enum(new_values),
+                ) => {
+                    if let Some(missing) =
+                        old_values.iter().find(|v| !new_values.contains(v))
+                    {
+                        return Err(format_err!(
+                            "cannot remove value {:?} from PostgreSQL type {}",
+                            missing,
+                            ty.name.quoted(),
+                        ));
+                    }
+                    for value in new_values {
+                        if !old_values.contains(value) {
+                            writeln!(
+                                f,
+                                "ALTER TYPE {} ADD VALUE IF NOT EXISTS {};",
+                                ty.name.quoted(),
+                                pg_quote(value),
+                            )?;
+                        }
+                    }
+                }
+            }
+        }
+        write!(f, "{}", alter_table)?;
+        Ok(())
+    }
+
     /// Write a `COPY (SELECT ...) TO STDOUT ...` statement for this schema's
     /// table.
     pub(crate) fn write_export_sql(
@@ -169,13 +236,16 @@ impl PgSchema {
         self.table()?.write_export_sql(f, source_args)
     }
 
-    /// Write a `SELECT ...` statement for this schema's table.
+    /// Write a `SELECT ...` statement for this schema's table, using
+    /// `dialect` for any `--filter`.
     pub(crate) fn write_export_select_sql(
         &self,
         f: &mut dyn Write,
         source_args: &SourceArguments<Verified>,
+        dialect: SqlDialect,
     ) -> Result<()> {
-        self.table()?.write_export_select_sql(f, source_args)
+        self.table()?
+            .write_export_select_sql(f, source_args, dialect)
     }
 
     /// Write a `SELECT COUNT(*) ...` statement for this schema's table.
@@ -186,6 +256,17 @@ impl PgSchema {
     ) -> Result<()> {
         self.table()?.write_count_sql(f, source_args)
     }
+
+    /// Write a `SELECT` statement which computes a fingerprint of this
+    /// schema's table, using the columns in `schema`.
+    pub(crate) fn write_fingerprint_sql(
+        &self,
+        f: &mut dyn Write,
+        schema: &Schema,
+        source_args: &SourceArguments<Verified>,
+    ) -> Result<()> {
+        self.table()?.write_fingerprint_sql(f, schema, source_args)
+    }
 }
 
 impl fmt::Display for PgSchema {
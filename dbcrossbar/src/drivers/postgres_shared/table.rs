@@ -6,8 +6,12 @@ use std::{
     fmt,
 };
 
-use super::{PgColumn, PgDataType, PgName, PgScalarDataType};
+use super::{Ident, PgColumn, PgDataType, PgName, PgScalarDataType};
 use crate::common::*;
+use crate::dedupe::{Dedupe, Keep};
+use crate::filter::SqlDialect;
+use crate::fingerprint::{aggregates, Aggregate};
+use crate::order_by::OrderBy;
 use crate::schema::Column;
 use crate::separator::Separator;
 
@@ -153,16 +157,18 @@ impl PgCreateTable {
         source_args: &SourceArguments<Verified>,
     ) -> Result<()> {
         write!(f, "COPY (")?;
-        self.write_export_select_sql(f, source_args)?;
+        self.write_export_select_sql(f, source_args, SqlDialect::Postgres)?;
         write!(f, ") TO STDOUT WITH CSV HEADER")?;
         Ok(())
     }
 
-    /// Write a `SELECT ...` statement for this table.
+    /// Write a `SELECT ...` statement for this table, using `dialect` for any
+    /// `--filter`.
     pub(crate) fn write_export_select_sql(
         &self,
         f: &mut dyn Write,
         source_args: &SourceArguments<Verified>,
+        dialect: SqlDialect,
     ) -> Result<()> {
         write!(f, "SELECT ")?;
         if self.columns.is_empty() {
@@ -173,9 +179,42 @@ impl PgCreateTable {
             write!(f, "{}", sep.display())?;
             col.write_export_select_expr(f)?;
         }
-        write!(f, " FROM {}", &self.name.quoted())?;
-        if let Some(where_clause) = source_args.where_clause() {
-            write!(f, " WHERE ({})", where_clause)?;
+        if let Some(dedupe) = source_args.dedupe() {
+            // `TABLESAMPLE` would run before we deduplicate, so `cp` never
+            // asks us to do both.
+            if source_args.sample().is_some() {
+                return Err(format_err!("cannot use --sample with --dedupe-on"));
+            }
+            write!(f, " FROM ")?;
+            if dialect == SqlDialect::RedShift {
+                // RedShift doesn't support `DISTINCT ON`, or `LIMIT` in the
+                // outermost `SELECT`, so we do everything in a subquery. We
+                // never sort RedShift exports, because `UNLOAD` writes several
+                // files anyway.
+                write_qualify_subquery_sql(f, &self.name, dedupe, source_args)?;
+                return Ok(());
+            }
+            write_dedupe_subquery_sql(f, &self.name, dedupe, source_args, dialect)?;
+            if let Some(order_by) = source_args.order_by() {
+                write_order_by_sql(f, Some("deduped"), order_by)?;
+            }
+            if let Some(limit) = source_args.limit() {
+                write!(f, " LIMIT {}", limit)?;
+            }
+        } else if source_args.limit().is_some() || source_args.order_by().is_some() {
+            // RedShift doesn't allow `UNLOAD` to use `LIMIT` in the outermost
+            // `SELECT`, so we filter our rows in a subquery. We also need a
+            // subquery to sort, because our `SELECT` converts some columns to
+            // text using the same names, and `ORDER BY` would see those.
+            write!(f, " FROM (SELECT * FROM {}", &self.name.quoted())?;
+            write_export_filters_sql(f, source_args, dialect)?;
+            write!(f, ") AS \"filtered\"")?;
+            if let Some(order_by) = source_args.order_by() {
+                write_order_by_sql(f, Some("filtered"), order_by)?;
+            }
+        } else {
+            write!(f, " FROM {}", &self.name.quoted())?;
+            write_export_filters_sql(f, source_args, dialect)?;
         }
         Ok(())
     }
@@ -188,13 +227,263 @@ impl PgCreateTable {
     ) -> Result<()> {
         writeln!(f, "SELECT COUNT(*)")?;
         writeln!(f, " FROM {}", &self.name.quoted())?;
-        if let Some(where_clause) = source_args.where_clause() {
-            writeln!(f, " WHERE ({})", where_clause)?;
+        let conditions = filter_conditions(source_args, SqlDialect::Postgres)?;
+        if !conditions.is_empty() {
+            writeln!(f, " WHERE {}", conditions.join(" AND "))?;
+        }
+        Ok(())
+    }
+
+    /// Write a `SELECT` statement which computes each value of a
+    /// [`Fingerprint`](crate::fingerprint::Fingerprint) for the columns in
+    /// `schema`, as text.
+    pub(crate) fn write_fingerprint_sql(
+        &self,
+        f: &mut dyn Write,
+        schema: &Schema,
+        source_args: &SourceArguments<Verified>,
+    ) -> Result<()> {
+        write!(f, "SELECT CAST(COUNT(*) AS TEXT)")?;
+        for (col, aggregate) in aggregates(schema) {
+            let name = Ident(&col.name);
+            match aggregate {
+                Aggregate::NullCount => {
+                    write!(f, ", CAST(COUNT(*) - COUNT({}) AS TEXT)", name)?
+                }
+                Aggregate::Sum => write!(f, ", CAST(SUM({}) AS TEXT)", name)?,
+            }
+        }
+        writeln!(f)?;
+        writeln!(f, " FROM {}", &self.name.quoted())?;
+        let conditions = filter_conditions(source_args, SqlDialect::Postgres)?;
+        if !conditions.is_empty() {
+            writeln!(f, " WHERE {}", conditions.join(" AND "))?;
         }
         Ok(())
     }
 }
 
+/// Write the `TABLESAMPLE`, `WHERE`, `ORDER BY` and `LIMIT` clauses for an
+/// export. We only sort here when we need to choose which rows to keep.
+///
+/// Seeded samples use `TABLESAMPLE`, which only PostgreSQL supports. Unseeded
+/// samples use `random()`, which also works on RedShift.
+fn write_export_filters_sql(
+    f: &mut dyn Write,
+    source_args: &SourceArguments<Verified>,
+    dialect: SqlDialect,
+) -> Result<()> {
+    let sample = source_args.sample();
+    if let Some(sample) = sample {
+        if let Some(seed) = sample.seed() {
+            write!(
+                f,
+                " TABLESAMPLE BERNOULLI ({}) REPEATABLE ({})",
+                sample.percent(),
+                seed,
+            )?;
+        }
+    }
+    let mut conditions = filter_conditions(source_args, dialect)?;
+    if let Some(sample) = sample {
+        if sample.seed().is_none() {
+            conditions.push(format!("(random() < {})", sample.fraction()));
+        }
+    }
+    if !conditions.is_empty() {
+        write!(f, " WHERE {}", conditions.join(" AND "))?;
+    }
+    if let Some(limit) = source_args.limit() {
+        if let Some(order_by) = source_args.order_by() {
+            write_order_by_sql(f, None, order_by)?;
+        }
+        write!(f, " LIMIT {}", limit)?;
+    }
+    Ok(())
+}
+
+/// Write an `ORDER BY` clause, qualifying our column names with `relation` if
+/// specified. Text is sorted by byte value, and `NULL` always comes last, so
+/// that we match `sort_csv_streams`.
+fn write_order_by_sql(
+    f: &mut dyn Write,
+    relation: Option<&str>,
+    order_by: &OrderBy,
+) -> Result<()> {
+    write!(f, " ORDER BY ")?;
+    let mut sep = Separator::new(", ");
+    for column in order_by.columns() {
+        write!(f, "{}", sep.display())?;
+        let name = match relation {
+            Some(relation) => format!("{}.{}", Ident(relation), Ident(column.name())),
+            None => Ident(column.name()).to_string(),
+        };
+        if column.is_text() {
+            write!(f, "({}::text) COLLATE \"C\"", name)?;
+        } else {
+            write!(f, "{}", name)?;
+        }
+        if column.is_descending() {
+            write!(f, " DESC")?;
+        }
+        write!(f, " NULLS LAST")?;
+    }
+    Ok(())
+}
+
+/// Build the conditions for `--where` and `--filter`, each wrapped in
+/// parentheses.
+fn filter_conditions(
+    source_args: &SourceArguments<Verified>,
+    dialect: SqlDialect,
+) -> Result<Vec<String>> {
+    let mut conditions = vec![];
+    if let Some(where_clause) = source_args.where_clause() {
+        conditions.push(format!("({})", where_clause));
+    }
+    if let Some(filter) = source_args.filter() {
+        conditions.push(format!("({})", filter.to_sql(dialect)?));
+    }
+    Ok(conditions)
+}
+
+/// Write a subquery which applies `--where` and `--filter`, and then keeps one
+/// row for each key using `DISTINCT ON`. This is only supported by PostgreSQL,
+/// not RedShift.
+fn write_dedupe_subquery_sql(
+    f: &mut dyn Write,
+    name: &PgName,
+    dedupe: &Dedupe,
+    source_args: &SourceArguments<Verified>,
+    dialect: SqlDialect,
+) -> Result<()> {
+    let keys = dedupe.keys().iter().map(|k| Ident(k)).join(", ");
+    write!(f, "(SELECT DISTINCT ON ({}) * FROM {}", keys, name.quoted())?;
+    let conditions = filter_conditions(source_args, dialect)?;
+    if !conditions.is_empty() {
+        write!(f, " WHERE {}", conditions.join(" AND "))?;
+    }
+    write!(f, " ORDER BY {}", keys)?;
+    if let Keep::LatestBy(column) = dedupe.keep() {
+        write!(f, ", {} DESC NULLS LAST", Ident(column))?;
+    }
+    write!(f, ") AS \"deduped\"")?;
+    Ok(())
+}
+
+/// Write a subquery which applies `--where` and `--filter`, and then keeps one
+/// row for each key using `QUALIFY`, followed by any `LIMIT`. This is the
+/// RedShift version of [`write_dedupe_subquery_sql`].
+fn write_qualify_subquery_sql(
+    f: &mut dyn Write,
+    name: &PgName,
+    dedupe: &Dedupe,
+    source_args: &SourceArguments<Verified>,
+) -> Result<()> {
+    let keys = dedupe.keys().iter().map(|k| Ident(k)).join(", ");
+    write!(f, "(SELECT * FROM {}", name.quoted())?;
+    let conditions = filter_conditions(source_args, SqlDialect::RedShift)?;
+    if !conditions.is_empty() {
+        write!(f, " WHERE {}", conditions.join(" AND "))?;
+    }
+    write!(f, " QUALIFY ROW_NUMBER() OVER (PARTITION BY {}", keys)?;
+    if let Keep::LatestBy(column) = dedupe.keep() {
+        write!(f, " ORDER BY {} DESC NULLS LAST", Ident(column))?;
+    }
+    write!(f, ") = 1")?;
+    if let Some(limit) = source_args.limit() {
+        write!(f, " LIMIT {}", limit)?;
+    }
+    write!(f, ") AS \"deduped\"")?;
+    Ok(())
+}
+
+#[test]
+fn write_dedupe_sql_for_each_dialect() {
+    use crate::schema::DataType;
+
+    let schema = Schema::from_types_and_table(
+        vec![],
+        Table {
+            name: "t".to_owned(),
+            columns: ["id", "name", "updated_at"]
+                .iter()
+                .map(|&name| Column {
+                    name: name.to_owned(),
+                    is_nullable: true,
+                    data_type: DataType::Text,
+                    comment: None,
+                })
+                .collect(),
+        },
+    )
+    .unwrap();
+    let table = PgCreateTable::from_name_and_columns(
+        &schema,
+        PgName::new(None, "t"),
+        &schema.table.columns,
+    )
+    .unwrap();
+    let dedupe = Dedupe::new("id", Some("latest-by=updated_at"), &schema).unwrap();
+    let source_args = SourceArguments::for_temporary()
+        .with_dedupe(Some(dedupe))
+        .with_limit(Some(10))
+        .verify(crate::drivers::redshift::RedshiftLocator::features())
+        .unwrap();
+
+    let mut sql = vec![];
+    table
+        .write_export_select_sql(&mut sql, &source_args, SqlDialect::Postgres)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(sql).unwrap(),
+        r#"SELECT "id","name","updated_at" FROM (SELECT DISTINCT ON ("id") * FROM "t" ORDER BY "id", "updated_at" DESC NULLS LAST) AS "deduped" LIMIT 10"#,
+    );
+
+    let mut sql = vec![];
+    table
+        .write_export_select_sql(&mut sql, &source_args, SqlDialect::RedShift)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(sql).unwrap(),
+        r#"SELECT "id","name","updated_at" FROM (SELECT * FROM "t" QUALIFY ROW_NUMBER() OVER (PARTITION BY "id" ORDER BY "updated_at" DESC NULLS LAST) = 1 LIMIT 10) AS "deduped""#,
+    );
+}
+
+#[test]
+fn write_fingerprint_sql_with_filter() {
+    use crate::filter::Filter;
+
+    let mut schema = crate::filter::test_schema();
+    schema
+        .table
+        .columns
+        .retain(|c| ["id", "name", "tags", "price"].contains(&c.name.as_str()));
+    let table = PgCreateTable::from_name_and_columns(
+        &schema,
+        PgName::new(None, "t"),
+        &schema.table.columns,
+    )
+    .unwrap();
+    let filter = Filter::new("id > 1", &schema).unwrap();
+    let source_args = SourceArguments::for_temporary()
+        .with_filter(Some(filter))
+        .verify(crate::drivers::postgres::PostgresLocator::features())
+        .unwrap();
+
+    let mut sql = vec![];
+    table
+        .write_fingerprint_sql(&mut sql, &schema, &source_args)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(sql).unwrap(),
+        r#"SELECT CAST(COUNT(*) AS TEXT), CAST(COUNT(*) - COUNT("id") AS TEXT), CAST(SUM("id") AS TEXT), CAST(COUNT(*) - COUNT("name") AS TEXT), CAST(COUNT(*) - COUNT("price") AS TEXT), CAST(SUM("price") AS TEXT)
+ FROM "t"
+ WHERE (("id" > 1))
+"#,
+    );
+}
+
 impl fmt::Display for PgCreateTable {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "CREATE")?;
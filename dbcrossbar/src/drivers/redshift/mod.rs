@@ -17,10 +17,12 @@ use crate::drivers::{
 };
 
 mod local_data;
+mod migration_sql;
 mod write_local_data;
 mod write_remote_data;
 
 use local_data::local_data_helper;
+use migration_sql::migration_sql_helper;
 use write_local_data::write_local_data_helper;
 use write_remote_data::write_remote_data_helper;
 
@@ -90,6 +92,14 @@ impl Locator for RedshiftLocator {
         self.postgres_locator.schema(ctx, source_args)
     }
 
+    fn migration_sql(
+        &self,
+        old_schema: &Schema,
+        new_schema: &Schema,
+    ) -> Result<String> {
+        migration_sql_helper(self, old_schema, new_schema)
+    }
+
     fn local_data(
         &self,
         ctx: Context,
@@ -111,9 +121,13 @@ impl Locator for RedshiftLocator {
     }
 
     fn supports_write_remote_data(&self, source: &dyn Locator) -> bool {
-        // We can only do `write_remote_data` if `source` is a `S3Locator`.
-        // Otherwise, we need to do `write_local_data` like normal.
-        source.as_any().is::<S3Locator>()
+        // We can only do `write_remote_data` if `source` is a `S3Locator`
+        // pointing at a directory. Otherwise, we need to do `write_local_data`
+        // like normal.
+        source
+            .as_any()
+            .downcast_ref::<S3Locator>()
+            .is_some_and(|s3| s3.is_directory())
     }
 
     fn write_remote_data(
@@ -145,11 +159,17 @@ impl LocatorStatic for RedshiftLocator {
         Features {
             locator: LocatorFeatures::Schema
                 | LocatorFeatures::LocalData
-                | LocatorFeatures::WriteLocalData,
+                | LocatorFeatures::WriteLocalData
+                | LocatorFeatures::MigrationSql,
             write_schema_if_exists: EnumSet::empty(),
             source_args: SourceArgumentsFeatures::DriverArgs
-                | SourceArgumentsFeatures::WhereClause,
-            dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
+                | SourceArgumentsFeatures::WhereClause
+                | SourceArgumentsFeatures::Filter
+                | SourceArgumentsFeatures::Dedupe
+                | SourceArgumentsFeatures::Limit
+                | SourceArgumentsFeatures::Sample,
+            dest_args: DestinationArgumentsFeatures::DriverArgs
+                | DestinationArgumentsFeatures::SchemaEvolution,
             dest_if_exists: IfExistsFeatures::Append
                 | IfExistsFeatures::Error
                 | IfExistsFeatures::Overwrite
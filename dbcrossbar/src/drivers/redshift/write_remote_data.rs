@@ -6,10 +6,12 @@ use super::{RedshiftDriverArguments, RedshiftLocator};
 use crate::common::*;
 use crate::drivers::postgres_shared::{PgColumn, PgDataType, PgScalarDataType};
 use crate::drivers::{
-    postgres::{columns_to_update_for_upsert, create_temp_table_for, prepare_table},
+    postgres::{
+        columns_to_update_for_upsert, create_temp_table_for, evolve_table_schema,
+        prepare_table,
+    },
     postgres_shared::{
-        connect, pg_quote, CheckCatalog, Client, Ident, PgCreateTable, PgName,
-        PgSchema,
+        connect, pg_quote, CheckCatalog, Client, Ident, PgCreateTable, PgSchema,
     },
     s3::S3Locator,
 };
@@ -58,6 +60,9 @@ pub(crate) async fn write_remote_data_helper(
     // Try to look up our table schema in the database.
     schema.verify_redshift_can_import_from_csv()?;
     let table_name = dest.table_name();
+    if dest_args.allow_schema_evolution() {
+        evolve_table_schema(&ctx, &dest, dest.url(), table_name, schema).await?;
+    }
     let mut pg_schema = PgSchema::from_pg_catalog_or_default(
         &ctx,
         CheckCatalog::from(&if_exists),
@@ -78,7 +83,7 @@ pub(crate) async fn write_remote_data_helper(
         let temp_table = create_temp_table_for(&mut client, &pg_schema).await?;
 
         // Copy data into our temporary table.
-        copy_in(&client, &source_url, &temp_table.name, &to_args).await?;
+        copy_in(&client, &source_url, &temp_table, &to_args).await?;
 
         // Build our upsert SQL.
         upsert_from_temp_table(
@@ -89,29 +94,34 @@ pub(crate) async fn write_remote_data_helper(
         )
         .await?;
     } else {
-        copy_in(&client, &source_url, table_name, &to_args).await?;
+        copy_in(&client, &source_url, pg_schema.table()?, &to_args).await?;
     }
 
     Ok(vec![dest.boxed()])
 }
 
 /// Copy data from S3 into a RedShift table.
-#[instrument(level = "trace", skip(client))]
+///
+/// We list the columns of `dest_table` explicitly, so that the CSV columns
+/// will still line up if the table's column order differs from our data.
+#[instrument(level = "trace", skip(client, dest_table), fields(dest_table.name = ?dest_table.name))]
 async fn copy_in(
     client: &Client,
     source_s3_url: &Url,
-    dest_table: &PgName,
+    dest_table: &PgCreateTable,
     to_args: &RedshiftDriverArguments,
 ) -> Result<()> {
+    let dest_name = &dest_table.name;
     debug!(
         "Copying into {} from {}",
-        dest_table.unquoted(),
+        dest_name.unquoted(),
         source_s3_url.as_str(),
     );
     let copy_sql = format!(
-        "{partner}COPY {dest} FROM {source}\n{credentials}FORMAT CSV\nIGNOREHEADER 1\nDATEFORMAT 'auto'\nTIMEFORMAT 'auto'",
+        "{partner}COPY {dest} ({columns}) FROM {source}\n{credentials}FORMAT CSV\nIGNOREHEADER 1\nDATEFORMAT 'auto'\nTIMEFORMAT 'auto'",
         partner = to_args.partner_sql()?,
-        dest = dest_table.quoted(),
+        dest = dest_name.quoted(),
+        columns = dest_table.columns.iter().map(|c| Ident(&c.name)).join(", "),
         source = pg_quote(source_s3_url.as_str()), // `$1` doesn't work here.
         credentials = to_args.credentials_sql()?,
     );
@@ -119,7 +129,7 @@ async fn copy_in(
     client.execute(&copy_stmt, &[]).await.with_context(|| {
         format!(
             "error copying to {} from {}",
-            dest_table.quoted(),
+            dest_name.quoted(),
             source_s3_url
         )
     })?;
@@ -222,7 +232,7 @@ INSERT INTO {dest_table} ({all_columns}) (
 }
 
 /// Convert specific types to use their RedShift-specific versions instead.
-trait UseRedshiftSpecificTypes {
+pub(super) trait UseRedshiftSpecificTypes {
     /// Convert this type to use its RedShift-specific version.
     fn use_redshift_specific_types(&mut self) -> Result<()>;
 }
@@ -0,0 +1,52 @@
+//! Implementation of `list`, but as a real `async` function.
+
+use super::S3Locator;
+use crate::clouds::aws::s3;
+use crate::common::*;
+use crate::wildcard::PathGlob;
+use futures::future;
+
+/// Implementation of `list`, but as a real `async` function.
+#[instrument(level = "trace", name = "s3::list", skip(ctx, source_args))]
+pub(crate) async fn list_helper(
+    ctx: Context,
+    container: String,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Vec<ListEntry>> {
+    let _source_args = source_args.verify(S3Locator::features())?;
+    let locator = container.parse::<S3Locator>()?;
+    let glob = PathGlob::parse(locator.as_url().as_str());
+    let url = match &glob {
+        Some(glob) => glob.base().parse::<Url>()?,
+        None if locator.is_directory() => locator.as_url().to_owned(),
+        None => {
+            return Err(format_err!(
+                "{} must end with a '/' or contain a glob",
+                locator
+            ))
+        }
+    };
+
+    s3::ls_with_sizes(&ctx, &url)
+        .await?
+        .try_filter(|(file_url, _)| {
+            future::ready(
+                glob.as_ref()
+                    .is_none_or(|glob| glob.matches(file_url.as_str())),
+            )
+        })
+        .map_ok(|(file_url, size)| ListEntry {
+            name: file_url
+                .as_str()
+                .strip_prefix(url.as_str())
+                .unwrap_or_else(|| file_url.as_str())
+                .to_owned(),
+            locator: file_url.to_string(),
+            kind: ListEntryKind::File,
+            rows: None,
+            bytes: Some(size),
+            modified: None,
+        })
+        .try_collect()
+        .await
+}
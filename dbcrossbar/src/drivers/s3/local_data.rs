@@ -4,6 +4,9 @@ use super::S3Locator;
 use crate::clouds::aws::s3;
 use crate::common::*;
 use crate::csv_stream::csv_stream_name;
+use crate::partition::{add_partition_columns, should_read_partition};
+use crate::wildcard::PathGlob;
+use futures::future;
 
 /// Implementation of `local_data`, but as a real `async` function.
 #[instrument(
@@ -17,13 +20,34 @@ pub(crate) async fn local_data_helper(
     shared_args: SharedArguments<Unverified>,
     source_args: SourceArguments<Unverified>,
 ) -> Result<Option<BoxStream<CsvStream>>> {
-    let _shared_args = shared_args.verify(S3Locator::features())?;
-    let _source_args = source_args.verify(S3Locator::features())?;
+    let shared_args = shared_args.verify(S3Locator::features())?;
+    let schema = shared_args.schema().to_owned();
+    let source_args = source_args.verify(S3Locator::features())?;
+    let partition_filter = source_args.partition_filter().cloned();
 
     debug!("getting CSV files from {}", url);
 
-    // List the files at our URL.
-    let file_urls = s3::ls(&ctx, &url).await?;
+    // List the files at our URL. If we have a glob, we list its base
+    // directory and keep the files which match.
+    let glob = PathGlob::parse(url.as_str());
+    let is_file = glob.is_none() && !url.path().ends_with('/');
+    let url = match &glob {
+        Some(glob) => glob.base().parse::<Url>()?,
+        None => url,
+    };
+    let file_urls = s3::ls(&ctx, &url).await?.try_filter({
+        let url = url.clone();
+        move |file_url| {
+            // `aws s3 ls` matches prefixes, so when we were asked for a single
+            // file, make sure we don't also read `file.csv_trailing`.
+            future::ready(if is_file {
+                file_url == &url
+            } else {
+                glob.as_ref()
+                    .is_none_or(|glob| glob.matches(file_url.as_str()))
+            })
+        }
+    });
 
     // Convert into `CsvStream` values lazily in case there are a lot of CSV
     // files we need to read.
@@ -31,12 +55,21 @@ pub(crate) async fn local_data_helper(
     // XXX - This will fail (either silently or noisily, I'm not sure) if there
     // are 1000+ files in the S3 directory, and we can't fix this without
     // switching from `aws s3` to native S3 API calls from Rust.
-    let csv_streams = file_urls.and_then(move |file_url| {
+    let csv_streams = file_urls.try_filter_map(move |file_url| {
         let ctx = ctx.clone();
         let url = url.clone();
+        let schema = schema.clone();
+        let partition_filter = partition_filter.clone();
         async move {
-            // Stream the file from the cloud.
+            // Skip Hive-style partition directories which can't match our
+            // filter.
             let name = csv_stream_name(url.as_str(), file_url.as_str())?.to_owned();
+            if !should_read_partition(&name, partition_filter.as_ref())? {
+                trace!("skipping partition {}", file_url);
+                return Ok(None);
+            }
+
+            // Stream the file from the cloud.
             let data = s3::download_file(&ctx, &file_url)
                 .instrument(
                     debug_span!("read_stream", stream.name = %name, url = %file_url),
@@ -44,7 +77,8 @@ pub(crate) async fn local_data_helper(
                 .await?;
 
             // Assemble everything into a CSV stream.
-            Ok(CsvStream { name, data })
+            let csv_stream = CsvStream { name, data };
+            add_partition_columns(ctx, &schema, csv_stream).map(Some)
         }
         .boxed()
     });
@@ -6,12 +6,15 @@ use std::{fmt, str::FromStr};
 use crate::common::*;
 use crate::drivers::redshift::RedshiftLocator;
 use crate::locator::PathLikeLocator;
+use crate::wildcard::PathGlob;
 
+mod list;
 mod local_data;
 mod prepare_as_destination;
 mod write_local_data;
 mod write_remote_data;
 
+use list::list_helper;
 use local_data::local_data_helper;
 pub(crate) use prepare_as_destination::prepare_as_destination_helper;
 use write_local_data::write_local_data_helper;
@@ -29,6 +32,21 @@ impl S3Locator {
     pub(crate) fn as_url(&self) -> &Url {
         &self.url
     }
+
+    /// Does this locator point at an `s3://` directory?
+    pub(crate) fn is_directory(&self) -> bool {
+        self.url.path().ends_with('/') && !self.is_glob()
+    }
+
+    /// Does this locator point at an `s3://` CSV file?
+    pub(crate) fn is_csv_file(&self) -> bool {
+        self.url.path().to_ascii_lowercase().ends_with(".csv") && !self.is_glob()
+    }
+
+    /// Does this locator contain a glob pattern like `*`?
+    pub(crate) fn is_glob(&self) -> bool {
+        PathGlob::parse(self.url.as_str()).is_some()
+    }
 }
 
 impl fmt::Display for S3Locator {
@@ -47,10 +65,19 @@ impl FromStr for S3Locator {
                 .with_context(|| format!("cannot parse {}", s))?;
             if !url.path().starts_with('/') {
                 Err(format_err!("{} must start with s3://", url))
-            } else if !url.path().ends_with('/') {
-                Err(format_err!("{} must end with a '/'", url))
             } else {
-                Ok(S3Locator { url })
+                let locator = S3Locator { url };
+                if !locator.is_directory()
+                    && !locator.is_csv_file()
+                    && !locator.is_glob()
+                {
+                    Err(format_err!(
+                        "{} must end with a '/' or '.csv', or contain a glob",
+                        locator
+                    ))
+                } else {
+                    Ok(locator)
+                }
             }
         } else {
             Err(format_err!("expected {} to begin with s3://", s))
@@ -78,19 +105,23 @@ impl Locator for S3Locator {
 
     fn write_local_data(
         &self,
-        _ctx: Context,
+        ctx: Context,
         data: BoxStream<CsvStream>,
         shared_args: SharedArguments<Unverified>,
         dest_args: DestinationArguments<Unverified>,
     ) -> BoxFuture<BoxStream<BoxFuture<BoxLocator>>> {
-        write_local_data_helper(self.url.clone(), data, shared_args, dest_args).boxed()
+        write_local_data_helper(ctx, self.url.clone(), data, shared_args, dest_args)
+            .boxed()
     }
 
     fn supports_write_remote_data(&self, source: &dyn Locator) -> bool {
         // We can only do `write_remote_data` if `source` is a `RedshiftLocator`
         // or a `TrinoLocator`. Otherwise, we need to do `write_local_data` like
-        // normal.
-        source.as_any().is::<RedshiftLocator>() || source.as_any().is::<TrinoLocator>()
+        // normal. Globs are only meaningful as sources, so we leave them to
+        // `write_local_data`, which will report an error.
+        (source.as_any().is::<RedshiftLocator>()
+            || source.as_any().is::<TrinoLocator>())
+            && self.is_directory()
     }
 
     fn write_remote_data(
@@ -120,14 +151,24 @@ impl LocatorStatic for S3Locator {
 
     fn features() -> Features {
         Features {
-            locator: LocatorFeatures::LocalData | LocatorFeatures::WriteLocalData,
+            locator: LocatorFeatures::LocalData
+                | LocatorFeatures::WriteLocalData
+                | LocatorFeatures::List,
             write_schema_if_exists: EnumSet::empty(),
             source_args: Default::default(),
-            dest_args: Default::default(),
+            dest_args: DestinationArgumentsFeatures::PartitionBy.into(),
             dest_if_exists: IfExistsFeatures::Overwrite.into(),
             _placeholder: (),
         }
     }
+
+    fn list(
+        ctx: Context,
+        container: &str,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>> {
+        list_helper(ctx, container.to_owned(), source_args).boxed()
+    }
 }
 
 /// Given a `TemporaryStorage`, extract a unique `s3://` temporary directory,
@@ -155,6 +196,8 @@ impl PathLikeLocator for S3Locator {
 
 #[cfg(test)]
 mod tests {
+    use crate::data_streams::DataFormat;
+
     use super::*;
 
     #[test]
@@ -174,14 +217,17 @@ mod tests {
         assert!(locator.data_format().is_none());
     }
 
-    // Actually not yet supported!
-    //
-    // #[test]
-    // fn test_file_locator_has_correct_path_like_properties() {
-    //     let locator = S3Locator::from_str("s3://bucket/path/file.csv").unwrap();
-    //     assert_eq!(locator.path().unwrap(), "/path/file.csv");
-    //     assert!(!locator.is_directory_like());
-    //     assert_eq!(locator.extension().unwrap(), "csv");
-    //     assert_eq!(locator.data_format(), Some(DataFormat::Csv));
-    // }
+    #[test]
+    fn test_file_locator_has_correct_path_like_properties() {
+        let locator = S3Locator::from_str("s3://bucket/path/file.csv").unwrap();
+        assert_eq!(locator.path().unwrap(), "/path/file.csv");
+        assert!(!locator.is_directory_like());
+        assert_eq!(locator.extension().unwrap(), "csv");
+        assert_eq!(locator.data_format(), Some(DataFormat::Csv));
+    }
+
+    #[test]
+    fn test_other_file_locators_are_rejected() {
+        assert!(S3Locator::from_str("s3://bucket/path/_SUCCESS").is_err());
+    }
 }
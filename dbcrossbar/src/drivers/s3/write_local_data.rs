@@ -3,6 +3,8 @@
 use super::{prepare_as_destination_helper, S3Locator};
 use crate::clouds::aws::s3;
 use crate::common::*;
+use crate::partition::partition_csv_streams;
+use crate::wildcard::PathGlob;
 
 /// Implementation of `write_local_data`, but as a real `async` function.
 #[instrument(
@@ -12,13 +14,22 @@ use crate::common::*;
     fields(url = %url)
 )]
 pub(crate) async fn write_local_data_helper(
+    ctx: Context,
     url: Url,
     data: BoxStream<CsvStream>,
     shared_args: SharedArguments<Unverified>,
     dest_args: DestinationArguments<Unverified>,
 ) -> Result<BoxStream<BoxFuture<BoxLocator>>> {
-    let _shared_args = shared_args.verify(S3Locator::features())?;
+    let shared_args = shared_args.verify(S3Locator::features())?;
     let dest_args = dest_args.verify(S3Locator::features())?;
+    if PathGlob::parse(url.as_str()).is_some() {
+        return Err(format_err!("cannot write to glob {}", url));
+    } else if !url.path().ends_with('/') {
+        return Err(format_err!(
+            "can only write to s3:// directories, not {}",
+            url
+        ));
+    }
 
     // Look up our arguments.
     let if_exists = dest_args.if_exists().to_owned();
@@ -26,6 +37,17 @@ pub(crate) async fn write_local_data_helper(
     // Delete the existing output, if it exists.
     prepare_as_destination_helper(url.clone(), if_exists).await?;
 
+    // Split our data into partition directories if requested.
+    let data = match dest_args.partition_by() {
+        Some(partition_by) => partition_csv_streams(
+            ctx,
+            partition_by.to_owned(),
+            shared_args.max_streams(),
+            data,
+        )?,
+        None => data,
+    };
+
     // Spawn our uploader threads.
     let written = data.map_ok(move |stream| {
         let url = url.clone();
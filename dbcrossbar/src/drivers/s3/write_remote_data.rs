@@ -10,6 +10,7 @@ use crate::drivers::{
     postgres_shared::{connect, pg_quote, CheckCatalog, PgSchema},
     redshift::{RedshiftDriverArguments, RedshiftLocator},
 };
+use crate::filter::SqlDialect;
 
 /// Copy `source` to `dest`.
 ///
@@ -98,7 +99,11 @@ async fn write_redshift_remote_data_helper(
 
     // Generate SQL for query.
     let mut sql_bytes: Vec<u8> = vec![];
-    pg_schema.write_export_select_sql(&mut sql_bytes, &source_args)?;
+    pg_schema.write_export_select_sql(
+        &mut sql_bytes,
+        &source_args,
+        SqlDialect::RedShift,
+    )?;
     let select_sql = String::from_utf8(sql_bytes).expect("should always be UTF-8");
     debug!("export SQL: {}", select_sql);
 
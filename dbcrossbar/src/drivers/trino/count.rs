@@ -17,13 +17,26 @@ pub(crate) async fn count_helper(
     let sql = format!(
         "SELECT COUNT(*) AS \"count\"\nFROM {}{}",
         locator.table_name()?,
-        if let Some(where_clause) = source_args.where_clause() {
-            format!("\nWHERE ({})", where_clause)
-        } else {
-            "".to_string()
-        }
+        where_sql(&source_args)?,
     );
     debug!(sql = %sql, "counting rows");
     let count = client.get_one_value::<i64>(&sql).await?;
     usize::try_from(count).context("could not convert count to usize")
 }
+
+/// Build a `WHERE` clause for `--where` and `--filter`, or an empty string if
+/// we don't need one.
+pub(super) fn where_sql(source_args: &SourceArguments<Verified>) -> Result<String> {
+    let mut conditions = vec![];
+    if let Some(where_clause) = source_args.where_clause() {
+        conditions.push(format!("({})", where_clause));
+    }
+    if let Some(filter) = source_args.filter() {
+        conditions.push(format!("({})", filter.to_trino_expr()?));
+    }
+    Ok(if conditions.is_empty() {
+        "".to_string()
+    } else {
+        format!("\nWHERE {}", conditions.join(" AND "))
+    })
+}
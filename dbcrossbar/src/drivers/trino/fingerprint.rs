@@ -0,0 +1,91 @@
+//! Implementation of `fingerprint`, but as a real `async` function.
+
+use super::{count::where_sql, TrinoLocator};
+use crate::common::*;
+use crate::drivers::trino_shared::{TrinoIdent, TrinoTableName};
+use crate::fingerprint::{aggregates, Aggregate, Fingerprint};
+use crate::schema::DataType;
+
+/// Implementation of `fingerprint`, but as a real `async` function.
+#[instrument(
+    level = "trace",
+    name = "trino::fingerprint",
+    skip(shared_args, source_args)
+)]
+pub(crate) async fn fingerprint_helper(
+    locator: TrinoLocator,
+    shared_args: SharedArguments<Unverified>,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Fingerprint> {
+    let shared_args = shared_args.verify(TrinoLocator::features())?;
+    let source_args = source_args.verify(TrinoLocator::features())?;
+
+    let client = locator.client()?;
+    let schema = shared_args.schema();
+    let sql = fingerprint_sql(&locator.table_name()?, schema, &source_args)?;
+    debug!(sql = %sql, "fingerprinting rows");
+    let values = client
+        .get_one_row::<Vec<String>>(&sql)
+        .await?
+        .into_iter()
+        .map(|value| Some(value).filter(|v| !v.is_empty()))
+        .collect();
+    Fingerprint::from_values(schema, values)
+}
+
+/// Build a query which computes each value of a [`Fingerprint`] as a
+/// `VARCHAR`. Our client can't return `NULL` values here, so we return empty
+/// strings instead.
+fn fingerprint_sql(
+    table_name: &TrinoTableName,
+    schema: &Schema,
+    source_args: &SourceArguments<Verified>,
+) -> Result<String> {
+    let mut exprs = vec!["CAST(COUNT(*) AS VARCHAR)".to_owned()];
+    for (col, aggregate) in aggregates(schema) {
+        let name = TrinoIdent::new(&col.name)?;
+        exprs.push(match aggregate {
+            Aggregate::NullCount => {
+                format!("CAST(count_if({} IS NULL) AS VARCHAR)", name)
+            }
+            // Integer sums would overflow `BIGINT`, so we use `DECIMAL`.
+            // `DECIMAL` sums are already `DECIMAL(38, s)`.
+            Aggregate::Sum if matches!(col.data_type, DataType::Decimal) => {
+                format!("COALESCE(CAST(SUM({}) AS VARCHAR), '')", name)
+            }
+            Aggregate::Sum => format!(
+                "COALESCE(CAST(SUM(CAST({} AS DECIMAL(38, 0))) AS VARCHAR), '')",
+                name,
+            ),
+        });
+    }
+    Ok(format!(
+        "SELECT {}\nFROM {}{}",
+        exprs.join(", "),
+        table_name,
+        where_sql(source_args)?,
+    ))
+}
+
+#[test]
+fn fingerprint_sql_with_filter() {
+    use crate::filter::Filter;
+
+    let mut schema = crate::filter::test_schema();
+    schema
+        .table
+        .columns
+        .retain(|c| ["id", "name", "tags", "price"].contains(&c.name.as_str()));
+    let filter = Filter::new("id > 1", &schema).unwrap();
+    let source_args = SourceArguments::for_temporary()
+        .with_filter(Some(filter))
+        .verify(TrinoLocator::features())
+        .unwrap();
+    let table_name = TrinoTableName::new("t").unwrap();
+    assert_eq!(
+        fingerprint_sql(&table_name, &schema, &source_args).unwrap(),
+        r#"SELECT CAST(COUNT(*) AS VARCHAR), CAST(count_if("id" IS NULL) AS VARCHAR), COALESCE(CAST(SUM(CAST("id" AS DECIMAL(38, 0))) AS VARCHAR), ''), CAST(count_if("name" IS NULL) AS VARCHAR), CAST(count_if("price" IS NULL) AS VARCHAR), COALESCE(CAST(SUM("price") AS VARCHAR), '')
+FROM "t"
+WHERE ("id" > 1)"#,
+    );
+}
@@ -0,0 +1,79 @@
+//! Implementation of `list`, but as a real `async` function.
+
+use super::TrinoLocator;
+use crate::common::*;
+use crate::drivers::trino_shared::{TrinoIdent, TrinoStringLiteral};
+
+/// Implementation of `list`, but as a real `async` function.
+#[instrument(level = "trace", name = "trino::list", skip(source_args))]
+pub(crate) async fn list_helper(
+    container: String,
+    source_args: SourceArguments<Unverified>,
+) -> Result<Vec<ListEntry>> {
+    let _source_args = source_args.verify(TrinoLocator::features())?;
+
+    if !container.starts_with(TrinoLocator::scheme()) {
+        // Be careful not to leak the password in this error message.
+        return Err(format_err!("expected a trino: locator"));
+    }
+    let url = container.parse::<UrlWithHiddenPassword>()?;
+    if url.as_url().fragment().is_some() {
+        return Err(format_err!(
+            "expected trino://host:port/catalog/schema without #table_name: {}",
+            url,
+        ));
+    }
+    let path = url
+        .as_url()
+        .path_segments()
+        .map(|segments| segments.collect::<Vec<_>>())
+        .unwrap_or_default();
+    if path.len() != 2 {
+        return Err(format_err!("expected /catalog/schema in {}", url));
+    }
+    let catalog = TrinoIdent::new(path[0])?;
+    let schema = path[1].to_owned();
+
+    // Build a locator without a table name, so we can get a client.
+    let container = TrinoLocator {
+        url,
+        table_name: String::new(),
+    };
+    let client = container.client()?;
+    let sql = format!(
+        "\
+SELECT table_name, table_type
+FROM {catalog}.information_schema.tables
+WHERE table_schema = {schema}
+ORDER BY table_name",
+        catalog = catalog,
+        schema = TrinoStringLiteral(&schema),
+    );
+    debug!(%sql, "listing tables");
+    let rows = client.get_all::<Vec<String>>(&sql).await?;
+    rows.into_iter()
+        .map(|row| {
+            let (table_name, table_type) = match row.as_slice() {
+                [table_name, table_type] => (table_name.to_owned(), table_type),
+                _ => return Err(format_err!("expected 2 columns, found {:?}", row)),
+            };
+            let kind = if table_type == "VIEW" {
+                ListEntryKind::View
+            } else {
+                ListEntryKind::Table
+            };
+            let locator = TrinoLocator {
+                url: container.url.clone(),
+                table_name: table_name.clone(),
+            };
+            Ok(ListEntry {
+                name: table_name,
+                locator: locator.to_string(),
+                kind,
+                rows: None,
+                bytes: None,
+                modified: None,
+            })
+        })
+        .collect()
+}
@@ -4,9 +4,13 @@ use std::{fmt, str::FromStr};
 
 use dbcrossbar_trino::client::{Client, ClientBuilder};
 
-use crate::{common::*, drivers::trino_shared::TrinoStringLiteral};
+use crate::{
+    common::*, drivers::trino_shared::TrinoStringLiteral, fingerprint::Fingerprint,
+};
 
 use self::count::count_helper;
+use self::fingerprint::fingerprint_helper;
+use self::list::list_helper;
 use self::local_data::local_data_helper;
 use self::schema::schema_helper;
 use self::write_local_data::write_local_data_helper;
@@ -15,10 +19,12 @@ use self::write_schema::write_schema_helper;
 
 use super::{
     s3::S3Locator,
-    trino_shared::{TrinoConnectorType, TrinoTableName},
+    trino_shared::{TrinoConnectorType, TrinoCreateTable, TrinoTableName},
 };
 
 mod count;
+mod fingerprint;
+mod list;
 mod local_data;
 mod schema;
 mod write_local_data;
@@ -152,6 +158,23 @@ impl Locator for TrinoLocator {
         write_schema_helper(self.to_owned(), schema, if_exists, dest_args).boxed()
     }
 
+    fn migration_sql(
+        &self,
+        old_schema: &Schema,
+        new_schema: &Schema,
+    ) -> Result<String> {
+        let table_name = self.table_name()?;
+        let old_table =
+            TrinoCreateTable::from_schema_and_name(old_schema, &table_name)?;
+        let new_table =
+            TrinoCreateTable::from_schema_and_name(new_schema, &table_name)?;
+        Ok(new_table
+            .alter_table_statements(&old_table)?
+            .into_iter()
+            .map(|stmt| format!("{};\n", stmt))
+            .collect())
+    }
+
     fn count(
         &self,
         _ctx: Context,
@@ -161,6 +184,15 @@ impl Locator for TrinoLocator {
         count_helper(self.to_owned(), shared_args, source_args).boxed()
     }
 
+    fn fingerprint(
+        &self,
+        _ctx: Context,
+        shared_args: SharedArguments<Unverified>,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Fingerprint> {
+        fingerprint_helper(self.to_owned(), shared_args, source_args).boxed()
+    }
+
     fn local_data(
         &self,
         ctx: Context,
@@ -182,7 +214,10 @@ impl Locator for TrinoLocator {
     }
 
     fn supports_write_remote_data(&self, source: &dyn Locator) -> bool {
-        source.as_any().is::<S3Locator>()
+        source
+            .as_any()
+            .downcast_ref::<S3Locator>()
+            .is_some_and(|s3| s3.is_directory())
     }
 
     fn write_remote_data(
@@ -219,11 +254,19 @@ impl LocatorStatic for TrinoLocator {
                 | LocatorFeatures::WriteSchema
                 | LocatorFeatures::LocalData
                 | LocatorFeatures::WriteLocalData
-                | LocatorFeatures::Count,
+                | LocatorFeatures::Count
+                | LocatorFeatures::Fingerprint
+                | LocatorFeatures::MigrationSql
+                | LocatorFeatures::List,
             write_schema_if_exists: if_exists,
             source_args: SourceArgumentsFeatures::DriverArgs
-                | SourceArgumentsFeatures::WhereClause,
-            dest_args: DestinationArgumentsFeatures::DriverArgs.into(),
+                | SourceArgumentsFeatures::WhereClause
+                | SourceArgumentsFeatures::Filter
+                | SourceArgumentsFeatures::Dedupe
+                | SourceArgumentsFeatures::Limit
+                | SourceArgumentsFeatures::Sample,
+            dest_args: DestinationArgumentsFeatures::DriverArgs
+                | DestinationArgumentsFeatures::SchemaEvolution,
             dest_if_exists: if_exists,
             _placeholder: (),
         }
@@ -233,4 +276,12 @@ impl LocatorStatic for TrinoLocator {
     fn is_unstable() -> bool {
         true
     }
+
+    fn list(
+        _ctx: Context,
+        container: &str,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>> {
+        list_helper(container.to_owned(), source_args).boxed()
+    }
 }
@@ -1,6 +1,6 @@
 //! Extract a table declaration from Trino.
 
-use dbcrossbar_trino::TrinoRow;
+use dbcrossbar_trino::{client::Client, TrinoRow};
 
 use crate::{
     common::*,
@@ -20,6 +20,17 @@ pub(crate) async fn schema_helper(
     let _source_args = source_args.verify(TrinoLocator::features())?;
 
     let client = src.client()?;
+    let schema = schema_if_exists(&client, &src)
+        .await?
+        .ok_or_else(|| format_err!("could not find table {}", src))?;
+    Ok(Some(schema))
+}
+
+/// Look up the schema of `src`, returning `None` if the table does not exist.
+pub(super) async fn schema_if_exists(
+    client: &Client,
+    src: &TrinoLocator,
+) -> Result<Option<Schema>> {
     let table_name = src.table_name()?;
     let catalog = table_name
         .catalog()
@@ -43,6 +54,10 @@ SELECT column_name, is_nullable, data_type
     debug!(%sql, "getting table schema");
     let column_infos = client.get_all::<ColumnInfo>(&sql).await?;
     trace!(columns = ?column_infos, "got columns");
+    if column_infos.is_empty() {
+        // `information_schema.columns` has no rows for missing tables.
+        return Ok(None);
+    }
     let trino_columns = column_infos
         .into_iter()
         .map(|column_info| column_info.to_trino_column())
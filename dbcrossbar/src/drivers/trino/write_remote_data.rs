@@ -1,15 +1,18 @@
 //! Write data from a remote storage location into Trino without passing it
 //! through `dbcrossbar`.
 
+use dbcrossbar_trino::client::Client;
+
 use crate::{
     common::*,
     drivers::{
         s3::S3Locator,
         trino_shared::{TrinoCreateTable, TrinoDriverArguments, PRETTY_WIDTH},
     },
+    schema_evolution::SchemaEvolution,
 };
 
-use super::TrinoLocator;
+use super::{schema::schema_if_exists, TrinoLocator};
 
 /// Implementation of `write_remote_data` for Trino.
 #[instrument(
@@ -51,6 +54,9 @@ pub(super) async fn write_remote_data_helper(
     let client = dest.client()?;
     let connector_type = dest.connector_type(&client).await?;
     let table_name = dest.table_name()?;
+    if dest_args.allow_schema_evolution() {
+        evolve_table_schema(&client, &dest, schema).await?;
+    }
     let mut create_ideal_table =
         TrinoCreateTable::from_schema_and_name(schema, &table_name)?;
     create_ideal_table.set_if_exists_options(&if_exists, &connector_type);
@@ -117,3 +123,29 @@ pub(super) async fn write_remote_data_helper(
 
     Ok(vec![dest.boxed()])
 }
+
+/// Add any missing columns to `dest`, and widen any column types that need it,
+/// so that it can hold data matching `schema`. This implements
+/// `--allow-schema-evolution`. If `dest` doesn't exist yet, we do nothing.
+#[instrument(level = "trace", skip_all, fields(dest = %dest))]
+async fn evolve_table_schema(
+    client: &Client,
+    dest: &TrinoLocator,
+    schema: &Schema,
+) -> Result<()> {
+    let existing = match schema_if_exists(client, dest).await? {
+        Some(existing) => existing,
+        None => return Ok(()),
+    };
+    let evolution = SchemaEvolution::new(&existing, schema)?;
+    let table_name = dest.table_name()?;
+    let old_table =
+        TrinoCreateTable::from_schema_and_name(&evolution.before, &table_name)?;
+    let new_table =
+        TrinoCreateTable::from_schema_and_name(&evolution.after, &table_name)?;
+    for alter_sql in new_table.alter_table_statements(&old_table)? {
+        debug!(sql = %alter_sql, "evolving destination table schema");
+        client.run_statement(&alter_sql).await?;
+    }
+    Ok(())
+}
@@ -12,13 +12,14 @@ use proptest_derive::Arbitrary;
 
 use crate::{
     common::*,
+    dedupe::Keep,
     parse_error::{Annotation, FileInfo, ParseError},
     schema::Column,
 };
 
 use super::{
     data_type::TrinoDataTypeExt as _,
-    pretty::{select_from, sql_clause, WIDTH},
+    pretty::{select_from, select_from_doc, sql_clause, WIDTH},
     TrinoConnectorType, TrinoDataType, TrinoField, TrinoIdent, TrinoTableName,
 };
 
@@ -218,6 +219,86 @@ impl TrinoCreateTable {
         self.columns.iter().map(|column| &column.name)
     }
 
+    /// Generate `ALTER TABLE` statements which will migrate `old_table` so that
+    /// it matches this table. We match columns by name, and we refuse to add
+    /// `NOT NULL` columns or constraints, or to change column types in ways
+    /// which might lose data.
+    ///
+    /// We return a list of separate statements, without semicolons, because
+    /// Trino can only run one statement at a time.
+    pub fn alter_table_statements(
+        &self,
+        old_table: &TrinoCreateTable,
+    ) -> Result<Vec<String>> {
+        let new_columns = self
+            .columns
+            .iter()
+            .map(|c| (&c.name, c))
+            .collect::<HashMap<_, _>>();
+        let old_columns = old_table
+            .columns
+            .iter()
+            .map(|c| (&c.name, c))
+            .collect::<HashMap<_, _>>();
+
+        let mut statements = vec![];
+        for old_col in &old_table.columns {
+            if !new_columns.contains_key(&old_col.name) {
+                statements.push(format!(
+                    "ALTER TABLE {} DROP COLUMN {}",
+                    self.name, old_col.name,
+                ));
+            }
+        }
+        for new_col in &self.columns {
+            let old_col = match old_columns.get(&new_col.name) {
+                Some(old_col) => old_col,
+                None if !new_col.is_nullable => {
+                    return Err(format_err!(
+                        "Trino cannot add NOT NULL column {} to an existing table",
+                        new_col.name,
+                    ));
+                }
+                None => {
+                    statements.push(format!(
+                        "ALTER TABLE {} ADD COLUMN {}",
+                        self.name,
+                        new_col.to_doc().pretty(WIDTH),
+                    ));
+                    continue;
+                }
+            };
+            if old_col.data_type != new_col.data_type {
+                if !is_widening(&old_col.data_type, &new_col.data_type) {
+                    return Err(format_err!(
+                        "Trino cannot safely change column {} from {} to {}",
+                        new_col.name,
+                        old_col.data_type,
+                        new_col.data_type,
+                    ));
+                }
+                statements.push(format!(
+                    "ALTER TABLE {} ALTER COLUMN {} SET DATA TYPE {}",
+                    self.name, new_col.name, new_col.data_type,
+                ));
+            }
+            match (old_col.is_nullable, new_col.is_nullable) {
+                (false, true) => statements.push(format!(
+                    "ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL",
+                    self.name, new_col.name,
+                )),
+                (true, false) => {
+                    return Err(format_err!(
+                        "Trino cannot add NOT NULL to existing column {}",
+                        new_col.name,
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(statements)
+    }
+
     /// Generate a `SELECT` expression that will fetch data for this table from
     /// a wrapper table, and convert it the appropriate data types.
     fn select_from_wrapper_table_doc(
@@ -449,26 +530,89 @@ impl TrinoCreateTable {
         connector_type: &TrinoConnectorType,
         source_args: &SourceArguments<Verified>,
     ) -> Result<RcDoc<'static, ()>> {
-        Ok(select_from(
-            self.columns
-                .iter()
-                .map(|column| {
-                    Ok(RcDoc::concat(vec![
-                        column.export_expr(connector_type)?.to_doc(),
-                        RcDoc::text(" AS "),
-                        RcDoc::as_string(&column.name),
-                    ]))
-                })
-                .collect::<Result<Vec<_>>>()?,
-            &self.name,
-        )
-        .append(if let Some(where_clause) = source_args.where_clause() {
-            sql_clause(RcDoc::concat(vec![
-                RcDoc::text("WHERE"),
-                RcDoc::space(),
+        let select_exprs = self
+            .columns
+            .iter()
+            .map(|column| {
+                Ok(RcDoc::concat(vec![
+                    column.export_expr(connector_type)?.to_doc(),
+                    RcDoc::text(" AS "),
+                    RcDoc::as_string(&column.name),
+                ]))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let where_doc = {
+            let mut conditions = vec![];
+            if let Some(where_clause) = source_args.where_clause() {
                 // The `--where` clause is always raw SQL.
-                parens(RcDoc::text(where_clause.to_owned())),
-            ]))
+                conditions.push(parens(RcDoc::text(where_clause.to_owned())));
+            }
+            if let Some(filter) = source_args.filter() {
+                conditions.push(parens(filter.to_trino_expr()?.to_doc()));
+            }
+            if conditions.is_empty() {
+                RcDoc::nil()
+            } else {
+                sql_clause(RcDoc::concat(vec![
+                    RcDoc::text("WHERE"),
+                    RcDoc::space(),
+                    RcDoc::intersperse(conditions, RcDoc::text(" AND ")),
+                ]))
+            }
+        };
+        let select_doc = match source_args.dedupe() {
+            Some(dedupe) => {
+                // `TABLESAMPLE` would run before we deduplicate, so `cp` never
+                // asks us to do both.
+                if source_args.sample().is_some() {
+                    return Err(format_err!("cannot use --sample with --dedupe-on"));
+                }
+                // Trino has no `DISTINCT ON` or `QUALIFY`, so we number the
+                // rows for each key in a subquery, and keep the first one.
+                let keys = dedupe
+                    .keys()
+                    .iter()
+                    .map(|key| Ok(RcDoc::as_string(TrinoIdent::new(key)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                let mut window = RcDoc::text("PARTITION BY ")
+                    .append(RcDoc::intersperse(keys, RcDoc::text(", ")));
+                if let Keep::LatestBy(column) = dedupe.keep() {
+                    window = window.append(RcDoc::text(format!(
+                        " ORDER BY {} DESC NULLS LAST",
+                        TrinoIdent::new(column)?,
+                    )));
+                }
+                let row_number = RcDoc::concat(vec![
+                    RcDoc::text("ROW_NUMBER() OVER "),
+                    parens(window),
+                    RcDoc::text(" AS \"dbcrossbar_row_number\""),
+                ]);
+                let subquery =
+                    select_from(vec![RcDoc::text("*"), row_number], &self.name)
+                        .append(where_doc);
+                select_from_doc(
+                    select_exprs,
+                    parens(subquery).append(RcDoc::text(" AS \"deduped\"")),
+                )
+                .append(sql_clause(RcDoc::text(
+                    "WHERE \"dbcrossbar_row_number\" = 1",
+                )))
+            }
+            None => {
+                // Trino's `TABLESAMPLE` can't be seeded, so `cp` samples
+                // locally when it has a seed.
+                let mut from = RcDoc::as_string(&self.name);
+                if let Some(sample) = source_args.sample() {
+                    from = from.append(RcDoc::text(format!(
+                        " TABLESAMPLE BERNOULLI ({})",
+                        sample.percent(),
+                    )));
+                }
+                select_from_doc(select_exprs, from).append(where_doc)
+            }
+        };
+        Ok(select_doc.append(if let Some(limit) = source_args.limit() {
+            sql_clause(RcDoc::text(format!("LIMIT {}", limit)))
         } else {
             RcDoc::nil()
         }))
@@ -492,6 +636,37 @@ impl TrinoCreateTable {
     }
 }
 
+/// Can we change a Trino column from `old` to `new` without losing data?
+fn is_widening(old: &TrinoDataType, new: &TrinoDataType) -> bool {
+    use TrinoDataType as T;
+    match (old, new) {
+        (T::TinyInt, T::SmallInt | T::Int | T::BigInt)
+        | (T::SmallInt, T::Int | T::BigInt)
+        | (T::Int, T::BigInt)
+        | (T::Real, T::Double) => true,
+        (T::Varchar { length: Some(_) }, T::Varchar { length: None }) => true,
+        (T::Varchar { length: Some(old) }, T::Varchar { length: Some(new) }) => {
+            new >= old
+        }
+        (
+            T::Decimal {
+                precision: old_precision,
+                scale: old_scale,
+            },
+            T::Decimal {
+                precision: new_precision,
+                scale: new_scale,
+            },
+        ) => {
+            // We need room for at least as many digits on both sides of the
+            // decimal point.
+            new_scale >= old_scale
+                && new_precision - new_scale >= old_precision - old_scale
+        }
+        _ => old == new,
+    }
+}
+
 impl fmt::Display for TrinoCreateTable {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // NOTE: We don't include `self.separate_drop_if_exists` because it's
@@ -1027,6 +1202,107 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_select_with_dedupe() {
+        let table = TrinoCreateTable::parse(
+            "test_select_with_dedupe",
+            "CREATE TABLE t (id INT, updated_at DATE);",
+        )
+        .unwrap();
+        let schema = table.to_schema().unwrap();
+        let dedupe =
+            crate::dedupe::Dedupe::new("id", Some("latest-by=updated_at"), &schema)
+                .unwrap();
+        let source_args = SourceArguments::for_temporary()
+            .with_dedupe(Some(dedupe))
+            .with_limit(Some(10))
+            .verify(crate::drivers::trino::TrinoLocator::features())
+            .unwrap();
+        let sql = table
+            .select_as_named_varchar_values_doc(
+                &TrinoConnectorType::Memory,
+                &source_args,
+            )
+            .unwrap()
+            .pretty(WIDTH)
+            .to_string();
+        let sql = normalize_whitespace(&sql);
+        assert!(
+            sql.contains(r#"FROM (SELECT *, ROW_NUMBER() OVER (PARTITION BY "id" ORDER BY "updated_at" DESC NULLS LAST) AS "dbcrossbar_row_number" FROM "t") AS "deduped" WHERE "dbcrossbar_row_number" = 1 LIMIT 10"#),
+            "unexpected SQL: {}",
+            sql,
+        );
+    }
+
+    #[test]
+    fn test_select_with_sample() {
+        let table = TrinoCreateTable::parse(
+            "test_select_with_sample",
+            "CREATE TABLE t (id INT);",
+        )
+        .unwrap();
+        let sample = crate::sampling::Sample::new(0.25, None).unwrap();
+        let source_args = SourceArguments::for_temporary()
+            .with_sample(Some(sample))
+            .verify(crate::drivers::trino::TrinoLocator::features())
+            .unwrap();
+        let sql = table
+            .select_as_named_varchar_values_doc(
+                &TrinoConnectorType::Memory,
+                &source_args,
+            )
+            .unwrap()
+            .pretty(WIDTH)
+            .to_string();
+        assert_eq!(
+            normalize_whitespace(&sql),
+            r#"SELECT CAST("id" AS VARCHAR) AS "id" FROM "t" TABLESAMPLE BERNOULLI (25)"#,
+        );
+    }
+
+    #[test]
+    fn test_alter_table_statements() {
+        let old_table = TrinoCreateTable::parse(
+            "old_table",
+            "CREATE TABLE foo (id INT NOT NULL, name VARCHAR, legacy VARCHAR);",
+        )
+        .unwrap();
+        let new_table = TrinoCreateTable::parse(
+            "new_table",
+            "CREATE TABLE foo (id BIGINT NOT NULL, name VARCHAR, age INT);",
+        )
+        .unwrap();
+        assert_eq!(
+            new_table.alter_table_statements(&old_table).unwrap(),
+            vec![
+                r#"ALTER TABLE "foo" DROP COLUMN "legacy""#,
+                r#"ALTER TABLE "foo" ALTER COLUMN "id" SET DATA TYPE BIGINT"#,
+                r#"ALTER TABLE "foo" ADD COLUMN "age" INT"#,
+            ],
+        );
+        // Narrowing `BIGINT` to `INT` might lose data.
+        assert!(old_table.alter_table_statements(&new_table).is_err());
+
+        // Widening `VARCHAR(10)` to `VARCHAR` is fine.
+        let short_table = TrinoCreateTable::parse(
+            "short_table",
+            "CREATE TABLE foo (name VARCHAR(10));",
+        )
+        .unwrap();
+        let long_table =
+            TrinoCreateTable::parse("long_table", "CREATE TABLE foo (name VARCHAR);")
+                .unwrap();
+        assert_eq!(
+            long_table.alter_table_statements(&short_table).unwrap(),
+            vec![r#"ALTER TABLE "foo" ALTER COLUMN "name" SET DATA TYPE VARCHAR"#],
+        );
+        assert!(short_table.alter_table_statements(&long_table).is_err());
+        assert!(new_table
+            .alter_table_statements(&new_table)
+            .unwrap()
+            .is_empty());
+    }
+
     // A few odd tables, mostly found by proptest, that should parse. We put
     // these into a separate test to prevent future regressions, and to
     // pretty-print any parse errors they produce.
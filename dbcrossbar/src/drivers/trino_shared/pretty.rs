@@ -36,15 +36,21 @@ pub(super) fn sql_clause(doc: RcDoc<'static, ()>) -> RcDoc<'static, ()> {
 pub(super) fn select_from(
     select_exprs: impl IntoIterator<Item = RcDoc<'static, ()>>,
     from_table: &TrinoTableName,
+) -> RcDoc<'static, ()> {
+    select_from_doc(select_exprs, RcDoc::as_string(from_table))
+}
+
+/// A `SELECT ... FROM ...` clause, where `from` may be a subquery or a table
+/// with a `TABLESAMPLE` clause.
+pub(super) fn select_from_doc(
+    select_exprs: impl IntoIterator<Item = RcDoc<'static, ()>>,
+    from: RcDoc<'static, ()>,
 ) -> RcDoc<'static, ()> {
     RcDoc::concat(vec![
         sql_clause(RcDoc::concat(vec![
             RcDoc::text("SELECT"),
             indent(comma_sep_list(select_exprs.into_iter())),
         ])),
-        sql_clause(RcDoc::concat(vec![
-            RcDoc::text("FROM"),
-            indent(RcDoc::as_string(from_table)),
-        ])),
+        sql_clause(RcDoc::concat(vec![RcDoc::text("FROM"), indent(from)])),
     ])
 }
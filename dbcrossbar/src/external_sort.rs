@@ -0,0 +1,161 @@
+//! Sorting CSV rows which may not fit in memory.
+
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{BufReader, BufWriter, Seek, SeekFrom},
+    mem,
+};
+
+use crate::common::*;
+
+/// How many bytes of CSV rows should we sort in memory before we write them to
+/// a temporary file?
+pub(crate) const MAX_RUN_BYTES: usize = 64 * 1024 * 1024;
+
+/// How to sort rows, and which rows to drop as duplicates.
+pub(crate) trait RowOrder {
+    /// Compare two rows. Rows which compare as equal keep their input order.
+    fn compare(&self, a: &csv::StringRecord, b: &csv::StringRecord) -> Ordering;
+
+    /// Should we drop `b`, which sorts immediately after `a`?
+    fn is_duplicate(&self, _a: &csv::StringRecord, _b: &csv::StringRecord) -> bool {
+        false
+    }
+}
+
+/// Sort `rows` using `order`, and write them to `wtr`.
+///
+/// We do this using an external merge sort: We sort up to `max_run_bytes` of
+/// rows in memory, drop any duplicates, and write them to a temporary file.
+/// Then we merge our sorted files. This allows us to handle inputs which are
+/// much larger than RAM.
+pub(crate) fn external_sort(
+    rows: impl Iterator<Item = Result<csv::StringRecord>>,
+    order: &impl RowOrder,
+    max_run_bytes: usize,
+    wtr: &mut csv::Writer<impl Write>,
+) -> Result<()> {
+    // Read sorted runs of rows, writing them to disk when we have too many.
+    let mut runs = vec![];
+    let mut run = vec![];
+    let mut run_bytes = 0;
+    for row in rows {
+        let row = row?;
+        run_bytes += row.as_slice().len();
+        run.push(row);
+        if run_bytes >= max_run_bytes {
+            runs.push(write_run(order, mem::take(&mut run))?);
+            run_bytes = 0;
+        }
+    }
+
+    if runs.is_empty() {
+        // Everything fit in memory.
+        for row in sort_run(order, run) {
+            wtr.write_record(&row)?;
+        }
+    } else {
+        if !run.is_empty() {
+            runs.push(write_run(order, run)?);
+        }
+        debug!("merging {} sorted runs", runs.len());
+        merge_runs(order, runs, wtr)?;
+    }
+    Ok(())
+}
+
+/// Sort `rows` and drop any duplicates.
+fn sort_run(
+    order: &impl RowOrder,
+    mut rows: Vec<csv::StringRecord>,
+) -> Vec<csv::StringRecord> {
+    // This is a stable sort, so equal rows keep their input order.
+    rows.sort_by(|a, b| order.compare(a, b));
+    rows.dedup_by(|b, a| order.is_duplicate(a, b));
+    rows
+}
+
+/// Sort `rows`, and write them to a temporary file.
+fn write_run(order: &impl RowOrder, rows: Vec<csv::StringRecord>) -> Result<File> {
+    let file = tempfile::tempfile().context("cannot create temporary file")?;
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(BufWriter::new(file));
+    for row in sort_run(order, rows) {
+        wtr.write_record(&row)?;
+    }
+    let mut file = wtr
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .into_inner()
+        .map_err(|err| err.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// Merge sorted `runs`, dropping duplicates. When several runs contain equal
+/// rows, we prefer the earliest run, which keeps our sort stable.
+fn merge_runs(
+    order: &impl RowOrder,
+    runs: Vec<File>,
+    wtr: &mut csv::Writer<impl Write>,
+) -> Result<()> {
+    let mut readers = runs
+        .into_iter()
+        .map(|file| {
+            csv::ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(BufReader::new(file))
+        })
+        .collect::<Vec<_>>();
+    let mut heads = readers
+        .iter_mut()
+        .map(read_next)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut last_written: Option<csv::StringRecord> = None;
+    loop {
+        // Find the smallest row at the head of any run. We have relatively few
+        // runs, so a linear search is fine.
+        let mut best: Option<usize> = None;
+        for (i, head) in heads.iter().enumerate() {
+            if let Some(row) = head {
+                let is_better = match best {
+                    None => true,
+                    Some(b) => {
+                        let best_row = heads[b].as_ref().expect("should have row");
+                        order.compare(row, best_row) == Ordering::Less
+                    }
+                };
+                if is_better {
+                    best = Some(i);
+                }
+            }
+        }
+        let Some(best) = best else {
+            break;
+        };
+
+        let row = heads[best].take().expect("should have row");
+        heads[best] = read_next(&mut readers[best])?;
+        if !last_written
+            .as_ref()
+            .is_some_and(|last| order.is_duplicate(last, &row))
+        {
+            wtr.write_record(&row)?;
+            last_written = Some(row);
+        }
+    }
+    Ok(())
+}
+
+/// Read the next row from `rdr`, if there is one.
+fn read_next(rdr: &mut csv::Reader<impl Read>) -> Result<Option<csv::StringRecord>> {
+    let mut row = csv::StringRecord::new();
+    Ok(if rdr.read_record(&mut row)? {
+        Some(row)
+    } else {
+        None
+    })
+}
@@ -0,0 +1,177 @@
+//! Support for `--file-name-template`.
+
+use std::collections::HashSet;
+
+use futures::future;
+
+use crate::common::*;
+use crate::DataFormat;
+
+/// One piece of a parsed `FileNameTemplate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    /// Literal text.
+    Literal(String),
+    /// `{table}`: The name of the table we're copying.
+    Table,
+    /// `{stream}`: The name of the stream we're writing.
+    Stream,
+    /// `{n}` or `{n:0W}`: The 1-based number of the file, optionally padded
+    /// with zeros to `width` digits.
+    Number { width: usize },
+}
+
+/// A template used to name the files written to directory-like destinations.
+/// Example: `{table}-{stream}-{n:05}.csv`.
+#[derive(Clone, Debug)]
+pub(crate) struct FileNameTemplate {
+    parts: Vec<Part>,
+}
+
+impl FileNameTemplate {
+    /// Parse a template. Destinations add their own file extension, so we
+    /// drop any trailing extension which matches `format`.
+    pub(crate) fn new(template: &str, format: &DataFormat) -> Result<Self> {
+        let suffix = format!(".{}", format.extension());
+        let template = if template.to_ascii_lowercase().ends_with(&suffix) {
+            &template[..template.len() - suffix.len()]
+        } else {
+            template
+        };
+
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let (placeholder, rest) =
+                        chars.as_str().split_once('}').ok_or_else(|| {
+                            format_err!(
+                                "unclosed `{{` in --file-name-template {:?}",
+                                template
+                            )
+                        })?;
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(placeholder)?);
+                    chars = rest.chars();
+                }
+                '}' => {
+                    return Err(format_err!(
+                        "unmatched `}}` in --file-name-template {:?}",
+                        template
+                    ))
+                }
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        if parts.is_empty() {
+            return Err(format_err!("--file-name-template cannot be empty"));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Render the name of file number `n`.
+    fn render(&self, table: &str, stream: &str, n: usize) -> String {
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => name.push_str(text),
+                Part::Table => name.push_str(table),
+                Part::Stream => name.push_str(stream),
+                Part::Number { width } => {
+                    name.push_str(&format!("{:0width$}", n, width = width))
+                }
+            }
+        }
+        name
+    }
+
+    /// Rename each stream in `streams` using this template. Files are
+    /// numbered in the order that their streams arrive, starting from 1.
+    pub(crate) fn rename_csv_streams(
+        &self,
+        table: &str,
+        streams: BoxStream<CsvStream>,
+    ) -> BoxStream<CsvStream> {
+        let template = self.clone();
+        let table = table.to_owned();
+        let mut n = 0;
+        let mut seen = HashSet::new();
+        streams
+            .and_then(move |mut stream| {
+                n += 1;
+                let name = template.render(&table, &stream.name, n);
+                let result = if seen.insert(name.clone()) {
+                    trace!("renaming stream {} to {}", stream.name, name);
+                    stream.name = name;
+                    Ok(stream)
+                } else {
+                    Err(format_err!(
+                        "--file-name-template generated the name {:?} more than once",
+                        name
+                    ))
+                };
+                future::ready(result)
+            })
+            .boxed()
+    }
+}
+
+/// Parse the text between `{` and `}`.
+fn parse_placeholder(placeholder: &str) -> Result<Part> {
+    match placeholder {
+        "table" => Ok(Part::Table),
+        "stream" => Ok(Part::Stream),
+        "n" => Ok(Part::Number { width: 0 }),
+        _ => placeholder
+            .strip_prefix("n:0")
+            .and_then(|width| width.parse::<usize>().ok())
+            .map(|width| Part::Number { width })
+            .ok_or_else(|| {
+                format_err!(
+                    "unknown placeholder {{{}}} in --file-name-template",
+                    placeholder
+                )
+            }),
+    }
+}
+
+#[test]
+fn render_file_name_templates() {
+    let template =
+        FileNameTemplate::new("{table}-{stream}-{n:05}.csv", &DataFormat::Csv)
+            .unwrap();
+    assert_eq!(
+        template.render("orders", "chunk_0002", 2),
+        "orders-chunk_0002-00002"
+    );
+
+    let template = FileNameTemplate::new("{{{n}}}.CSV", &DataFormat::Csv).unwrap();
+    assert_eq!(template.render("t", "s", 12), "{12}");
+
+    let template =
+        FileNameTemplate::new("{table}.csv", &DataFormat::JsonLines).unwrap();
+    assert_eq!(template.render("t", "s", 1), "t.csv");
+
+    for bad in ["", ".csv", "{table", "table}", "{name}", "{n:5}", "{n:0x}"] {
+        assert!(
+            FileNameTemplate::new(bad, &DataFormat::Csv).is_err(),
+            "should reject {:?}",
+            bad
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Exact decimal numbers, so that we can compare `DECIMAL` values without
+//! rounding them to `f64`.
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use crate::common::*;
+
+/// An exact decimal number, with any number of digits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Decimal {
+    /// Is this number negative? Always `false` for zero.
+    negative: bool,
+    /// Our significant digits, with no leading or trailing zeros. Empty for
+    /// zero.
+    digits: Vec<u8>,
+    /// Our value is `0.{digits} * 10^exponent`.
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Compare the absolute values of two decimals.
+    fn cmp_magnitude(&self, other: &Decimal) -> Ordering {
+        match (self.digits.is_empty(), other.digits.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => self
+                .exponent
+                .cmp(&other.exponent)
+                .then_with(|| self.digits.cmp(&other.digits)),
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || format_err!("cannot parse {:?} as decimal", s);
+        let (negative, rest) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (mantissa, exp) = match rest.find(['e', 'E']) {
+            Some(i) => (&rest[..i], rest[i + 1..].parse::<i64>().map_err(|_| err())?),
+            None => (rest, 0),
+        };
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = int_part.bytes().chain(frac_part.bytes());
+        if mantissa == "." || !all_digits.clone().all(|b| b.is_ascii_digit()) {
+            return Err(err());
+        }
+        if int_part.is_empty() && frac_part.is_empty() {
+            return Err(err());
+        }
+
+        let leading_zeros = all_digits.clone().take_while(|&b| b == b'0').count();
+        let mut digits = all_digits.skip(leading_zeros).collect::<Vec<_>>();
+        while digits.last() == Some(&b'0') {
+            digits.pop();
+        }
+        if digits.is_empty() {
+            return Ok(Decimal {
+                negative: false,
+                digits,
+                exponent: 0,
+            });
+        }
+        let exponent = i64::try_from(int_part.len())
+            .ok()
+            .and_then(|len| len.checked_sub(i64::try_from(leading_zeros).ok()?))
+            .and_then(|e| e.checked_add(exp))
+            .ok_or_else(err)?;
+        Ok(Decimal {
+            negative,
+            digits,
+            exponent,
+        })
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.digits.is_empty() {
+            return write!(f, "0");
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let digits = String::from_utf8_lossy(&self.digits);
+        match usize::try_from(self.exponent) {
+            // 0.000ddd
+            Err(_) | Ok(0) => {
+                write!(f, "0.")?;
+                for _ in 0..self.exponent.unsigned_abs() {
+                    write!(f, "0")?;
+                }
+                write!(f, "{}", digits)
+            }
+            // dd.ddd
+            Ok(int_len) if int_len < digits.len() => {
+                write!(f, "{}.{}", &digits[..int_len], &digits[int_len..])
+            }
+            // ddd000
+            Ok(int_len) => {
+                write!(f, "{}", digits)?;
+                for _ in digits.len()..int_len {
+                    write!(f, "0")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[test]
+fn compare_decimals() {
+    let d = |s: &str| s.parse::<Decimal>().unwrap();
+    assert_eq!(d("1.50"), d("1.5"));
+    assert_eq!(d("0"), d("-0.000"));
+    assert_eq!(d("1.5e2"), d("150"));
+    assert_eq!(d(".5"), d("0.5"));
+    assert_eq!(d("+2."), d("2"));
+    // Too many digits to compare exactly as `f64`.
+    assert!(d("0.10000000000000000001") > d("0.1"));
+    assert!(d("12345678901234567890123") < d("12345678901234567890124"));
+    assert!(d("-1") < d("0"));
+    assert!(d("-2") < d("-1.5"));
+    assert!(d("0.05") < d("0.5"));
+    assert!(d("99") < d("100"));
+    assert_eq!(d("1.50").to_string(), "1.5");
+    assert_eq!(d("-0.00").to_string(), "0");
+    assert_eq!(d("-0.0012").to_string(), "-0.0012");
+    assert_eq!(d("0.5").to_string(), "0.5");
+    assert_eq!(d("1.5e3").to_string(), "1500");
+    assert_eq!(d("120").to_string(), "120");
+    for bad in ["", "-", ".", "1.2.3", "e5", "1e", "abc", "1_000"] {
+        assert!(bad.parse::<Decimal>().is_err(), "parsed {:?}", bad);
+    }
+}
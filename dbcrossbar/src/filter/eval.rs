@@ -0,0 +1,354 @@
+//! Evaluating filters locally, for sources which can't do it for us.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use futures::future;
+use uuid::Uuid;
+
+use super::{
+    decimal::Decimal, CompareOp, Expr, Filter, FilterType, Literal, NumberType,
+    Operand,
+};
+use crate::common::*;
+use crate::from_csv_cell::FromCsvCell;
+use crate::transform::spawn_sync_transform;
+
+/// Only keep the rows of `data` which match `filter`.
+pub(crate) fn filter_csv_streams(
+    ctx: Context,
+    filter: Filter,
+    data: BoxStream<CsvStream>,
+) -> BoxStream<CsvStream> {
+    data.and_then(move |stream| {
+        let filter = filter.clone();
+        let result = spawn_sync_transform(
+            ctx.clone(),
+            "filter_csv".to_owned(),
+            stream.data,
+            move |_ctx, rdr, wtr| filter.filter_csv(rdr, wtr),
+        )
+        .map(|data| CsvStream {
+            name: stream.name,
+            data,
+        });
+        future::ready(result)
+    })
+    .boxed()
+}
+
+/// A value we can compare. SQL `NULL` is represented by `None`, outside of
+/// this type.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub(crate) enum Scalar<'a> {
+    Bool(bool),
+    Int(i64),
+    Decimal(Decimal),
+    Float(f64),
+    Text(&'a str),
+    Uuid(Uuid),
+    Date(NaiveDate),
+    Timestamp(NaiveDateTime),
+    TimestampWithTimeZone(DateTime<FixedOffset>),
+}
+
+impl Filter {
+    /// Synchronously filter a single CSV stream.
+    fn filter_csv(&self, rdr: impl Read, wtr: impl Write) -> Result<()> {
+        let mut rdr = csv::Reader::from_reader(rdr);
+        let mut wtr = csv::Writer::from_writer(wtr);
+
+        // Look up the columns we need.
+        let headers = rdr.headers()?.clone();
+        let indices = self
+            .column_names()
+            .map(|name| {
+                let idx = headers.iter().position(|h| h == name).ok_or_else(|| {
+                    format_err!("cannot find column {:?} in CSV data", name)
+                })?;
+                Ok((name.to_owned(), idx))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        wtr.write_record(&headers)?;
+
+        let mut row = csv::StringRecord::new();
+        while rdr.read_record(&mut row)? {
+            if self.eval(&self.expr, &indices, &row)? == Some(true) {
+                wtr.write_record(&row)?;
+            }
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Evaluate this filter when we only know the values of some columns,
+    /// such as the partition columns of a Hive-style directory. Other columns
+    /// could contain anything, so this returns `None` unless `values` are
+    /// enough to decide. `NULL` values are represented by empty strings.
+    pub(crate) fn eval_partial(
+        &self,
+        values: &[(String, String)],
+    ) -> Result<Option<bool>> {
+        let row = values
+            .iter()
+            .map(|(_, value)| value.as_str())
+            .collect::<csv::StringRecord>();
+        let indices = values
+            .iter()
+            .enumerate()
+            .filter(|(_, (name, _))| self.columns.contains_key(name))
+            .map(|(i, (name, _))| (name.to_owned(), i))
+            .collect::<HashMap<_, _>>();
+        self.eval(&self.expr, &indices, &row)
+    }
+
+    /// Evaluate `expr` using SQL's three-valued logic, where `None` means
+    /// "unknown". Columns which aren't in `indices` are also unknown.
+    fn eval(
+        &self,
+        expr: &Expr,
+        indices: &HashMap<String, usize>,
+        row: &csv::StringRecord,
+    ) -> Result<Option<bool>> {
+        Ok(match expr {
+            Expr::And(lhs, rhs) => {
+                match (self.eval(lhs, indices, row)?, self.eval(rhs, indices, row)?) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                }
+            }
+            Expr::Or(lhs, rhs) => {
+                match (self.eval(lhs, indices, row)?, self.eval(rhs, indices, row)?) {
+                    (Some(true), _) | (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                }
+            }
+            Expr::Not(expr) => self.eval(expr, indices, row)?.map(|b| !b),
+            Expr::Compare { lhs, op, rhs } => {
+                let ty =
+                    comparison_type(self.operand_type(lhs), self.operand_type(rhs));
+                let lhs = self.eval_operand(lhs, ty, indices, row)?;
+                let rhs = self.eval_operand(rhs, ty, indices, row)?;
+                match (lhs, rhs) {
+                    (Some(lhs), Some(rhs)) => compare(&lhs, *op, &rhs),
+                    _ => None,
+                }
+            }
+            Expr::IsNull { operand, negated } => {
+                let is_null = match operand {
+                    Operand::Column(name) => {
+                        indices.get(name).map(|&i| row[i].is_empty())
+                    }
+                    Operand::Literal(_) => Some(false),
+                };
+                is_null.map(|is_null| is_null != *negated)
+            }
+            Expr::In {
+                operand,
+                list,
+                negated,
+            } => {
+                // Each literal may need a different comparison type, such as
+                // `id IN (1, 1.5)`.
+                let operand_ty = self.operand_type(operand);
+                let mut found = Some(false);
+                for lit in list {
+                    let ty = comparison_type(operand_ty, lit.filter_type());
+                    let Some(value) = self.eval_operand(operand, ty, indices, row)?
+                    else {
+                        found = None;
+                        break;
+                    };
+                    let lit = literal_value(lit, ty)?;
+                    if compare(&value, CompareOp::Eq, &lit) == Some(true) {
+                        found = Some(true);
+                        break;
+                    }
+                }
+                found.map(|found| found != *negated)
+            }
+            Expr::Operand(operand) => {
+                match self.eval_operand(operand, FilterType::Bool, indices, row)? {
+                    Some(Scalar::Bool(b)) => Some(b),
+                    Some(_) => unreachable!("filter should have been type-checked"),
+                    None => None,
+                }
+            }
+        })
+    }
+
+    /// Get the value of `operand` as `ty`.
+    fn eval_operand<'a>(
+        &'a self,
+        operand: &'a Operand,
+        ty: FilterType,
+        indices: &HashMap<String, usize>,
+        row: &'a csv::StringRecord,
+    ) -> Result<Option<Scalar<'a>>> {
+        match operand {
+            Operand::Column(name) => {
+                let Some(&i) = indices.get(name) else {
+                    return Ok(None);
+                };
+                let cell = &row[i];
+                if cell.is_empty() {
+                    Ok(None)
+                } else {
+                    cell_value(cell, ty)
+                        .with_context(|| format!("cannot filter on column {:?}", name))
+                        .map(Some)
+                }
+            }
+            Operand::Literal(lit) => literal_value(lit, ty).map(Some),
+        }
+    }
+}
+
+/// Which type should we use to compare values of type `lhs` and `rhs`? This
+/// only differs from the type of either side when comparing UUIDs with
+/// strings, or different kinds of numbers.
+fn comparison_type(lhs: FilterType, rhs: FilterType) -> FilterType {
+    match (lhs, rhs) {
+        (FilterType::Uuid, _) | (_, FilterType::Uuid) => FilterType::Uuid,
+        (FilterType::Number(lhs), FilterType::Number(rhs)) => {
+            FilterType::Number(lhs.max(rhs))
+        }
+        (ty, _) => ty,
+    }
+}
+
+/// Parse a non-empty CSV cell as `ty`.
+pub(crate) fn cell_value(cell: &str, ty: FilterType) -> Result<Scalar<'_>> {
+    Ok(match ty {
+        FilterType::Bool => Scalar::Bool(bool::from_csv_cell(cell)?),
+        FilterType::Number(NumberType::Int) => Scalar::Int(i64::from_csv_cell(cell)?),
+        FilterType::Number(NumberType::Decimal) => Scalar::Decimal(cell.parse()?),
+        FilterType::Number(NumberType::Float) => {
+            Scalar::Float(f64::from_csv_cell(cell)?)
+        }
+        FilterType::Text => Scalar::Text(cell),
+        FilterType::Uuid => Scalar::Uuid(Uuid::from_csv_cell(cell)?),
+        FilterType::Date => Scalar::Date(NaiveDate::from_csv_cell(cell)?),
+        FilterType::Timestamp => {
+            Scalar::Timestamp(NaiveDateTime::from_csv_cell(cell)?)
+        }
+        FilterType::TimestampWithTimeZone => {
+            Scalar::TimestampWithTimeZone(DateTime::from_csv_cell(cell)?)
+        }
+        FilterType::Other => {
+            unreachable!("filter should have been type-checked")
+        }
+    })
+}
+
+/// Convert `lit` to a value we can compare with a value of type `ty`.
+fn literal_value(lit: &Literal, ty: FilterType) -> Result<Scalar<'_>> {
+    Ok(match lit {
+        Literal::Text(s) if ty == FilterType::Uuid => Scalar::Uuid(s.parse()?),
+        Literal::Text(s) => Scalar::Text(s),
+        Literal::Number { text, value } => match ty {
+            FilterType::Number(NumberType::Int) => Scalar::Int(text.parse()?),
+            FilterType::Number(NumberType::Decimal) => Scalar::Decimal(text.parse()?),
+            _ => Scalar::Float(*value),
+        },
+        Literal::Bool(b) => Scalar::Bool(*b),
+        Literal::Date(d) => Scalar::Date(*d),
+        Literal::Timestamp(ts) => Scalar::Timestamp(*ts),
+        Literal::TimestampWithTimeZone(ts) => Scalar::TimestampWithTimeZone(*ts),
+    })
+}
+
+/// Compare two non-`NULL` values. Returns `None` if they can't be ordered,
+/// which only happens for `NaN`.
+fn compare(lhs: &Scalar, op: CompareOp, rhs: &Scalar) -> Option<bool> {
+    let ordering = lhs.partial_cmp(rhs)?;
+    Some(match op {
+        CompareOp::Eq => ordering == Ordering::Equal,
+        CompareOp::NotEq => ordering != Ordering::Equal,
+        CompareOp::Lt => ordering == Ordering::Less,
+        CompareOp::LtEq => ordering != Ordering::Greater,
+        CompareOp::Gt => ordering == Ordering::Greater,
+        CompareOp::GtEq => ordering != Ordering::Less,
+    })
+}
+
+#[test]
+fn evaluate_filters() {
+    use std::io::Cursor;
+
+    let schema = super::test_schema();
+    let input = "\
+id,name,active,born,seen_at,uid,tags,Odd Name,price
+1,a,t,2000-01-01,2024-01-01 00:00:00Z,1f4b8cf2-4c5e-4a8d-9d33-2b3f7c6f7e10,[],1.5,0.10000000000000000001
+2,b,f,2010-01-01,,,,,0.1
+3,,,,2024-06-01 00:00:00+02:00,,,-2,
+9007199254740993,,,,,,,,
+";
+    let examples = &[
+        ("id >= 2", vec![2, 3, 9007199254740993]),
+        ("active", vec![1]),
+        ("NOT active", vec![2]),
+        ("name IS NULL OR id = 1", vec![1, 3, 9007199254740993]),
+        ("name NOT IN ('a')", vec![2]),
+        ("born < DATE '2005-01-01'", vec![1]),
+        ("seen_at > TIMESTAMP '2024-01-01 00:00:00Z'", vec![3]),
+        ("uid = '1F4B8CF2-4C5E-4A8D-9D33-2B3F7C6F7E10'", vec![1]),
+        ("\"Odd Name\" < 0 OR tags IS NOT NULL", vec![1, 3]),
+        // `NULL AND FALSE` is `FALSE`, so row 3 matches.
+        ("NOT (active AND id = 1)", vec![2, 3, 9007199254740993]),
+        // Integers are compared exactly, even when `f64` would round them.
+        ("id = 9007199254740992", vec![]),
+        ("id > 9007199254740992", vec![9007199254740993]),
+        // Integers compared with fractions are compared as decimals.
+        ("id < 1.5", vec![1]),
+        ("id IN (1.5, 2)", vec![2]),
+        // Decimals are compared exactly, too.
+        ("price > 0.1", vec![1]),
+        ("price = 0.1", vec![2]),
+        ("price IN (0.1, 1)", vec![2]),
+        ("\"Odd Name\" > 1.4", vec![1]),
+    ];
+    for (text, expected_ids) in examples {
+        let filter = Filter::new(text, &schema).unwrap();
+        let mut out = vec![];
+        filter
+            .filter_csv(Cursor::new(input.as_bytes()), &mut out)
+            .unwrap();
+        let mut rdr = csv::Reader::from_reader(&out[..]);
+        let ids = rdr
+            .records()
+            .map(|r| r.unwrap()[0].parse::<i64>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(&ids, expected_ids, "wrong rows for {:?}", text);
+    }
+}
+
+#[test]
+fn evaluate_partial_filters() {
+    let schema = super::test_schema();
+    let values = vec![
+        ("id".to_owned(), "2".to_owned()),
+        ("born".to_owned(), String::new()),
+    ];
+    let examples = &[
+        ("id = 2", Some(true)),
+        ("id = 1", Some(false)),
+        ("id = 1 AND name = 'a'", Some(false)),
+        ("id = 2 AND name = 'a'", None),
+        ("id = 1 OR name = 'a'", None),
+        ("born IS NULL", Some(true)),
+        ("name IS NULL", None),
+        ("NOT active", None),
+    ];
+    for &(text, expected) in examples {
+        let filter = Filter::new(text, &schema).unwrap();
+        assert_eq!(
+            filter.eval_partial(&values).unwrap(),
+            expected,
+            "wrong result for {:?}",
+            text
+        );
+    }
+}
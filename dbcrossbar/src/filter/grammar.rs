@@ -0,0 +1,109 @@
+//! This file contains a [`rust-peg`][peg] grammar for `--filter` expressions.
+//! See the parent module for an overview of the syntax.
+//!
+//! [peg]: https://github.com/kevinmehall/rust-peg
+
+pub(crate) use filter_grammar::filter;
+
+peg::parser! {
+    grammar filter_grammar() for str {
+        use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+        use super::super::{CompareOp, Expr, Literal, Operand};
+        use crate::from_csv_cell::FromCsvCell;
+
+        pub rule filter() -> Expr
+            = ws()? e:expr() ws()? { e }
+
+        rule expr() -> Expr = precedence! {
+            l:(@) ws() kw("OR") ws() r:@ { Expr::Or(Box::new(l), Box::new(r)) }
+            --
+            l:(@) ws() kw("AND") ws() r:@ { Expr::And(Box::new(l), Box::new(r)) }
+            --
+            kw("NOT") ws()? e:@ { Expr::Not(Box::new(e)) }
+            --
+            p:predicate() { p }
+        }
+
+        rule predicate() -> Expr
+            = "(" ws()? e:expr() ws()? ")" { e }
+            / operand:operand() ws() kw("IS") ws() not:(kw("NOT") ws())? kw("NULL") {
+                Expr::IsNull { operand, negated: not.is_some() }
+            }
+            / operand:operand() ws() not:(kw("NOT") ws())? kw("IN") ws()?
+                "(" ws()? list:(literal() ++ (ws()? "," ws()?)) ws()? ")"
+            {
+                Expr::In { operand, list, negated: not.is_some() }
+            }
+            / lhs:operand() ws()? op:compare_op() ws()? rhs:operand() {
+                Expr::Compare { lhs, op, rhs }
+            }
+            / operand:operand() { Expr::Operand(operand) }
+
+        rule compare_op() -> CompareOp
+            = "<=" { CompareOp::LtEq }
+            / ">=" { CompareOp::GtEq }
+            / "<>" { CompareOp::NotEq }
+            / "!=" { CompareOp::NotEq }
+            / "=" { CompareOp::Eq }
+            / "<" { CompareOp::Lt }
+            / ">" { CompareOp::Gt }
+
+        rule operand() -> Operand
+            = lit:literal() { Operand::Literal(lit) }
+            / name:column() { Operand::Column(name) }
+
+        rule literal() -> Literal
+            = s:string() { Literal::Text(s) }
+            / kw("TRUE") { Literal::Bool(true) }
+            / kw("FALSE") { Literal::Bool(false) }
+            / kw("DATE") ws()? s:string() {?
+                NaiveDate::from_csv_cell(&s)
+                    .map(Literal::Date)
+                    .or(Err("date in YYYY-MM-DD format"))
+            }
+            / kw("TIMESTAMP") ws()? s:string() {?
+                DateTime::from_csv_cell(&s)
+                    .map(Literal::TimestampWithTimeZone)
+                    .or_else(|_| NaiveDateTime::from_csv_cell(&s).map(Literal::Timestamp))
+                    .or(Err("timestamp in YYYY-MM-DD HH:MM:SS format"))
+            }
+            / n:$("-"? ['0'..='9']+ ("." ['0'..='9']+)? (['e' | 'E'] ['+' | '-']? ['0'..='9']+)?) {?
+                n.parse::<f64>()
+                    .map(|value| Literal::Number { text: n.to_owned(), value })
+                    .or(Err("number"))
+            }
+
+        // A single-quoted SQL string, using `''` to escape quotes.
+        rule string() -> String
+            = quiet! { "'" s:$(([^ '\''] / "''")*) "'" { s.replace("''", "'") } }
+            / expected!("string")
+
+        // A column name, either as a C identifier or double-quoted.
+        rule column() -> String
+            = quiet! {
+                "\"" s:$(([^ '"'] / "\"\"")+) "\"" { s.replace("\"\"", "\"") }
+                / !keyword() s:$(
+                    ['A'..='Z' | 'a'..='z' | '_']
+                    ['A'..='Z' | 'a'..='z' | '_' | '0'..='9']*
+                ) { s.to_owned() }
+            }
+            / expected!("column name")
+
+        rule keyword()
+            = kw("AND") / kw("OR") / kw("NOT") / kw("IN") / kw("IS") / kw("NULL")
+            / kw("TRUE") / kw("FALSE")
+
+        // A case-insensitive keyword.
+        rule kw(k: &'static str)
+            = quiet! {
+                s:$([_]*<{k.len()}>) !['A'..='Z' | 'a'..='z' | '_' | '0'..='9'] {?
+                    if s.eq_ignore_ascii_case(k) { Ok(()) } else { Err(k) }
+                }
+            }
+            / expected!(k)
+
+        // One or more characters of whitespace.
+        rule ws() = quiet! { [' ' | '\t' | '\r' | '\n']+ }
+    }
+}
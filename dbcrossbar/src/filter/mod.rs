@@ -0,0 +1,515 @@
+//! Support for `--filter`, a small, portable expression language for choosing
+//! which rows to copy.
+//!
+//! Unlike `--where`, which is passed to the source database verbatim, a
+//! `--filter` expression means the same thing everywhere:
+//!
+//! ```text
+//! status IN ('active', 'trial') AND created_at >= DATE '2024-01-01'
+//!     AND NOT (deleted_at IS NOT NULL OR is_test)
+//! ```
+//!
+//! We check each filter against the source [`Schema`], and then either compile
+//! it to the source's SQL dialect, or evaluate it locally on our CSV data.
+
+use std::{collections::HashMap, fmt};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+
+use crate::common::*;
+use crate::schema::DataType;
+
+mod decimal;
+mod eval;
+mod grammar;
+mod sql;
+mod trino;
+
+pub(crate) use self::decimal::Decimal;
+pub(crate) use self::eval::{cell_value, filter_csv_streams, Scalar};
+pub(crate) use self::sql::SqlDialect;
+
+/// A comparison operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CompareOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl CompareOp {
+    /// Does this operator depend on how values are ordered, and not just on
+    /// whether they're equal?
+    fn is_ordering(self) -> bool {
+        !matches!(self, CompareOp::Eq | CompareOp::NotEq)
+    }
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompareOp::Eq => write!(f, "="),
+            CompareOp::NotEq => write!(f, "<>"),
+            CompareOp::Lt => write!(f, "<"),
+            CompareOp::LtEq => write!(f, "<="),
+            CompareOp::Gt => write!(f, ">"),
+            CompareOp::GtEq => write!(f, ">="),
+        }
+    }
+}
+
+/// A literal value.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Literal {
+    /// A string, like `'abc'`.
+    Text(String),
+    /// A number, like `-1.5`. We keep the original text for generating SQL.
+    Number { text: String, value: f64 },
+    /// `TRUE` or `FALSE`.
+    Bool(bool),
+    /// A date, like `DATE '2024-01-31'`.
+    Date(NaiveDate),
+    /// A timestamp without a time zone, like `TIMESTAMP '2024-01-31 12:00:00'`.
+    Timestamp(NaiveDateTime),
+    /// A timestamp with a time zone, like `TIMESTAMP '2024-01-31 12:00:00Z'`.
+    TimestampWithTimeZone(DateTime<FixedOffset>),
+}
+
+impl Literal {
+    /// The type of this literal.
+    fn filter_type(&self) -> FilterType {
+        match self {
+            Literal::Text(_) => FilterType::Text,
+            Literal::Number { text, .. } => {
+                FilterType::Number(NumberType::for_literal(text))
+            }
+            Literal::Bool(_) => FilterType::Bool,
+            Literal::Date(_) => FilterType::Date,
+            Literal::Timestamp(_) => FilterType::Timestamp,
+            Literal::TimestampWithTimeZone(_) => FilterType::TimestampWithTimeZone,
+        }
+    }
+}
+
+/// Something we can compare: either a column or a literal.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Operand {
+    Column(String),
+    Literal(Literal),
+}
+
+/// A filter expression.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare {
+        lhs: Operand,
+        op: CompareOp,
+        rhs: Operand,
+    },
+    IsNull {
+        operand: Operand,
+        negated: bool,
+    },
+    In {
+        operand: Operand,
+        list: Vec<Literal>,
+        negated: bool,
+    },
+    /// A Boolean column or literal, used as a condition.
+    Operand(Operand),
+}
+
+/// How we compare numbers. When comparing two different kinds of numbers, we
+/// use the greater of the two, so an integer column compared to `1.5` is
+/// compared as a decimal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum NumberType {
+    /// Compare as `i64`.
+    Int,
+    /// Compare as an exact decimal.
+    Decimal,
+    /// Compare as `f64`.
+    Float,
+}
+
+impl NumberType {
+    /// How should we compare the numeric literal `text`?
+    fn for_literal(text: &str) -> NumberType {
+        if text.parse::<i64>().is_ok() {
+            NumberType::Int
+        } else if text.contains(['e', 'E']) {
+            NumberType::Float
+        } else {
+            NumberType::Decimal
+        }
+    }
+}
+
+/// The types of values used in filters. This is much simpler than
+/// [`DataType`], because we only need to know how to compare values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FilterType {
+    Bool,
+    Number(NumberType),
+    Text,
+    /// A UUID, which may be compared against a string literal.
+    Uuid,
+    Date,
+    Timestamp,
+    TimestampWithTimeZone,
+    /// A type which can only be tested using `IS NULL`.
+    Other,
+}
+
+impl FilterType {
+    /// Choose a `FilterType` for a column with the specified `data_type`.
+    pub(crate) fn for_data_type(schema: &Schema, data_type: &DataType) -> FilterType {
+        match data_type {
+            DataType::Bool => FilterType::Bool,
+            DataType::Date => FilterType::Date,
+            DataType::Decimal => FilterType::Number(NumberType::Decimal),
+            DataType::Float32 | DataType::Float64 => {
+                FilterType::Number(NumberType::Float)
+            }
+            DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+                FilterType::Number(NumberType::Int)
+            }
+            DataType::Named(name) => {
+                FilterType::for_data_type(schema, schema.data_type_for_name(name))
+            }
+            DataType::OneOf(_) | DataType::Text => FilterType::Text,
+            DataType::TimestampWithoutTimeZone => FilterType::Timestamp,
+            DataType::TimestampWithTimeZone => FilterType::TimestampWithTimeZone,
+            DataType::Uuid => FilterType::Uuid,
+            DataType::Array(_)
+            | DataType::GeoJson(_)
+            | DataType::Json
+            | DataType::Struct(_) => FilterType::Other,
+        }
+    }
+}
+
+impl fmt::Display for FilterType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterType::Bool => write!(f, "boolean"),
+            FilterType::Number(_) => write!(f, "number"),
+            FilterType::Text => write!(f, "string"),
+            FilterType::Uuid => write!(f, "uuid"),
+            FilterType::Date => write!(f, "date"),
+            FilterType::Timestamp => write!(f, "timestamp without time zone"),
+            FilterType::TimestampWithTimeZone => {
+                write!(f, "timestamp with time zone")
+            }
+            FilterType::Other => write!(f, "non-comparable value"),
+        }
+    }
+}
+
+/// A `--filter` expression which has been checked against a schema.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    /// Our expression.
+    expr: Expr,
+    /// The types of the columns we use.
+    columns: HashMap<String, FilterType>,
+}
+
+impl Filter {
+    /// Parse `text` as a filter, and check it against `schema`.
+    pub(crate) fn new(text: &str, schema: &Schema) -> Result<Self> {
+        let expr = grammar::filter(text)
+            .map_err(|err| format_err!("cannot parse --filter {:?}: {}", text, err))?;
+        let mut filter = Filter {
+            expr,
+            columns: HashMap::new(),
+        };
+        filter
+            .check(schema)
+            .with_context(|| format!("invalid --filter {:?}", text))?;
+        Ok(filter)
+    }
+
+    /// The names of the columns used by this filter.
+    pub(crate) fn column_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.columns.keys().map(|name| &name[..])
+    }
+
+    /// The type of the column `name`, which must be used by this filter.
+    fn column_type(&self, name: &str) -> FilterType {
+        self.columns[name]
+    }
+
+    /// The type of `operand`. Columns must be in `self.columns`.
+    fn operand_type(&self, operand: &Operand) -> FilterType {
+        match operand {
+            Operand::Column(name) => self.column_type(name),
+            Operand::Literal(lit) => lit.filter_type(),
+        }
+    }
+
+    /// Look up all our columns, and make sure our expression is well-typed.
+    fn check(&mut self, schema: &Schema) -> Result<()> {
+        let mut names = vec![];
+        collect_column_names(&self.expr, &mut names);
+        for name in names {
+            let col = schema
+                .table
+                .columns
+                .iter()
+                .find(|c| c.name == name)
+                .ok_or_else(|| format_err!("unknown column {:?}", name))?;
+            self.columns.insert(
+                name.to_owned(),
+                FilterType::for_data_type(schema, &col.data_type),
+            );
+        }
+        self.check_expr(&self.expr)?;
+
+        // Now that we know our UUID literals are valid, put them in canonical
+        // form, so that every driver can compare them as strings.
+        let mut expr = self.expr.clone();
+        self.normalize_uuid_literals(&mut expr);
+        self.expr = expr;
+        Ok(())
+    }
+
+    /// Convert any string literals compared against UUID columns to lowercase,
+    /// hyphenated form.
+    fn normalize_uuid_literals(&self, expr: &mut Expr) {
+        let normalize = |lit: &mut Literal| {
+            if let Literal::Text(s) = lit {
+                if let Ok(uuid) = s.parse::<uuid::Uuid>() {
+                    *s = uuid.to_string();
+                }
+            }
+        };
+        match expr {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                self.normalize_uuid_literals(lhs);
+                self.normalize_uuid_literals(rhs);
+            }
+            Expr::Not(expr) => self.normalize_uuid_literals(expr),
+            Expr::Compare { lhs, rhs, .. } => {
+                let lhs_is_uuid = self.operand_type(lhs) == FilterType::Uuid;
+                let rhs_is_uuid = self.operand_type(rhs) == FilterType::Uuid;
+                if let (true, Operand::Literal(lit)) = (lhs_is_uuid, &mut *rhs) {
+                    normalize(lit);
+                }
+                if let (true, Operand::Literal(lit)) = (rhs_is_uuid, &mut *lhs) {
+                    normalize(lit);
+                }
+            }
+            Expr::In { operand, list, .. } => {
+                if self.operand_type(operand) == FilterType::Uuid {
+                    list.iter_mut().for_each(normalize);
+                }
+            }
+            Expr::IsNull { .. } | Expr::Operand(_) => {}
+        }
+    }
+
+    /// Make sure `expr` is well-typed.
+    fn check_expr(&self, expr: &Expr) -> Result<()> {
+        match expr {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                self.check_expr(lhs)?;
+                self.check_expr(rhs)
+            }
+            Expr::Not(expr) => self.check_expr(expr),
+            Expr::Compare { lhs, op, rhs } => {
+                self.check_comparable(lhs, rhs).with_context(|| {
+                    format!("cannot compare {} {} {}", Desc(lhs), op, Desc(rhs))
+                })
+            }
+            Expr::IsNull { operand, .. } => match operand {
+                Operand::Column(_) => Ok(()),
+                Operand::Literal(_) => {
+                    Err(format_err!("IS NULL can only be used with columns"))
+                }
+            },
+            Expr::In { operand, list, .. } => {
+                for lit in list {
+                    let lit = Operand::Literal(lit.to_owned());
+                    self.check_comparable(operand, &lit).with_context(|| {
+                        format!("cannot check if {} IN {}", Desc(operand), Desc(&lit))
+                    })?;
+                }
+                Ok(())
+            }
+            Expr::Operand(operand) => match self.operand_type(operand) {
+                FilterType::Bool => Ok(()),
+                ty => Err(format_err!(
+                    "expected a condition, but {} is a {}",
+                    Desc(operand),
+                    ty,
+                )),
+            },
+        }
+    }
+
+    /// Make sure that we can compare `lhs` and `rhs`.
+    fn check_comparable(&self, lhs: &Operand, rhs: &Operand) -> Result<()> {
+        let lhs_ty = self.operand_type(lhs);
+        let rhs_ty = self.operand_type(rhs);
+        fn text_literal(operand: &Operand) -> Option<&str> {
+            match operand {
+                Operand::Literal(Literal::Text(s)) => Some(s),
+                _ => None,
+            }
+        }
+        match (lhs_ty, rhs_ty) {
+            (FilterType::Other, _) | (_, FilterType::Other) => {
+                Err(format_err!("only IS NULL may be used with this type"))
+            }
+            (FilterType::Uuid, FilterType::Text)
+            | (FilterType::Text, FilterType::Uuid) => {
+                // Allow comparing UUID columns against string literals, as
+                // long as they contain valid UUIDs.
+                let s = text_literal(lhs).or_else(|| text_literal(rhs)).ok_or_else(
+                    || format_err!("a uuid can only be compared to a string literal"),
+                )?;
+                s.parse::<uuid::Uuid>()
+                    .with_context(|| format!("{:?} is not a valid uuid", s))?;
+                Ok(())
+            }
+            (FilterType::Number(_), FilterType::Number(_)) => Ok(()),
+            (lhs_ty, rhs_ty) if lhs_ty == rhs_ty => Ok(()),
+            (lhs_ty, rhs_ty) => {
+                Err(format_err!("cannot compare a {} with a {}", lhs_ty, rhs_ty))
+            }
+        }
+    }
+}
+
+/// Add the column names used by `expr` to `names`.
+fn collect_column_names<'a>(expr: &'a Expr, names: &mut Vec<&'a str>) {
+    let mut add = |operand: &'a Operand| {
+        if let Operand::Column(name) = operand {
+            names.push(name);
+        }
+    };
+    match expr {
+        Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+            collect_column_names(lhs, names);
+            collect_column_names(rhs, names);
+        }
+        Expr::Not(expr) => collect_column_names(expr, names),
+        Expr::Compare { lhs, rhs, .. } => {
+            add(lhs);
+            add(rhs);
+        }
+        Expr::IsNull { operand, .. }
+        | Expr::In { operand, .. }
+        | Expr::Operand(operand) => add(operand),
+    }
+}
+
+/// Describe an operand for use in error messages.
+struct Desc<'a>(&'a Operand);
+
+impl fmt::Display for Desc<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Operand::Column(name) => write!(f, "column {:?}", name),
+            Operand::Literal(Literal::Text(s)) => write!(f, "{:?}", s),
+            Operand::Literal(Literal::Number { text, .. }) => write!(f, "{}", text),
+            Operand::Literal(Literal::Bool(b)) => write!(f, "{}", b),
+            Operand::Literal(Literal::Date(d)) => write!(f, "DATE '{}'", d),
+            Operand::Literal(Literal::Timestamp(ts)) => {
+                write!(f, "TIMESTAMP '{}'", ts)
+            }
+            Operand::Literal(Literal::TimestampWithTimeZone(ts)) => {
+                write!(f, "TIMESTAMP '{}'", ts)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_schema() -> Schema {
+    use crate::schema::Column;
+
+    let mut schema = Schema::dummy_test_schema();
+    let column = |name: &str, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable: true,
+        data_type,
+        comment: None,
+    };
+    schema.table.columns = vec![
+        column("id", DataType::Int64),
+        column("name", DataType::Text),
+        column("active", DataType::Bool),
+        column("born", DataType::Date),
+        column("seen_at", DataType::TimestampWithTimeZone),
+        column("uid", DataType::Uuid),
+        column("tags", DataType::Array(Box::new(DataType::Text))),
+        column("Odd Name", DataType::Float64),
+        column("price", DataType::Decimal),
+    ];
+    schema
+}
+
+#[test]
+fn parse_filters() {
+    let schema = test_schema();
+    let valid = &[
+        "id = 1",
+        "id>=1 and name <> 'x'",
+        "NOT active OR id IN (1, 2, 3)",
+        "name NOT IN ('a', 'b''c') AND tags IS NOT NULL",
+        "born < DATE '2024-01-01' AND seen_at >= TIMESTAMP '2024-01-01 00:00:00Z'",
+        "(id = 1 OR id = 2) AND (\"Odd Name\" > -1.5e3)",
+        "uid = '1f4b8cf2-4c5e-4a8d-9d33-2b3f7c6f7e10'",
+        "active",
+        "not(active)",
+    ];
+    for &text in valid {
+        if let Err(err) = Filter::new(text, &schema) {
+            panic!("could not parse {:?}: {:?}", text, err);
+        }
+    }
+
+    let invalid = &[
+        "id =",
+        "missing = 1",
+        "id = 'one'",
+        "born < '2024-01-01'",
+        "born < DATE '2024-13-01'",
+        "seen_at > TIMESTAMP '2024-01-01 00:00:00'",
+        "tags = 'x'",
+        "uid = 'not-a-uuid'",
+        "uid = name",
+        "id",
+        "1 IS NULL",
+        "id = 1 AND",
+        "and = 1",
+    ];
+    for &text in invalid {
+        assert!(
+            Filter::new(text, &schema).is_err(),
+            "should not parse {:?}",
+            text,
+        );
+    }
+}
+
+#[test]
+fn filter_precedence() {
+    let expr = grammar::filter("NOT a OR b AND c").unwrap();
+    let col = |name: &str| Box::new(Expr::Operand(Operand::Column(name.to_owned())));
+    assert_eq!(
+        expr,
+        Expr::Or(
+            Box::new(Expr::Not(col("a"))),
+            Box::new(Expr::And(col("b"), col("c")))
+        ),
+    );
+}
@@ -0,0 +1,226 @@
+//! Compiling filters to SQL strings.
+
+use std::fmt::Write as _;
+
+use super::{Expr, Filter, FilterType, Literal, Operand};
+use crate::common::*;
+use crate::drivers::{bigquery_shared, postgres_shared};
+
+/// The SQL dialects we can compile a filter to. Trino uses a separate
+/// AST-based compiler.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SqlDialect {
+    /// PostgreSQL.
+    Postgres,
+    /// RedShift, which is mostly like PostgreSQL, but which doesn't support
+    /// `COLLATE`.
+    RedShift,
+    /// BigQuery.
+    BigQuery,
+}
+
+impl Filter {
+    /// Compile this filter to an SQL expression in `dialect`.
+    pub(crate) fn to_sql(&self, dialect: SqlDialect) -> Result<String> {
+        let mut out = String::new();
+        self.write_expr_sql(dialect, &self.expr, &mut out)?;
+        Ok(out)
+    }
+
+    /// Write `expr` as SQL. We wrap every compound expression in parentheses,
+    /// so that we never need to worry about precedence.
+    fn write_expr_sql(
+        &self,
+        dialect: SqlDialect,
+        expr: &Expr,
+        out: &mut String,
+    ) -> Result<()> {
+        match expr {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                let op = if matches!(expr, Expr::And(..)) {
+                    "AND"
+                } else {
+                    "OR"
+                };
+                out.push('(');
+                self.write_expr_sql(dialect, lhs, out)?;
+                write!(out, " {} ", op)?;
+                self.write_expr_sql(dialect, rhs, out)?;
+                out.push(')');
+            }
+            Expr::Not(expr) => {
+                out.push_str("(NOT ");
+                self.write_expr_sql(dialect, expr, out)?;
+                out.push(')');
+            }
+            Expr::Compare { lhs, op, rhs } => {
+                // We compare text by byte value everywhere, but PostgreSQL
+                // uses the database's collation unless we ask for "C".
+                // RedShift and BigQuery always compare by byte value.
+                let collate = dialect == SqlDialect::Postgres
+                    && op.is_ordering()
+                    && self.operand_type(lhs) == FilterType::Text;
+                out.push('(');
+                if collate {
+                    out.push('(');
+                    write_operand_sql(dialect, lhs, out)?;
+                    out.push_str("::text) COLLATE \"C\"");
+                    write!(out, " {} ", op)?;
+                    out.push('(');
+                    write_operand_sql(dialect, rhs, out)?;
+                    out.push_str("::text)");
+                } else {
+                    write_operand_sql(dialect, lhs, out)?;
+                    write!(out, " {} ", op)?;
+                    write_operand_sql(dialect, rhs, out)?;
+                }
+                out.push(')');
+            }
+            Expr::IsNull { operand, negated } => {
+                out.push('(');
+                write_operand_sql(dialect, operand, out)?;
+                out.push_str(if *negated {
+                    " IS NOT NULL)"
+                } else {
+                    " IS NULL)"
+                });
+            }
+            Expr::In {
+                operand,
+                list,
+                negated,
+            } => {
+                out.push('(');
+                write_operand_sql(dialect, operand, out)?;
+                out.push_str(if *negated { " NOT IN (" } else { " IN (" });
+                for (i, lit) in list.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    write_literal_sql(dialect, lit, out)?;
+                }
+                out.push_str("))");
+            }
+            Expr::Operand(operand) => {
+                debug_assert_eq!(self.operand_type(operand), FilterType::Bool);
+                write_operand_sql(dialect, operand, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write an operand as SQL.
+fn write_operand_sql(
+    dialect: SqlDialect,
+    operand: &Operand,
+    out: &mut String,
+) -> Result<()> {
+    match (dialect, operand) {
+        (SqlDialect::Postgres | SqlDialect::RedShift, Operand::Column(name)) => {
+            write!(out, "{}", postgres_shared::Ident(name))?;
+        }
+        (SqlDialect::BigQuery, Operand::Column(name)) => {
+            write!(out, "{}", bigquery_shared::Ident(name)).map_err(|_| {
+                format_err!("cannot use column {:?} in a BigQuery filter", name)
+            })?;
+        }
+        (_, Operand::Literal(lit)) => write_literal_sql(dialect, lit, out)?,
+    }
+    Ok(())
+}
+
+/// Write a literal as SQL.
+fn write_literal_sql(
+    dialect: SqlDialect,
+    lit: &Literal,
+    out: &mut String,
+) -> Result<()> {
+    match (dialect, lit) {
+        (SqlDialect::Postgres | SqlDialect::RedShift, Literal::Text(s)) => {
+            out.push_str(&postgres_shared::pg_quote(s))
+        }
+        (SqlDialect::BigQuery, Literal::Text(s)) => out.push_str(&bq_quote(s)),
+        (_, Literal::Number { text, .. }) => out.push_str(text),
+        (_, Literal::Bool(true)) => out.push_str("TRUE"),
+        (_, Literal::Bool(false)) => out.push_str("FALSE"),
+        (_, Literal::Date(d)) => write!(out, "DATE '{}'", d.format("%Y-%m-%d"))?,
+        (SqlDialect::Postgres | SqlDialect::RedShift, Literal::Timestamp(ts)) => {
+            write!(out, "TIMESTAMP '{}'", ts.format("%Y-%m-%d %H:%M:%S%.f"))?
+        }
+        (SqlDialect::BigQuery, Literal::Timestamp(ts)) => {
+            write!(out, "DATETIME '{}'", ts.format("%Y-%m-%d %H:%M:%S%.f"))?
+        }
+        (
+            SqlDialect::Postgres | SqlDialect::RedShift,
+            Literal::TimestampWithTimeZone(ts),
+        ) => write!(
+            out,
+            "TIMESTAMPTZ '{}'",
+            ts.format("%Y-%m-%d %H:%M:%S%.f%:z")
+        )?,
+        (SqlDialect::BigQuery, Literal::TimestampWithTimeZone(ts)) => {
+            write!(out, "TIMESTAMP '{}'", ts.format("%Y-%m-%d %H:%M:%S%.f%:z"))?
+        }
+    }
+    Ok(())
+}
+
+/// Quote a BigQuery string literal. See the [docs][].
+///
+/// [docs]: https://cloud.google.com/bigquery/docs/reference/standard-sql/lexical#string_and_bytes_literals
+fn bq_quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+#[test]
+fn compile_to_sql() {
+    let schema = super::test_schema();
+    let filter = Filter::new(
+        "id > 1 AND NOT (name IN ('a', 'b''c') OR seen_at IS NULL) \
+         AND born >= DATE '2024-01-01' \
+         AND seen_at < TIMESTAMP '2024-01-01 12:00:00+02:00' AND active",
+        &schema,
+    )
+    .unwrap();
+    assert_eq!(
+        filter.to_sql(SqlDialect::Postgres).unwrap(),
+        r#"((((("id" > 1) AND (NOT (("name" IN ('a', 'b''c')) OR ("seen_at" IS NULL)))) AND ("born" >= DATE '2024-01-01')) AND ("seen_at" < TIMESTAMPTZ '2024-01-01 12:00:00+02:00')) AND "active")"#,
+    );
+    assert_eq!(
+        filter.to_sql(SqlDialect::BigQuery).unwrap(),
+        r#"(((((`id` > 1) AND (NOT ((`name` IN ('a', 'b\'c')) OR (`seen_at` IS NULL)))) AND (`born` >= DATE '2024-01-01')) AND (`seen_at` < TIMESTAMP '2024-01-01 12:00:00+02:00')) AND `active`)"#,
+    );
+}
+
+#[test]
+fn compile_text_comparisons_to_sql() {
+    let schema = super::test_schema();
+    let filter = Filter::new("name < 'a' AND name <> 'B'", &schema).unwrap();
+    // PostgreSQL would sort `'B'` after `'a'` in most locales, but we sort by
+    // byte value.
+    assert_eq!(
+        filter.to_sql(SqlDialect::Postgres).unwrap(),
+        r#"((("name"::text) COLLATE "C" < ('a'::text)) AND ("name" <> 'B'))"#,
+    );
+    assert_eq!(
+        filter.to_sql(SqlDialect::RedShift).unwrap(),
+        r#"(("name" < 'a') AND ("name" <> 'B'))"#,
+    );
+    assert_eq!(
+        filter.to_sql(SqlDialect::BigQuery).unwrap(),
+        r#"((`name` < 'a') AND (`name` <> 'B'))"#,
+    );
+}
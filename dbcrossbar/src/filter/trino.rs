@@ -0,0 +1,118 @@
+//! Compiling filters to Trino SQL, using the `pretty` AST.
+
+use dbcrossbar_trino::pretty::ast::{BinOp, Expr as TrinoExpr};
+
+use super::{CompareOp, Expr, Filter, FilterType, Literal, Operand};
+use crate::common::*;
+use crate::drivers::trino_shared::{TrinoDataType, TrinoIdent};
+
+impl Filter {
+    /// Compile this filter to a Trino expression.
+    pub(crate) fn to_trino_expr(&self) -> Result<TrinoExpr> {
+        self.trino_expr(&self.expr)
+    }
+
+    /// Compile `expr` to a Trino expression.
+    fn trino_expr(&self, expr: &Expr) -> Result<TrinoExpr> {
+        Ok(match expr {
+            Expr::And(lhs, rhs) => TrinoExpr::binop(
+                self.trino_expr(lhs)?,
+                BinOp::And,
+                self.trino_expr(rhs)?,
+            ),
+            Expr::Or(lhs, rhs) => TrinoExpr::binop(
+                self.trino_expr(lhs)?,
+                BinOp::Or,
+                self.trino_expr(rhs)?,
+            ),
+            Expr::Not(expr) => TrinoExpr::negate(self.trino_expr(expr)?),
+            Expr::Compare { lhs, op, rhs } => {
+                let op = match op {
+                    CompareOp::Eq => BinOp::Eq,
+                    CompareOp::NotEq => BinOp::NotEq,
+                    CompareOp::Lt => BinOp::Lt,
+                    CompareOp::LtEq => BinOp::LtEq,
+                    CompareOp::Gt => BinOp::Gt,
+                    CompareOp::GtEq => BinOp::GtEq,
+                };
+                TrinoExpr::binop(
+                    self.trino_operand(lhs)?,
+                    op,
+                    self.trino_operand(rhs)?,
+                )
+            }
+            Expr::IsNull { operand, negated } => {
+                TrinoExpr::is_null(self.trino_operand(operand)?, *negated)
+            }
+            Expr::In {
+                operand,
+                list,
+                negated,
+            } => TrinoExpr::in_list(
+                self.trino_operand(operand)?,
+                list.iter().map(trino_literal).collect(),
+                *negated,
+            ),
+            Expr::Operand(operand) => self.trino_operand(operand)?,
+        })
+    }
+
+    /// Compile `operand` to a Trino expression.
+    fn trino_operand(&self, operand: &Operand) -> Result<TrinoExpr> {
+        match operand {
+            Operand::Column(name) => {
+                let var = TrinoExpr::Var(TrinoIdent::new(name)?);
+                // Trino won't compare `UUID` values with `VARCHAR`, so compare
+                // them as strings. This works because Trino formats UUIDs in
+                // lowercase, and we normalize UUID literals the same way.
+                if self.column_type(name) == FilterType::Uuid {
+                    Ok(TrinoExpr::cast(var, TrinoDataType::varchar()))
+                } else {
+                    Ok(var)
+                }
+            }
+            Operand::Literal(lit) => Ok(trino_literal(lit)),
+        }
+    }
+}
+
+/// Compile a literal to a Trino expression.
+fn trino_literal(lit: &Literal) -> TrinoExpr {
+    match lit {
+        Literal::Text(s) => TrinoExpr::str(s.to_owned()),
+        Literal::Number { text, .. } => match text.parse::<i64>() {
+            Ok(i) => TrinoExpr::int(i),
+            // Trino parses `1.5` as a `DECIMAL` and `1.5e3` as a `DOUBLE`,
+            // which is what we want.
+            Err(_) => TrinoExpr::raw_sql(text),
+        },
+        Literal::Bool(b) => TrinoExpr::bool(*b),
+        Literal::Date(d) => TrinoExpr::cast(
+            TrinoExpr::str(d.format("%Y-%m-%d").to_string()),
+            TrinoDataType::Date,
+        ),
+        Literal::Timestamp(ts) => TrinoExpr::cast(
+            TrinoExpr::str(ts.format("%Y-%m-%d %H:%M:%S%.f").to_string()),
+            TrinoDataType::Timestamp { precision: 6 },
+        ),
+        Literal::TimestampWithTimeZone(ts) => TrinoExpr::cast(
+            TrinoExpr::str(ts.format("%Y-%m-%d %H:%M:%S%.f %:z").to_string()),
+            TrinoDataType::TimestampWithTimeZone { precision: 6 },
+        ),
+    }
+}
+
+#[test]
+fn compile_to_trino() {
+    let schema = super::test_schema();
+    let filter = Filter::new(
+        "id > 1.5 AND born >= DATE '2024-01-01' \
+         AND uid NOT IN ('1F4B8CF2-4C5E-4A8D-9D33-2B3F7C6F7E10') OR name IS NULL",
+        &schema,
+    )
+    .unwrap();
+    assert_eq!(
+        filter.to_trino_expr().unwrap().to_string(),
+        r#"((("id" > 1.5) AND ("born" >= CAST('2024-01-01' AS DATE))) AND (CAST("uid" AS VARCHAR) NOT IN ('1f4b8cf2-4c5e-4a8d-9d33-2b3f7c6f7e10'))) OR ("name" IS NULL)"#,
+    );
+}
@@ -0,0 +1,266 @@
+//! Support for `cp --verify`, which checks that a copy has the same data as its
+//! source.
+//!
+//! A row count catches missing rows, but not rows whose values were changed
+//! along the way. A [`Fingerprint`] also records a few aggregates for each
+//! column, which each driver computes using its own SQL. We only use
+//! aggregates which every database calculates exactly, so that they can be
+//! compared between databases:
+//!
+//! - The number of `NULL` values in each column, except for arrays, because
+//!   BigQuery stores `NULL` arrays as empty arrays.
+//! - The sum of each integer and `decimal` column. Floating point sums depend
+//!   on the order in which values are added, so we don't compare them.
+
+use std::{fmt, str::FromStr};
+
+use crate::common::*;
+use crate::filter::Decimal;
+use crate::schema::{Column, DataType};
+
+/// What should `cp --verify` compare?
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum VerifyMode {
+    /// Compare row counts.
+    Count,
+    /// Compare row counts and per-column aggregates.
+    Fingerprint,
+}
+
+impl FromStr for VerifyMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "count" => Ok(VerifyMode::Count),
+            "fingerprint" => Ok(VerifyMode::Fingerprint),
+            _ => Err(format_err!(
+                "unknown verify mode {:?} (expected count or fingerprint)",
+                s,
+            )),
+        }
+    }
+}
+
+/// An aggregate which we compute for a column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Aggregate {
+    /// The number of `NULL` values.
+    NullCount,
+    /// The sum of all non-`NULL` values, or `NULL` if there are none.
+    Sum,
+}
+
+impl Aggregate {
+    /// Which aggregates should we compute for `column`?
+    pub(crate) fn for_column(column: &Column) -> Vec<Aggregate> {
+        match &column.data_type {
+            DataType::Array(_) => vec![],
+            DataType::Decimal
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64 => vec![Aggregate::NullCount, Aggregate::Sum],
+            _ => vec![Aggregate::NullCount],
+        }
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Aggregate::NullCount => write!(f, "NULL count"),
+            Aggregate::Sum => write!(f, "sum"),
+        }
+    }
+}
+
+/// List the aggregates we compute for each column of `schema`, in the order
+/// that [`Fingerprint::from_values`] expects them.
+pub(crate) fn aggregates(
+    schema: &Schema,
+) -> impl Iterator<Item = (&Column, Aggregate)> {
+    schema.table.columns.iter().flat_map(|column| {
+        Aggregate::for_column(column)
+            .into_iter()
+            .map(move |aggregate| (column, aggregate))
+    })
+}
+
+/// Row counts and per-column aggregates for a table, which should be the same
+/// for a table and for a copy of it.
+#[derive(Clone, Debug)]
+pub struct Fingerprint {
+    /// The number of rows.
+    count: u64,
+    /// Our column aggregates, in schema order.
+    values: Vec<FingerprintValue>,
+}
+
+/// A single column aggregate.
+#[derive(Clone, Debug)]
+struct FingerprintValue {
+    /// The column we aggregated.
+    column: String,
+    /// The aggregate we computed.
+    aggregate: Aggregate,
+    /// The value of our aggregate. Sums of no values are `NULL`.
+    value: Option<Decimal>,
+}
+
+impl Fingerprint {
+    /// Build a fingerprint from the output of a query.
+    ///
+    /// `values` should contain the row count, followed by each aggregate
+    /// returned by [`Aggregate::for_column`] for each column in `schema`, in
+    /// order. Numbers may be formatted any way that [`Decimal`] can parse.
+    pub(crate) fn from_values(
+        schema: &Schema,
+        values: Vec<Option<String>>,
+    ) -> Result<Fingerprint> {
+        let mut values = values.into_iter();
+        let count = values
+            .next()
+            .flatten()
+            .ok_or_else(|| format_err!("fingerprint query returned no row count"))?
+            .parse::<u64>()
+            .context("could not parse fingerprint row count")?;
+        let mut fingerprint_values = vec![];
+        for (column, aggregate) in aggregates(schema) {
+            let value = values.next().ok_or_else(|| {
+                format_err!("fingerprint query returned too few values")
+            })?;
+            let value = value.as_deref().map(str::parse::<Decimal>).transpose()?;
+            fingerprint_values.push(FingerprintValue {
+                column: column.name.clone(),
+                aggregate,
+                value,
+            });
+        }
+        if values.next().is_some() {
+            return Err(format_err!("fingerprint query returned too many values"));
+        }
+        Ok(Fingerprint {
+            count,
+            values: fingerprint_values,
+        })
+    }
+
+    /// Build a fingerprint which only contains a row count.
+    pub(crate) fn from_count(count: usize) -> Result<Fingerprint> {
+        Ok(Fingerprint {
+            count: u64::try_from(count)?,
+            values: vec![],
+        })
+    }
+
+    /// The number of rows.
+    pub(crate) fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Check that `dest` matches this fingerprint, and explain any
+    /// differences. The destination's columns may have been renamed, but they
+    /// must be in the same order as ours.
+    pub(crate) fn check_matches(&self, dest: &Fingerprint) -> Result<()> {
+        if self.count != dest.count {
+            return Err(format_err!(
+                "verification failed: source has {} rows, but destination has {}",
+                self.count,
+                dest.count,
+            ));
+        }
+        if self.values.len() != dest.values.len() {
+            return Err(format_err!(
+                "verification failed: source has {} column aggregates, but destination has {}",
+                self.values.len(),
+                dest.values.len(),
+            ));
+        }
+        let display = |value: &Option<Decimal>| match value {
+            Some(value) => value.to_string(),
+            None => "NULL".to_owned(),
+        };
+        let mismatches = self
+            .values
+            .iter()
+            .zip(&dest.values)
+            .filter(|(src, dest)| src.value != dest.value)
+            .map(|(src, dest)| {
+                format!(
+                    "{} of {:?} is {} in source, but {} in destination",
+                    src.aggregate,
+                    src.column,
+                    display(&src.value),
+                    display(&dest.value),
+                )
+            })
+            .collect::<Vec<_>>();
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(format_err!(
+                "verification failed:\n- {}",
+                mismatches.join("\n- "),
+            ))
+        }
+    }
+}
+
+/// Build a fingerprint for `crate::filter::test_schema`, with the given row
+/// count, `NULL` count and sum for `price`. Other columns have no `NULL`s.
+#[cfg(test)]
+fn test_fingerprint(
+    count: &str,
+    price_nulls: &str,
+    price_sum: Option<&str>,
+) -> Fingerprint {
+    let schema = crate::filter::test_schema();
+    // `id` has two aggregates, six columns have one, `tags` has none, and
+    // `price` comes last.
+    let mut values = vec![Some(count), Some("0"), Some("6")];
+    values.extend([Some("0"); 6]);
+    values.extend([Some(price_nulls), price_sum]);
+    Fingerprint::from_values(
+        &schema,
+        values.into_iter().map(|v| v.map(str::to_owned)).collect(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn compare_fingerprints() {
+    // Databases may format sums differently.
+    let src = test_fingerprint("3", "1", Some("10.50"));
+    assert_eq!(src.count(), 3);
+    src.check_matches(&test_fingerprint("3", "1", Some("10.5")))
+        .unwrap();
+
+    let err = src
+        .check_matches(&test_fingerprint("3", "3", None))
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        err,
+        "verification failed:\n\
+         - NULL count of \"price\" is 1 in source, but 3 in destination\n\
+         - sum of \"price\" is 10.5 in source, but NULL in destination",
+    );
+
+    let err = src
+        .check_matches(&test_fingerprint("2", "1", Some("10.5")))
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        err,
+        "verification failed: source has 3 rows, but destination has 2",
+    );
+}
+
+#[test]
+fn fingerprints_need_one_value_per_aggregate() {
+    let schema = crate::filter::test_schema();
+    let values = |n: usize| vec![Some("0".to_owned()); n];
+    assert!(Fingerprint::from_values(&schema, values(11)).is_ok());
+    assert!(Fingerprint::from_values(&schema, values(10)).is_err());
+    assert!(Fingerprint::from_values(&schema, values(12)).is_err());
+}
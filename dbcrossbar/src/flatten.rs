@@ -0,0 +1,374 @@
+//! Support for `--flatten-structs` and `--explode`.
+
+use std::collections::HashSet;
+
+use futures::future;
+use serde_json::Value;
+
+use crate::common::*;
+use crate::data_streams::jsonl_converter::write_json_value;
+use crate::schema::{Column, DataType};
+use crate::transform::spawn_sync_transform;
+
+/// How to turn nested columns into flat ones, for destinations which can't
+/// handle `Struct` and `Array` values well.
+#[derive(Clone, Debug)]
+pub(crate) struct Flatten {
+    /// Should we replace each struct column with one column per field?
+    flatten_structs: bool,
+    /// An array column to replace with its elements, one row per element.
+    explode: Option<String>,
+    /// The schema of our input data, which we need to look up named types.
+    schema: Schema,
+}
+
+impl Flatten {
+    /// Build a `Flatten` from the values of `--flatten-structs` and
+    /// `--explode`, and check that it makes sense for `schema`.
+    pub(crate) fn new(
+        flatten_structs: bool,
+        explode: Option<&str>,
+        schema: &Schema,
+    ) -> Result<Self> {
+        if let Some(explode) = explode {
+            let column = schema
+                .table
+                .columns
+                .iter()
+                .find(|c| c.name == explode)
+                .ok_or_else(|| {
+                    format_err!("cannot --explode unknown column {:?}", explode)
+                })?;
+            if !matches!(column.data_type.resolve(schema), DataType::Array(_)) {
+                return Err(format_err!(
+                    "cannot --explode column {:?}, because it isn't an array",
+                    explode,
+                ));
+            }
+        }
+
+        let flatten = Flatten {
+            flatten_structs,
+            explode: explode.map(|e| e.to_owned()),
+            schema: schema.clone(),
+        };
+
+        // Make sure we don't end up with duplicate column names.
+        let mut seen = HashSet::new();
+        for col in &flatten.flattened_schema(schema.clone()).table.columns {
+            if !seen.insert(col.name.clone()) {
+                return Err(format_err!(
+                    "--flatten-structs would produce duplicate column {:?}",
+                    col.name,
+                ));
+            }
+        }
+        Ok(flatten)
+    }
+
+    /// Does this flatten change anything?
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.flatten_structs && self.explode.is_none()
+    }
+
+    /// Flatten the columns in `schema`.
+    pub(crate) fn flattened_schema(&self, mut schema: Schema) -> Schema {
+        let columns = schema.table.columns.drain(..).collect::<Vec<_>>();
+        for col in columns {
+            let (is_nullable, data_type) = if self.is_exploded(&col.name) {
+                (true, element_type(&self.schema, &col.data_type).clone())
+            } else {
+                (col.is_nullable, col.data_type)
+            };
+            self.push_flattened_columns(
+                &mut schema.table.columns,
+                col.name,
+                is_nullable,
+                data_type,
+                col.comment,
+            );
+        }
+        schema
+    }
+
+    /// Push the flattened columns for a single value onto `columns`.
+    fn push_flattened_columns(
+        &self,
+        columns: &mut Vec<Column>,
+        name: String,
+        is_nullable: bool,
+        data_type: DataType,
+        comment: Option<String>,
+    ) {
+        match data_type.resolve(&self.schema) {
+            DataType::Struct(fields) if self.flatten_structs => {
+                for field in fields {
+                    self.push_flattened_columns(
+                        columns,
+                        format!("{}_{}", name, field.name),
+                        is_nullable || field.is_nullable,
+                        field.data_type.clone(),
+                        None,
+                    );
+                }
+            }
+            _ => columns.push(Column {
+                name,
+                is_nullable,
+                data_type,
+                comment,
+            }),
+        }
+    }
+
+    /// Is `name` the column we're exploding?
+    fn is_exploded(&self, name: &str) -> bool {
+        self.explode.as_deref() == Some(name)
+    }
+
+    /// Flatten and explode each of our CSV streams.
+    pub(crate) fn flatten_csv_streams(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+    ) -> BoxStream<CsvStream> {
+        let flatten = self.clone();
+        data.and_then(move |stream| {
+            let flatten = flatten.clone();
+            let result = spawn_sync_transform(
+                ctx.clone(),
+                "flatten_csv".to_owned(),
+                stream.data,
+                move |_ctx, rdr, wtr| flatten.flatten_csv(rdr, wtr),
+            )
+            .map(|data| CsvStream {
+                name: stream.name,
+                data,
+            });
+            future::ready(result)
+        })
+        .boxed()
+    }
+
+    /// Synchronously flatten a single CSV stream.
+    fn flatten_csv(&self, rdr: impl Read, wtr: impl Write) -> Result<()> {
+        let mut rdr = csv::Reader::from_reader(rdr);
+        let mut wtr = csv::Writer::from_writer(wtr);
+
+        // Look up the type of each column, and write our new headers.
+        let headers = rdr.headers()?.clone();
+        let columns = headers
+            .iter()
+            .map(|name| {
+                self.schema
+                    .table
+                    .columns
+                    .iter()
+                    .find(|c| c.name == name)
+                    .ok_or_else(|| {
+                        format_err!("found unexpected column {:?} in CSV data", name)
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut in_schema = self.schema.clone();
+        in_schema.table.columns = columns.iter().map(|&c| c.clone()).collect();
+        let out_schema = self.flattened_schema(in_schema);
+        wtr.write_record(out_schema.table.columns.iter().map(|c| &c.name))?;
+
+        let mut row = csv::StringRecord::new();
+        let mut before = vec![];
+        let mut after = vec![];
+        let mut elements = vec![];
+        let mut out = vec![];
+        while rdr.read_record(&mut row)? {
+            before.clear();
+            after.clear();
+            elements.clear();
+            let mut exploded = None;
+            for (cell, col) in row.iter().zip(&columns) {
+                let cells = if exploded.is_some() {
+                    &mut after
+                } else {
+                    &mut before
+                };
+                if self.is_exploded(&col.name) {
+                    exploded = Some(parse_json_cell(cell)?);
+                } else if self.flatten_structs
+                    && matches!(
+                        col.data_type.resolve(&self.schema),
+                        DataType::Struct(_)
+                    )
+                {
+                    let value = parse_json_cell(cell)?;
+                    self.push_flattened_cells(cells, &col.data_type, &value)?;
+                } else {
+                    cells.push(cell.to_owned());
+                }
+            }
+
+            match exploded {
+                // No `--explode`, so write a single row.
+                None => wtr.write_record(&before)?,
+
+                // Write one row per array element. `NULL` and empty arrays
+                // produce no rows at all.
+                Some(Value::Null) => {}
+                Some(Value::Array(values)) => {
+                    let col = columns
+                        .iter()
+                        .find(|c| self.is_exploded(&c.name))
+                        .expect("should have found exploded column");
+                    let data_type = element_type(&self.schema, &col.data_type);
+                    for value in &values {
+                        elements.clear();
+                        self.push_flattened_cells(&mut elements, data_type, value)?;
+                        out.clear();
+                        out.extend_from_slice(&before);
+                        out.extend_from_slice(&elements);
+                        out.extend_from_slice(&after);
+                        wtr.write_record(&out)?;
+                    }
+                }
+                Some(value) => {
+                    return Err(format_err!(
+                        "cannot --explode {}, because it isn't an array",
+                        value,
+                    ));
+                }
+            }
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Convert `value` to one or more CSV cells, flattening structs if
+    /// requested.
+    fn push_flattened_cells(
+        &self,
+        cells: &mut Vec<String>,
+        data_type: &DataType,
+        value: &Value,
+    ) -> Result<()> {
+        match data_type.resolve(&self.schema) {
+            DataType::Struct(fields) if self.flatten_structs => {
+                let obj = match value {
+                    Value::Object(obj) => Some(obj),
+                    Value::Null => None,
+                    _ => {
+                        return Err(format_err!(
+                            "expected JSON object, found {}",
+                            value,
+                        ))
+                    }
+                };
+                for field in fields {
+                    let field_value = obj
+                        .and_then(|obj| obj.get(&field.name))
+                        .unwrap_or(&Value::Null);
+                    self.push_flattened_cells(cells, &field.data_type, field_value)?;
+                }
+            }
+            _ => {
+                let mut buffer = vec![];
+                write_json_value(&mut buffer, &self.schema, data_type, value)?;
+                cells.push(String::from_utf8(buffer)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Get the element type of the array type `data_type`.
+fn element_type<'a>(schema: &'a Schema, data_type: &'a DataType) -> &'a DataType {
+    match data_type.resolve(schema) {
+        DataType::Array(elem) => elem,
+        _ => panic!("{:?} should have been checked by Flatten::new", data_type),
+    }
+}
+
+/// Parse a CSV cell containing JSON, treating empty cells as `NULL`.
+fn parse_json_cell(cell: &str) -> Result<Value> {
+    if cell.is_empty() {
+        Ok(Value::Null)
+    } else {
+        serde_json::from_str(cell)
+            .with_context(|| format!("cannot parse {:?} as JSON", cell))
+    }
+}
+
+#[test]
+fn flatten_and_explode() {
+    use crate::schema::StructField;
+    use std::io::Cursor;
+
+    let field = |name: &str, data_type: DataType| StructField {
+        name: name.to_owned(),
+        is_nullable: true,
+        data_type,
+    };
+    let column = |name: &str, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable: false,
+        data_type,
+        comment: None,
+    };
+    let address = DataType::Struct(vec![
+        field("city", DataType::Text),
+        field(
+            "geo",
+            DataType::Struct(vec![
+                field("lat", DataType::Float64),
+                field("lon", DataType::Float64),
+            ]),
+        ),
+    ]);
+    let schema = Schema::from_table(Table {
+        name: "example".to_owned(),
+        columns: vec![
+            column("id", DataType::Int64),
+            column("tags", DataType::Array(Box::new(DataType::Text))),
+            column("address", address),
+        ],
+    })
+    .unwrap();
+    assert!(Flatten::new(false, None, &schema).unwrap().is_empty());
+    assert!(Flatten::new(false, Some("nope"), &schema).is_err());
+    assert!(Flatten::new(false, Some("address"), &schema).is_err());
+
+    let flatten = Flatten::new(true, Some("tags"), &schema).unwrap();
+    let names = flatten
+        .flattened_schema(schema.clone())
+        .table
+        .columns
+        .into_iter()
+        .map(|c| (c.name, c.data_type))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            ("id".to_owned(), DataType::Int64),
+            ("tags".to_owned(), DataType::Text),
+            ("address_city".to_owned(), DataType::Text),
+            ("address_geo_lat".to_owned(), DataType::Float64),
+            ("address_geo_lon".to_owned(), DataType::Float64),
+        ],
+    );
+
+    let input = r#"id,tags,address
+1,"[""a"",""b""]","{""city"":""Paris"",""geo"":{""lat"":48.9,""lon"":2.3}}"
+2,[],
+3,"[""c""]",
+"#;
+    let mut out = vec![];
+    flatten
+        .flatten_csv(Cursor::new(input.as_bytes().to_vec()), &mut out)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id,tags,address_city,address_geo_lat,address_geo_lon
+1,a,Paris,48.9,2.3
+1,b,Paris,48.9,2.3
+3,c,,,
+",
+    );
+}
@@ -1,7 +1,9 @@
 //! Specify the location of data or a schema.
 
+use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::Serialize;
 use std::ffi::OsStr;
 use std::path::Path;
 use std::{fmt, marker::PhantomData, str::FromStr};
@@ -11,6 +13,7 @@ use crate::common::*;
 #[cfg(test)]
 use crate::data_streams::DataFormat;
 use crate::drivers::find_driver;
+use crate::fingerprint::Fingerprint;
 
 /// When called from the CLI, should we display a list of individual locators
 /// for each data stream?
@@ -103,6 +106,30 @@ pub trait Locator: fmt::Debug + fmt::Display + Send + Sync + 'static {
         async move { Err(err) }.boxed()
     }
 
+    /// Compute a [`Fingerprint`] of the records specified by this locator, so
+    /// that `cp --verify=fingerprint` can compare them with a copy.
+    fn fingerprint(
+        &self,
+        _ctx: Context,
+        _shared_args: SharedArguments<Unverified>,
+        _source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Fingerprint> {
+        let err = format_err!("cannot fingerprint records at {}", self);
+        async move { Err(err) }.boxed()
+    }
+
+    /// Generate SQL which will migrate the table at this locator from
+    /// `old_schema` to `new_schema`, using `ALTER TABLE` and similar
+    /// statements. We don't connect to the database or run anything here; we
+    /// just return the SQL so that it can be reviewed and applied separately.
+    fn migration_sql(
+        &self,
+        _old_schema: &Schema,
+        _new_schema: &Schema,
+    ) -> Result<String> {
+        Err(format_err!("cannot generate migration SQL for {}", self))
+    }
+
     /// If this locator can be used as a local data source, return a stream of
     /// CSV streams. This function type is bit hairy:
     ///
@@ -192,6 +219,14 @@ pub trait Locator: fmt::Debug + fmt::Display + Send + Sync + 'static {
 pub type BoxLocator = Box<dyn Locator>;
 
 fn parse_locator(s: &str, enable_unstable: bool) -> Result<BoxLocator> {
+    driver_for_locator(s, enable_unstable)?.parse(s)
+}
+
+/// Find the driver for the locator `s`, based on its scheme.
+fn driver_for_locator(
+    s: &str,
+    enable_unstable: bool,
+) -> Result<&'static dyn LocatorDriver> {
     // Parse our locator into a URL-style scheme and the rest.
     lazy_static! {
         static ref SCHEME_RE: Regex =
@@ -203,8 +238,7 @@ fn parse_locator(s: &str, enable_unstable: bool) -> Result<BoxLocator> {
     let scheme = &cap[0];
 
     // Select an appropriate locator type.
-    let driver = find_driver(scheme, enable_unstable)?;
-    driver.parse(s)
+    find_driver(scheme, enable_unstable)
 }
 
 #[test]
@@ -285,6 +319,26 @@ impl UnparsedLocator {
     pub fn parse(&self, enable_unstable: bool) -> Result<BoxLocator> {
         parse_locator(&self.0, enable_unstable)
     }
+
+    /// The unparsed locator. This may contain a password.
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// List the tables or files in this locator, which should not include a
+    /// table or file name. See [`LocatorStatic::list`].
+    pub(crate) async fn list(
+        &self,
+        ctx: Context,
+        enable_unstable: bool,
+        source_args: SourceArguments<Unverified>,
+    ) -> Result<Vec<ListEntry>> {
+        let driver = driver_for_locator(&self.0, enable_unstable)?;
+        if !driver.features().locator.contains(LocatorFeatures::List) {
+            return Err(format_err!("cannot list {}", driver.name()));
+        }
+        driver.list(ctx, &self.0, source_args).await
+    }
 }
 
 impl FromStr for UnparsedLocator {
@@ -303,6 +357,9 @@ pub enum LocatorFeatures {
     LocalData,
     WriteLocalData,
     Count,
+    Fingerprint,
+    MigrationSql,
+    List,
 }
 
 /// A collection of all the features supported by a given driver. This is
@@ -343,12 +400,21 @@ impl fmt::Display for Features {
                 writeln!(f, "  {}", self.write_schema_if_exists.display())?;
             }
         }
+        if self.locator.contains(LocatorFeatures::MigrationSql) {
+            writeln!(f, "- schema migrate")?;
+        }
+        if self.locator.contains(LocatorFeatures::List) {
+            writeln!(f, "- ls")?;
+        }
         if self.locator.contains(LocatorFeatures::Count) {
             writeln!(f, "- count")?;
             if !self.source_args.is_empty() {
                 writeln!(f, "  {}", self.source_args.display())?;
             }
         }
+        if self.locator.contains(LocatorFeatures::Fingerprint) {
+            writeln!(f, "- cp --verify=fingerprint")?;
+        }
         if self.locator.contains(LocatorFeatures::LocalData) {
             writeln!(f, "- cp FROM:")?;
             if !self.source_args.is_empty() {
@@ -386,6 +452,57 @@ pub trait LocatorStatic: Locator + Clone + FromStr<Err = Error> + Sized {
     fn is_unstable() -> bool {
         false
     }
+
+    /// List the tables or files in `container`, which is a locator without a
+    /// table or file name, such as `postgres://host/db` or `s3://bucket/dir/`.
+    fn list(
+        _ctx: Context,
+        container: &str,
+        _source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>> {
+        let err = format_err!("cannot list {}", container);
+        async move { Err(err) }.boxed()
+    }
+}
+
+/// A table or file found by [`LocatorStatic::list`].
+#[derive(Clone, Debug, Serialize)]
+pub struct ListEntry {
+    /// The name of this entry, relative to the container we listed.
+    pub name: String,
+    /// A locator which can be used to read this entry. This may hide
+    /// passwords.
+    pub locator: String,
+    /// What kind of entry is this?
+    pub kind: ListEntryKind,
+    /// The approximate number of rows, if known.
+    pub rows: Option<u64>,
+    /// The size in bytes, if known.
+    pub bytes: Option<u64>,
+    /// When this entry was last modified, if known.
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// What kind of entry did we find?
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListEntryKind {
+    /// A database table.
+    Table,
+    /// A database view.
+    View,
+    /// A file or cloud storage object.
+    File,
+}
+
+impl fmt::Display for ListEntryKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListEntryKind::Table => write!(f, "table"),
+            ListEntryKind::View => write!(f, "view"),
+            ListEntryKind::File => write!(f, "file"),
+        }
+    }
 }
 
 /// Interface to a locator driver. This exists because we Rust can't treat
@@ -412,6 +529,14 @@ pub trait LocatorDriver: Send + Sync + 'static {
 
     /// Parse a locator string and return a [`BoxLocator`].
     fn parse(&self, s: &str) -> Result<BoxLocator>;
+
+    /// List the tables or files in `container`. See [`LocatorStatic::list`].
+    fn list(
+        &self,
+        ctx: Context,
+        container: &str,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>>;
 }
 
 /// A wrapper type which converts a [`LocatorStatic`] class into an
@@ -446,4 +571,13 @@ impl<L: LocatorStatic> LocatorDriver for LocatorDriverWrapper<L> {
     fn parse(&self, s: &str) -> Result<BoxLocator> {
         Ok(Box::new(s.parse::<L>()?))
     }
+
+    fn list(
+        &self,
+        ctx: Context,
+        container: &str,
+        source_args: SourceArguments<Unverified>,
+    ) -> BoxFuture<Vec<ListEntry>> {
+        L::list(ctx, container, source_args)
+    }
 }
@@ -36,6 +36,8 @@ use tracing::{debug, info_span};
 use self::config::Configuration;
 
 pub(crate) mod args;
+pub(crate) mod bad_rows;
+pub(crate) mod byte_ranges;
 pub(crate) mod clouds;
 mod cmd;
 pub(crate) mod concat;
@@ -44,23 +46,40 @@ pub(crate) mod context;
 pub(crate) mod credentials;
 pub(crate) mod csv_stream;
 pub(crate) mod data_streams;
+pub(crate) mod dedupe;
 mod driver_args;
 pub mod drivers;
+pub(crate) mod external_sort;
+pub(crate) mod file_name_template;
+pub(crate) mod filter;
+pub(crate) mod fingerprint;
+pub(crate) mod flatten;
 pub(crate) mod from_csv_cell;
 pub(crate) mod from_json_value;
 pub(crate) mod if_exists;
 pub(crate) mod locator;
+pub(crate) mod masking;
+pub(crate) mod order_by;
 pub(crate) mod parse_error;
+pub(crate) mod partition;
 pub(crate) mod path_or_stdio;
+pub(crate) mod pipeline;
+pub(crate) mod projection;
 pub mod rechunk;
+pub(crate) mod sampling;
 pub mod schema;
+pub(crate) mod schema_evolution;
 pub(crate) mod separator;
 mod temporary_storage;
+pub(crate) mod time_zones;
 pub(crate) mod tls;
 pub mod tokio_glue;
 pub(crate) mod transform;
+pub(crate) mod union;
 mod url_with_hidden_password;
+pub(crate) mod validate;
 pub(crate) mod wait;
+pub(crate) mod wildcard;
 
 /// The buffer size to use by default when buffering I/O.
 pub(crate) const BUFFER_SIZE: usize = 64 * 1024;
@@ -126,8 +145,8 @@ pub(crate) mod common {
         driver_args::DriverArguments,
         if_exists::{IfExists, IfExistsFeatures},
         locator::{
-            BoxLocator, DisplayOutputLocators, Features, Locator, LocatorFeatures,
-            LocatorStatic,
+            BoxLocator, DisplayOutputLocators, Features, ListEntry, ListEntryKind,
+            Locator, LocatorFeatures, LocatorStatic,
         },
         path_or_stdio::PathOrStdio,
         schema::{Schema, Table},
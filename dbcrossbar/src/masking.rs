@@ -0,0 +1,534 @@
+//! Column-level masking, for removing sensitive data during a copy.
+
+use std::{collections::HashMap, env, fmt, fs, path::Path, str::FromStr, sync::Arc};
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use futures::future;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use toml_edit::DocumentMut;
+
+use crate::common::*;
+use crate::from_csv_cell::FromCsvCell;
+use crate::schema::DataType;
+use crate::transform::spawn_sync_transform;
+
+/// The environment variable containing our secret key for `hmac` and
+/// `tokenize`.
+const MASK_KEY_VAR: &str = "DBCROSSBAR_MASK_KEY";
+
+/// How much of a date or timestamp to keep.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DateUnit {
+    Year,
+    Month,
+    Day,
+    Hour,
+}
+
+impl FromStr for DateUnit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "year" => Ok(DateUnit::Year),
+            "month" => Ok(DateUnit::Month),
+            "day" => Ok(DateUnit::Day),
+            "hour" => Ok(DateUnit::Hour),
+            _ => Err(format_err!(
+                "unknown date unit {:?} (expected year, month, day or hour)",
+                s
+            )),
+        }
+    }
+}
+
+/// How to mask a single column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MaskPolicy {
+    /// Replace values with a hex-encoded, keyed HMAC-SHA256 hash.
+    Hmac,
+    /// Replace values with `NULL`.
+    Null,
+    /// Truncate dates and timestamps to the specified unit.
+    TruncateDate(DateUnit),
+    /// Round GeoJSON coordinates to the specified number of decimal places.
+    RoundGeo(u32),
+    /// Replace each ASCII letter and digit with a keyed pseudo-random
+    /// character of the same kind, keeping punctuation and length.
+    Tokenize,
+}
+
+impl MaskPolicy {
+    /// Does this policy need a secret key?
+    fn needs_key(self) -> bool {
+        matches!(self, MaskPolicy::Hmac | MaskPolicy::Tokenize)
+    }
+}
+
+impl FromStr for MaskPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+        match (name, arg) {
+            ("hmac", None) => Ok(MaskPolicy::Hmac),
+            ("null", None) => Ok(MaskPolicy::Null),
+            ("truncate-date", Some(unit)) => {
+                Ok(MaskPolicy::TruncateDate(unit.parse()?))
+            }
+            ("round-geo", Some(digits)) => Ok(MaskPolicy::RoundGeo(
+                digits
+                    .parse()
+                    .with_context(|| format!("invalid digits in {:?}", s))?,
+            )),
+            ("tokenize", None) => Ok(MaskPolicy::Tokenize),
+            _ => Err(format_err!("unknown mask policy {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for MaskPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaskPolicy::Hmac => write!(f, "hmac"),
+            MaskPolicy::Null => write!(f, "null"),
+            MaskPolicy::TruncateDate(unit) => {
+                write!(f, "truncate-date:{}", format!("{:?}", unit).to_lowercase())
+            }
+            MaskPolicy::RoundGeo(digits) => write!(f, "round-geo:{}", digits),
+            MaskPolicy::Tokenize => write!(f, "tokenize"),
+        }
+    }
+}
+
+/// A masking policy for a column, plus the column's original type.
+#[derive(Clone, Debug)]
+struct ColumnMask {
+    policy: MaskPolicy,
+    data_type: DataType,
+}
+
+/// Masking policies for a table.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Masks {
+    /// Policies, indexed by column name.
+    columns: HashMap<String, ColumnMask>,
+    /// Our secret key, if we need one.
+    key: Option<Arc<Vec<u8>>>,
+}
+
+impl Masks {
+    /// Build masking policies from `--mask COL=POLICY` arguments and an
+    /// optional `--mask-config` file, and check them against `schema`.
+    /// Command-line arguments override the config file.
+    pub(crate) fn new(
+        mask_args: &[String],
+        config_path: Option<&Path>,
+        schema: &Schema,
+    ) -> Result<Self> {
+        let mut policies = vec![];
+        if let Some(config_path) = config_path {
+            policies.extend(read_mask_config(config_path)?);
+        }
+        for arg in mask_args {
+            let (column, policy) = arg.split_once('=').ok_or_else(|| {
+                format_err!("expected --mask COL=POLICY, found {:?}", arg)
+            })?;
+            policies.push((column.to_owned(), policy.parse::<MaskPolicy>()?));
+        }
+
+        let mut columns = HashMap::new();
+        for (column, policy) in policies {
+            let col = schema
+                .table
+                .columns
+                .iter()
+                .find(|c| c.name == column)
+                .ok_or_else(|| {
+                    format_err!("cannot mask unknown column {:?}", column)
+                })?;
+            let data_type = match &col.data_type {
+                DataType::Named(name) => schema.data_type_for_name(name),
+                other => other,
+            };
+            check_policy(data_type, policy)
+                .with_context(|| format!("cannot mask column {:?}", column))?;
+            columns.insert(
+                column,
+                ColumnMask {
+                    policy,
+                    data_type: data_type.clone(),
+                },
+            );
+        }
+
+        let key = if columns.values().any(|m| m.policy.needs_key()) {
+            let key = env::var(MASK_KEY_VAR).map_err(|_| {
+                format_err!("{} must be set to use hmac or tokenize", MASK_KEY_VAR)
+            })?;
+            if key.is_empty() {
+                return Err(format_err!("{} must not be empty", MASK_KEY_VAR));
+            }
+            Some(Arc::new(key.into_bytes()))
+        } else {
+            None
+        };
+        Ok(Masks { columns, key })
+    }
+
+    /// Are there any columns to mask?
+    pub(crate) fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Update `schema` to describe our masked data.
+    pub(crate) fn masked_schema(&self, mut schema: Schema) -> Schema {
+        for col in &mut schema.table.columns {
+            if let Some(mask) = self.columns.get(&col.name) {
+                match mask.policy {
+                    MaskPolicy::Hmac | MaskPolicy::Tokenize => {
+                        col.data_type = DataType::Text
+                    }
+                    MaskPolicy::Null => col.is_nullable = true,
+                    MaskPolicy::TruncateDate(_) | MaskPolicy::RoundGeo(_) => {}
+                }
+            }
+        }
+        schema
+    }
+
+    /// Mask the columns in each of our CSV streams.
+    pub(crate) fn mask_csv_streams(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+    ) -> BoxStream<CsvStream> {
+        let masks = self.clone();
+        data.and_then(move |stream| {
+            let masks = masks.clone();
+            let result = spawn_sync_transform(
+                ctx.clone(),
+                "mask_csv".to_owned(),
+                stream.data,
+                move |_ctx, rdr, wtr| masks.mask_csv(rdr, wtr),
+            )
+            .map(|data| CsvStream {
+                name: stream.name,
+                data,
+            });
+            future::ready(result)
+        })
+        .boxed()
+    }
+
+    /// Synchronously mask a single CSV stream.
+    fn mask_csv(&self, rdr: impl Read, wtr: impl Write) -> Result<()> {
+        let mut rdr = csv::Reader::from_reader(rdr);
+        let mut wtr = csv::Writer::from_writer(wtr);
+
+        // Look up the mask for each column.
+        let headers = rdr.headers()?.clone();
+        let masks = headers
+            .iter()
+            .map(|name| self.columns.get(name))
+            .collect::<Vec<_>>();
+        wtr.write_record(&headers)?;
+
+        let mut row = csv::StringRecord::new();
+        let mut out = csv::StringRecord::new();
+        while rdr.read_record(&mut row)? {
+            out.clear();
+            for (cell, mask) in row.iter().zip(&masks) {
+                match mask {
+                    // Leave `NULL` values alone.
+                    Some(mask) if !cell.is_empty() => {
+                        let masked =
+                            self.mask_cell(mask, cell).with_context(|| {
+                                format!(
+                                    "cannot mask value on line {}",
+                                    row.position().map(|p| p.line()).unwrap_or(0)
+                                )
+                            })?;
+                        out.push_field(&masked);
+                    }
+                    _ => out.push_field(cell),
+                }
+            }
+            wtr.write_record(&out)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Mask a single non-empty cell.
+    fn mask_cell(&self, mask: &ColumnMask, cell: &str) -> Result<String> {
+        match mask.policy {
+            MaskPolicy::Hmac => Ok(hex::encode(self.hmac(&[cell.as_bytes()])?)),
+            MaskPolicy::Null => Ok(String::new()),
+            MaskPolicy::TruncateDate(unit) => {
+                truncate_date(&mask.data_type, unit, cell)
+            }
+            MaskPolicy::RoundGeo(digits) => round_geo(digits, cell),
+            MaskPolicy::Tokenize => self.tokenize(cell),
+        }
+    }
+
+    /// Compute an HMAC-SHA256 of the concatenation of `parts` using our key.
+    fn hmac(&self, parts: &[&[u8]]) -> Result<Vec<u8>> {
+        let key = self
+            .key
+            .as_ref()
+            .ok_or_else(|| format_err!("no masking key available"))?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(key)
+            .map_err(|err| format_err!("cannot compute HMAC: {}", err))?;
+        for part in parts {
+            mac.update(part);
+        }
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+
+    /// Replace letters and digits in `cell` with keyed pseudo-random letters
+    /// and digits. The same input always produces the same output.
+    fn tokenize(&self, cell: &str) -> Result<String> {
+        let mut bytes = vec![];
+        let mut counter = 0u32;
+        let mut out = String::with_capacity(cell.len());
+        for (idx, c) in cell.chars().enumerate() {
+            while bytes.len() <= idx {
+                bytes.extend(self.hmac(&[
+                    b"tokenize:",
+                    &counter.to_be_bytes(),
+                    cell.as_bytes(),
+                ])?);
+                counter += 1;
+            }
+            let b = bytes[idx];
+            out.push(match c {
+                '0'..='9' => char::from(b'0' + b % 10),
+                'a'..='z' => char::from(b'a' + b % 26),
+                'A'..='Z' => char::from(b'A' + b % 26),
+                other => other,
+            });
+        }
+        Ok(out)
+    }
+}
+
+/// Read masking policies from the `[mask]` table of a TOML file.
+fn read_mask_config(path: &Path) -> Result<Vec<(String, MaskPolicy)>> {
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    let doc = data
+        .parse::<DocumentMut>()
+        .with_context(|| format!("could not parse {}", path.display()))?;
+    let table = doc
+        .get("mask")
+        .and_then(|item| item.as_table_like())
+        .ok_or_else(|| format_err!("expected [mask] table in {}", path.display()))?;
+    table
+        .iter()
+        .map(|(column, item)| {
+            let policy = item.as_str().ok_or_else(|| {
+                format_err!("expected string for {:?} in {}", column, path.display())
+            })?;
+            Ok((column.to_owned(), policy.parse()?))
+        })
+        .collect()
+}
+
+/// Make sure `policy` can be applied to `data_type`.
+fn check_policy(data_type: &DataType, policy: MaskPolicy) -> Result<()> {
+    match (policy, data_type) {
+        (
+            MaskPolicy::TruncateDate(unit),
+            DataType::Date
+            | DataType::TimestampWithoutTimeZone
+            | DataType::TimestampWithTimeZone,
+        ) => {
+            if unit == DateUnit::Hour && *data_type == DataType::Date {
+                Err(format_err!("cannot truncate a date to the hour"))
+            } else {
+                Ok(())
+            }
+        }
+        (MaskPolicy::TruncateDate(_), _) => Err(format_err!(
+            "truncate-date only works with dates and timestamps"
+        )),
+        (MaskPolicy::RoundGeo(_), DataType::GeoJson(_)) => Ok(()),
+        (MaskPolicy::RoundGeo(_), _) => {
+            Err(format_err!("round-geo only works with GeoJSON columns"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Truncate a date or timestamp cell to `unit`.
+fn truncate_date(data_type: &DataType, unit: DateUnit, cell: &str) -> Result<String> {
+    let truncate = |dt: NaiveDateTime| -> NaiveDateTime {
+        let date = dt.date();
+        let date = match unit {
+            DateUnit::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1),
+            DateUnit::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1),
+            DateUnit::Day | DateUnit::Hour => Some(date),
+        }
+        .expect("truncated date should always be valid");
+        let time = match unit {
+            DateUnit::Hour => NaiveTime::from_hms_opt(dt.hour(), 0, 0),
+            _ => NaiveTime::from_hms_opt(0, 0, 0),
+        }
+        .expect("truncated time should always be valid");
+        date.and_time(time)
+    };
+    match data_type {
+        DataType::Date => {
+            let date = NaiveDate::from_csv_cell(cell)?;
+            Ok(truncate(date.and_time(NaiveTime::MIN))
+                .date()
+                .format("%Y-%m-%d")
+                .to_string())
+        }
+        DataType::TimestampWithoutTimeZone => {
+            let dt = NaiveDateTime::from_csv_cell(cell)?;
+            Ok(truncate(dt).format("%Y-%m-%dT%H:%M:%S").to_string())
+        }
+        DataType::TimestampWithTimeZone => {
+            let dt = DateTime::<Utc>::from_csv_cell(cell)?;
+            Ok(truncate(dt.naive_utc())
+                .format("%Y-%m-%dT%H:%M:%SZ")
+                .to_string())
+        }
+        _ => Err(format_err!("cannot truncate {:?}", data_type)),
+    }
+}
+
+/// Round all the coordinates in a GeoJSON cell to `digits` decimal places.
+fn round_geo(digits: u32, cell: &str) -> Result<String> {
+    fn round_numbers(value: &mut Value, scale: f64) {
+        match value {
+            Value::Number(n) => {
+                if let Some(f) = n.as_f64() {
+                    let rounded = (f * scale).round() / scale;
+                    if let Some(rounded) = serde_json::Number::from_f64(rounded) {
+                        *n = rounded;
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for v in values {
+                    round_numbers(v, scale);
+                }
+            }
+            _ => {}
+        }
+    }
+    fn round_geometry(value: &mut Value, scale: f64) {
+        match value {
+            Value::Object(obj) => {
+                for (key, v) in obj.iter_mut() {
+                    if key == "coordinates" || key == "bbox" {
+                        round_numbers(v, scale);
+                    } else {
+                        round_geometry(v, scale);
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for v in values {
+                    round_geometry(v, scale);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut json =
+        serde_json::from_str::<Value>(cell).context("cannot parse GeoJSON")?;
+    round_geometry(&mut json, 10f64.powi(i32::try_from(digits)?));
+    Ok(serde_json::to_string(&json)?)
+}
+
+#[test]
+fn parse_mask_policies() {
+    for policy in &[
+        "hmac",
+        "null",
+        "truncate-date:year",
+        "truncate-date:hour",
+        "round-geo:2",
+        "tokenize",
+    ] {
+        assert_eq!(policy.parse::<MaskPolicy>().unwrap().to_string(), *policy);
+    }
+    assert!("truncate-date".parse::<MaskPolicy>().is_err());
+    assert!("truncate-date:week".parse::<MaskPolicy>().is_err());
+    assert!("sha1".parse::<MaskPolicy>().is_err());
+}
+
+#[test]
+fn mask_cells() {
+    let masks = Masks {
+        columns: HashMap::new(),
+        key: Some(Arc::new(b"secret".to_vec())),
+    };
+    let mask = |policy: &str, data_type: DataType, cell: &str| {
+        let mask = ColumnMask {
+            policy: policy.parse().unwrap(),
+            data_type,
+        };
+        masks.mask_cell(&mask, cell).unwrap()
+    };
+
+    // Compatible with `openssl dgst -sha256 -hmac secret`.
+    let hashed = mask("hmac", DataType::Text, "alice@example.com");
+    assert_eq!(
+        hashed,
+        "a398d49ce1980b3642bc4dbd110121e3c953e1eadb497d50dea23e9611f83ee7"
+    );
+    assert_ne!(hashed, mask("hmac", DataType::Text, "bob@example.com"));
+
+    assert_eq!(mask("null", DataType::Text, "secret"), "");
+
+    assert_eq!(
+        mask("truncate-date:month", DataType::Date, "1990-05-17"),
+        "1990-05-01"
+    );
+    assert_eq!(
+        mask(
+            "truncate-date:hour",
+            DataType::TimestampWithTimeZone,
+            "2020-03-04T05:06:07+01:00"
+        ),
+        "2020-03-04T04:00:00Z"
+    );
+    assert_eq!(
+        mask(
+            "truncate-date:year",
+            DataType::TimestampWithoutTimeZone,
+            "2020-03-04 05:06:07"
+        ),
+        "2020-01-01T00:00:00"
+    );
+
+    assert_eq!(
+        mask(
+            "round-geo:2",
+            DataType::GeoJson(crate::schema::Srid::wgs84()),
+            r#"{"type":"Point","coordinates":[-71.06123,42.35789]}"#
+        ),
+        r#"{"coordinates":[-71.06,42.36],"type":"Point"}"#
+    );
+
+    let token = mask("tokenize", DataType::Text, "555-01-Ab");
+    assert_eq!(token.len(), 9);
+    assert_eq!(&token[3..4], "-");
+    assert_eq!(&token[6..7], "-");
+    assert!(token[..3].chars().all(|c| c.is_ascii_digit()));
+    assert!(token[7..8].chars().all(|c| c.is_ascii_uppercase()));
+    assert!(token[8..].chars().all(|c| c.is_ascii_lowercase()));
+    assert_eq!(token, mask("tokenize", DataType::Text, "555-01-Ab"));
+}
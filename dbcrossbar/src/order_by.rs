@@ -0,0 +1,268 @@
+//! Support for `--order-by`.
+
+use std::cmp::Ordering;
+
+use crate::common::*;
+use crate::concat::concatenate_csv_streams;
+use crate::external_sort::{external_sort, RowOrder, MAX_RUN_BYTES};
+use crate::filter::{cell_value, FilterType, Scalar};
+use crate::transform::spawn_sync_transform;
+
+/// A column to sort by.
+#[derive(Clone, Debug)]
+pub(crate) struct SortColumn {
+    /// The name of the column.
+    name: String,
+    /// Should we sort from largest to smallest?
+    descending: bool,
+    /// The type we use to compare values.
+    ty: FilterType,
+}
+
+impl SortColumn {
+    /// The name of the column.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Should we sort from largest to smallest?
+    pub(crate) fn is_descending(&self) -> bool {
+        self.descending
+    }
+
+    /// Should we sort this column as text? Databases need to be told to sort
+    /// text by byte value, so that they agree with us.
+    pub(crate) fn is_text(&self) -> bool {
+        self.ty == FilterType::Text
+    }
+}
+
+/// The order in which to output rows. `NULL` values always come last.
+#[derive(Clone, Debug)]
+pub struct OrderBy {
+    /// The columns to sort by, most important first.
+    columns: Vec<SortColumn>,
+}
+
+impl OrderBy {
+    /// Parse `--order-by`, which looks like `COL1,COL2 DESC`, and check it
+    /// against `schema`.
+    pub(crate) fn new(text: &str, schema: &Schema) -> Result<Self> {
+        let mut columns: Vec<SortColumn> = vec![];
+        for part in text.split(',') {
+            let part = part.trim();
+            let (name, descending) = match part.rsplit_once(char::is_whitespace) {
+                Some((name, dir)) if dir.eq_ignore_ascii_case("asc") => {
+                    (name.trim_end(), false)
+                }
+                Some((name, dir)) if dir.eq_ignore_ascii_case("desc") => {
+                    (name.trim_end(), true)
+                }
+                _ => (part, false),
+            };
+            if name.is_empty() {
+                return Err(format_err!("--order-by contains an empty column name"));
+            }
+            if columns.iter().any(|c| c.name == name) {
+                return Err(format_err!("--order-by lists {:?} twice", name));
+            }
+            let column = schema
+                .table
+                .columns
+                .iter()
+                .find(|c| c.name == name)
+                .ok_or_else(|| {
+                    format_err!("invalid --order-by: unknown column {:?}", name)
+                })?;
+            let ty = match FilterType::for_data_type(schema, &column.data_type) {
+                FilterType::Other => {
+                    return Err(format_err!(
+                        "cannot sort by column {:?} of type {}",
+                        name,
+                        FilterType::Other,
+                    ))
+                }
+                ty => ty,
+            };
+            columns.push(SortColumn {
+                name: name.to_owned(),
+                descending,
+                ty,
+            });
+        }
+        Ok(OrderBy { columns })
+    }
+
+    /// The columns to sort by, most important first.
+    pub(crate) fn columns(&self) -> &[SortColumn] {
+        &self.columns
+    }
+
+    /// The names of the columns we sort by.
+    pub(crate) fn column_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.columns.iter().map(|c| c.name.as_str())
+    }
+
+    /// Synchronously sort a single CSV stream.
+    fn sort_csv(
+        &self,
+        rdr: impl Read,
+        wtr: impl Write,
+        max_run_bytes: usize,
+    ) -> Result<()> {
+        let mut rdr = csv::Reader::from_reader(rdr);
+        let mut wtr = csv::Writer::from_writer(wtr);
+
+        // Look up the columns we need.
+        let headers = rdr.headers()?.clone();
+        let indices = self
+            .column_names()
+            .map(|name| {
+                headers.iter().position(|h| h == name).ok_or_else(|| {
+                    format_err!("cannot find column {:?} in CSV data", name)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let order = SortOrder {
+            order_by: self,
+            indices,
+        };
+        wtr.write_record(&headers)?;
+
+        let rows = rdr.records().map(|row| {
+            let row = row?;
+            order.check_values(&row)?;
+            Ok(row)
+        });
+        external_sort(rows, &order, max_run_bytes, &mut wtr)?;
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Sorts rows according to an [`OrderBy`].
+struct SortOrder<'a> {
+    /// The order we want.
+    order_by: &'a OrderBy,
+    /// The CSV index of each column in `order_by`.
+    indices: Vec<usize>,
+}
+
+impl SortOrder<'_> {
+    /// Make sure we can parse all the values we sort by, so that `compare`
+    /// won't need to report errors.
+    fn check_values(&self, row: &csv::StringRecord) -> Result<()> {
+        for (column, &i) in self.order_by.columns.iter().zip(&self.indices) {
+            if !row[i].is_empty() {
+                cell_value(&row[i], column.ty).with_context(|| {
+                    format!("cannot sort by column {:?}", column.name)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the value of `column`, or `None` if it's `NULL`.
+    fn value<'a>(
+        column: &SortColumn,
+        i: usize,
+        row: &'a csv::StringRecord,
+    ) -> Option<Scalar<'a>> {
+        if row[i].is_empty() {
+            None
+        } else {
+            cell_value(&row[i], column.ty).ok()
+        }
+    }
+}
+
+impl RowOrder for SortOrder<'_> {
+    fn compare(&self, a: &csv::StringRecord, b: &csv::StringRecord) -> Ordering {
+        for (column, &i) in self.order_by.columns.iter().zip(&self.indices) {
+            let ordering = match (Self::value(column, i, a), Self::value(column, i, b))
+            {
+                (Some(a), Some(b)) => {
+                    let ordering = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+                    if column.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                }
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+/// Sort all the rows in `data`. This combines all our streams into one.
+pub(crate) fn sort_csv_streams(
+    ctx: Context,
+    order_by: OrderBy,
+    data: BoxStream<CsvStream>,
+) -> Result<BoxStream<CsvStream>> {
+    let input = concatenate_csv_streams(ctx.clone(), data)?;
+    let data = spawn_sync_transform(
+        ctx,
+        "sort_csv".to_owned(),
+        input.data,
+        move |_ctx, rdr, wtr| order_by.sort_csv(rdr, wtr, MAX_RUN_BYTES),
+    )?;
+    Ok(box_stream_once(Ok(CsvStream {
+        name: "sorted".to_owned(),
+        data,
+    })))
+}
+
+#[test]
+fn sort_csv_with_and_without_runs() {
+    use std::io::Cursor;
+
+    let schema = crate::filter::test_schema();
+    let input = "\
+id,name,seen_at
+10,b,
+9,a,2024-01-01 00:00:00Z
+10,a,2024-03-01 00:00:00Z
+,c,2024-02-01 00:00:00+02:00
+9,b,2024-02-01 00:00:00Z
+";
+    let examples = &[
+        ("id", "9,a\n9,b\n10,b\n10,a\n,c\n"),
+        ("id DESC, name", "10,a\n10,b\n9,a\n9,b\n,c\n"),
+        ("seen_at desc", "10,a\n9,b\n,c\n9,a\n10,b\n"),
+    ];
+    for &(text, expected) in examples {
+        let order_by = OrderBy::new(text, &schema).unwrap();
+        // A tiny `max_run_bytes` forces us to write every row to a separate
+        // run.
+        for max_run_bytes in [MAX_RUN_BYTES, 1] {
+            let mut out = vec![];
+            order_by
+                .sort_csv(Cursor::new(input.as_bytes()), &mut out, max_run_bytes)
+                .unwrap();
+            let mut rdr = csv::Reader::from_reader(&out[..]);
+            let rows = rdr
+                .records()
+                .map(|r| {
+                    let r = r.unwrap();
+                    format!("{},{}\n", &r[0], &r[1])
+                })
+                .collect::<String>();
+            assert_eq!(
+                rows, expected,
+                "order by: {:?}, max_run_bytes: {}",
+                text, max_run_bytes
+            );
+        }
+    }
+    assert!(OrderBy::new("id,id", &schema).is_err());
+    assert!(OrderBy::new("tags", &schema).is_err());
+    assert!(OrderBy::new("missing DESC", &schema).is_err());
+}
@@ -0,0 +1,559 @@
+//! Hive-style `col=value/` partition directories. We write these for
+//! `--partition-by`, and read them when copying from a directory.
+
+use futures::executor::block_on;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::common::*;
+use crate::concat::concatenate_csv_streams;
+use crate::filter::{Filter, FilterType};
+use crate::tokio_glue::{SyncStreamReader, SyncStreamWriter};
+use crate::transform::spawn_sync_transform;
+
+/// The directory name used for `NULL` partition values. This is what Hive and
+/// Spark use.
+const NULL_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// Split rows into `col=value/` directories, using one or more columns.
+#[derive(Clone, Debug)]
+pub struct PartitionBy {
+    /// The columns to partition by, outermost directory first.
+    columns: Vec<String>,
+}
+
+impl PartitionBy {
+    /// Parse `--partition-by`, which looks like `COL1,COL2`, and check it
+    /// against `schema`.
+    pub(crate) fn new(text: &str, schema: &Schema) -> Result<Self> {
+        let mut columns: Vec<String> = vec![];
+        for name in text.split(',').map(|name| name.trim()) {
+            if name.is_empty() {
+                return Err(format_err!(
+                    "--partition-by contains an empty column name"
+                ));
+            }
+            if columns.iter().any(|c| c == name) {
+                return Err(format_err!("--partition-by lists {:?} twice", name));
+            }
+            let column = schema
+                .table
+                .columns
+                .iter()
+                .find(|c| c.name == name)
+                .ok_or_else(|| {
+                    format_err!("invalid --partition-by: unknown column {:?}", name)
+                })?;
+            if FilterType::for_data_type(schema, &column.data_type)
+                == FilterType::Other
+            {
+                return Err(format_err!(
+                    "cannot partition by column {:?} of type {}",
+                    name,
+                    FilterType::Other,
+                ));
+            }
+            columns.push(name.to_owned());
+        }
+        if columns.len() == schema.table.columns.len() {
+            return Err(format_err!(
+                "--partition-by must leave at least one column in the data files"
+            ));
+        }
+        Ok(PartitionBy { columns })
+    }
+
+    /// The schema of our data files. These don't include our partition
+    /// columns, because those values are stored in the directory names.
+    pub(crate) fn data_schema(&self, schema: &Schema) -> Schema {
+        let mut schema = schema.to_owned();
+        schema
+            .table
+            .columns
+            .retain(|c| !self.columns.contains(&c.name));
+        schema
+    }
+
+    /// The directory for a row with the specified partition `values`, without
+    /// a trailing `/`.
+    fn partition_dir<'a>(&self, values: impl Iterator<Item = &'a str>) -> String {
+        self.columns
+            .iter()
+            .zip(values)
+            .map(|(column, value)| {
+                let value = if value.is_empty() {
+                    NULL_PARTITION.to_owned()
+                } else {
+                    escape_path_component(value)
+                };
+                format!("{}={}", escape_path_component(column), value)
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Escape `s` for use as a key or value in a partition directory, the same way
+/// Hive does.
+fn escape_path_component(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\u{01}'..='\u{1F}'
+            | '\u{7F}'
+            | '"'
+            | '#'
+            | '%'
+            | '\''
+            | '*'
+            | '/'
+            | ':'
+            | '='
+            | '?'
+            | '\\'
+            | '['
+            | ']'
+            | '^'
+            | '{' => escaped.push_str(&format!("%{:02X}", c as u32)),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Undo [`escape_path_component`].
+fn unescape_path_component(s: &str) -> Result<String> {
+    let bytes = s.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| {
+                    format_err!("invalid escape sequence in partition {:?}", s)
+                })?;
+            unescaped.push(byte);
+            i += 3;
+        } else {
+            unescaped.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(unescaped)
+        .map_err(|_| format_err!("invalid UTF-8 in partition {:?}", s))
+}
+
+/// Hive-style partition values, parsed from the `col=value` directories in a
+/// stream name like `year=2024/month=3/part-0001`.
+#[derive(Clone, Debug, Default)]
+struct PartitionValues {
+    /// Column names and values. `NULL` values are represented by empty
+    /// strings, as in CSV.
+    values: Vec<(String, String)>,
+}
+
+impl PartitionValues {
+    /// Parse the partition values in a stream name. Directories which don't
+    /// contain `=` are ignored.
+    fn from_stream_name(name: &str) -> Result<Self> {
+        let mut values: Vec<(String, String)> = vec![];
+        let mut components = name.split('/');
+        // The last component is a file name, not a directory.
+        components.next_back();
+        for dir in components {
+            let Some((column, value)) = dir.split_once('=') else {
+                continue;
+            };
+            let column = unescape_path_component(column)?;
+            let value = if value == NULL_PARTITION {
+                String::new()
+            } else {
+                unescape_path_component(value)?
+            };
+            if values.iter().any(|(c, _)| *c == column) {
+                return Err(format_err!(
+                    "partition column {:?} appears twice in {:?}",
+                    column,
+                    name,
+                ));
+            }
+            values.push((column, value));
+        }
+        Ok(PartitionValues { values })
+    }
+
+    /// Did we find any partition values?
+    fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Could any rows in this partition match `filter`? We only know the
+    /// values of our partition columns, so we return `true` unless those are
+    /// enough to rule out every row.
+    fn might_match(&self, filter: &Filter) -> Result<bool> {
+        Ok(filter.eval_partial(&self.values)? != Some(false))
+    }
+
+    /// Add our partition values to `stream` as columns. The output contains the
+    /// columns in `schema`, in order. Partition values replace any values
+    /// with the same name in the data file.
+    fn add_to_csv_stream(
+        self,
+        ctx: Context,
+        schema: &Schema,
+        stream: CsvStream,
+    ) -> Result<CsvStream> {
+        let columns = schema
+            .table
+            .columns
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>();
+        let name = stream.name.clone();
+        let data = spawn_sync_transform(
+            ctx,
+            "add_partition_columns".to_owned(),
+            stream.data,
+            move |_ctx, rdr, wtr| self.add_columns(&name, &columns, rdr, wtr),
+        )?;
+        Ok(CsvStream {
+            name: stream.name,
+            data,
+        })
+    }
+
+    /// Synchronously add our partition values to a CSV stream.
+    fn add_columns(
+        &self,
+        stream_name: &str,
+        columns: &[String],
+        rdr: impl Read,
+        wtr: impl Write,
+    ) -> Result<()> {
+        let mut rdr = csv::Reader::from_reader(rdr);
+        let mut wtr = csv::Writer::from_writer(wtr);
+
+        /// Where we get the value of each output column.
+        enum Source<'a> {
+            /// A column in the data file.
+            File(usize),
+            /// A partition value.
+            Partition(&'a str),
+        }
+
+        let headers = rdr.headers()?.clone();
+        let sources = columns
+            .iter()
+            .map(|column| {
+                if let Some((_, value)) = self.values.iter().find(|(c, _)| c == column)
+                {
+                    Ok(Source::Partition(value))
+                } else if let Some(i) = headers.iter().position(|h| h == column) {
+                    Ok(Source::File(i))
+                } else {
+                    Err(format_err!(
+                        "cannot find column {:?} in {} or its partition directories",
+                        column,
+                        stream_name,
+                    ))
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        wtr.write_record(columns)?;
+
+        let mut row = csv::StringRecord::new();
+        while rdr.read_record(&mut row)? {
+            wtr.write_record(sources.iter().map(|source| match source {
+                Source::File(i) => &row[*i],
+                Source::Partition(value) => value,
+            }))?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Should we read the file for the stream `name`? This returns `false` if
+/// `name` is in a Hive-style partition directory which can't match
+/// `partition_filter`.
+pub(crate) fn should_read_partition(
+    name: &str,
+    partition_filter: Option<&Filter>,
+) -> Result<bool> {
+    match partition_filter {
+        Some(filter) => PartitionValues::from_stream_name(name)?.might_match(filter),
+        None => Ok(true),
+    }
+}
+
+/// If `stream` was read from a Hive-style partition directory, add the
+/// partition values as columns, so that it matches `schema`.
+pub(crate) fn add_partition_columns(
+    ctx: Context,
+    schema: &Schema,
+    stream: CsvStream,
+) -> Result<CsvStream> {
+    let values = PartitionValues::from_stream_name(&stream.name)?;
+    if values.is_empty() {
+        Ok(stream)
+    } else {
+        values.add_to_csv_stream(ctx, schema, stream)
+    }
+}
+
+/// Split `streams` into one stream per partition, named like
+/// `col=value/part-0001`. Partition columns are removed from the output.
+///
+/// We keep at most `max_open_partitions` output streams open at once. If we
+/// need to write to another partition, we close the one we used least
+/// recently, and if we see its values again, we start a new `part-NNNN` stream
+/// in the same directory. The caller must consume at least
+/// `max_open_partitions` streams in parallel.
+pub(crate) fn partition_csv_streams(
+    ctx: Context,
+    partition_by: PartitionBy,
+    max_open_partitions: usize,
+    streams: BoxStream<CsvStream>,
+) -> Result<BoxStream<CsvStream>> {
+    let max_open_partitions = max_open_partitions.max(1);
+    let input_csv_stream = concatenate_csv_streams(ctx.clone(), streams)?;
+    let csv_rdr = SyncStreamReader::new(input_csv_stream.data);
+
+    // Create a channel to which we can write `CsvStream` values once we've
+    // created them.
+    let (csv_stream_sender, csv_stream_receiver) =
+        mpsc::channel::<Result<CsvStream>>(1);
+
+    let worker_fut = spawn_blocking(move || -> Result<()> {
+        let mut rdr = csv::Reader::from_reader(csv_rdr);
+        let headers = rdr.headers().context("cannot read CSV header")?.to_owned();
+        let indices = partition_by
+            .columns
+            .iter()
+            .map(|name| {
+                headers.iter().position(|h| h == name).ok_or_else(|| {
+                    format_err!("cannot find column {:?} in CSV data", name)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let data_columns = |row: &csv::StringRecord| -> csv::StringRecord {
+            row.iter()
+                .enumerate()
+                .filter(|(i, _)| !indices.contains(i))
+                .map(|(_, value)| value)
+                .collect()
+        };
+        let data_headers = data_columns(&headers);
+
+        /// An output stream for a single partition.
+        struct OpenPartition {
+            /// Write to this to add data to the partition.
+            wtr: csv::Writer<SyncStreamWriter>,
+            /// The number of the last input row we wrote.
+            last_used: u64,
+        }
+
+        // Our open partitions, and the last part number used for each
+        // partition directory.
+        let mut open: HashMap<String, OpenPartition> = HashMap::new();
+        let mut part_numbers: HashMap<String, usize> = HashMap::new();
+
+        let mut row_number: u64 = 0;
+        let mut row = csv::StringRecord::new();
+        while rdr.read_record(&mut row).context("cannot read row")? {
+            row_number += 1;
+            let dir = partition_by.partition_dir(indices.iter().map(|&i| &row[i]));
+            if !open.contains_key(&dir) {
+                // Make room for a new partition.
+                if open.len() >= max_open_partitions {
+                    let lru = open
+                        .iter()
+                        .min_by_key(|(_, partition)| partition.last_used)
+                        .map(|(dir, _)| dir.to_owned())
+                        .expect("should have an open partition");
+                    trace!("closing partition {}", lru);
+                    let mut partition = open.remove(&lru).expect("should be open");
+                    partition.wtr.flush().context("cannot close partition")?;
+                }
+
+                // Send our new `CsvStream` before writing any data, so that
+                // somebody can start consuming it.
+                let part_number = part_numbers.entry(dir.clone()).or_default();
+                *part_number += 1;
+                let name = format!("{}/part-{:04}", dir, part_number);
+                trace!("opening partition stream {}", name);
+                let (wtr, data) = SyncStreamWriter::pipe();
+                block_on(csv_stream_sender.send(Ok(CsvStream {
+                    name,
+                    data: data.boxed(),
+                })))
+                .map_send_err()?;
+                let mut wtr = csv::Writer::from_writer(wtr);
+                wtr.write_record(&data_headers)
+                    .context("cannot write partition header")?;
+                open.insert(dir.clone(), OpenPartition { wtr, last_used: 0 });
+            }
+
+            let partition = open.get_mut(&dir).expect("should be open");
+            partition.last_used = row_number;
+            partition
+                .wtr
+                .write_record(&data_columns(&row))
+                .context("cannot write row")?;
+        }
+        for (_, mut partition) in open {
+            partition.wtr.flush().context("cannot close partition")?;
+        }
+        trace!("finished partitioning CSV data");
+        Ok(())
+    })
+    .instrument(debug_span!("partition_csvs"));
+    ctx.spawn_worker(debug_span!("partition"), worker_fut.boxed());
+
+    Ok(ReceiverStream::new(csv_stream_receiver).boxed())
+}
+
+#[test]
+fn escape_path_component_matches_hive() {
+    assert_eq!(escape_path_component("plain value"), "plain value");
+    assert_eq!(
+        escape_path_component("2024-01-01 12:00:00"),
+        "2024-01-01 12%3A00%3A00"
+    );
+    assert_eq!(escape_path_component("a/b=c%"), "a%2Fb%3Dc%25");
+}
+
+#[test]
+fn partition_values_round_trip() {
+    let partition_by = PartitionBy {
+        columns: vec!["seen_at".to_owned(), "name".to_owned(), "id".to_owned()],
+    };
+    let dir =
+        partition_by.partition_dir(["2024-01-01 12:00:00Z", "a/b=c%", ""].into_iter());
+    let values =
+        PartitionValues::from_stream_name(&format!("plain/{}/part-0001", dir))
+            .unwrap();
+    assert_eq!(
+        values.values,
+        &[
+            ("seen_at".to_owned(), "2024-01-01 12:00:00Z".to_owned()),
+            ("name".to_owned(), "a/b=c%".to_owned()),
+            ("id".to_owned(), String::new()),
+        ]
+    );
+    assert!(PartitionValues::from_stream_name("part-0001")
+        .unwrap()
+        .is_empty());
+    assert!(PartitionValues::from_stream_name("a=%zz/part-0001").is_err());
+    assert!(PartitionValues::from_stream_name("a=1/a=2/part-0001").is_err());
+}
+
+#[test]
+fn partition_values_add_columns() {
+    use std::io::Cursor;
+
+    let values = PartitionValues::from_stream_name("id=7/name=x/part-0001").unwrap();
+    let columns = ["id", "name", "seen_at"]
+        .iter()
+        .map(|&c| c.to_owned())
+        .collect::<Vec<_>>();
+    let mut out = vec![];
+    values
+        .add_columns(
+            "test",
+            &columns,
+            Cursor::new("seen_at,name\n2024-01-01,y\n,\n"),
+            &mut out,
+        )
+        .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "id,name,seen_at\n7,x,2024-01-01\n7,x,\n"
+    );
+
+    let columns = vec!["missing".to_owned()];
+    assert!(values
+        .add_columns("test", &columns, Cursor::new("a\n1\n"), &mut vec![])
+        .is_err());
+}
+
+#[tokio::test]
+async fn partition_csv_streams_closes_least_recently_used() {
+    use std::str;
+
+    let schema = crate::filter::test_schema();
+    let input = "\
+id,name,seen_at
+1,a,2024-01-01 00:00:00Z
+2,b,
+1,c,
+,d,
+2,e,
+";
+    let examples: &[(usize, &[(&str, &str)])] = &[
+        (
+            4,
+            &[
+                (
+                    "id=1/part-0001",
+                    "name,seen_at\na,2024-01-01 00:00:00Z\nc,\n",
+                ),
+                ("id=2/part-0001", "name,seen_at\nb,\ne,\n"),
+                (
+                    "id=__HIVE_DEFAULT_PARTITION__/part-0001",
+                    "name,seen_at\nd,\n",
+                ),
+            ],
+        ),
+        (
+            2,
+            &[
+                (
+                    "id=1/part-0001",
+                    "name,seen_at\na,2024-01-01 00:00:00Z\nc,\n",
+                ),
+                ("id=2/part-0001", "name,seen_at\nb,\n"),
+                (
+                    "id=__HIVE_DEFAULT_PARTITION__/part-0001",
+                    "name,seen_at\nd,\n",
+                ),
+                ("id=2/part-0002", "name,seen_at\ne,\n"),
+            ],
+        ),
+    ];
+
+    for &(max_open_partitions, expected) in examples {
+        let (ctx, worker_fut) = Context::create();
+        let partition_by = PartitionBy::new("id", &schema).unwrap();
+        let cmd_fut = async move {
+            let streams =
+                box_stream_once(Ok(CsvStream::from_bytes(input.as_bytes()).await));
+            let outputs =
+                partition_csv_streams(ctx, partition_by, max_open_partitions, streams)
+                    .unwrap()
+                    .map_ok(|stream| async move {
+                        let name = stream.name.clone();
+                        let bytes = stream.into_bytes().await?;
+                        Ok((name, str::from_utf8(&bytes)?.to_owned()))
+                    })
+                    .try_buffered(max_open_partitions)
+                    .try_collect::<Vec<_>>()
+                    .await
+                    .unwrap();
+            let expected = expected
+                .iter()
+                .map(|&(name, data)| (name.to_owned(), data.to_owned()))
+                .collect::<Vec<_>>();
+            assert_eq!(outputs, expected);
+            Ok(())
+        };
+        try_join!(cmd_fut, worker_fut).unwrap();
+    }
+
+    assert!(PartitionBy::new("missing", &schema).is_err());
+    assert!(PartitionBy::new("id,id", &schema).is_err());
+    assert!(PartitionBy::new("tags", &schema).is_err());
+}
@@ -0,0 +1,418 @@
+//! Pipeline files for `dbcrossbar run`.
+//!
+//! A pipeline file is a TOML file containing named copy jobs:
+//!
+//! ```toml
+//! [jobs.users]
+//! from = "postgres://localhost:5432/db#users"
+//! to = "bigquery:project:dataset.users"
+//! if_exists = "overwrite"
+//! temporary = ["gs://bucket/temp/", "bigquery:project:temp"]
+//!
+//! [jobs.user_stats]
+//! from = "bigquery:project:dataset.user_stats_view"
+//! to = "csv:user_stats.csv"
+//! depends_on = ["users"]
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+use crate::common::*;
+
+/// The keys we allow in a job.
+const JOB_KEYS: &[&str] = &[
+    "from",
+    "to",
+    "schema",
+    "if_exists",
+    "from_args",
+    "to_args",
+    "temporary",
+    "depends_on",
+    "args",
+];
+
+/// A single copy job in a pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Job {
+    /// The name of this job.
+    pub(crate) name: String,
+    /// The source locator.
+    from: String,
+    /// The destination locator.
+    to: String,
+    /// The schema locator, if any.
+    schema: Option<String>,
+    /// What to do if the destination exists.
+    if_exists: Option<String>,
+    /// Arguments for the source driver, as `key=value` strings.
+    from_args: Vec<String>,
+    /// Arguments for the destination driver, as `key=value` strings.
+    to_args: Vec<String>,
+    /// Temporary storage locations.
+    temporaries: Vec<String>,
+    /// Jobs which must succeed before this one can run.
+    pub(crate) depends_on: Vec<String>,
+    /// Extra arguments to pass to `cp`.
+    args: Vec<String>,
+}
+
+impl Job {
+    /// Parse a job from a TOML table.
+    fn from_table(name: &str, table: &dyn TableLike) -> Result<Self> {
+        for (key, _) in table.iter() {
+            if !JOB_KEYS.contains(&key) {
+                return Err(format_err!("unknown key {:?} in job {:?}", key, name));
+            }
+        }
+        let string = |key: &str| -> Result<Option<String>> {
+            table
+                .get(key)
+                .map(|item| {
+                    item.as_str().map(|s| s.to_owned()).ok_or_else(|| {
+                        format_err!(
+                            "expected {:?} in job {:?} to be a string",
+                            key,
+                            name
+                        )
+                    })
+                })
+                .transpose()
+        };
+        let required = |key: &str| -> Result<String> {
+            string(key)?
+                .ok_or_else(|| format_err!("job {:?} must have a {:?}", name, key))
+        };
+        let strings = |key: &str| -> Result<Vec<String>> {
+            match table.get(key) {
+                None => Ok(vec![]),
+                Some(item) => item
+                    .as_array()
+                    .and_then(|array| {
+                        array
+                            .iter()
+                            .map(|v| v.as_str().map(|s| s.to_owned()))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        format_err!(
+                            "expected {:?} in job {:?} to be a list of strings",
+                            key,
+                            name,
+                        )
+                    }),
+            }
+        };
+        let driver_args = |key: &str| -> Result<Vec<String>> {
+            match table.get(key) {
+                None => Ok(vec![]),
+                Some(item) => {
+                    let args = item.as_table_like().ok_or_else(|| {
+                        format_err!(
+                            "expected {:?} in job {:?} to be a table",
+                            key,
+                            name
+                        )
+                    })?;
+                    args.iter()
+                        .map(|(arg, item)| {
+                            Ok(format!(
+                                "{}={}",
+                                arg,
+                                driver_arg_value(name, arg, item)?
+                            ))
+                        })
+                        .collect()
+                }
+            }
+        };
+
+        Ok(Job {
+            name: name.to_owned(),
+            from: required("from")?,
+            to: required("to")?,
+            schema: string("schema")?,
+            if_exists: string("if_exists")?,
+            from_args: driver_args("from_args")?,
+            to_args: driver_args("to_args")?,
+            temporaries: strings("temporary")?,
+            depends_on: strings("depends_on")?,
+            args: strings("args")?,
+        })
+    }
+
+    /// Build the command-line arguments for running this job using `cp`,
+    /// starting with the command name.
+    pub(crate) fn cp_args(&self) -> Vec<String> {
+        let mut args = vec!["cp".to_owned()];
+        if let Some(if_exists) = &self.if_exists {
+            args.push(format!("--if-exists={}", if_exists));
+        }
+        if let Some(schema) = &self.schema {
+            args.push(format!("--schema={}", schema));
+        }
+        for temporary in &self.temporaries {
+            args.push(format!("--temporary={}", temporary));
+        }
+        for from_arg in &self.from_args {
+            args.push(format!("--from-arg={}", from_arg));
+        }
+        for to_arg in &self.to_args {
+            args.push(format!("--to-arg={}", to_arg));
+        }
+        args.extend(self.args.iter().cloned());
+        // Make sure that locators starting with `-` aren't treated as flags.
+        args.push("--".to_owned());
+        args.push(self.from.clone());
+        args.push(self.to.clone());
+        args
+    }
+}
+
+/// Convert a driver argument to a string.
+fn driver_arg_value(job: &str, arg: &str, item: &Item) -> Result<String> {
+    match item.as_value() {
+        Some(Value::String(s)) => Ok(s.value().to_owned()),
+        Some(Value::Integer(i)) => Ok(i.value().to_string()),
+        Some(Value::Float(f)) => Ok(f.value().to_string()),
+        Some(Value::Boolean(b)) => Ok(b.value().to_string()),
+        _ => Err(format_err!(
+            "expected {:?} in job {:?} to be a string, number or boolean",
+            arg,
+            job,
+        )),
+    }
+}
+
+/// A pipeline of copy jobs.
+#[derive(Clone, Debug)]
+pub(crate) struct Pipeline {
+    /// Our jobs, in the order they appear in the file.
+    jobs: Vec<Job>,
+}
+
+impl Pipeline {
+    /// Load the pipeline file at `path`.
+    pub(crate) fn from_path(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        Self::from_toml(&data)
+            .with_context(|| format!("could not parse {}", path.display()))
+    }
+
+    /// Parse a pipeline from TOML source.
+    fn from_toml(data: &str) -> Result<Self> {
+        let doc = data.parse::<DocumentMut>()?;
+        let jobs_table = doc
+            .get("jobs")
+            .and_then(|item| item.as_table_like())
+            .ok_or_else(|| format_err!("expected a [jobs] table"))?;
+        let jobs = jobs_table
+            .iter()
+            .map(|(name, item)| {
+                let table = item.as_table_like().ok_or_else(|| {
+                    format_err!("expected job {:?} to be a table", name)
+                })?;
+                Job::from_table(name, table)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let pipeline = Pipeline { jobs };
+        pipeline.check_dependencies()?;
+        Ok(pipeline)
+    }
+
+    /// Make sure all our dependencies exist, and that there are no cycles.
+    fn check_dependencies(&self) -> Result<()> {
+        let jobs = self
+            .jobs
+            .iter()
+            .map(|job| (job.name.as_str(), job))
+            .collect::<HashMap<_, _>>();
+        for job in &self.jobs {
+            for dep in &job.depends_on {
+                if !jobs.contains_key(dep.as_str()) {
+                    return Err(format_err!(
+                        "job {:?} depends on unknown job {:?}",
+                        job.name,
+                        dep
+                    ));
+                }
+            }
+        }
+
+        // Depth-first search, keeping track of the jobs on our current path.
+        fn visit<'a>(
+            job: &'a Job,
+            jobs: &HashMap<&str, &'a Job>,
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> Result<()> {
+            if done.contains(job.name.as_str()) {
+                return Ok(());
+            }
+            if path.contains(&job.name.as_str()) {
+                path.push(&job.name);
+                return Err(format_err!(
+                    "jobs have a dependency cycle: {}",
+                    path.join(" -> ")
+                ));
+            }
+            path.push(&job.name);
+            for dep in &job.depends_on {
+                visit(jobs[dep.as_str()], jobs, path, done)?;
+            }
+            path.pop();
+            done.insert(&job.name);
+            Ok(())
+        }
+        let mut done = HashSet::new();
+        for job in &self.jobs {
+            visit(job, &jobs, &mut vec![], &mut done)?;
+        }
+        Ok(())
+    }
+
+    /// Choose which jobs to run.
+    ///
+    /// If `only` is non-empty, we run exactly those jobs. If `from` is
+    /// specified, we run that job and every job which depends on it, directly
+    /// or indirectly. Otherwise, we run all jobs.
+    pub(crate) fn select_jobs(
+        &self,
+        only: &[String],
+        from: Option<&str>,
+    ) -> Result<Vec<Job>> {
+        let names = self
+            .jobs
+            .iter()
+            .map(|job| job.name.as_str())
+            .collect::<HashSet<_>>();
+        for name in only.iter().map(|s| s.as_str()).chain(from) {
+            if !names.contains(name) {
+                return Err(format_err!("unknown job {:?}", name));
+            }
+        }
+
+        let selected = if !only.is_empty() {
+            only.iter().map(|s| s.as_str()).collect::<HashSet<_>>()
+        } else if let Some(from) = from {
+            // Keep adding downstream jobs until nothing changes.
+            let mut selected = HashSet::from([from]);
+            loop {
+                let before = selected.len();
+                for job in &self.jobs {
+                    if job.depends_on.iter().any(|d| selected.contains(d.as_str())) {
+                        selected.insert(job.name.as_str());
+                    }
+                }
+                if selected.len() == before {
+                    break;
+                }
+            }
+            selected
+        } else {
+            names
+        };
+        Ok(self
+            .jobs
+            .iter()
+            .filter(|job| selected.contains(job.name.as_str()))
+            .cloned()
+            .collect())
+    }
+}
+
+#[test]
+fn parse_pipeline() {
+    let pipeline = Pipeline::from_toml(
+        r#"
+[jobs.users]
+from = "postgres://localhost:5432/db#users"
+to = "bigquery:project:dataset.users"
+if_exists = "overwrite"
+temporary = ["gs://bucket/temp/"]
+from_args = { batch = 10 }
+args = ["--where=id > 10"]
+
+[jobs.stats]
+from = "bigquery:project:dataset.users"
+to = "csv:stats.csv"
+depends_on = ["users"]
+
+[jobs.report]
+from = "csv:stats.csv"
+to = "csv:report.csv"
+depends_on = ["stats"]
+
+[jobs.other]
+from = "csv:other.csv"
+to = "csv:other_copy.csv"
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        pipeline.jobs[0].cp_args(),
+        vec![
+            "cp",
+            "--if-exists=overwrite",
+            "--temporary=gs://bucket/temp/",
+            "--from-arg=batch=10",
+            "--where=id > 10",
+            "--",
+            "postgres://localhost:5432/db#users",
+            "bigquery:project:dataset.users",
+        ],
+    );
+
+    let names = |jobs: Vec<Job>| jobs.into_iter().map(|j| j.name).collect::<Vec<_>>();
+    assert_eq!(
+        names(pipeline.select_jobs(&[], None).unwrap()),
+        vec!["users", "stats", "report", "other"],
+    );
+    assert_eq!(
+        names(pipeline.select_jobs(&[], Some("stats")).unwrap()),
+        vec!["stats", "report"],
+    );
+    assert_eq!(
+        names(pipeline.select_jobs(&["other".to_owned()], None).unwrap()),
+        vec!["other"],
+    );
+    assert!(pipeline.select_jobs(&["missing".to_owned()], None).is_err());
+}
+
+#[test]
+fn reject_invalid_pipelines() {
+    let cycle = r#"
+[jobs.a]
+from = "csv:a.csv"
+to = "csv:b.csv"
+depends_on = ["b"]
+
+[jobs.b]
+from = "csv:b.csv"
+to = "csv:a.csv"
+depends_on = ["a"]
+"#;
+    let err = Pipeline::from_toml(cycle).unwrap_err();
+    assert!(err.to_string().contains("a -> b -> a"));
+
+    let unknown_dep = r#"
+[jobs.a]
+from = "csv:a.csv"
+to = "csv:b.csv"
+depends_on = ["missing"]
+"#;
+    assert!(Pipeline::from_toml(unknown_dep).is_err());
+
+    let typo = r#"
+[jobs.a]
+form = "csv:a.csv"
+to = "csv:b.csv"
+"#;
+    assert!(Pipeline::from_toml(typo).is_err());
+}
@@ -0,0 +1,219 @@
+//! Support for `--select` and `--rename`.
+
+use std::collections::{HashMap, HashSet};
+
+use futures::future;
+
+use crate::common::*;
+use crate::transform::spawn_sync_transform;
+
+/// Which columns to copy, and what to call them in the destination.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Projection {
+    /// The source columns to copy, in order, or `None` to copy all columns.
+    select: Option<Vec<String>>,
+    /// A map from source column names to destination column names.
+    renames: HashMap<String, String>,
+}
+
+impl Projection {
+    /// Build a projection from the values of `--select` and `--rename`, and
+    /// check that it makes sense for `schema`.
+    pub(crate) fn new(
+        select: Option<&str>,
+        renames: &[String],
+        schema: &Schema,
+    ) -> Result<Self> {
+        let has_column =
+            |name: &str| schema.table.columns.iter().any(|c| c.name == name);
+
+        let select = select
+            .map(|select| {
+                let names = select
+                    .split(',')
+                    .map(|name| name.trim().to_owned())
+                    .collect::<Vec<_>>();
+                let mut selected = HashSet::new();
+                for name in &names {
+                    if !has_column(name) {
+                        return Err(format_err!(
+                            "cannot --select unknown column {:?}",
+                            name
+                        ));
+                    }
+                    if !selected.insert(name) {
+                        return Err(format_err!(
+                            "column {:?} selected more than once",
+                            name
+                        ));
+                    }
+                }
+                Ok(names)
+            })
+            .transpose()?;
+
+        let mut rename_map = HashMap::new();
+        for rename in renames {
+            let (old, new) = rename.split_once('=').ok_or_else(|| {
+                format_err!("expected --rename OLD=NEW, found {:?}", rename)
+            })?;
+            if !has_column(old) {
+                return Err(format_err!("cannot --rename unknown column {:?}", old));
+            }
+            if rename_map.insert(old.to_owned(), new.to_owned()).is_some() {
+                return Err(format_err!("column {:?} renamed more than once", old));
+            }
+        }
+
+        let projection = Projection {
+            select,
+            renames: rename_map,
+        };
+
+        // Make sure we don't end up with duplicate column names.
+        let mut seen = HashSet::new();
+        for col in &projection
+            .dest_schema(projection.source_schema(schema.clone()))
+            .table
+            .columns
+        {
+            if !seen.insert(col.name.clone()) {
+                return Err(format_err!(
+                    "--select and --rename would produce duplicate column {:?}",
+                    col.name,
+                ));
+            }
+        }
+        Ok(projection)
+    }
+
+    /// Does this projection change anything?
+    pub(crate) fn is_empty(&self) -> bool {
+        self.select.is_none() && self.renames.is_empty()
+    }
+
+    /// Limit `schema` to the selected columns, keeping the original names.
+    /// Database drivers will only export these columns.
+    pub(crate) fn source_schema(&self, mut schema: Schema) -> Schema {
+        if let Some(select) = &self.select {
+            let mut columns = schema.table.columns.drain(..).collect::<Vec<_>>();
+            schema.table.columns = select
+                .iter()
+                .filter_map(|name| {
+                    let idx = columns.iter().position(|c| &c.name == name)?;
+                    Some(columns.remove(idx))
+                })
+                .collect();
+        }
+        schema
+    }
+
+    /// Rename the columns in `schema`, which should be the output of
+    /// `source_schema`.
+    pub(crate) fn dest_schema(&self, mut schema: Schema) -> Schema {
+        for col in &mut schema.table.columns {
+            if let Some(new_name) = self.renames.get(&col.name) {
+                col.name = new_name.to_owned();
+            }
+        }
+        schema
+    }
+
+    /// Select and rename columns in each of our CSV streams.
+    ///
+    /// Database drivers will usually have done the selection for us, but this
+    /// works either way, because we look up columns by name.
+    pub(crate) fn project_csv_streams(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+    ) -> BoxStream<CsvStream> {
+        let projection = self.clone();
+        data.and_then(move |stream| {
+            let projection = projection.clone();
+            let result = spawn_sync_transform(
+                ctx.clone(),
+                "project_csv".to_owned(),
+                stream.data,
+                move |_ctx, rdr, wtr| projection.project_csv(rdr, wtr),
+            )
+            .map(|data| CsvStream {
+                name: stream.name,
+                data,
+            });
+            future::ready(result)
+        })
+        .boxed()
+    }
+
+    /// Synchronously project a single CSV stream.
+    fn project_csv(&self, rdr: impl Read, wtr: impl Write) -> Result<()> {
+        let mut rdr = csv::Reader::from_reader(rdr);
+        let mut wtr = csv::Writer::from_writer(wtr);
+
+        // Figure out which input columns we want, in order.
+        let headers = rdr.headers()?.clone();
+        let indices = match &self.select {
+            Some(select) => select
+                .iter()
+                .map(|name| {
+                    headers.iter().position(|h| h == name).ok_or_else(|| {
+                        format_err!("cannot find column {:?} in CSV data", name)
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => (0..headers.len()).collect(),
+        };
+
+        // Write our renamed headers.
+        wtr.write_record(indices.iter().map(|&idx| {
+            let name = &headers[idx];
+            self.renames.get(name).map(|n| &n[..]).unwrap_or(name)
+        }))?;
+
+        // Copy our selected cells.
+        let mut row = csv::StringRecord::new();
+        while rdr.read_record(&mut row)? {
+            wtr.write_record(indices.iter().map(|&idx| &row[idx]))?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[test]
+fn select_and_rename() {
+    use crate::schema::{Column, DataType};
+    use std::io::Cursor;
+
+    let column = |name: &str| Column {
+        name: name.to_owned(),
+        is_nullable: true,
+        data_type: DataType::Text,
+        comment: None,
+    };
+    let schema = Schema::from_table(Table {
+        name: "example".to_owned(),
+        columns: vec![column("other"), column("test")],
+    })
+    .unwrap();
+    assert!(Projection::new(None, &[], &schema).unwrap().is_empty());
+    assert!(Projection::new(Some("nope"), &[], &schema).is_err());
+    assert!(Projection::new(Some("test,test"), &[], &schema).is_err());
+    assert!(Projection::new(None, &["nope=x".to_owned()], &schema).is_err());
+    assert!(Projection::new(None, &["other=test".to_owned()], &schema).is_err());
+
+    let projection =
+        Projection::new(Some("test"), &["test=renamed".to_owned()], &schema).unwrap();
+    let source = projection.source_schema(schema.clone());
+    assert_eq!(source.table.columns.len(), 1);
+    assert_eq!(source.table.columns[0].name, "test");
+    let dest = projection.dest_schema(source);
+    assert_eq!(dest.table.columns[0].name, "renamed");
+
+    let mut out = vec![];
+    projection
+        .project_csv(Cursor::new(b"other,test\n1,a\n2,b\n".to_vec()), &mut out)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "renamed\na\nb\n");
+}
@@ -13,12 +13,19 @@ use crate::tokio_glue::{SyncStreamReader, SyncStreamWriter};
 const MAX_CSV_BUFFER_SIZE: usize = 8 * (1 << 10);
 
 /// Given a stream of streams CSV data, return another stream of CSV streams
-/// where the CSV data is approximately `chunk_size` long whenever possible.
+/// where the CSV data is approximately `chunk_size` long whenever possible,
+/// and where each stream contains at most `max_rows` rows. At least one of
+/// these should be specified.
 pub fn rechunk_csvs(
     ctx: Context,
-    chunk_size: usize,
+    chunk_size: Option<usize>,
+    max_rows: Option<u64>,
     streams: BoxStream<CsvStream>,
 ) -> Result<BoxStream<CsvStream>> {
+    if max_rows == Some(0) {
+        return Err(format_err!("cannot split data into chunks of 0 rows"));
+    }
+
     // Convert out input `BoxStream<CsvStream>` into a single, concatenated
     // synchronous `Read` object.
     let input_csv_stream = concatenate_csv_streams(ctx.clone(), streams)?;
@@ -46,6 +53,8 @@ pub fn rechunk_csvs(
             /// Approximately how much data have we written, not counting the
             /// buffer in `wtr`?
             total_written: Rc<Cell<usize>>,
+            /// How many rows have we written?
+            rows_written: u64,
             /// The `CsvStream` which will output the data produced by `wtr`.
             /// Once we publish this vaue to `csv_stream_sender`, we'll set the
             /// field `csv_stream` to `None`.
@@ -78,11 +87,15 @@ pub fn rechunk_csvs(
             // Now, make a `csv::Writer` we can write to. We limit our buffer
             // size so that `chunk_size` is vaguely accurate.
             let wtr = csv::WriterBuilder::default()
-                .buffer_capacity(min(MAX_CSV_BUFFER_SIZE, chunk_size))
+                .buffer_capacity(min(
+                    MAX_CSV_BUFFER_SIZE,
+                    chunk_size.unwrap_or(MAX_CSV_BUFFER_SIZE),
+                ))
                 .from_writer(wtr);
             Ok(Chunk {
                 wtr,
                 total_written,
+                rows_written: 0,
                 csv_stream: Some(csv_stream),
             })
         };
@@ -111,9 +124,13 @@ pub fn rechunk_csvs(
                 .wtr
                 .write_byte_record(&row)
                 .context("cannot write row")?;
+            chunk.rows_written += 1;
 
-            // If total written exceeds chunk size, then start a new chunk.
-            if chunk.total_written.get() >= chunk_size {
+            // If total written exceeds chunk size, or we've written the
+            // maximum number of rows, then start a new chunk.
+            if chunk_size.is_some_and(|size| chunk.total_written.get() >= size)
+                || max_rows.is_some_and(|max| chunk.rows_written >= max)
+            {
                 trace!("finishing chunk");
                 chunk = new_chunk()?;
             }
@@ -130,11 +147,28 @@ pub fn rechunk_csvs(
 
 #[tokio::test]
 async fn rechunk_csvs_honors_chunk_size() {
-    use std::str;
-
     let inputs: &[&[u8]] = &[b"a,b\n1,1\n2,1\n", b"a,b\n1,2\n2,2\n"];
     let expected: &[&[u8]] =
         &[b"a,b\n1,1\n", b"a,b\n2,1\n", b"a,b\n1,2\n", b"a,b\n2,2\n"];
+    check_rechunk_csvs(Some(7), None, inputs, expected).await;
+}
+
+#[tokio::test]
+async fn rechunk_csvs_honors_max_rows() {
+    let inputs: &[&[u8]] = &[b"a,b\n1,1\n2,1\n", b"a,b\n1,2\n2,2\n"];
+    let expected: &[&[u8]] = &[b"a,b\n1,1\n2,1\n1,2\n", b"a,b\n2,2\n"];
+    check_rechunk_csvs(None, Some(3), inputs, expected).await;
+}
+
+/// Rechunk `inputs`, and check that we get `expected`.
+#[cfg(test)]
+async fn check_rechunk_csvs(
+    chunk_size: Option<usize>,
+    max_rows: Option<u64>,
+    inputs: &'static [&'static [u8]],
+    expected: &'static [&'static [u8]],
+) {
+    use std::str;
 
     let (ctx, worker_fut) = Context::create();
 
@@ -153,7 +187,8 @@ async fn rechunk_csvs_honors_chunk_size() {
         drop(sender);
         let csv_streams = ReceiverStream::new(receiver).boxed();
 
-        let rechunked_csv_streams = rechunk_csvs(ctx.clone(), 7, csv_streams).unwrap();
+        let rechunked_csv_streams =
+            rechunk_csvs(ctx.clone(), chunk_size, max_rows, csv_streams).unwrap();
 
         let outputs = rechunked_csv_streams
             // We need to use `map` here (and handle both `Ok` and `Err`)
@@ -0,0 +1,200 @@
+//! Support for `--limit` and `--sample`.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use futures::future;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+
+use crate::common::*;
+use crate::transform::spawn_sync_transform;
+
+/// A random sample of rows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// The fraction of rows to keep, greater than 0 and at most 1.
+    fraction: f64,
+    /// A seed to make our sample repeatable.
+    seed: Option<u64>,
+}
+
+impl Sample {
+    /// Keep approximately `fraction` of all rows, using `seed` if specified.
+    pub(crate) fn new(fraction: f64, seed: Option<u64>) -> Result<Self> {
+        if !(fraction > 0.0 && fraction <= 1.0) {
+            return Err(format_err!(
+                "--sample must be greater than 0 and at most 1, found {}",
+                fraction
+            ));
+        }
+        Ok(Sample { fraction, seed })
+    }
+
+    /// The fraction of rows to keep.
+    pub(crate) fn fraction(&self) -> f64 {
+        self.fraction
+    }
+
+    /// The percentage of rows to keep, for SQL `TABLESAMPLE` clauses.
+    pub(crate) fn percent(&self) -> f64 {
+        self.fraction * 100.0
+    }
+
+    /// Our seed, if we have one.
+    pub(crate) fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
+/// Only keep the first `limit` rows of `data`, counting across all streams.
+///
+/// Once we've seen enough rows, we stop reading each stream early, and we stop
+/// asking our source for more streams.
+pub(crate) fn limit_csv_streams(
+    ctx: Context,
+    limit: u64,
+    data: BoxStream<CsvStream>,
+) -> BoxStream<CsvStream> {
+    let rows_taken = Arc::new(AtomicU64::new(0));
+    let rows_taken_2 = rows_taken.clone();
+    data.try_take_while(move |_| {
+        future::ready(Ok(rows_taken_2.load(Ordering::SeqCst) < limit))
+    })
+    .and_then(move |stream| {
+        let rows_taken = rows_taken.clone();
+        let result = spawn_sync_transform(
+            ctx.clone(),
+            "limit_csv".to_owned(),
+            stream.data,
+            move |_ctx, rdr, wtr| {
+                filter_csv(rdr, wtr, |_| {
+                    // Reserve a row. This works even if several streams are
+                    // running in parallel.
+                    if rows_taken.fetch_add(1, Ordering::SeqCst) < limit {
+                        Keep::Yes
+                    } else {
+                        Keep::Stop
+                    }
+                })
+            },
+        )
+        .map(|data| CsvStream {
+            name: stream.name,
+            data,
+        });
+        future::ready(result)
+    })
+    .boxed()
+}
+
+/// Keep a random sample of the rows in `data`.
+///
+/// If `sample` has a seed, each stream will be sampled the same way every
+/// time, as long as it has the same name and contents.
+pub(crate) fn sample_csv_streams(
+    ctx: Context,
+    sample: Sample,
+    data: BoxStream<CsvStream>,
+) -> BoxStream<CsvStream> {
+    data.and_then(move |stream| {
+        let mut rng = match sample.seed() {
+            Some(seed) => StdRng::seed_from_u64(stream_seed(seed, &stream.name)),
+            None => StdRng::from_os_rng(),
+        };
+        let result = spawn_sync_transform(
+            ctx.clone(),
+            "sample_csv".to_owned(),
+            stream.data,
+            move |_ctx, rdr, wtr| {
+                filter_csv(rdr, wtr, |_| {
+                    if rng.random_bool(sample.fraction()) {
+                        Keep::Yes
+                    } else {
+                        Keep::No
+                    }
+                })
+            },
+        )
+        .map(|data| CsvStream {
+            name: stream.name,
+            data,
+        });
+        future::ready(result)
+    })
+    .boxed()
+}
+
+/// Combine `seed` with `stream_name`, so that each stream gets a different
+/// (but repeatable) sequence of random numbers.
+fn stream_seed(seed: u64, stream_name: &str) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_be_bytes());
+    hasher.update(stream_name.as_bytes());
+    let hash = hasher.finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// What should [`filter_csv`] do with a row?
+enum Keep {
+    /// Keep this row.
+    Yes,
+    /// Skip this row.
+    No,
+    /// Skip this row and all remaining rows.
+    Stop,
+}
+
+/// Synchronously copy the rows of `rdr` to `wtr`, asking `keep` about each
+/// row.
+fn filter_csv(
+    rdr: impl Read,
+    wtr: impl Write,
+    mut keep: impl FnMut(&csv::ByteRecord) -> Keep,
+) -> Result<()> {
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+    wtr.write_byte_record(rdr.byte_headers()?)?;
+    let mut row = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut row)? {
+        match keep(&row) {
+            Keep::Yes => wtr.write_byte_record(&row)?,
+            Keep::No => {}
+            Keep::Stop => break,
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[test]
+fn sample_must_be_a_fraction() {
+    assert!(Sample::new(0.5, None).is_ok());
+    assert!(Sample::new(1.0, Some(1)).is_ok());
+    assert!(Sample::new(0.0, None).is_err());
+    assert!(Sample::new(1.5, None).is_err());
+    assert!(Sample::new(f64::NAN, None).is_err());
+}
+
+#[test]
+fn filter_csv_stops_early() {
+    use std::io::Cursor;
+
+    let mut count = 0;
+    let mut out = vec![];
+    filter_csv(Cursor::new(b"a\n1\n2\n3\n4\n".to_vec()), &mut out, |_| {
+        count += 1;
+        match count {
+            1 => Keep::No,
+            2 => Keep::Yes,
+            _ => Keep::Stop,
+        }
+    })
+    .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "a\n2\n");
+    assert_eq!(count, 3);
+}
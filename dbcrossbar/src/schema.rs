@@ -460,6 +460,14 @@ impl DataType {
         }
     }
 
+    /// Look up this type, following any `DataType::Named` references.
+    pub(crate) fn resolve<'a>(&'a self, schema: &'a Schema) -> &'a DataType {
+        match self {
+            DataType::Named(name) => schema.data_type_for_name(name).resolve(schema),
+            _ => self,
+        }
+    }
+
     /// Should we serialize values of this type as JSON in a CSV file?
     pub(crate) fn serializes_as_json_for_csv(&self, schema: &Schema) -> bool {
         match self {
@@ -0,0 +1,147 @@
+//! Automatic schema evolution when appending to an existing table.
+
+use std::collections::HashMap;
+
+use crate::common::*;
+use crate::schema::{Column, DataType};
+
+/// Changes needed so that an existing table can accept data with a new schema.
+///
+/// We represent these changes as a pair of schemas, `before` and `after`,
+/// which can be passed directly to [`Locator::migration_sql`]. Both schemas
+/// start from the existing table, so any columns we don't touch will be
+/// converted to the same native types, and won't appear in the migration.
+#[derive(Clone, Debug)]
+pub(crate) struct SchemaEvolution {
+    /// The existing table.
+    pub(crate) before: Schema,
+    /// The existing table, with new columns and widened types.
+    pub(crate) after: Schema,
+}
+
+impl SchemaEvolution {
+    /// Figure out how `existing` should change to accept data matching
+    /// `incoming`.
+    ///
+    /// We add any new columns (always as nullable, because existing rows won't
+    /// have values), and we apply a few safe widenings, like `int32` to
+    /// `int64`. We don't drop columns or narrow types, and we leave any other
+    /// type mismatches for the database to sort out when loading the data.
+    pub(crate) fn new(existing: &Schema, incoming: &Schema) -> Result<Self> {
+        let incoming_columns = incoming
+            .table
+            .columns
+            .iter()
+            .map(|c| (&c.name[..], c))
+            .collect::<HashMap<_, _>>();
+
+        // Widen any existing columns that need it.
+        let mut columns = existing
+            .table
+            .columns
+            .iter()
+            .map(|col| match incoming_columns.get(&col.name[..]) {
+                Some(incoming_col)
+                    if is_safe_widening(&col.data_type, &incoming_col.data_type) =>
+                {
+                    debug!(
+                        "schema evolution: widening column {:?} from {:?} to {:?}",
+                        col.name, col.data_type, incoming_col.data_type,
+                    );
+                    Column {
+                        data_type: incoming_col.data_type.clone(),
+                        ..col.clone()
+                    }
+                }
+                _ => col.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        // Add any new columns.
+        for col in &incoming.table.columns {
+            if !existing.table.columns.iter().any(|c| c.name == col.name) {
+                debug!("schema evolution: adding column {:?}", col.name);
+                columns.push(Column {
+                    is_nullable: true,
+                    ..col.clone()
+                });
+            }
+        }
+
+        // Our new columns may refer to named types from `incoming`.
+        let mut types = existing.named_data_types.clone();
+        types.extend(incoming.named_data_types.clone());
+        let after = Schema::from_types_and_table(
+            types.into_values().collect(),
+            Table {
+                name: existing.table.name.clone(),
+                columns,
+            },
+        )?;
+        Ok(SchemaEvolution {
+            before: existing.clone(),
+            after,
+        })
+    }
+
+    /// Is there anything to change?
+    pub(crate) fn is_empty(&self) -> bool {
+        self.before == self.after
+    }
+}
+
+/// Can we safely change a column from `old` to `new` without losing data?
+fn is_safe_widening(old: &DataType, new: &DataType) -> bool {
+    matches!(
+        (old, new),
+        (DataType::Int16, DataType::Int32 | DataType::Int64)
+            | (DataType::Int32, DataType::Int64)
+            | (DataType::Float32, DataType::Float64)
+    )
+}
+
+#[test]
+fn adds_columns_and_widens_types() {
+    let column = |name: &str, data_type: DataType, is_nullable: bool| Column {
+        name: name.to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let schema = |columns: Vec<Column>| {
+        Schema::from_table(Table {
+            name: "example".to_owned(),
+            columns,
+        })
+        .unwrap()
+    };
+    let existing = schema(vec![
+        column("id", DataType::Int32, false),
+        column("score", DataType::Float32, true),
+        column("name", DataType::Text, true),
+        column("legacy", DataType::Text, true),
+    ]);
+    let incoming = schema(vec![
+        column("id", DataType::Int64, false),
+        column("score", DataType::Float64, true),
+        column("name", DataType::Int64, true),
+        column("created_at", DataType::TimestampWithTimeZone, false),
+    ]);
+
+    let evolution = SchemaEvolution::new(&existing, &incoming).unwrap();
+    assert_eq!(evolution.before, existing);
+    assert_eq!(
+        evolution.after.table.columns,
+        vec![
+            column("id", DataType::Int64, false),
+            column("score", DataType::Float64, true),
+            column("name", DataType::Text, true),
+            column("legacy", DataType::Text, true),
+            column("created_at", DataType::TimestampWithTimeZone, true),
+        ],
+    );
+
+    assert!(SchemaEvolution::new(&incoming, &incoming)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,297 @@
+//! Support for `--from-time-zone` and `--to-time-zone`.
+
+use std::collections::HashSet;
+
+use chrono::{
+    DateTime, Duration, FixedOffset, LocalResult, NaiveDateTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use futures::future;
+
+use crate::common::*;
+use crate::from_csv_cell::FromCsvCell;
+use crate::schema::DataType;
+use crate::transform::spawn_sync_transform;
+
+/// How we format timestamps without time zones.
+const NAIVE_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// How to convert timestamps between time zones.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TimeZones {
+    /// The time zone of `TimestampWithoutTimeZone` values in our source.
+    from: Option<Tz>,
+    /// The time zone we should use when writing timestamps, which will be
+    /// written as `TimestampWithoutTimeZone` values.
+    to: Option<Tz>,
+    /// Columns of type `TimestampWithoutTimeZone`.
+    naive_columns: HashSet<String>,
+    /// Columns of type `TimestampWithTimeZone`.
+    aware_columns: HashSet<String>,
+}
+
+impl TimeZones {
+    /// Build a `TimeZones` from the values of `--from-time-zone` and
+    /// `--to-time-zone`, and look up the timestamp columns in `schema`.
+    pub(crate) fn new(
+        from: Option<&str>,
+        to: Option<&str>,
+        schema: &Schema,
+    ) -> Result<Self> {
+        let parse_tz = |name: &str| {
+            name.parse::<Tz>().map_err(|_| {
+                format_err!(
+                    "unknown time zone {:?} (expected a name like America/New_York)",
+                    name,
+                )
+            })
+        };
+        let mut time_zones = TimeZones {
+            from: from.map(parse_tz).transpose()?,
+            to: to.map(parse_tz).transpose()?,
+            ..TimeZones::default()
+        };
+        for col in &schema.table.columns {
+            match col.data_type.resolve(schema) {
+                DataType::TimestampWithoutTimeZone => {
+                    time_zones.naive_columns.insert(col.name.clone());
+                }
+                DataType::TimestampWithTimeZone => {
+                    time_zones.aware_columns.insert(col.name.clone());
+                }
+                _ => {}
+            }
+        }
+        Ok(time_zones)
+    }
+
+    /// Does this change anything?
+    pub(crate) fn is_empty(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    /// Update the column types in `schema`. If we have a `to` time zone, all
+    /// timestamps become `TimestampWithoutTimeZone`.
+    pub(crate) fn converted_schema(&self, mut schema: Schema) -> Schema {
+        if self.to.is_some() {
+            for col in &mut schema.table.columns {
+                if self.aware_columns.contains(&col.name) {
+                    col.data_type = DataType::TimestampWithoutTimeZone;
+                }
+            }
+        }
+        schema
+    }
+
+    /// Convert the timestamps in each of our CSV streams.
+    pub(crate) fn convert_csv_streams(
+        &self,
+        ctx: Context,
+        data: BoxStream<CsvStream>,
+    ) -> BoxStream<CsvStream> {
+        let time_zones = self.clone();
+        data.and_then(move |stream| {
+            let time_zones = time_zones.clone();
+            let result = spawn_sync_transform(
+                ctx.clone(),
+                "convert_time_zones_csv".to_owned(),
+                stream.data,
+                move |_ctx, rdr, wtr| time_zones.convert_csv(rdr, wtr),
+            )
+            .map(|data| CsvStream {
+                name: stream.name,
+                data,
+            });
+            future::ready(result)
+        })
+        .boxed()
+    }
+
+    /// Synchronously convert a single CSV stream.
+    fn convert_csv(&self, rdr: impl Read, wtr: impl Write) -> Result<()> {
+        let mut rdr = csv::Reader::from_reader(rdr);
+        let mut wtr = csv::Writer::from_writer(wtr);
+
+        // Decide which columns to convert, and how.
+        let headers = rdr.headers()?.clone();
+        let kinds = headers
+            .iter()
+            .map(|name| {
+                if self.from.is_some() && self.naive_columns.contains(name) {
+                    Some(TimestampKind::Naive)
+                } else if self.to.is_some() && self.aware_columns.contains(name) {
+                    Some(TimestampKind::Aware)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        wtr.write_record(&headers)?;
+
+        let mut row = csv::StringRecord::new();
+        let mut out = csv::StringRecord::new();
+        while rdr.read_record(&mut row)? {
+            out.clear();
+            for (cell, kind) in row.iter().zip(&kinds) {
+                match kind {
+                    // Leave `NULL` values alone.
+                    Some(kind) if !cell.is_empty() => {
+                        let converted =
+                            self.convert_cell(*kind, cell).with_context(|| {
+                                format!(
+                                    "cannot convert time zone on line {}",
+                                    row.position().map(|p| p.line()).unwrap_or(0)
+                                )
+                            })?;
+                        out.push_field(&converted);
+                    }
+                    _ => out.push_field(cell),
+                }
+            }
+            wtr.write_record(&out)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Convert a single timestamp.
+    fn convert_cell(&self, kind: TimestampKind, cell: &str) -> Result<String> {
+        let utc = match kind {
+            TimestampKind::Naive => {
+                let naive = NaiveDateTime::from_csv_cell(cell)?;
+                let from = self.from.expect("should have a from time zone");
+                local_to_utc(&from, naive)
+            }
+            TimestampKind::Aware => {
+                DateTime::<FixedOffset>::from_csv_cell(cell)?.with_timezone(&Utc)
+            }
+        };
+        let naive = match &self.to {
+            Some(to) => utc.with_timezone(to).naive_local(),
+            None => utc.naive_utc(),
+        };
+        Ok(naive.format(NAIVE_TIMESTAMP_FORMAT).to_string())
+    }
+}
+
+/// The kind of timestamp in a column.
+#[derive(Clone, Copy, Debug)]
+enum TimestampKind {
+    /// A `TimestampWithoutTimeZone`.
+    Naive,
+    /// A `TimestampWithTimeZone`.
+    Aware,
+}
+
+/// Convert a local time in `tz` to UTC.
+///
+/// When clocks go back, some local times happen twice, and we use the earlier
+/// one. When clocks go forward, some local times never happen, and we use the
+/// offset from before the change, like PostgreSQL.
+fn local_to_utc(tz: &Tz, naive: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(dt) => dt.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+        LocalResult::None => {
+            // Look up the offset a day earlier, which is safely before the
+            // change.
+            let before = naive - Duration::hours(24);
+            let offset = tz
+                .from_local_datetime(&before)
+                .earliest()
+                .map(|dt| dt.offset().fix())
+                .unwrap_or_else(|| tz.offset_from_utc_datetime(&before).fix());
+            Utc.from_utc_datetime(&(naive - offset))
+        }
+    }
+}
+
+#[test]
+fn convert_time_zones() {
+    use crate::schema::Column;
+    use std::io::Cursor;
+
+    let column = |name: &str, data_type: DataType| Column {
+        name: name.to_owned(),
+        is_nullable: true,
+        data_type,
+        comment: None,
+    };
+    let schema = Schema::from_table(Table {
+        name: "example".to_owned(),
+        columns: vec![
+            column("id", DataType::Int64),
+            column("local", DataType::TimestampWithoutTimeZone),
+            column("aware", DataType::TimestampWithTimeZone),
+        ],
+    })
+    .unwrap();
+    assert!(TimeZones::new(None, None, &schema).unwrap().is_empty());
+    assert!(TimeZones::new(Some("Mars/Olympus_Mons"), None, &schema).is_err());
+
+    let input = "id,local,aware
+1,2024-01-15 09:30:00,2024-01-15T09:30:00Z
+2,2024-07-15T09:30:00.5,2024-07-15 09:30:00-04
+3,2024-03-10 02:30:00,
+4,2024-11-03 01:30:00,
+";
+
+    // Convert local times to UTC.
+    let time_zones = TimeZones::new(Some("America/New_York"), None, &schema).unwrap();
+    let dest = time_zones.converted_schema(schema.clone());
+    assert_eq!(
+        dest.table.columns[2].data_type,
+        DataType::TimestampWithTimeZone
+    );
+    let mut out = vec![];
+    time_zones
+        .convert_csv(Cursor::new(input.as_bytes().to_vec()), &mut out)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id,local,aware
+1,2024-01-15T14:30:00,2024-01-15T09:30:00Z
+2,2024-07-15T13:30:00.500,2024-07-15 09:30:00-04
+3,2024-03-10T07:30:00,
+4,2024-11-03T05:30:00,
+",
+    );
+
+    // Convert everything to local times in Paris.
+    let time_zones =
+        TimeZones::new(Some("America/New_York"), Some("Europe/Paris"), &schema)
+            .unwrap();
+    let dest = time_zones.converted_schema(schema.clone());
+    assert_eq!(
+        dest.table.columns[2].data_type,
+        DataType::TimestampWithoutTimeZone
+    );
+    let mut out = vec![];
+    time_zones
+        .convert_csv(Cursor::new(input.as_bytes().to_vec()), &mut out)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id,local,aware
+1,2024-01-15T15:30:00,2024-01-15T10:30:00
+2,2024-07-15T15:30:00.500,2024-07-15T15:30:00
+3,2024-03-10T08:30:00,
+4,2024-11-03T06:30:00,
+",
+    );
+
+    // Clocks in Paris skip from 02:00 to 03:00, so 02:30 uses the winter
+    // offset of +01:00, as it does in New York.
+    let time_zones = TimeZones::new(Some("Europe/Paris"), None, &schema).unwrap();
+    let mut out = vec![];
+    time_zones
+        .convert_csv(
+            Cursor::new(b"id,local,aware\n1,2024-03-31 02:30:00,\n".to_vec()),
+            &mut out,
+        )
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id,local,aware\n1,2024-03-31T01:30:00,\n",
+    );
+}
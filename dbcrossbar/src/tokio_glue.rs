@@ -191,7 +191,9 @@ where
                             trace!("sent bytes to stream");
                         }
                         Err(_err) => {
-                            error!("broken pipe forwarding async data to stream");
+                            // Our consumer may have stopped reading early
+                            // because of `--limit`, so this isn't an error.
+                            debug!("broken pipe forwarding async data to stream");
                             return Ok(());
                         }
                     }
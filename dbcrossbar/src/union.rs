@@ -0,0 +1,218 @@
+//! Support for copying several sources into one destination, and for
+//! `--add-source-column`.
+
+use std::{collections::HashMap, str::FromStr};
+
+use futures::future;
+
+use crate::common::*;
+use crate::schema::{Column, DataType};
+use crate::transform::spawn_sync_transform;
+
+/// What should `--add-source-column` record for each row?
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum SourceColumnValue {
+    /// The locator we read the row from.
+    #[default]
+    Locator,
+    /// The name of the data stream we read the row from.
+    Stream,
+}
+
+impl FromStr for SourceColumnValue {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "locator" => Ok(SourceColumnValue::Locator),
+            "stream" => Ok(SourceColumnValue::Stream),
+            _ => Err(format_err!(
+                "unknown source column value {:?} (expected locator or stream)",
+                s,
+            )),
+        }
+    }
+}
+
+/// An extra column recording where each row came from.
+#[derive(Clone, Debug)]
+pub(crate) struct SourceColumn {
+    /// The name of our column.
+    name: String,
+    /// What to put in our column.
+    value: SourceColumnValue,
+}
+
+impl SourceColumn {
+    /// Create a new source column, and check that `schema` doesn't already
+    /// have a column with the same name.
+    pub(crate) fn new(
+        name: &str,
+        value: SourceColumnValue,
+        schema: &Schema,
+    ) -> Result<Self> {
+        if name.is_empty() {
+            return Err(format_err!("--add-source-column cannot be empty"));
+        }
+        if schema.table.columns.iter().any(|c| c.name == name) {
+            return Err(format_err!(
+                "--add-source-column {:?} is already a column in the schema",
+                name,
+            ));
+        }
+        Ok(SourceColumn {
+            name: name.to_owned(),
+            value,
+        })
+    }
+
+    /// The name of our column.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Add our column to the end of `schema`.
+    pub(crate) fn add_to_schema(&self, mut schema: Schema) -> Schema {
+        schema.table.columns.push(Column {
+            name: self.name.clone(),
+            is_nullable: false,
+            data_type: DataType::Text,
+            comment: Some("Where this row was copied from.".to_owned()),
+        });
+        schema
+    }
+
+    /// Remove our column from `schema`, if it's present. Our sources don't
+    /// know about it.
+    pub(crate) fn remove_from_schema(&self, mut schema: Schema) -> Schema {
+        schema.table.columns.retain(|c| c.name != self.name);
+        schema
+    }
+}
+
+/// Combine the data from several sources into a single stream of CSV streams,
+/// reading each source in turn.
+///
+/// Each of `sources` is a locator (as displayed to the user) and its data.
+/// Every CSV stream is rewritten to contain exactly `columns`, in order, so
+/// that sources which list their columns differently can be combined. If
+/// `source_column` is specified, we also add a column recording where each
+/// row came from. Stream names are made unique by adding a numeric suffix.
+pub(crate) fn union_csv_streams(
+    ctx: Context,
+    sources: Vec<(String, BoxStream<CsvStream>)>,
+    columns: Vec<String>,
+    source_column: Option<SourceColumn>,
+) -> BoxStream<CsvStream> {
+    let mut name_counts = HashMap::<String, usize>::new();
+    stream::iter(sources)
+        .flat_map(|(locator, data)| {
+            data.map_ok(move |stream| (locator.clone(), stream))
+        })
+        .map_ok(move |(locator, stream)| {
+            // Make our stream name unique.
+            let count = name_counts.entry(stream.name.clone()).or_default();
+            *count += 1;
+            let name = if *count == 1 {
+                stream.name.clone()
+            } else {
+                format!("{}_{}", stream.name, count)
+            };
+            let source_value = source_column.as_ref().map(|source_column| {
+                let value = match source_column.value {
+                    SourceColumnValue::Locator => locator,
+                    SourceColumnValue::Stream => stream.name,
+                };
+                (source_column.name.clone(), value)
+            });
+            (name, stream.data, source_value)
+        })
+        .and_then(move |(name, data, source_value)| {
+            let columns = columns.clone();
+            let result = spawn_sync_transform(
+                ctx.clone(),
+                "union_csv".to_owned(),
+                data,
+                move |_ctx, rdr, wtr| {
+                    align_csv(rdr, wtr, &columns, source_value.as_ref())
+                },
+            )
+            .map(|data| CsvStream { name, data });
+            future::ready(result)
+        })
+        .boxed()
+}
+
+/// Synchronously rewrite a single CSV stream to contain `columns`, in order,
+/// followed by an optional `(name, value)` source column.
+fn align_csv(
+    rdr: impl Read,
+    wtr: impl Write,
+    columns: &[String],
+    source_value: Option<&(String, String)>,
+) -> Result<()> {
+    let mut rdr = csv::Reader::from_reader(rdr);
+    let mut wtr = csv::Writer::from_writer(wtr);
+
+    // Figure out where to find each of our columns.
+    let headers = rdr.headers()?.clone();
+    let indices = columns
+        .iter()
+        .map(|name| {
+            headers.iter().position(|h| h == name).ok_or_else(|| {
+                format_err!("cannot find column {:?} in CSV data", name)
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Write our headers.
+    let mut out = csv::StringRecord::new();
+    for name in columns {
+        out.push_field(name);
+    }
+    if let Some((name, _)) = source_value {
+        out.push_field(name);
+    }
+    wtr.write_record(&out)?;
+
+    // Copy our rows.
+    let mut row = csv::StringRecord::new();
+    while rdr.read_record(&mut row)? {
+        out.clear();
+        for &idx in &indices {
+            out.push_field(&row[idx]);
+        }
+        if let Some((_, value)) = source_value {
+            out.push_field(value);
+        }
+        wtr.write_record(&out)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[test]
+fn align_csv_reorders_columns_and_adds_source() {
+    use std::io::Cursor;
+
+    let columns = vec!["id".to_owned(), "name".to_owned()];
+    let source = ("source".to_owned(), "csv:eu.csv".to_owned());
+    let mut out = vec![];
+    align_csv(
+        Cursor::new(b"name,extra,id\na,x,1\nb,y,2\n".to_vec()),
+        &mut out,
+        &columns,
+        Some(&source),
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id,name,source\n1,a,csv:eu.csv\n2,b,csv:eu.csv\n",
+    );
+
+    let mut out = vec![];
+    assert!(
+        align_csv(Cursor::new(b"name\na\n".to_vec()), &mut out, &columns, None)
+            .is_err()
+    );
+}
@@ -0,0 +1,335 @@
+//! Checking CSV data against a schema.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use geo_types::Geometry;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::common::*;
+use crate::from_csv_cell::FromCsvCell;
+use crate::from_json_value::FromJsonValue;
+use crate::schema::{Column, DataType};
+
+/// A problem found while validating a CSV stream.
+#[derive(Debug)]
+pub(crate) struct ValidationError {
+    /// The line number in the CSV data, counting the header as line 1.
+    pub(crate) line: u64,
+    /// The column containing the problem, if we know it.
+    pub(crate) column: Option<String>,
+    /// What went wrong.
+    pub(crate) error: Error,
+}
+
+/// Read CSV data from `rdr`, and check every cell against `schema`.
+///
+/// We call `report` for every problem we find, and write each invalid row to
+/// `rejects` (along with a header). Returns the number of invalid rows.
+///
+/// This is synchronous because it relies on `csv::Reader`, so you'll probably
+/// want to run it using `spawn_sync_transform`.
+pub(crate) fn validate_csv(
+    schema: &Schema,
+    rdr: Box<dyn Read>,
+    rejects: &mut dyn Write,
+    report: &mut dyn FnMut(ValidationError),
+) -> Result<u64> {
+    let columns = &schema.table.columns;
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(rdr);
+    let mut wtr = csv::WriterBuilder::new()
+        .flexible(true)
+        .from_writer(rejects);
+
+    // Check to make sure our CSV headers and table column names match.
+    let headers = rdr.headers()?;
+    if headers.len() != columns.len()
+        || headers
+            .iter()
+            .zip(columns)
+            .any(|(hdr, col)| hdr != col.name)
+    {
+        return Err(format_err!(
+            "CSV file has columns {:?}, but schema has {:?}",
+            headers.iter().collect::<Vec<_>>(),
+            columns.iter().map(|c| &c.name[..]).collect::<Vec<_>>(),
+        ));
+    }
+    wtr.write_record(headers)?;
+
+    let mut invalid_rows = 0;
+    for row in rdr.records() {
+        let row = row?;
+        let line = row.position().map(|p| p.line()).unwrap_or(0);
+        let mut is_valid = true;
+        if row.len() != columns.len() {
+            is_valid = false;
+            report(ValidationError {
+                line,
+                column: None,
+                error: format_err!(
+                    "expected {} columns, found {}",
+                    columns.len(),
+                    row.len(),
+                ),
+            });
+        } else {
+            for (cell, col) in row.iter().zip(columns) {
+                if let Err(error) = validate_cell(schema, col, cell) {
+                    is_valid = false;
+                    report(ValidationError {
+                        line,
+                        column: Some(col.name.clone()),
+                        error,
+                    });
+                }
+            }
+        }
+        if !is_valid {
+            invalid_rows += 1;
+            wtr.write_record(&row)?;
+        }
+    }
+    wtr.flush()?;
+    Ok(invalid_rows)
+}
+
+/// Check whether `cell` is a valid value for `column`.
+pub(crate) fn validate_cell(
+    schema: &Schema,
+    column: &Column,
+    cell: &str,
+) -> Result<()> {
+    if cell.is_empty() {
+        // Our CSV interchange format represents `NULL` as an empty cell.
+        if column.is_nullable {
+            Ok(())
+        } else {
+            Err(format_err!("missing value in NOT NULL column"))
+        }
+    } else {
+        validate_csv_value(schema, &column.data_type, cell)
+            .with_context(|| format!("invalid value {:?}", cell))
+    }
+}
+
+/// Check whether a non-empty CSV cell is a valid value of `data_type`.
+fn validate_csv_value(
+    schema: &Schema,
+    data_type: &DataType,
+    cell: &str,
+) -> Result<()> {
+    match data_type {
+        DataType::Array(_) | DataType::Struct(_) => {
+            let json =
+                serde_json::from_str::<Value>(cell).context("cannot parse JSON")?;
+            validate_json_value(schema, data_type, &json)
+        }
+        DataType::Bool => check_cell::<bool>(cell),
+        DataType::Date => check_cell::<NaiveDate>(cell),
+        DataType::Decimal => check_decimal(cell),
+        DataType::Float32 => check_cell::<f32>(cell),
+        DataType::Float64 => check_cell::<f64>(cell),
+        DataType::GeoJson(_) => check_cell::<Geometry<f64>>(cell),
+        DataType::Int16 => check_cell::<i16>(cell),
+        DataType::Int32 => check_cell::<i32>(cell),
+        DataType::Int64 => check_cell::<i64>(cell),
+        DataType::Json => check_cell::<Value>(cell),
+        DataType::Named(name) => {
+            validate_csv_value(schema, schema.data_type_for_name(name), cell)
+        }
+        DataType::OneOf(values) => check_one_of(values, cell),
+        DataType::Text => Ok(()),
+        DataType::TimestampWithoutTimeZone => check_cell::<NaiveDateTime>(cell),
+        DataType::TimestampWithTimeZone => check_cell::<DateTime<Utc>>(cell),
+        DataType::Uuid => check_cell::<Uuid>(cell),
+    }
+}
+
+/// Check whether a JSON value (found inside an array or struct) is a valid
+/// value of `data_type`. JSON `null` values are handled by our caller.
+fn validate_json_value(
+    schema: &Schema,
+    data_type: &DataType,
+    json: &Value,
+) -> Result<()> {
+    match data_type {
+        DataType::Array(elem_ty) => match json {
+            Value::Array(elems) => {
+                for elem in elems {
+                    if !elem.is_null() {
+                        validate_json_value(schema, elem_ty, elem)?;
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(format_err!("expected JSON array, found {}", json)),
+        },
+        DataType::Bool => check_json::<bool>(json),
+        DataType::Date => check_json::<NaiveDate>(json),
+        DataType::Decimal => match json {
+            Value::Number(_) => Ok(()),
+            Value::String(s) => check_decimal(s),
+            _ => Err(format_err!("expected decimal, found {}", json)),
+        },
+        DataType::Float32 => check_json::<f32>(json),
+        DataType::Float64 => check_json::<f64>(json),
+        DataType::GeoJson(_) => match json {
+            Value::Object(_) => check_cell::<Geometry<f64>>(&json.to_string()),
+            _ => check_json::<Geometry<f64>>(json),
+        },
+        DataType::Int16 => check_json::<i16>(json),
+        DataType::Int32 => check_json::<i32>(json),
+        DataType::Int64 => check_json::<i64>(json),
+        DataType::Json => Ok(()),
+        DataType::Named(name) => {
+            validate_json_value(schema, schema.data_type_for_name(name), json)
+        }
+        DataType::OneOf(values) => match json {
+            Value::String(s) => check_one_of(values, s),
+            _ => Err(format_err!("expected JSON string, found {}", json)),
+        },
+        DataType::Struct(fields) => match json {
+            Value::Object(obj) => {
+                for field in fields {
+                    match obj.get(&field.name) {
+                        None | Some(Value::Null) if field.is_nullable => {}
+                        None | Some(Value::Null) => {
+                            return Err(format_err!(
+                                "missing value for NOT NULL field {:?}",
+                                field.name,
+                            ));
+                        }
+                        Some(value) => {
+                            validate_json_value(schema, &field.data_type, value)
+                                .with_context(|| {
+                                    format!("invalid field {:?}", field.name)
+                                })?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(format_err!("expected JSON object, found {}", json)),
+        },
+        DataType::Text => check_json::<String>(json),
+        DataType::TimestampWithoutTimeZone => check_json::<NaiveDateTime>(json),
+        DataType::TimestampWithTimeZone => check_json::<DateTime<Utc>>(json),
+        DataType::Uuid => check_json::<Uuid>(json),
+    }
+}
+
+/// Check whether `cell` can be parsed as a `T`.
+fn check_cell<T: FromCsvCell>(cell: &str) -> Result<()> {
+    T::from_csv_cell(cell).map(|_| ())
+}
+
+/// Check whether `json` can be parsed as a `T`.
+fn check_json<T: FromJsonValue>(json: &Value) -> Result<()> {
+    T::from_json_value(json).map(|_| ())
+}
+
+/// Check whether `value` is one of `values`.
+fn check_one_of(values: &[String], value: &str) -> Result<()> {
+    if values.iter().any(|v| v == value) {
+        Ok(())
+    } else {
+        Err(format_err!("expected one of {:?}", values))
+    }
+}
+
+/// Check whether `value` looks like a decimal number. We don't have a lossless
+/// decimal type, so we only check the syntax.
+fn check_decimal(value: &str) -> Result<()> {
+    lazy_static! {
+        static ref DECIMAL_RE: Regex =
+            Regex::new(r"^[-+]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:[eE][-+]?[0-9]+)?$")
+                .expect("invalid `DECIMAL_RE` in source");
+    }
+    if DECIMAL_RE.is_match(value) {
+        Ok(())
+    } else {
+        Err(format_err!("cannot parse decimal"))
+    }
+}
+
+#[test]
+fn validate_cells() {
+    use crate::schema::{Srid, StructField};
+
+    let schema = Schema::dummy_test_schema();
+    let column = |data_type: DataType, is_nullable: bool| Column {
+        name: "c".to_owned(),
+        is_nullable,
+        data_type,
+        comment: None,
+    };
+    let valid = &[
+        (column(DataType::Int32, true), ""),
+        (column(DataType::Int32, false), "-12"),
+        (column(DataType::Decimal, false), "1.50"),
+        (column(DataType::OneOf(vec!["a".to_owned()]), false), "a"),
+        (
+            column(DataType::Uuid, false),
+            "3d8fa7a2-6cf1-4b8e-9a55-3c4c4d3b2c6a",
+        ),
+        (
+            column(DataType::GeoJson(Srid::wgs84()), false),
+            r#"{"type":"Point","coordinates":[-71,42]}"#,
+        ),
+        (
+            column(DataType::Array(Box::new(DataType::Int64)), false),
+            "[1,null,3]",
+        ),
+        (
+            column(
+                DataType::Struct(vec![StructField {
+                    name: "x".to_owned(),
+                    is_nullable: true,
+                    data_type: DataType::Float64,
+                }]),
+                false,
+            ),
+            r#"{"x":1.5}"#,
+        ),
+    ];
+    for (col, cell) in valid {
+        validate_cell(&schema, col, cell).unwrap();
+    }
+
+    let invalid = &[
+        (column(DataType::Int32, false), ""),
+        (column(DataType::Int16, false), "70000"),
+        (column(DataType::Decimal, false), "1.5.0"),
+        (column(DataType::OneOf(vec!["a".to_owned()]), false), "b"),
+        (column(DataType::Uuid, false), "not-a-uuid"),
+        (
+            column(DataType::GeoJson(Srid::wgs84()), false),
+            r#"{"type":"Point"}"#,
+        ),
+        (
+            column(DataType::Array(Box::new(DataType::Int64)), false),
+            r#"[1,"x"]"#,
+        ),
+        (
+            column(
+                DataType::Struct(vec![StructField {
+                    name: "x".to_owned(),
+                    is_nullable: false,
+                    data_type: DataType::Float64,
+                }]),
+                false,
+            ),
+            "{}",
+        ),
+    ];
+    for (col, cell) in invalid {
+        assert!(
+            validate_cell(&schema, col, cell).is_err(),
+            "expected {:?} to be invalid for {:?}",
+            cell,
+            col.data_type,
+        );
+    }
+}
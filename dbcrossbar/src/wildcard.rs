@@ -0,0 +1,297 @@
+//! Support for wildcard locators like `postgres://host/db#public.*`, which
+//! refer to many tables at once, and for glob locators like
+//! `s3://bucket/exports/2024-*/part-*.csv`, which refer to many files.
+
+use std::fmt;
+
+use crate::common::*;
+
+/// Locator schemes where a `*` is part of a path glob, not a table wildcard.
+const PATH_GLOB_SCHEMES: &[&str] = &["csv:", "file:", "gs:", "s3:"];
+
+/// Characters which turn a path into a glob.
+const GLOB_CHARS: &[char] = &['*', '?'];
+
+/// A string containing a single `*`, which can match or be replaced by any
+/// non-empty string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct WildcardPattern {
+    /// The text before the `*`.
+    prefix: String,
+    /// The text after the `*`.
+    suffix: String,
+}
+
+impl WildcardPattern {
+    /// Parse `s` as a pattern, returning `None` if it doesn't contain a `*`.
+    pub(crate) fn parse(s: &str) -> Result<Option<Self>> {
+        match s.split_once('*') {
+            None => Ok(None),
+            Some((_, suffix)) if suffix.contains('*') => Err(format_err!(
+                "only one `*` is allowed in a wildcard locator: {:?}",
+                s
+            )),
+            Some((prefix, suffix)) => Ok(Some(WildcardPattern {
+                prefix: prefix.to_owned(),
+                suffix: suffix.to_owned(),
+            })),
+        }
+    }
+
+    /// If `s` matches this pattern, return the part of `s` matched by the
+    /// `*`.
+    pub(crate) fn capture<'a>(&self, s: &'a str) -> Option<&'a str> {
+        s.strip_prefix(&self.prefix)
+            .and_then(|s| s.strip_suffix(&self.suffix))
+            .filter(|captured| !captured.is_empty())
+    }
+
+    /// Replace the `*` in this pattern with `captured`.
+    pub(crate) fn replace(&self, captured: &str) -> String {
+        format!("{}{}{}", self.prefix, captured, self.suffix)
+    }
+}
+
+/// A source locator with a `*` in the table name, such as
+/// `postgres://host/db#public.*` or `bigquery:project:dataset.*`.
+#[derive(Clone, Debug)]
+pub(crate) struct WildcardLocator {
+    /// The locator of the database, dataset or schema containing our tables.
+    container: String,
+    /// The separator between `container` and the table name.
+    separator: char,
+    /// A pattern matching table names.
+    name: WildcardPattern,
+}
+
+impl WildcardLocator {
+    /// Parse `s` as a wildcard locator, returning `None` if it doesn't contain
+    /// a `*`, or if it's a path glob handled by the driver itself.
+    ///
+    /// We split the table name from the container at the first `#` if there
+    /// is one, or at the last `.` before the `*` otherwise.
+    pub(crate) fn parse(s: &str) -> Result<Option<Self>> {
+        if PATH_GLOB_SCHEMES.iter().any(|scheme| s.starts_with(scheme)) {
+            return Ok(None);
+        }
+        let star = match s.find('*') {
+            Some(star) => star,
+            None => return Ok(None),
+        };
+        let separator_idx = s[..star]
+            .find('#')
+            .or_else(|| s[..star].rfind('.'))
+            .ok_or_else(|| {
+                format_err!(
+                    "expected a wildcard like `#schema.*` or `dataset.*`: {:?}",
+                    s
+                )
+            })?;
+        let separator = if s.as_bytes()[separator_idx] == b'#' {
+            '#'
+        } else {
+            '.'
+        };
+        let name = WildcardPattern::parse(&s[separator_idx + 1..])?
+            .expect("wildcard should contain `*`");
+        Ok(Some(WildcardLocator {
+            container: s[..separator_idx].to_owned(),
+            separator,
+            name,
+        }))
+    }
+
+    /// The locator of the database, dataset or schema containing our tables,
+    /// suitable for passing to [`LocatorStatic::list`].
+    pub(crate) fn container(&self) -> &str {
+        &self.container
+    }
+
+    /// If the table `name` (as returned by [`LocatorStatic::list`]) matches
+    /// this wildcard, return the part matched by the `*`.
+    pub(crate) fn capture<'a>(&self, name: &'a str) -> Option<&'a str> {
+        self.name.capture(name)
+    }
+
+    /// Build a locator for a single table by replacing the `*` with
+    /// `captured`.
+    pub(crate) fn replace(&self, captured: &str) -> String {
+        format!(
+            "{}{}{}",
+            self.container,
+            self.separator,
+            self.name.replace(captured)
+        )
+    }
+}
+
+/// A path or URL containing glob patterns, such as `data/**/*.jsonl` or
+/// `s3://bucket/exports/2024-*/part-*.csv`. `*` and `?` match within a single
+/// path component, and a `**` component matches any number of directories.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PathGlob {
+    /// The directory containing all possible matches, with a trailing `/`.
+    base: String,
+    /// The full pattern.
+    pattern: String,
+}
+
+impl PathGlob {
+    /// Parse `s` as a glob, returning `None` if it doesn't contain any glob
+    /// characters.
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        let first_glob_char = s.find(GLOB_CHARS)?;
+        Some(match s[..first_glob_char].rfind('/') {
+            Some(i) => PathGlob {
+                base: s[..=i].to_owned(),
+                pattern: s.to_owned(),
+            },
+            // Relative paths like `*.csv` are in the current directory.
+            None => PathGlob {
+                base: "./".to_owned(),
+                pattern: format!("./{}", s),
+            },
+        })
+    }
+
+    /// The directory containing all possible matches, with a trailing `/`.
+    /// We list this directory and check each file using [`PathGlob::matches`].
+    pub(crate) fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Does `path` match this glob?
+    pub(crate) fn matches(&self, path: &str) -> bool {
+        let pattern = self.pattern.split('/').collect::<Vec<_>>();
+        let path = path.split('/').collect::<Vec<_>>();
+        path_components_match(&pattern, &path)
+    }
+}
+
+impl fmt::Display for PathGlob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pattern.fmt(f)
+    }
+}
+
+/// Do the components of `path` match the components of `pattern`?
+fn path_components_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", pattern_rest)) => {
+            (0..=path.len()).any(|i| path_components_match(pattern_rest, &path[i..]))
+        }
+        Some((glob, pattern_rest)) => {
+            path.split_first().is_some_and(|(name, path_rest)| {
+                glob_matches(glob, name)
+                    && path_components_match(pattern_rest, path_rest)
+            })
+        }
+    }
+}
+
+/// Does `name` match the shell-style `glob`? We support `*` (which matches
+/// any string) and `?` (which matches any one character).
+pub(crate) fn glob_matches(glob: &str, name: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    // Classic backtracking matcher. We only need to remember our most recent
+    // `*`, because any later match for it would also work for earlier ones.
+    let (mut g, mut n) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match glob.get(g) {
+            Some('*') => {
+                last_star = Some((g, n));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                g += 1;
+                n += 1;
+            }
+            _ => match last_star {
+                Some((star_g, star_n)) => {
+                    last_star = Some((star_g, star_n + 1));
+                    g = star_g + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[test]
+fn parse_wildcard_locators() {
+    let pg = WildcardLocator::parse("postgres://host/db#public.*")
+        .unwrap()
+        .unwrap();
+    assert_eq!(pg.container(), "postgres://host/db");
+    assert_eq!(pg.capture("public.users"), Some("users"));
+    assert_eq!(pg.capture("other.users"), None);
+    assert_eq!(pg.replace("users"), "postgres://host/db#public.users");
+
+    let bq = WildcardLocator::parse("bigquery:project:dataset.*")
+        .unwrap()
+        .unwrap();
+    assert_eq!(bq.container(), "bigquery:project:dataset");
+    assert_eq!(bq.capture("users"), Some("users"));
+    assert_eq!(bq.replace("users"), "bigquery:project:dataset.users");
+
+    assert!(WildcardLocator::parse("postgres://host/db#public.users")
+        .unwrap()
+        .is_none());
+    assert!(WildcardLocator::parse("postgres://host/db#*.*").is_err());
+    assert!(WildcardLocator::parse("postgres://host/db")
+        .unwrap()
+        .is_none());
+    assert!(WildcardLocator::parse("csv:*").unwrap().is_none());
+    assert!(WildcardLocator::parse("s3://bucket/2024-*/x.csv")
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn replace_wildcard_patterns() {
+    let pattern = WildcardPattern::parse("csv:out/*.csv").unwrap().unwrap();
+    assert_eq!(pattern.replace("users"), "csv:out/users.csv");
+    assert!(WildcardPattern::parse("csv:out/users.csv")
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn match_globs() {
+    assert!(glob_matches("*", "users"));
+    assert!(glob_matches("user*", "users"));
+    assert!(glob_matches("*_tmp", "users_tmp"));
+    assert!(glob_matches("u?ers", "users"));
+    assert!(glob_matches("*s*s", "sessions"));
+    assert!(!glob_matches("user", "users"));
+    assert!(!glob_matches("*_tmp", "users"));
+    assert!(!glob_matches("?", ""));
+}
+
+#[test]
+fn match_path_globs() {
+    let glob = PathGlob::parse("s3://bucket/exports/2024-*/part-*.csv").unwrap();
+    assert_eq!(glob.base(), "s3://bucket/exports/");
+    assert!(glob.matches("s3://bucket/exports/2024-01-02/part-0001.csv"));
+    assert!(!glob.matches("s3://bucket/exports/2023-12-31/part-0001.csv"));
+    assert!(!glob.matches("s3://bucket/exports/2024-01-02/x/part-0001.csv"));
+
+    let glob = PathGlob::parse("data/**/*.jsonl").unwrap();
+    assert_eq!(glob.base(), "data/");
+    assert!(glob.matches("data/a.jsonl"));
+    assert!(glob.matches("data/x/y/a.jsonl"));
+    assert!(!glob.matches("data/x/a.csv"));
+
+    let glob = PathGlob::parse("day?.csv").unwrap();
+    assert_eq!(glob.base(), "./");
+    assert!(glob.matches("./day1.csv"));
+    assert!(!glob.matches("./day10.csv"));
+
+    assert!(PathGlob::parse("data/day1.csv").is_none());
+}
@@ -171,3 +171,925 @@ fn cp_csv_to_jsonl_piped() {
         .expect_success();
     assert!(output.stdout_str().starts_with('{'));
 }
+
+#[test]
+fn cp_csv_with_bad_rows_and_rejects() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_bad_rows_and_rejects");
+    let input = testdir.src_path("fixtures/bad_rows/input.csv");
+    let schema = testdir.src_path("fixtures/bad_rows/schema.sql");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--max-bad-rows=2",
+            "--rejects=file:rejects.csv",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    let expected_path = testdir.src_path("fixtures/bad_rows/expected.csv");
+    let expected = fs::read_to_string(expected_path).unwrap();
+    testdir.expect_file_contents("out.csv", expected);
+    testdir.expect_contains("rejects.csv", "stream,line,data,error\n");
+    testdir.expect_contains("rejects.csv", "input,3,\"two,Bob\",");
+    testdir.expect_contains("rejects.csv", "input,4,\"3,\",");
+}
+
+#[test]
+fn cp_jsonl_with_bad_rows() {
+    let testdir = TestDir::new("dbcrossbar", "cp_jsonl_with_bad_rows");
+    let input = testdir.src_path("fixtures/bad_rows/input.jsonl");
+    let schema = testdir.src_path("fixtures/bad_rows/schema.sql");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--max-bad-rows=2",
+            "--rejects=file:rejects.csv",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    let expected_path = testdir.src_path("fixtures/bad_rows/expected.csv");
+    let expected = fs::read_to_string(expected_path).unwrap();
+    testdir.expect_file_contents("out.csv", expected);
+    testdir.expect_contains("rejects.csv", "input,2,not json,");
+    testdir.expect_contains("rejects.csv", "input,3,\"{\"\"id\"\":3}\",");
+}
+
+#[test]
+fn cp_masks_rejected_rows() {
+    let testdir = TestDir::new("dbcrossbar", "cp_masks_rejected_rows");
+    let schema = testdir.src_path("fixtures/bad_rows/schema.sql");
+    let cp = |input: &str, output: &str| {
+        let input = testdir.src_path(input);
+        let mut cmd = testdir.cmd();
+        cmd.args([
+            "cp",
+            "--max-bad-rows=2",
+            "--rejects=file:rejects.csv",
+            "--mask=name=null",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            &format!("file:{}", output),
+        ])
+        .tee_output()
+    };
+
+    // CSV rows are checked after masking, so we can write them to --rejects.
+    cp("fixtures/bad_rows/input.csv", "out.csv").expect_success();
+    testdir.expect_contains("rejects.csv", "input,3,\"two,\",");
+    let rejects = fs::read_to_string(testdir.path("rejects.csv")).unwrap();
+    assert!(!rejects.contains("Bob"));
+
+    // JSON Lines rows are rejected before masking, so we refuse to write them.
+    let output = cp("fixtures/bad_rows/input.jsonl", "out2.csv").expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("rejected rows would not be masked"));
+}
+
+#[test]
+fn cp_fails_with_too_many_bad_rows() {
+    let testdir = TestDir::new("dbcrossbar", "cp_fails_with_too_many_bad_rows");
+    let input = testdir.src_path("fixtures/bad_rows/input.csv");
+    let schema = testdir.src_path("fixtures/bad_rows/schema.sql");
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--max-bad-rows=1",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output.stderr_str().contains("found more than 1 bad rows"));
+}
+
+#[test]
+fn cp_rejects_requires_max_bad_rows() {
+    let testdir = TestDir::new("dbcrossbar", "cp_rejects_requires_max_bad_rows");
+    let input = testdir.src_path("fixtures/bad_rows/input.csv");
+    let schema = testdir.src_path("fixtures/bad_rows/schema.sql");
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--rejects=file:rejects.csv",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("--rejects requires --max-bad-rows"));
+}
+
+#[test]
+fn cp_csv_with_select_and_rename() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_select_and_rename");
+    let input = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--select=title,author_id",
+            "--rename=author_id=user_id",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir
+        .expect_file_contents("out.csv", "title,user_id\nWelcome,1\nMy weekend,2\n");
+}
+
+#[test]
+fn cp_select_unknown_column_fails() {
+    let testdir = TestDir::new("dbcrossbar", "cp_select_unknown_column_fails");
+    let input = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--select=title,body",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("cannot --select unknown column \"body\""));
+}
+
+#[test]
+fn cp_csv_with_masks() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_masks");
+    let input = testdir.src_path("fixtures/masking/input.csv");
+    let schema = testdir.src_path("fixtures/masking/schema.sql");
+    let mask_config = testdir.src_path("fixtures/masking/mask.toml");
+    let expected = testdir.src_path("fixtures/masking/expected.csv");
+    testdir
+        .cmd()
+        .env("DBCROSSBAR_MASK_KEY", "test-key")
+        .args([
+            "cp",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("--mask-config={}", mask_config.display()),
+            "--mask=phone=tokenize",
+            "--mask=notes=null",
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out.csv", fs::read_to_string(expected).unwrap());
+}
+
+#[test]
+fn cp_hmac_mask_requires_key() {
+    let testdir = TestDir::new("dbcrossbar", "cp_hmac_mask_requires_key");
+    let input = testdir.src_path("fixtures/masking/input.csv");
+    let schema = testdir.src_path("fixtures/masking/schema.sql");
+    let output = testdir
+        .cmd()
+        .env_remove("DBCROSSBAR_MASK_KEY")
+        .args([
+            "cp",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            "--mask=email=hmac",
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("DBCROSSBAR_MASK_KEY must be set"));
+}
+
+#[test]
+fn cp_csv_with_limit() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_limit");
+    let input = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--limit=1",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "author_id,title\n1,Welcome\n");
+}
+
+#[test]
+fn cp_csv_with_filter() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_filter");
+    let input = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--filter=author_id > 1 OR title IN ('Nope')",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "author_id,title\n2,My weekend\n");
+
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--filter=author_id = 'one'",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out2.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("cannot compare a number with a string"));
+}
+
+#[test]
+fn cp_csv_with_dedupe() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_dedupe");
+    let input = testdir.src_path("fixtures/dedupe/input.csv");
+    let schema = testdir.src_path("fixtures/dedupe/schema.sql");
+    let expected = testdir.src_path("fixtures/dedupe/expected.csv");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--dedupe-on=id",
+            "--keep=latest-by=updated_at",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", input.display()),
+            "csv:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out.csv", fs::read_to_string(&expected).unwrap());
+
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--dedupe-on=id",
+            "--select=name",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", input.display()),
+            "csv:out2.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("--dedupe-on uses column \"id\""));
+}
+
+#[test]
+fn cp_csv_with_order_by() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_order_by");
+    let input = testdir.src_path("fixtures/dedupe/input.csv");
+    let schema = testdir.src_path("fixtures/dedupe/schema.sql");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--order-by=updated_at DESC,id",
+            "--stream-size=60",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", input.display()),
+            "csv:out/",
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Our chunks should be numbered in order.
+    let mut paths = fs::read_dir(testdir.path("out"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(paths.len() > 1);
+    let ids = paths
+        .iter()
+        .flat_map(|path| {
+            let mut rdr = csv::Reader::from_path(path).unwrap();
+            rdr.records()
+                .map(|r| r.unwrap()[1].to_owned())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ids,
+        &[
+            "first (updated)",
+            "first (stale)",
+            "third",
+            "first",
+            "second",
+            "second (no date)",
+        ],
+    );
+}
+
+#[test]
+fn cp_csv_with_partition_by() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_partition_by");
+    let input = testdir.src_path("fixtures/dedupe/input.csv");
+    let schema = testdir.src_path("fixtures/dedupe/schema.sql");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--partition-by=id",
+            "--order-by=id,name",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", input.display()),
+            "csv:out/",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents(
+        "out/id=1/part-0001.csv",
+        "name,updated_at\nfirst,2024-01-01T00:00:00Z\nfirst (stale),2024-02-01T00:00:00Z\nfirst (updated),2024-03-01T00:00:00Z\n",
+    );
+    testdir.expect_file_contents(
+        "out/id=2/part-0001.csv",
+        "name,updated_at\nsecond,2024-01-01T00:00:00Z\nsecond (no date),\n",
+    );
+    testdir.expect_file_contents(
+        "out/id=3/part-0001.csv",
+        "name,updated_at\nthird,2024-02-01T00:00:00Z\n",
+    );
+
+    // With only one open partition at a time, unsorted input is split into
+    // several parts.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--partition-by=id",
+            "--max-streams=1",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", input.display()),
+            "csv:parts/",
+        ])
+        .tee_output()
+        .expect_success();
+    assert_eq!(fs::read_dir(testdir.path("parts/id=1")).unwrap().count(), 3);
+}
+
+#[test]
+fn cp_from_partitioned_csv_directory() {
+    let testdir = TestDir::new("dbcrossbar", "cp_from_partitioned_csv_directory");
+    let input = testdir.src_path("fixtures/dedupe/input.csv");
+    let schema = testdir.src_path("fixtures/dedupe/schema.sql");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--partition-by=id",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", input.display()),
+            "csv:partitioned/",
+        ])
+        .tee_output()
+        .expect_success();
+
+    // This file doesn't have the right columns, so we'd fail if we didn't skip
+    // its partition.
+    testdir.create_file("partitioned/id=1/part-9999.csv", "wrong\nx\n");
+
+    // Read our partitions back, skipping `id=1`.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--filter=id >= 2",
+            "--order-by=id,name",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            "csv:partitioned/",
+            "csv:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents(
+        "out.csv",
+        "id,name,updated_at\n2,second,2024-01-01T00:00:00Z\n2,second (no date),\n3,third,2024-02-01T00:00:00Z\n",
+    );
+}
+
+#[test]
+fn cp_from_csv_glob() {
+    let testdir = TestDir::new("dbcrossbar", "cp_from_csv_glob");
+    testdir.create_file("schema.sql", "CREATE TABLE t (id INT NOT NULL, name TEXT);");
+    testdir.create_file("in/a.csv", "id,name\n1,one\n");
+    testdir.create_file("in/nested/b.csv", "id,name\n2,two\n");
+    testdir.create_file("in/nested/c.txt", "not,csv\n");
+    testdir.create_file("in/other/d.csv.bak", "wrong\nx\n");
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--order-by=id",
+            "--schema=postgres-sql:schema.sql",
+            "csv:in/**/*.csv",
+            "csv:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "id,name\n1,one\n2,two\n");
+
+    // We can't write to a glob.
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "csv:out.csv",
+            "csv:out/*.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output.stderr_str().contains("cannot write to glob"));
+}
+
+#[test]
+fn cp_csv_with_split_files() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_split_files");
+    testdir.create_file("schema.sql", "CREATE TABLE t (id INT NOT NULL, text TEXT);");
+    let mut csv = "id,text\n".to_owned();
+    for i in 0..40 {
+        csv.push_str(&format!("{},\"line {}\nand, \"\"more\"\"\"\n", i, i));
+    }
+    testdir.create_file("big.csv", &csv);
+
+    // By default, we read the file as a single stream with its own name.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--max-streams=4",
+            "--schema=postgres-sql:schema.sql",
+            "csv:big.csv",
+            "csv:unsplit/",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("unsplit/big.csv", &csv);
+    testdir.expect_no_such_path("unsplit/big_0001.csv");
+
+    // With `--split-files`, we read it as several ranges.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--max-streams=4",
+            "--split-files=100",
+            "--schema=postgres-sql:schema.sql",
+            "csv:big.csv",
+            "csv:split/",
+        ])
+        .tee_output()
+        .expect_success();
+    let mut records = vec![];
+    for i in 1..=4 {
+        let path = testdir.path(format!("split/big_{:04}.csv", i));
+        let mut rdr = csv::Reader::from_path(path).unwrap();
+        for record in rdr.records() {
+            records.push(record.unwrap());
+        }
+    }
+    let expected = csv::Reader::from_reader(csv.as_bytes())
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(records, expected);
+    testdir.expect_no_such_path("split/big.csv");
+}
+
+#[test]
+fn cp_csv_with_rows_per_file_and_file_name_template() {
+    let testdir = TestDir::new(
+        "dbcrossbar",
+        "cp_csv_with_rows_per_file_and_file_name_template",
+    );
+    testdir.create_file("schema.sql", "CREATE TABLE orders (id INT NOT NULL);");
+    testdir.create_file("in.csv", "id\n1\n2\n3\n4\n5\n");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--rows-per-file=2",
+            "--file-name-template={table}-{n:03}.csv",
+            "--schema=postgres-sql:schema.sql",
+            "csv:in.csv",
+            "csv:out/",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out/orders-001.csv", "id\n1\n2\n");
+    testdir.expect_file_contents("out/orders-002.csv", "id\n3\n4\n");
+    testdir.expect_file_contents("out/orders-003.csv", "id\n5\n");
+    testdir.expect_no_such_path("out/orders-004.csv");
+}
+
+#[test]
+fn cp_csv_append_to_file_and_directory() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_append_to_file_and_directory");
+    testdir.create_file("schema.sql", "CREATE TABLE log (id INT NOT NULL);");
+    testdir.create_file("day1.csv", "id\n1\n2\n");
+    testdir.create_file("day2.csv", "id\n3\n");
+    for (src, dest) in [
+        ("csv:day1.csv", "csv:all.csv"),
+        ("csv:day2.csv", "csv:all.csv"),
+        ("csv:day1.csv", "csv:days/"),
+        ("csv:day2.csv", "csv:days/"),
+        ("csv:day2.csv", "csv:days/"),
+    ] {
+        testdir
+            .cmd()
+            .args([
+                "cp",
+                "--if-exists=append",
+                "--schema=postgres-sql:schema.sql",
+                src,
+                dest,
+            ])
+            .tee_output()
+            .expect_success();
+    }
+    testdir.expect_file_contents("all.csv", "id\n1\n2\n3\n");
+    testdir.expect_file_contents("days/day1.csv", "id\n1\n2\n");
+    testdir.expect_file_contents("days/day2.csv", "id\n3\n");
+    testdir.expect_file_contents("days/day2-2.csv", "id\n3\n");
+
+    // Appending data with different columns should fail.
+    testdir.create_file("other.sql", "CREATE TABLE log (other INT NOT NULL);");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=append",
+            "--schema=postgres-sql:other.sql",
+            "csv:day2.csv",
+            "csv:all.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+    testdir.expect_file_contents("all.csv", "id\n1\n2\n3\n");
+}
+
+#[test]
+fn cp_watch_once_copies_new_files() {
+    let testdir = TestDir::new("dbcrossbar", "cp_watch_once_copies_new_files");
+    testdir.create_file("schema.sql", "CREATE TABLE log (id INT NOT NULL);");
+    let watch = |testdir: &TestDir| {
+        testdir
+            .cmd()
+            .args([
+                "cp",
+                "--watch",
+                "--watch-once",
+                "--watch-ledger=ledger.txt",
+                "--watch-settle=0",
+                "--if-exists=append",
+                "--schema=postgres-sql:schema.sql",
+                "csv:drops/",
+                "csv:all.csv",
+            ])
+            .tee_output()
+            .expect_success();
+    };
+
+    testdir.create_file("drops/a.csv", "id\n1\n");
+    watch(&testdir);
+    testdir.create_file("drops/b.csv", "id\n2\n");
+    watch(&testdir);
+    watch(&testdir);
+    testdir.expect_file_contents("all.csv", "id\n1\n2\n");
+    testdir.expect_file_contents("ledger.txt", "csv:drops/a.csv\ncsv:drops/b.csv\n");
+
+    // --watch only makes sense with append or upsert.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--watch",
+            "--watch-ledger=ledger.txt",
+            "--schema=postgres-sql:schema.sql",
+            "csv:drops/",
+            "csv:all.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+}
+
+#[test]
+fn cp_watch_once_appends_rejects_from_each_file() {
+    let testdir =
+        TestDir::new("dbcrossbar", "cp_watch_once_appends_rejects_from_each_file");
+    testdir.create_file("schema.sql", "CREATE TABLE log (id INT NOT NULL);");
+    testdir.create_file("drops/a.csv", "id\n1\nx\n");
+    testdir.create_file("drops/b.csv", "id\n2\ny\n");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--watch",
+            "--watch-once",
+            "--watch-ledger=ledger.txt",
+            "--watch-settle=0",
+            "--if-exists=append",
+            "--max-bad-rows=1",
+            "--rejects=csv:rejects.csv",
+            "--schema=postgres-sql:schema.sql",
+            "csv:drops/",
+            "csv:all.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("all.csv", "id\n1\n2\n");
+    let rejects = fs::read_to_string(testdir.path("rejects.csv")).unwrap();
+    let mut lines = rejects.lines();
+    assert_eq!(lines.next(), Some("stream,line,data,error"));
+    let data = lines
+        .map(|line| line.split(',').nth(2).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(data, ["x", "y"]);
+}
+
+#[test]
+fn cp_watch_once_copies_new_files_from_file_directory() {
+    let testdir = TestDir::new(
+        "dbcrossbar",
+        "cp_watch_once_copies_new_files_from_file_directory",
+    );
+    testdir.create_file("schema.sql", "CREATE TABLE log (id INT NOT NULL);");
+    let watch = |testdir: &TestDir| {
+        testdir
+            .cmd()
+            .args([
+                "cp",
+                "--watch",
+                "--watch-once",
+                "--watch-ledger=ledger.txt",
+                "--watch-settle=0",
+                "--if-exists=append",
+                "--schema=postgres-sql:schema.sql",
+                "file:drops/",
+                "file:all.jsonl",
+            ])
+            .tee_output()
+            .expect_success();
+    };
+
+    testdir.create_file("drops/a.csv", "id\n1\n");
+    testdir.create_file("drops/b.jsonl", "{\"id\":2}\n");
+    watch(&testdir);
+    testdir.create_file("drops/c.csv", "id\n3\n");
+    watch(&testdir);
+    testdir.expect_file_contents("all.jsonl", "{\"id\":1}\n{\"id\":2}\n{\"id\":3}\n");
+    testdir.expect_file_contents(
+        "ledger.txt",
+        "file:drops/a.csv\nfile:drops/b.jsonl\nfile:drops/c.csv\n",
+    );
+}
+
+#[test]
+fn cp_csv_with_seeded_sample() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_with_seeded_sample");
+    let input = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    for out in ["out1.csv", "out2.csv"] {
+        testdir
+            .cmd()
+            .args([
+                "cp",
+                "--sample=0.5",
+                "--sample-seed=42",
+                &format!("--schema=postgres-sql:{}", schema.display()),
+                &format!("file:{}", input.display()),
+                &format!("file:{}", out),
+            ])
+            .tee_output()
+            .expect_success();
+    }
+    let out1 = fs::read_to_string(testdir.path("out1.csv")).unwrap();
+    let out2 = fs::read_to_string(testdir.path("out2.csv")).unwrap();
+    assert!(out1.starts_with("author_id,title\n"));
+    assert_eq!(out1, out2);
+
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--sample=1.5",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("file:{}", input.display()),
+            "file:out3.csv",
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("--sample must be greater than 0"));
+}
+
+#[test]
+fn cp_wildcard_requires_wildcard_destination() {
+    let testdir =
+        TestDir::new("dbcrossbar", "cp_wildcard_requires_wildcard_destination");
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "postgres://localhost:5432/db#public.*",
+            "bigquery:project:dataset.table",
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("the destination must contain a `*` if the source does"));
+}
+
+#[test]
+fn cp_many_sources_with_source_column() {
+    let testdir = TestDir::new("dbcrossbar", "cp_many_sources_with_source_column");
+    testdir.create_file("us.csv", "id,name\n1,a\n");
+    testdir.create_file("eu.csv", "name,id\nb,2\n");
+    let us = testdir.path("us.csv");
+    let eu = testdir.path("eu.csv");
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--add-source-column=source",
+            &format!("file:{}", us.display()),
+            &format!("file:{}", eu.display()),
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents(
+        "out.csv",
+        format!(
+            "id,name,source\n1,a,file:{}\n2,b,file:{}\n",
+            us.display(),
+            eu.display(),
+        ),
+    );
+
+    // Sources can also be listed in a file.
+    testdir.create_file(
+        "sources.txt",
+        format!("file:{}\n\n# Europe\nfile:{}\n", us.display(), eu.display()),
+    );
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--from-list=sources.txt",
+            "--add-source-column=source",
+            "--source-column-value=stream",
+            "file:out2.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out2.csv", "id,name,source\n1,a,us\n2,b,eu\n");
+}
+
+#[test]
+fn cp_flatten_structs_and_explode() {
+    let testdir = TestDir::new("dbcrossbar", "cp_flatten_structs_and_explode");
+    testdir.create_file(
+        "schema.json",
+        r#"{
+  "name": "people",
+  "columns": [
+    { "name": "id", "is_nullable": false, "data_type": "int64" },
+    { "name": "tags", "is_nullable": true, "data_type": { "array": "text" } },
+    {
+      "name": "address",
+      "is_nullable": true,
+      "data_type": {
+        "struct": [
+          { "name": "city", "is_nullable": true, "data_type": "text" },
+          { "name": "zip", "is_nullable": true, "data_type": "text" }
+        ]
+      }
+    }
+  ]
+}"#,
+    );
+    testdir.create_file(
+        "in.csv",
+        r#"id,tags,address
+1,"[""a"",""b""]","{""city"":""Paris"",""zip"":""75001""}"
+2,[],
+"#,
+    );
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--schema=dbcrossbar-schema:schema.json",
+            "--flatten-structs",
+            "--explode=tags",
+            "file:in.csv",
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents(
+        "out.csv",
+        "id,tags,address_city,address_zip\n1,a,Paris,75001\n1,b,Paris,75001\n",
+    );
+}
+
+#[test]
+fn cp_from_time_zone() {
+    let testdir = TestDir::new("dbcrossbar", "cp_from_time_zone");
+    testdir.create_file(
+        "schema.sql",
+        "CREATE TABLE t (id INT NOT NULL, created_at TIMESTAMP);",
+    );
+    testdir.create_file(
+        "in.csv",
+        "id,created_at\n1,2024-01-15 09:30:00\n2,2024-07-15 09:30:00\n3,\n",
+    );
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--schema=postgres-sql:schema.sql",
+            "--from-time-zone=America/New_York",
+            "file:in.csv",
+            "file:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents(
+        "out.csv",
+        "id,created_at\n1,2024-01-15T14:30:00\n2,2024-07-15T13:30:00\n3,\n",
+    );
+}
+
+#[test]
+fn cp_verify_checks_mode_and_driver_support() {
+    let testdir =
+        TestDir::new("dbcrossbar", "cp_verify_checks_mode_and_driver_support");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let verify = |testdir: &TestDir, flag: &str| {
+        testdir
+            .cmd()
+            .args([
+                "cp",
+                "--if-exists=overwrite",
+                flag,
+                &format!("--schema=postgres-sql:{}", schema.display()),
+                &format!("csv:{}", src.display()),
+                "csv:out.csv",
+            ])
+            .tee_output()
+            .expect_failure()
+            .stderr_str()
+            .to_owned()
+    };
+    assert!(verify(&testdir, "--verify").contains("does not support counting rows"));
+    assert!(
+        verify(&testdir, "--verify=count").contains("does not support counting rows")
+    );
+    assert!(verify(&testdir, "--verify=fingerprint")
+        .contains("does not support fingerprints"));
+    assert!(verify(&testdir, "--verify=checksum").contains("unknown verify mode"));
+}
@@ -279,3 +279,509 @@ fn cp_pg_tricky_column_types() {
     let actual = fs::read_to_string(testdir.path("out.csv")).unwrap();
     assert_diff!(&expected, &actual, ",", 0);
 }
+
+#[test]
+#[ignore]
+fn cp_csv_to_postgres_append_with_schema_evolution() {
+    let testdir = TestDir::new(
+        "dbcrossbar",
+        "cp_csv_to_postgres_append_with_schema_evolution",
+    );
+    let old_src = testdir.src_path("fixtures/schema_evolution/old.csv");
+    let old_schema = testdir.src_path("fixtures/schema_evolution/old.sql");
+    let new_src = testdir.src_path("fixtures/schema_evolution/new.csv");
+    let new_schema = testdir.src_path("fixtures/schema_evolution/new.sql");
+    let expected = testdir.src_path("fixtures/schema_evolution/expected.csv");
+    let pg_table =
+        post_test_table_url("cp_csv_to_postgres_append_with_schema_evolution");
+
+    // Create our table using the old schema.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            &format!("--schema=postgres-sql:{}", old_schema.display()),
+            &format!("csv:{}", old_src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Append data with a new column and a wider type.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=append",
+            "--allow-schema-evolution",
+            &format!("--schema=postgres-sql:{}", new_schema.display()),
+            &format!("csv:{}", new_src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Read it back using the evolved schema.
+    testdir
+        .cmd()
+        .args(["cp", &pg_table, "csv:out.csv"])
+        .tee_output()
+        .expect_success();
+    let expected = fs::read_to_string(expected).unwrap();
+    testdir.expect_file_contents("out.csv", expected);
+}
+
+#[test]
+fn cp_allow_schema_evolution_requires_append() {
+    let testdir =
+        TestDir::new("dbcrossbar", "cp_allow_schema_evolution_requires_append");
+    let src = testdir.src_path("fixtures/schema_evolution/new.csv");
+    let schema = testdir.src_path("fixtures/schema_evolution/new.sql");
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            "--allow-schema-evolution",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &post_test_table_url("cp_allow_schema_evolution_requires_append"),
+        ])
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("--allow-schema-evolution requires --if-exists=append"));
+}
+
+#[test]
+#[ignore]
+fn cp_csv_to_postgres_with_bad_rows() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_postgres_with_bad_rows");
+    let src = testdir.src_path("fixtures/bad_rows/input.csv");
+    let schema = testdir.src_path("fixtures/bad_rows/schema.sql");
+    let expected = testdir.src_path("fixtures/bad_rows/expected.csv");
+    let pg_table = post_test_table_url("cp_csv_to_postgres_with_bad_rows");
+
+    // Load our data, skipping bad rows.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            "--max-bad-rows=2",
+            "--rejects=csv:rejects.csv",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_contains("rejects.csv", "input,3,\"two,Bob\",");
+
+    // Read it back.
+    testdir
+        .cmd()
+        .args(["cp", &pg_table, "csv:out.csv"])
+        .tee_output()
+        .expect_success();
+    let expected = fs::read_to_string(expected).unwrap();
+    testdir.expect_file_contents("out.csv", expected);
+}
+
+#[test]
+#[ignore]
+fn cp_postgres_to_csv_with_select_and_rename() {
+    let testdir =
+        TestDir::new("dbcrossbar", "cp_postgres_to_csv_with_select_and_rename");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let pg_table = post_test_table_url("cp_postgres_to_csv_with_select_and_rename");
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Only export `title`, and give it a new name.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--select=title",
+            "--rename=title=headline",
+            &pg_table,
+            "csv:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "headline\nWelcome\nMy weekend\n");
+}
+
+#[test]
+#[ignore]
+fn cp_postgres_to_csv_with_limit_and_sample() {
+    let testdir =
+        TestDir::new("dbcrossbar", "cp_postgres_to_csv_with_limit_and_sample");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let pg_table = post_test_table_url("cp_postgres_to_csv_with_limit_and_sample");
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // `--limit` and a seeded `--sample` are both pushed down to PostgreSQL.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--select=title",
+            "--limit=1",
+            "--sample=1",
+            "--sample-seed=42",
+            &pg_table,
+            "csv:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "title\nWelcome\n");
+}
+
+#[test]
+#[ignore]
+fn cp_postgres_to_csv_with_filter() {
+    let testdir = TestDir::new("dbcrossbar", "cp_postgres_to_csv_with_filter");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let pg_table = post_test_table_url("cp_postgres_to_csv_with_filter");
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // `--filter` is compiled to SQL, so it can use columns we don't select.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--select=title",
+            "--filter=NOT (author_id IN (2, 3) OR title IS NULL)",
+            &pg_table,
+            "csv:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("out.csv", "title\nWelcome\n");
+}
+
+#[test]
+#[ignore]
+fn cp_postgres_to_csv_with_text_filter() {
+    let testdir = TestDir::new("dbcrossbar", "cp_postgres_to_csv_with_text_filter");
+    let pg_table = post_test_table_url("cp_postgres_to_csv_with_text_filter");
+    testdir.create_file("schema.sql", "CREATE TABLE fruits (name TEXT NOT NULL);");
+    testdir.create_file("in.csv", "name\napple\nBanana\ncherry\nDate\n");
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            "--schema=postgres-sql:schema.sql",
+            "csv:in.csv",
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Text is compared by byte value, so upper case sorts before lower case,
+    // whatever the database's collation. We should get the same rows from
+    // PostgreSQL as we do when filtering locally.
+    for (src, out) in [("csv:in.csv", "local.csv"), (&pg_table[..], "pg.csv")] {
+        testdir
+            .cmd()
+            .args([
+                "cp",
+                "--schema=postgres-sql:schema.sql",
+                "--filter=name < 'b'",
+                "--order-by=name",
+                src,
+                &format!("csv:{}", out),
+            ])
+            .tee_output()
+            .expect_success();
+        testdir.expect_file_contents(out, "name\nBanana\nDate\napple\n");
+    }
+}
+
+#[test]
+#[ignore]
+fn cp_postgres_to_csv_with_dedupe() {
+    let testdir = TestDir::new("dbcrossbar", "cp_postgres_to_csv_with_dedupe");
+    let src = testdir.src_path("fixtures/dedupe/input.csv");
+    let schema = testdir.src_path("fixtures/dedupe/schema.sql");
+    let pg_table = post_test_table_url("cp_postgres_to_csv_with_dedupe");
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // This uses `DISTINCT ON`, which returns rows sorted by key.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--dedupe-on=id",
+            "--keep=latest-by=updated_at",
+            "--select=id,name",
+            &pg_table,
+            "csv:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents(
+        "out.csv",
+        "id,name\n1,first (updated)\n2,second\n3,third\n",
+    );
+}
+
+#[test]
+#[ignore]
+fn cp_postgres_to_csv_with_order_by() {
+    let testdir = TestDir::new("dbcrossbar", "cp_postgres_to_csv_with_order_by");
+    let src = testdir.src_path("fixtures/dedupe/input.csv");
+    let schema = testdir.src_path("fixtures/dedupe/schema.sql");
+    let pg_table = post_test_table_url("cp_postgres_to_csv_with_order_by");
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Sort by a column we don't select, and make sure `NULL` comes last.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--order-by=updated_at DESC,name",
+            "--select=name",
+            "--limit=5",
+            &pg_table,
+            "csv:out.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents(
+        "out.csv",
+        "name\nfirst (updated)\nfirst (stale)\nthird\nfirst\nsecond\n",
+    );
+}
+
+#[test]
+#[ignore]
+fn cp_csv_to_postgres_with_verify() {
+    let testdir = TestDir::new("dbcrossbar", "cp_csv_to_postgres_with_verify");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let pg_table = post_test_table_url("cp_csv_to_postgres_with_verify");
+    let pg_table2 = post_test_table_url("cp_csv_to_postgres_with_verify_2");
+
+    // We can't count rows in a CSV file.
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            "--verify",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("does not support counting rows"));
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Our source count must honor `--filter`.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            "--verify",
+            "--filter=author_id = 1",
+            &pg_table,
+            &pg_table2,
+        ])
+        .tee_output()
+        .expect_success();
+    let output = testdir
+        .cmd()
+        .args(["count", &pg_table2])
+        .tee_output()
+        .expect_success();
+    assert_eq!(output.stdout_str().trim(), "1");
+
+    // Appending would always make our counts differ.
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=append",
+            "--verify",
+            &pg_table,
+            &pg_table2,
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output.stderr_str().contains("--verify can only be used"));
+}
+
+#[test]
+#[ignore]
+fn cp_postgres_to_postgres_with_verify_fingerprint() {
+    let testdir = TestDir::new(
+        "dbcrossbar",
+        "cp_postgres_to_postgres_with_verify_fingerprint",
+    );
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let pg_table = post_test_table_url("cp_postgres_with_verify_fingerprint");
+    let pg_table2 = post_test_table_url("cp_postgres_with_verify_fingerprint_2");
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Our source fingerprint must honor `--filter`, and renamed columns are
+    // compared with the original columns.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            "--verify=fingerprint",
+            "--filter=author_id = 1",
+            "--rename=title=headline",
+            &pg_table,
+            &pg_table2,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Masks change our values, so we can't compare fingerprints.
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            "--verify=fingerprint",
+            "--mask=title=null",
+            &pg_table,
+            &pg_table2,
+        ])
+        .tee_output()
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("--verify=fingerprint cannot be used with --mask"));
+}
+
+#[test]
+#[ignore]
+fn cp_postgres_wildcard_to_csv() {
+    let testdir = TestDir::new("dbcrossbar", "cp_postgres_wildcard_to_csv");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+
+    for name in ["a", "b", "skipped"] {
+        testdir
+            .cmd()
+            .args([
+                "cp",
+                "--if-exists=overwrite",
+                &format!("--schema=postgres-sql:{}", schema.display()),
+                &format!("csv:{}", src.display()),
+                &post_test_table_url(&format!("cp_postgres_wildcard_{}", name)),
+            ])
+            .tee_output()
+            .expect_success();
+    }
+
+    // Copy every matching table except the one we exclude.
+    let output = testdir
+        .cmd()
+        .args([
+            "cp",
+            "--exclude=skip*",
+            &post_test_table_url("public.cp_postgres_wildcard_*"),
+            "csv:out/*.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    assert!(output.stdout_str().contains("| ok"));
+    let expected = fs::read_to_string(&src).unwrap();
+    testdir.expect_file_contents("out/a.csv", &expected);
+    testdir.expect_file_contents("out/b.csv", &expected);
+    assert!(!testdir.path("out/skipped.csv").exists());
+}
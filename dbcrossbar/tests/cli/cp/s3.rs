@@ -56,3 +56,45 @@ fn cp_csv_to_s3_to_csv() {
     let actual = fs::read_to_string(testdir.path("out/many_types.csv")).unwrap();
     assert_diff!(&expected, &actual, ",", 0);
 }
+
+#[test]
+#[ignore]
+fn cp_watch_once_copies_new_files_from_s3() {
+    let testdir = TestDir::new("dbcrossbar", "cp_watch_once_copies_new_files_from_s3");
+    testdir.create_file("schema.sql", "CREATE TABLE log (id INT NOT NULL);");
+    testdir.create_file("drops/a.csv", "id\n1\n");
+    let s3_dir = s3_test_dir_url("cp_watch_once_copies_new_files_from_s3");
+
+    // Upload a file to S3.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            "--schema=postgres-sql:schema.sql",
+            "csv:drops/",
+            &s3_dir,
+        ])
+        .tee_output()
+        .expect_success();
+
+    // Copy it back using `--watch`, which needs to read each S3 object
+    // separately.
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--watch",
+            "--watch-once",
+            "--watch-ledger=ledger.txt",
+            "--watch-settle=0",
+            "--if-exists=append",
+            "--schema=postgres-sql:schema.sql",
+            &s3_dir,
+            "csv:all.csv",
+        ])
+        .tee_output()
+        .expect_success();
+    testdir.expect_file_contents("all.csv", "id\n1\n");
+    testdir.expect_file_contents("ledger.txt", format!("{}a.csv\n", s3_dir));
+}
@@ -0,0 +1,46 @@
+//! Tests for the `head` subcommand.
+
+use cli_test_dir::*;
+
+#[test]
+fn head_csv_as_table() {
+    let testdir = TestDir::new("dbcrossbar", "head_csv_as_table");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let output = testdir
+        .cmd()
+        .args([
+            "head",
+            "-n",
+            "1",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+        ])
+        .tee_output()
+        .expect_success();
+    assert_eq!(
+        output.stdout_str(),
+        "author_id | title\n----------+--------\n        1 | Welcome\n",
+    );
+}
+
+#[test]
+fn head_csv_as_json() {
+    let testdir = TestDir::new("dbcrossbar", "head_csv_as_json");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let output = testdir
+        .cmd()
+        .args([
+            "head",
+            "--json",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+        ])
+        .tee_output()
+        .expect_success();
+    assert_eq!(
+        output.stdout_str(),
+        "{\"author_id\":1,\"title\":\"Welcome\"}\n{\"author_id\":2,\"title\":\"My weekend\"}\n",
+    );
+}
@@ -0,0 +1,64 @@
+//! Tests for the `ls` subcommand.
+
+use cli_test_dir::*;
+
+use super::cp::*;
+
+#[test]
+fn ls_unsupported_driver() {
+    let testdir = TestDir::new("dbcrossbar", "ls_unsupported_driver");
+    let output = testdir
+        .cmd()
+        .args(["ls", "postgres-sql:schema.sql"])
+        .tee_output()
+        .expect_failure();
+    assert!(output.stderr_str().contains("cannot list postgres-sql"));
+}
+
+#[test]
+fn ls_csv_directory() {
+    let testdir = TestDir::new("dbcrossbar", "ls_csv_directory");
+    testdir.create_file("data/b.csv", "id\n2\n");
+    testdir.create_file("data/a.csv", "id\n1\n");
+    let output = testdir
+        .cmd()
+        .args(["ls", "--json", "csv:data/"])
+        .tee_output()
+        .expect_success();
+    let stdout = output.stdout_str();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains(r#""locator":"csv:data/a.csv""#));
+    assert!(lines[1].contains(r#""name":"b.csv""#));
+}
+
+#[test]
+#[ignore]
+fn ls_postgres() {
+    let testdir = TestDir::new("dbcrossbar", "ls_postgres");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    let pg_table = post_test_table_url("ls_postgres");
+
+    testdir
+        .cmd()
+        .args([
+            "cp",
+            "--if-exists=overwrite",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+            &pg_table,
+        ])
+        .tee_output()
+        .expect_success();
+
+    let output = testdir
+        .cmd()
+        .args(["ls", "--json", &postgres_test_url()])
+        .tee_output()
+        .expect_success();
+    assert!(output
+        .stdout_str()
+        .contains("\"name\":\"public.ls_postgres\""));
+    assert!(output.stdout_str().contains("\"kind\":\"table\""));
+}
@@ -6,3 +6,8 @@ pub(crate) mod about;
 pub(crate) mod conv;
 pub(crate) mod count;
 pub(crate) mod cp;
+pub(crate) mod head;
+pub(crate) mod ls;
+pub(crate) mod migrate;
+pub(crate) mod run;
+pub(crate) mod validate;
@@ -0,0 +1,105 @@
+//! Tests for the `schema migrate` subcommand.
+
+use cli_test_dir::*;
+
+#[test]
+fn migrate_help_flag() {
+    let testdir = TestDir::new("dbcrossbar", "migrate_help_flag");
+    let output = testdir
+        .cmd()
+        .args(["schema", "migrate", "--help"])
+        .expect_success();
+    assert!(output.stdout_str().contains("EXAMPLE LOCATORS:"));
+}
+
+#[test]
+fn migrate_postgres() {
+    let testdir = TestDir::new("dbcrossbar", "migrate_postgres");
+    let old = testdir.src_path("fixtures/migrate/old.sql");
+    let new = testdir.src_path("fixtures/migrate/new.sql");
+    let output = testdir
+        .cmd()
+        .args([
+            "schema",
+            "migrate",
+            &format!("postgres-sql:{}", old.display()),
+            &format!("postgres-sql:{}", new.display()),
+            "postgres://localhost:5432/db#example",
+        ])
+        .expect_success();
+    assert_eq!(
+        output.stdout_str(),
+        r#"ALTER TABLE "example" DROP COLUMN "legacy";
+ALTER TABLE "example" ALTER COLUMN "id" TYPE bigint USING "id"::bigint;
+ALTER TABLE "example" ALTER COLUMN "name" DROP NOT NULL;
+ALTER TABLE "example" ADD COLUMN "created_at" timestamp with time zone;
+"#,
+    );
+}
+
+#[test]
+fn migrate_postgres_rejects_not_null_columns() {
+    let testdir =
+        TestDir::new("dbcrossbar", "migrate_postgres_rejects_not_null_columns");
+    testdir.create_file("old.sql", "CREATE TABLE example (id INT NOT NULL);");
+    testdir.create_file(
+        "new.sql",
+        "CREATE TABLE example (id INT NOT NULL, name TEXT NOT NULL);",
+    );
+    let output = testdir
+        .cmd()
+        .args([
+            "schema",
+            "migrate",
+            "postgres-sql:old.sql",
+            "postgres-sql:new.sql",
+            "postgres://localhost:5432/db#example",
+        ])
+        .expect_failure();
+    assert!(output
+        .stderr_str()
+        .contains("cannot add NOT NULL column \"name\""));
+}
+
+#[test]
+fn migrate_bigquery_rejects_unsupported_changes() {
+    let testdir =
+        TestDir::new("dbcrossbar", "migrate_bigquery_rejects_unsupported_changes");
+    let old = testdir.src_path("fixtures/migrate/old.sql");
+    let new = testdir.src_path("fixtures/migrate/new.sql");
+    let output = testdir
+        .cmd()
+        .args([
+            "schema",
+            "migrate",
+            &format!("postgres-sql:{}", new.display()),
+            &format!("postgres-sql:{}", old.display()),
+            "bigquery:project:dataset.example",
+        ])
+        .expect_failure();
+    assert!(output.stderr_str().contains("REQUIRED"));
+}
+
+#[test]
+fn migrate_bigquery() {
+    let testdir = TestDir::new("dbcrossbar", "migrate_bigquery");
+    let old = testdir.src_path("fixtures/migrate/old.sql");
+    let new = testdir.src_path("fixtures/migrate/new.sql");
+    let output = testdir
+        .cmd()
+        .args([
+            "schema",
+            "migrate",
+            &format!("postgres-sql:{}", old.display()),
+            &format!("postgres-sql:{}", new.display()),
+            "bigquery:project:dataset.example",
+        ])
+        .expect_success();
+    assert_eq!(
+        output.stdout_str(),
+        "ALTER TABLE `project`.`dataset`.`example` DROP COLUMN `legacy`;
+ALTER TABLE `project`.`dataset`.`example` ALTER COLUMN `name` DROP NOT NULL;
+ALTER TABLE `project`.`dataset`.`example` ADD COLUMN `created_at` TIMESTAMP;
+",
+    );
+}
@@ -0,0 +1,63 @@
+//! Tests for the `run` subcommand.
+
+use cli_test_dir::*;
+
+#[test]
+fn run_pipeline_with_dependencies() {
+    let testdir = TestDir::new("dbcrossbar", "run_pipeline_with_dependencies");
+    let src = testdir.src_path("fixtures/posts.csv");
+    let schema = testdir.src_path("fixtures/posts.sql");
+    testdir.create_file(
+        "pipeline.toml",
+        format!(
+            r#"
+[jobs.copy]
+from = "csv:{src}"
+to = "csv:copy.csv"
+if_exists = "overwrite"
+schema = "postgres-sql:{schema}"
+
+[jobs.copy_again]
+from = "csv:copy.csv"
+to = "csv:copy_again.csv"
+if_exists = "overwrite"
+schema = "postgres-sql:{schema}"
+depends_on = ["copy"]
+
+[jobs.broken]
+from = "csv:missing.csv"
+to = "csv:broken.csv"
+schema = "postgres-sql:{schema}"
+
+[jobs.after_broken]
+from = "csv:copy.csv"
+to = "csv:after_broken.csv"
+schema = "postgres-sql:{schema}"
+depends_on = ["broken"]
+"#,
+            src = src.display(),
+            schema = schema.display(),
+        ),
+    );
+    let output = testdir
+        .cmd()
+        .args(["run", "pipeline.toml"])
+        .tee_output()
+        .expect_failure();
+    let stdout = output.stdout_str();
+    assert!(stdout.contains("copy         | ok"));
+    assert!(stdout.contains("copy_again   | ok"));
+    assert!(stdout.contains("broken       | failed"));
+    assert!(stdout.contains("after_broken | skipped"));
+    assert!(output.stderr_str().contains("2 of 4 jobs did not succeed"));
+    let expected = std::fs::read_to_string(&src).unwrap();
+    testdir.expect_file_contents("copy_again.csv", &expected);
+    assert!(!testdir.path("after_broken.csv").exists());
+
+    // Only run the jobs after `copy`.
+    testdir
+        .cmd()
+        .args(["run", "--from=copy", "pipeline.toml"])
+        .tee_output()
+        .expect_success();
+}
@@ -0,0 +1,47 @@
+//! Tests for the `validate` subcommand.
+
+use cli_test_dir::*;
+
+#[test]
+fn validate_valid_csv() {
+    let testdir = TestDir::new("dbcrossbar", "validate_valid_csv");
+    let src = testdir.src_path("fixtures/validate/valid.csv");
+    let schema = testdir.src_path("fixtures/validate/schema.sql");
+    testdir
+        .cmd()
+        .args([
+            "validate",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            &format!("csv:{}", src.display()),
+        ])
+        .tee_output()
+        .expect_success();
+}
+
+#[test]
+fn validate_invalid_csv_with_rejects() {
+    let testdir = TestDir::new("dbcrossbar", "validate_invalid_csv_with_rejects");
+    let src = testdir.src_path("fixtures/validate/input.csv");
+    let schema = testdir.src_path("fixtures/validate/schema.sql");
+    let expected = testdir.src_path("fixtures/validate/rejects.csv");
+    let output = testdir
+        .cmd()
+        .args([
+            "validate",
+            &format!("--schema=postgres-sql:{}", schema.display()),
+            "--rejects=csv:rejects.csv",
+            &format!("csv:{}", src.display()),
+        ])
+        .tee_output()
+        .expect_failure();
+    let stdout = output.stdout_str();
+    assert!(stdout.contains(":3:id: "));
+    assert!(stdout.contains(":4:name: "));
+    assert!(stdout.contains(":5:born: "));
+    assert!(stdout.contains(":5:score: "));
+    assert!(output.stderr_str().contains("found 3 invalid rows"));
+    testdir.expect_file_contents(
+        "rejects.csv",
+        std::fs::read_to_string(expected).unwrap(),
+    );
+}
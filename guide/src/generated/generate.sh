@@ -10,7 +10,7 @@ function dbxb {
     ../../../target/debug/dbcrossbar --enable-unstable "$@" 2>&1
 }
 
-for c in cp count "schema conv"; do
+for c in cp count head ls run "schema conv" "schema migrate" validate; do
     dbxb $c --help | tail -n +2 > "$(echo "$c" | sed 's/ /_/g')"_help.txt
 done
 